@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/BaseFlashBorrower.sol";
+import "contracts/interfaces/IERC20.sol";
+import "contracts/interfaces/IAaveV3Pool.sol";
+import "contracts/interfaces/IAaveFlashLoanSimpleReceiver.sol";
+
+/// @title AaveFlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice A Minimal, Multicallable Aave V3 Flashloan Receiver
+contract AaveFlashBorrower is BaseFlashBorrower, IAaveFlashLoanSimpleReceiver {
+    /// @notice Receiver Construction
+    /// @param pool_ The Aave V3 Pool to borrow from
+    constructor(IAaveV3Pool pool_, address owner_) BaseFlashBorrower(address(pool_), owner_) {}
+
+    /// @notice Executes the flashloan and middle calls
+    /// @notice This is the only contract entrypoint
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        override
+        onlyOwner
+        returns (Call3Result[] memory)
+    {
+        IAaveV3Pool(lender).flashLoanSimple(address(this), token, amount, abi.encode(calls), 0);
+        return takeResults();
+    }
+
+    /// @dev Aave V3 simple flashloan callback
+    function executeOperation(
+        address asset,
+        uint256 amount,
+        uint256 premium,
+        address initiator,
+        bytes calldata params
+    ) external override onlyLender returns (bool) {
+        // The flashloan initiater must be this contract
+        if (initiator != address(this)) revert UntrustedInitiator();
+
+        // Execute multicall
+        (Call3[] memory calls) = abi.decode(params, (Call3[]));
+        execute(calls);
+
+        // The Aave pool pulls the approved token (amount + premium) from this contract
+        IERC20(asset).approve(lender, amount + premium);
+        return true;
+    }
+}
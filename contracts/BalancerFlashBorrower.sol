@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/BaseFlashBorrower.sol";
+import "contracts/interfaces/IERC20.sol";
+import "contracts/interfaces/IBalancerVault.sol";
+import "contracts/interfaces/IBalancerFlashLoanRecipient.sol";
+
+/// @title BalancerFlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice A Minimal, Multicallable Balancer V2 Flashloan Receiver
+contract BalancerFlashBorrower is BaseFlashBorrower, IBalancerFlashLoanRecipient {
+    /// @notice The hash of the user data of the flashloan in flight, zero otherwise
+    /// @notice The Balancer callback has no initiator, anyone can name this contract as the recipient,
+    /// @notice so the callback only runs the calls this contract passed to the vault
+    bytes32 internal pendingLoan;
+
+    /// @notice Receiver Construction
+    /// @param vault_ The Balancer V2 Vault to borrow from
+    constructor(IBalancerVault vault_, address owner_) BaseFlashBorrower(address(vault_), owner_) {}
+
+    /// @notice Executes the flashloan and middle calls
+    /// @notice This is the only contract entrypoint
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        override
+        onlyOwner
+        returns (Call3Result[] memory)
+    {
+        address[] memory tokens = new address[](1);
+        tokens[0] = token;
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = amount;
+
+        bytes memory userData = abi.encode(calls);
+        pendingLoan = keccak256(userData);
+        IBalancerVault(lender).flashLoan(this, tokens, amounts, userData);
+        delete pendingLoan;
+        return takeResults();
+    }
+
+    /// @dev Balancer V2 flashloan callback
+    function receiveFlashLoan(
+        address[] memory tokens,
+        uint256[] memory amounts,
+        uint256[] memory feeAmounts,
+        bytes memory userData
+    ) external override onlyLender {
+        // The flashloan must have been initiated by this contract, with the calls it passed
+        if (keccak256(userData) != pendingLoan) revert UntrustedInitiator();
+
+        // Consume the loan, so a loan taken by the calls can't run them again
+        delete pendingLoan;
+
+        // Execute multicall
+        (Call3[] memory calls) = abi.decode(userData, (Call3[]));
+        execute(calls);
+
+        // Balancer expects the principal and fee to be transferred back to the vault
+        for (uint256 i = 0; i < tokens.length;) {
+            IERC20(tokens[i]).transfer(lender, amounts[i] + feeAmounts[i]);
+            unchecked {
+                ++i;
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/interfaces/IERC20.sol";
+
+/// @title BaseFlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice Shared ownership, withdrawal and multicall logic for the flashloan receivers
+/// @notice Lender specific borrowers implement `flashBorrow` and the lender callback
+abstract contract BaseFlashBorrower {
+    /// @notice The flashloan lender
+    address public lender;
+
+    /// @notice The owner set at construction
+    address internal immutable initialOwner;
+
+    /// @notice The encoded results of the last multicall, cleared once returned by `flashBorrow`
+    /// @dev The slots are written and cleared in the same transaction, so most of the cost is refunded
+    bytes internal lastResults;
+
+    /// @notice Emitted with the result of each call executed in the lender callback
+    event Call3Results(Call3Result[] results);
+
+    /// @notice Errors if the caller is not the msg.sender
+    error Unauthorized();
+
+    /// @notice Errors if the caller is not the flashloan lender
+    error UntrustedLender();
+
+    /// @notice Errors if the flashloan initiator is not this contract
+    error UntrustedInitiator();
+
+    /// @notice Thrown if the contract has no eth balance
+    error EmptyBalance();
+
+    /// @notice Thrown if a call that does not allow failure fails
+    /// @param index The index of the failed call
+    /// @param returnData The revert data of the failed call
+    error CallFailed(uint256 index, bytes returnData);
+
+    /// @notice Thrown if the profit token balance after the flashloan is below the threshold
+    /// @param balance The profit token balance after the flashloan
+    /// @param threshold The balance before the flashloan plus the minimum profit
+    error InsufficientProfit(uint256 balance, uint256 threshold);
+
+    /// @notice Only this contract can call
+    modifier onlySelf() {
+        if (msg.sender != address(this)) revert Unauthorized();
+        _;
+    }
+
+    /// @notice Only the receiver owner can call
+    modifier onlyOwner() {
+        if (msg.sender != owner()) revert Unauthorized();
+        _;
+    }
+
+    /// @notice Only an account allowed to trigger flashloans can call
+    modifier onlyOperator() {
+        if (!_isOperator(msg.sender)) revert Unauthorized();
+        _;
+    }
+
+    /// @notice Only a trusted flashloan lender can call
+    modifier onlyLender() {
+        if (!_isTrustedLender(msg.sender)) revert UntrustedLender();
+        _;
+    }
+
+    /// @notice Reverts unless this contract's profit token balance grows by the minimum profit
+    modifier withProfit(address profitToken, uint256 minProfit) {
+        uint256 threshold = IERC20(profitToken).balanceOf(address(this)) + minProfit;
+        _;
+        uint256 balance = IERC20(profitToken).balanceOf(address(this));
+        if (balance < threshold) revert InsufficientProfit(balance, threshold);
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                      CORE LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Receiver Construction
+    constructor(address lender_, address owner_) {
+        lender = lender_;
+        initialOwner = owner_;
+    }
+
+    /// @notice The contract owner
+    function owner() public view virtual returns (address) {
+        return initialOwner;
+    }
+
+    /// @notice Whether the account can call back as a lender, only the constructor lender by default
+    function _isTrustedLender(address account) internal view virtual returns (bool) {
+        return account == lender;
+    }
+
+    /// @notice Whether the account can trigger flashloans, only the owner by default
+    function _isOperator(address account) internal view virtual returns (bool) {
+        return account == owner();
+    }
+
+    /// @notice Executes the flashloan and middle calls
+    /// @notice This is the only contract entrypoint
+    /// @notice Ether sent along funds the value of the calls, on top of the ether this contract holds
+    /// @return results The result of each call, in order
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        virtual
+        returns (Call3Result[] memory results);
+
+    /// @notice Executes the flashloan and middle calls, reverting unless they return a profit
+    /// @param profitToken The token the profit is realized in
+    /// @param minProfit The minimum increase of this contract's profit token balance
+    /// @return results The result of each call, in order
+    function flashBorrowWithProfit(
+        address token,
+        uint256 amount,
+        Call3[] calldata calls,
+        address profitToken,
+        uint256 minProfit
+    ) external payable withProfit(profitToken, minProfit) returns (Call3Result[] memory results) {
+        results = flashBorrow(token, amount, calls);
+    }
+
+    /// @notice owner can withdraw ERC20 tokens
+    function withdrawToken(IERC20 token, address to, uint256 amount, bool max) external onlyOwner returns (bool) {
+        if (max) amount = token.balanceOf(address(this));
+        token.transfer(to, amount);
+        return true;
+    }
+
+    /// @notice Owner can withdraw ETH
+    function withdrawEth(address payable to) external onlyOwner returns (bool) {
+        if (address(this).balance == 0) revert EmptyBalance();
+        to.transfer(address(this).balance);
+        return true;
+    }
+
+    receive() external payable {}
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                   MULTICALL LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    struct Call3 {
+        address target;
+        bool allowFailure;
+        uint256 value;
+        bytes callData;
+    }
+
+    struct Call3Result {
+        bool success;
+        bytes returnData;
+    }
+
+    /// @notice Executes the calls from the lender callback, recording their results
+    /// @param calls An array of Call3 objects
+    function execute(Call3[] memory calls) internal {
+        Call3Result[] memory results = aggregate3(calls);
+        emit Call3Results(results);
+        lastResults = abi.encode(results);
+    }
+
+    /// @notice Returns and clears the results recorded by the last `execute`
+    /// @return results An array of Call3Result objects
+    function takeResults() internal returns (Call3Result[] memory results) {
+        if (lastResults.length == 0) return results;
+        results = abi.decode(lastResults, (Call3Result[]));
+        delete lastResults;
+    }
+
+    /// @notice Aggregate calls, ensuring each returns success if required
+    /// @notice Only this contract can call from the lender callback
+    /// @param calls An array of Call3 objects
+    /// @return returnData An array of Call3Result objects
+    function aggregate3(Call3[] memory calls) internal returns (Call3Result[] memory returnData) {
+        uint256 length = calls.length;
+        returnData = new Call3Result[](length);
+        Call3 memory calli;
+        for (uint256 i = 0; i < length;) {
+            Call3Result memory result = returnData[i];
+            calli = calls[i];
+            (result.success, result.returnData) = calli.target.call{value: calli.value}(calli.callData);
+            // Revert with the call index and its revert data if failure is not allowed
+            if (!(calli.allowFailure || result.success)) revert CallFailed(i, result.returnData);
+            unchecked {
+                ++i;
+            }
+        }
+    }
+}
@@ -1,88 +1,139 @@
 // SPDX-License-Identifier: MIT
 pragma solidity ^0.8.15;
 
+import "contracts/BaseFlashBorrower.sol";
 import "contracts/interfaces/IERC20.sol";
 import "contracts/interfaces/IERC3156FlashBorrower.sol";
 import "contracts/interfaces/IERC3156FlashLender.sol";
 
 /// @title FlashBorrower
 /// @author asnared <https://github.com/abigger87>
-/// @notice A Minimal, Multicallable Flashloan Receiver
-contract FlashBorrower is IERC3156FlashBorrower {
-    /// @notice The flashloan lender
-    IERC3156FlashLender public lender;
+/// @notice A Minimal, Multicallable ERC-3156 Flashloan Receiver
+contract FlashBorrower is BaseFlashBorrower, IERC3156FlashBorrower {
+    /// @notice Whether a lender can be borrowed from and call back
+    mapping(address => bool) public trustedLenders;
 
-    /// @notice The contract owner
-    address public immutable owner;
+    /// @notice Emitted when a lender is trusted or untrusted
+    event LenderUpdated(address indexed lender, bool trusted);
 
-    /// @notice The amount we need to pay back to the lender
-    uint256 payback;
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                      CORE LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
 
-    /// @notice Errors if the caller is not the msg.sender
-    error Unauthorized();
+    /// @notice Receiver Construction
+    /// @param lender_ The default lender, trusted from construction
+    constructor(IERC3156FlashLender lender_, address owner_) BaseFlashBorrower(address(lender_), owner_) {
+        trustedLenders[address(lender_)] = true;
+        emit LenderUpdated(address(lender_), true);
+    }
 
-    /// @notice Errors if the caller is not the flashloan lender
-    error UntrustedLender();
+    /// @notice Executes the flashloan from the default lender and middle calls
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        override
+        onlyOperator
+        returns (Call3Result[] memory)
+    {
+        return borrow(IERC3156FlashLender(lender), token, amount, calls);
+    }
 
-    /// @notice Errors if the flashloan initiator is not this contract
-    error UntrustedInitiator();
+    /// @notice Executes the flashloan from a trusted lender and middle calls
+    /// @notice The lender is the last parameter, so the others keep their calldata positions
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls, IERC3156FlashLender lender_)
+        public payable
+        onlyOperator
+        returns (Call3Result[] memory)
+    {
+        if (!trustedLenders[address(lender_)]) revert UntrustedLender();
+        return borrow(lender_, token, amount, calls);
+    }
 
-    /// @notice Thrown if the contract has no eth balance
-    error EmptyBalance();
+    /// @notice Executes the flashloan from a trusted lender and middle calls, reverting unless they return a profit
+    function flashBorrowWithProfit(
+        address token,
+        uint256 amount,
+        Call3[] calldata calls,
+        address profitToken,
+        uint256 minProfit,
+        IERC3156FlashLender lender_
+    ) external payable withProfit(profitToken, minProfit) returns (Call3Result[] memory results) {
+        results = flashBorrow(token, amount, calls, lender_);
+    }
 
-    /// @notice Only this contract can call
-    modifier onlySelf() {
-        if (msg.sender != address(this)) revert Unauthorized();
-        _;
+    /// @notice Executes the flashloan and middle calls with a repayment computed off-chain
+    /// @notice Skips the `allowance` and `flashFee` view calls of `flashBorrow`
+    /// @param repayment The amount plus the flash fee, approved for the lender to pull
+    function flashBorrowWithRepayment(address token, uint256 amount, uint256 repayment, Call3[] calldata calls)
+        external payable
+        onlyOperator
+        returns (Call3Result[] memory)
+    {
+        return borrowWithRepayment(IERC3156FlashLender(lender), token, amount, repayment, calls);
     }
 
-    /// @notice Only the receiver owner can call
-    modifier onlyOwner() {
-        if (msg.sender != owner) revert Unauthorized();
-        _;
+    /// @notice Executes the flashloan from a trusted lender with a repayment computed off-chain
+    function flashBorrowWithRepayment(
+        address token,
+        uint256 amount,
+        uint256 repayment,
+        Call3[] calldata calls,
+        IERC3156FlashLender lender_
+    ) external payable onlyOperator returns (Call3Result[] memory) {
+        if (!trustedLenders[address(lender_)]) revert UntrustedLender();
+        return borrowWithRepayment(lender_, token, amount, repayment, calls);
     }
 
-    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
-    ///                      CORE LOGIC
-    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+    /// @notice Owner can trust a lender to be borrowed from and call back
+    function trustLender(address lender_) external onlyOwner {
+        trustedLenders[lender_] = true;
+        emit LenderUpdated(lender_, true);
+    }
 
-    /// @notice Receiver Construction
-    constructor(IERC3156FlashLender lender_, address owner_) {
-        lender = lender_;
-        owner = owner_;
+    /// @notice Owner can untrust a lender, including the default lender
+    function untrustLender(address lender_) external onlyOwner {
+        delete trustedLenders[lender_];
+        emit LenderUpdated(lender_, false);
+    }
+
+    /// @notice Only trusted lenders can call back
+    function _isTrustedLender(address account) internal view override returns (bool) {
+        return trustedLenders[account];
     }
 
-    /// @notice Executes the flashloan and middle calls
-    /// @notice This is the only contract entrypoint
-    function flashBorrow(address token, uint256 amount, Call3[] calldata calls) public onlyOwner {
+    /// @notice Approves the repayment on top of the current allowance and borrows from the lender
+    function borrow(IERC3156FlashLender lender_, address token, uint256 amount, Call3[] calldata calls)
+        internal
+        returns (Call3Result[] memory)
+    {
         // Approve the lender to pull the repayment tokens
-        // NOTE: Can make the below viewable calls offchain and pass as calldata to save gas
-        uint256 allowance = IERC20(token).allowance(address(this), address(lender));
-        uint256 fee = lender.flashFee(token, amount);
+        // NOTE: flashBorrowWithRepayment takes the repayment computed offchain to skip these calls
+        uint256 allowance = IERC20(token).allowance(address(this), address(lender_));
+        uint256 fee = lender_.flashFee(token, amount);
         uint256 repayment = amount + fee;
-        IERC20(token).approve(address(lender), allowance + repayment);
+        IERC20(token).approve(address(lender_), allowance + repayment);
 
         // Execute the flashloan with encoded calls
         bytes memory data = abi.encode(calls);
-        lender.flashLoan(this, token, amount, data);
+        lender_.flashLoan(this, token, amount, data);
+        return takeResults();
     }
 
-    /// @notice owner can withdraw ERC20 tokens
-    function withdrawToken(IERC20 token, address to, uint256 amount, bool max) external onlyOwner returns (bool) {
-        if (max) amount = token.balanceOf(address(this));
-        token.transfer(to, amount);
-        return true;
-    }
+    /// @notice Approves the repayment computed off-chain and borrows from the lender
+    function borrowWithRepayment(
+        IERC3156FlashLender lender_,
+        address token,
+        uint256 amount,
+        uint256 repayment,
+        Call3[] calldata calls
+    ) internal returns (Call3Result[] memory) {
+        // The lender reverts pulling the repayment if it doesn't cover the fee
+        IERC20(token).approve(address(lender_), repayment);
 
-    /// @notice Owner can withdraw ETH
-    function withdrawEth(address payable to) external onlyOwner returns (bool) {
-        if (address(this).balance == 0) revert EmptyBalance();
-        to.transfer(address(this).balance);
-        return true;
+        bytes memory data = abi.encode(calls);
+        lender_.flashLoan(this, token, amount, data);
+        return takeResults();
     }
 
-    receive() external payable {}
-
     ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
     ///                    ERC-3156 LOGIC
     ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
@@ -94,10 +145,7 @@ contract FlashBorrower is IERC3156FlashBorrower {
         uint256, // amount
         uint256, // fee
         bytes calldata data
-    ) external override returns (bytes32) {
-        // Can only be called by the flashloan lender
-        if (msg.sender != address(lender)) revert UntrustedLender();
-
+    ) external override onlyLender returns (bytes32) {
         // The flashloan initiater must be this contract
         if (initiator != address(this)) revert UntrustedInitiator();
 
@@ -105,59 +153,10 @@ contract FlashBorrower is IERC3156FlashBorrower {
         // Place for strategy execution (e.g. https://github.com/makerdao/dss-flash#usage)
         // NOTE: Can use a weiroll virtual machine here to construct contextual calls
         (Call3[] memory calls) = abi.decode(data, (Call3[]));
-        aggregate3(calls);
+        execute(calls);
 
         // Return the ERC-3156 success value
         // The Flashloan lender will pull the approved token (amount + fee) from this contract
         return keccak256("ERC3156FlashBorrower.onFlashLoan");
     }
-
-    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
-    ///                   MULTICALL LOGIC
-    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
-
-    struct Call3 {
-        address target;
-        bool allowFailure;
-        uint256 value;
-        bytes callData;
-    }
-
-    struct Call3Result {
-        bool success;
-        bytes returnData;
-    }
-
-    /// @notice Aggregate calls, ensuring each returns success if required
-    /// @notice Only this contract can call from the onFlashLoan callback
-    /// @param calls An array of Call3 objects
-    /// @return returnData An array of Call3Result objects
-    function aggregate3(Call3[] memory calls) internal returns (Call3Result[] memory returnData) {
-        uint256 length = calls.length;
-        returnData = new Call3Result[](length);
-        Call3 memory calli;
-        for (uint256 i = 0; i < length;) {
-            Call3Result memory result = returnData[i];
-            calli = calls[i];
-            (result.success, result.returnData) = calli.target.call{value: calli.value}(calli.callData);
-            assembly {
-                // Revert if the call fails and failure is not allowed
-                // `allowFailure := calldataload(add(calli, 0x20))` and `success := mload(result)`
-                if iszero(or(calldataload(add(calli, 0x20)), mload(result))) {
-                    // set "Error(string)" signature: bytes32(bytes4(keccak256("Error(string)")))
-                    mstore(0x00, 0x08c379a000000000000000000000000000000000000000000000000000000000)
-                    // set data offset
-                    mstore(0x04, 0x0000000000000000000000000000000000000000000000000000000000000020)
-                    // set length of revert string
-                    mstore(0x24, 0x0000000000000000000000000000000000000000000000000000000000000017)
-                    // set revert string: bytes32(abi.encodePacked("Multicall3: call failed"))
-                    mstore(0x44, 0x4d756c746963616c6c333a2063616c6c206661696c6564000000000000000000)
-                    revert(0x00, 0x64)
-                }
-            }
-            unchecked {
-                ++i;
-            }
-        }
-    }
 }
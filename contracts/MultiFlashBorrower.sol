@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/BaseFlashBorrower.sol";
+import "contracts/interfaces/IERC20.sol";
+import "contracts/interfaces/IERC3156FlashBorrower.sol";
+import "contracts/interfaces/IERC3156FlashLender.sol";
+import "contracts/interfaces/IAaveV3Pool.sol";
+import "contracts/interfaces/IAaveFlashLoanSimpleReceiver.sol";
+import "contracts/interfaces/IBalancerVault.sol";
+import "contracts/interfaces/IBalancerFlashLoanRecipient.sol";
+import "contracts/interfaces/IUniswapV3Pool.sol";
+import "contracts/interfaces/IUniswapV3FlashCallback.sol";
+
+/// @title MultiFlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice A Multicallable Flashloan Receiver borrowing several tokens from several lenders
+/// @notice Each leg is borrowed from the previous leg's callback, the calls run in the innermost
+/// @notice callback and every leg is repaid as the callbacks unwind
+contract MultiFlashBorrower is
+    BaseFlashBorrower,
+    IERC3156FlashBorrower,
+    IAaveFlashLoanSimpleReceiver,
+    IBalancerFlashLoanRecipient,
+    IUniswapV3FlashCallback
+{
+    /// @notice The lender protocol of a leg
+    enum LenderKind {
+        Erc3156,
+        AaveV3,
+        Balancer,
+        UniswapV3
+    }
+
+    /// @notice A token borrowed from a lender
+    struct Leg {
+        LenderKind kind;
+        address lender;
+        address token;
+        uint256 amount;
+    }
+
+    /// @notice The lender of the leg in flight, the only address allowed to call back
+    address internal activeLender;
+
+    /// @notice The hash of the encoded legs and calls of the flashloan in flight
+    /// @notice Every callback must carry them, so a loan taken by a call can't run other calls
+    bytes32 internal pendingLoan;
+
+    /// @notice Receiver Construction
+    /// @param lender_ The ERC-3156 lender borrowed from by `flashBorrow`
+    constructor(IERC3156FlashLender lender_, address owner_) BaseFlashBorrower(address(lender_), owner_) {}
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                      CORE LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Executes a single leg flashloan from the ERC-3156 `lender` and middle calls
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        override
+        onlyOwner
+        returns (Call3Result[] memory)
+    {
+        Leg[] memory legs = new Leg[](1);
+        legs[0] = Leg(LenderKind.Erc3156, lender, token, amount);
+        return borrowAll(legs, calls);
+    }
+
+    /// @notice Executes the nested flashloans and middle calls
+    /// @param legs The loans, borrowed in order
+    /// @return results The result of each call, in order
+    function flashBorrowMulti(Leg[] calldata legs, Call3[] calldata calls)
+        public payable
+        onlyOwner
+        returns (Call3Result[] memory results)
+    {
+        return borrowAll(legs, calls);
+    }
+
+    /// @notice Executes the nested flashloans and middle calls, reverting unless they return a profit
+    /// @param profitToken The token the profit is realized in
+    /// @param minProfit The minimum increase of this contract's profit token balance
+    /// @return results The result of each call, in order
+    function flashBorrowMultiWithProfit(
+        Leg[] calldata legs,
+        Call3[] calldata calls,
+        address profitToken,
+        uint256 minProfit
+    ) external payable returns (Call3Result[] memory results) {
+        uint256 threshold = IERC20(profitToken).balanceOf(address(this)) + minProfit;
+        results = flashBorrowMulti(legs, calls);
+        uint256 balance = IERC20(profitToken).balanceOf(address(this));
+        if (balance < threshold) revert InsufficientProfit(balance, threshold);
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                     NESTING LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Borrows every leg, returning the results of the calls
+    function borrowAll(Leg[] memory legs, Call3[] memory calls) internal returns (Call3Result[] memory) {
+        pendingLoan = keccak256(abi.encode(legs, calls));
+        borrow(legs, 0, calls);
+        activeLender = address(0);
+        delete pendingLoan;
+        return takeResults();
+    }
+
+    /// @notice Borrows the leg at `index`, or executes the calls once every leg is borrowed
+    function borrow(Leg[] memory legs, uint256 index, Call3[] memory calls) internal {
+        if (index == legs.length) {
+            execute(calls);
+            return;
+        }
+
+        Leg memory leg = legs[index];
+        bytes memory data = abi.encode(legs, index, calls);
+        activeLender = leg.lender;
+
+        if (leg.kind == LenderKind.Erc3156) {
+            // Approve the lender to pull the repayment tokens
+            uint256 allowance = IERC20(leg.token).allowance(address(this), leg.lender);
+            uint256 fee = IERC3156FlashLender(leg.lender).flashFee(leg.token, leg.amount);
+            IERC20(leg.token).approve(leg.lender, allowance + leg.amount + fee);
+            IERC3156FlashLender(leg.lender).flashLoan(this, leg.token, leg.amount, data);
+        } else if (leg.kind == LenderKind.AaveV3) {
+            IAaveV3Pool(leg.lender).flashLoanSimple(address(this), leg.token, leg.amount, data, 0);
+        } else if (leg.kind == LenderKind.Balancer) {
+            address[] memory tokens = new address[](1);
+            tokens[0] = leg.token;
+            uint256[] memory amounts = new uint256[](1);
+            amounts[0] = leg.amount;
+            IBalancerVault(leg.lender).flashLoan(this, tokens, amounts, data);
+        } else {
+            bool zeroForOne = leg.token == IUniswapV3Pool(leg.lender).token0();
+            IUniswapV3Pool(leg.lender).flash(
+                address(this), zeroForOne ? leg.amount : 0, zeroForOne ? 0 : leg.amount, data
+            );
+        }
+    }
+
+    /// @notice Borrows the remaining legs from a lender callback
+    /// @return leg The leg being called back for, to be repaid by the callback
+    function borrowNext(bytes memory data) internal returns (Leg memory leg) {
+        (Leg[] memory legs, uint256 index, Call3[] memory calls) = abi.decode(data, (Leg[], uint256, Call3[]));
+        leg = legs[index];
+
+        // Only the lender of the leg in flight can call back, with the legs and calls borrowed for
+        if (msg.sender != activeLender || msg.sender != leg.lender) revert UntrustedLender();
+        if (keccak256(abi.encode(legs, calls)) != pendingLoan) revert UntrustedInitiator();
+        borrow(legs, index + 1, calls);
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                   LENDER CALLBACKS
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @dev ERC-3156 Flash loan callback
+    function onFlashLoan(
+        address initiator,
+        address, // token,
+        uint256, // amount
+        uint256, // fee
+        bytes calldata data
+    ) external override returns (bytes32) {
+        // The flashloan initiater must be this contract
+        if (initiator != address(this)) revert UntrustedInitiator();
+        borrowNext(data);
+
+        // The Flashloan lender will pull the approved token (amount + fee) from this contract
+        return keccak256("ERC3156FlashBorrower.onFlashLoan");
+    }
+
+    /// @dev Aave V3 simple flashloan callback
+    function executeOperation(
+        address asset,
+        uint256 amount,
+        uint256 premium,
+        address initiator,
+        bytes calldata params
+    ) external override returns (bool) {
+        // The flashloan initiater must be this contract
+        if (initiator != address(this)) revert UntrustedInitiator();
+        Leg memory leg = borrowNext(params);
+
+        // The Aave pool pulls the approved token (amount + premium) from this contract
+        IERC20(asset).approve(leg.lender, amount + premium);
+        return true;
+    }
+
+    /// @dev Balancer V2 flashloan callback
+    /// @dev The active lender is only set while this contract borrows, so the loan was initiated here
+    function receiveFlashLoan(
+        address[] memory tokens,
+        uint256[] memory amounts,
+        uint256[] memory feeAmounts,
+        bytes memory userData
+    ) external override {
+        Leg memory leg = borrowNext(userData);
+
+        // Balancer expects the principal and fee to be transferred back to the vault
+        IERC20(tokens[0]).transfer(leg.lender, amounts[0] + feeAmounts[0]);
+    }
+
+    /// @dev Uniswap V3 flash callback
+    /// @dev The pool only calls back the address that invoked `flash`, so the initiator is always this contract
+    function uniswapV3FlashCallback(uint256 fee0, uint256 fee1, bytes calldata data) external override {
+        Leg memory leg = borrowNext(data);
+
+        // Only the borrowed side of the pool charges a fee
+        IERC20(leg.token).transfer(leg.lender, leg.amount + fee0 + fee1);
+    }
+}
@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/FlashBorrower.sol";
+import "contracts/interfaces/IERC3156FlashLender.sol";
+
+/// @title OperatedFlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice An ERC-3156 Flashloan Receiver with a transferable owner and flashloan operators
+/// @notice Operators can trigger flashloans, operator updates and ownership stay with the owner
+/// @dev Operators are fully trusted: the calls of a flashloan run as the borrower, so an operator
+///      can move any of the borrower's tokens and ether through them
+contract OperatedFlashBorrower is FlashBorrower {
+    /// @notice The current owner, the initial owner until ownership is transferred
+    address internal currentOwner;
+
+    /// @notice The account the owner has offered ownership to
+    address public pendingOwner;
+
+    /// @notice Whether an account can trigger flashloans
+    mapping(address => bool) public isOperator;
+
+    /// @notice Emitted when the owner offers ownership to a new account
+    event OwnershipTransferStarted(address indexed previousOwner, address indexed newOwner);
+
+    /// @notice Emitted when the pending owner accepts ownership
+    event OwnershipTransferred(address indexed previousOwner, address indexed newOwner);
+
+    /// @notice Emitted when an operator is added or removed
+    event OperatorUpdated(address indexed operator, bool allowed);
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                      CORE LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Receiver Construction
+    constructor(IERC3156FlashLender lender_, address owner_) FlashBorrower(lender_, owner_) {
+        currentOwner = owner_;
+    }
+
+    /// @notice The contract owner
+    function owner() public view override returns (address) {
+        return currentOwner;
+    }
+
+    /// @notice The owner and the operators can trigger flashloans
+    function _isOperator(address account) internal view override returns (bool) {
+        return account == currentOwner || isOperator[account];
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                    OPERATOR LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Owner can allow an account to trigger flashloans
+    /// @dev The operator can spend the borrower's funds through the flashloan calls
+    function addOperator(address operator) external onlyOwner {
+        isOperator[operator] = true;
+        emit OperatorUpdated(operator, true);
+    }
+
+    /// @notice Owner can revoke an operator
+    function removeOperator(address operator) external onlyOwner {
+        delete isOperator[operator];
+        emit OperatorUpdated(operator, false);
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                   OWNERSHIP LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Owner can offer ownership, which moves once the new owner accepts it
+    /// @dev Offering ownership to the zero address cancels a pending transfer
+    function transferOwnership(address newOwner) external onlyOwner {
+        pendingOwner = newOwner;
+        emit OwnershipTransferStarted(currentOwner, newOwner);
+    }
+
+    /// @notice The pending owner takes ownership
+    function acceptOwnership() external {
+        if (msg.sender != pendingOwner) revert Unauthorized();
+        emit OwnershipTransferred(currentOwner, msg.sender);
+        currentOwner = msg.sender;
+        delete pendingOwner;
+    }
+}
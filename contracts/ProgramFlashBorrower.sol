@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/BaseFlashBorrower.sol";
+import "contracts/interfaces/IERC20.sol";
+import "contracts/interfaces/IERC3156FlashBorrower.sol";
+import "contracts/interfaces/IERC3156FlashLender.sol";
+
+/// @title ProgramFlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice An ERC-3156 Flashloan Receiver interpreting weiroll-style command programs
+/// @notice Commands read their arguments from a state of abi encoded values and can write their
+/// @notice return value back to the state, so a call's output can be piped into a later call
+contract ProgramFlashBorrower is BaseFlashBorrower, IERC3156FlashBorrower {
+    /// @notice Command flag sending the value of the first input slot with the call
+    uint256 internal constant FLAG_CALL_WITH_VALUE = 0x01;
+
+    /// @notice Input slot flag marking an abi encoded dynamic value, passed by offset
+    uint256 internal constant IDX_DYNAMIC = 0x80;
+
+    /// @notice Ends the input slots, or discards the return value as the output slot
+    uint256 internal constant IDX_END = 0xff;
+
+    /// @notice Receiver Construction
+    constructor(IERC3156FlashLender lender_, address owner_) BaseFlashBorrower(address(lender_), owner_) {}
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                      CORE LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Executes the flashloan and middle calls
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        override
+        onlyOwner
+        returns (Call3Result[] memory)
+    {
+        bytes32[] memory commands;
+        bytes[] memory state;
+        return borrow(token, amount, abi.encode(commands, state, calls));
+    }
+
+    /// @notice Executes the flashloan and a command program
+    /// @param commands The packed commands, see `runCommands`
+    /// @param state The abi encoded values the commands read and write
+    /// @return results The result of each command, in order
+    function flashBorrowProgram(address token, uint256 amount, bytes32[] calldata commands, bytes[] calldata state)
+        public payable
+        onlyOwner
+        returns (Call3Result[] memory results)
+    {
+        Call3[] memory calls;
+        return borrow(token, amount, abi.encode(commands, state, calls));
+    }
+
+    /// @notice Executes the flashloan and a command program, reverting unless it returns a profit
+    /// @param profitToken The token the profit is realized in
+    /// @param minProfit The minimum increase of this contract's profit token balance
+    /// @return results The result of each command, in order
+    function flashBorrowProgramWithProfit(
+        address token,
+        uint256 amount,
+        bytes32[] calldata commands,
+        bytes[] calldata state,
+        address profitToken,
+        uint256 minProfit
+    ) external payable returns (Call3Result[] memory results) {
+        uint256 threshold = IERC20(profitToken).balanceOf(address(this)) + minProfit;
+        results = flashBorrowProgram(token, amount, commands, state);
+        uint256 balance = IERC20(profitToken).balanceOf(address(this));
+        if (balance < threshold) revert InsufficientProfit(balance, threshold);
+    }
+
+    /// @notice Borrows from the lender, passing the encoded calls or program to the callback
+    function borrow(address token, uint256 amount, bytes memory data) internal returns (Call3Result[] memory) {
+        // Approve the lender to pull the repayment tokens
+        uint256 allowance = IERC20(token).allowance(address(this), lender);
+        uint256 fee = IERC3156FlashLender(lender).flashFee(token, amount);
+        IERC20(token).approve(lender, allowance + amount + fee);
+
+        IERC3156FlashLender(lender).flashLoan(this, token, amount, data);
+        return takeResults();
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                    ERC-3156 LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @dev ERC-3156 Flash loan callback
+    function onFlashLoan(
+        address initiator,
+        address, // token,
+        uint256, // amount
+        uint256, // fee
+        bytes calldata data
+    ) external override onlyLender returns (bytes32) {
+        // The flashloan initiater must be this contract
+        if (initiator != address(this)) revert UntrustedInitiator();
+
+        // Execute the program, or the multicall without one
+        (bytes32[] memory commands, bytes[] memory state, Call3[] memory calls) =
+            abi.decode(data, (bytes32[], bytes[], Call3[]));
+        if (commands.length == 0) {
+            execute(calls);
+        } else {
+            Call3Result[] memory results = runCommands(commands, state);
+            emit Call3Results(results);
+            lastResults = abi.encode(results);
+        }
+
+        // The Flashloan lender will pull the approved token (amount + fee) from this contract
+        return keccak256("ERC3156FlashBorrower.onFlashLoan");
+    }
+
+    ///  /‾‾\__/‾‾\__/‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾\__/‾‾\__/‾‾\
+    ///                   COMMAND VM LOGIC
+    ///  \__/‾‾\__/‾‾\________________________/‾‾\__/‾‾\__/
+
+    /// @notice Runs a command program
+    /// @dev Each command packs, from the most significant byte:
+    /// @dev `selector (4) | flags (1) | input slots (6) | output slot (1) | target (20)`
+    /// @param commands The packed commands
+    /// @param state The abi encoded values the commands read and write
+    /// @return results The result of each command, in order
+    function runCommands(bytes32[] memory commands, bytes[] memory state)
+        internal
+        returns (Call3Result[] memory results)
+    {
+        uint256 length = commands.length;
+        results = new Call3Result[](length);
+        for (uint256 i = 0; i < length;) {
+            bytes32 command = commands[i];
+            address target = address(uint160(uint256(command)));
+
+            // With value, the first input slot is the value sent rather than an argument
+            uint256 value;
+            uint256 first;
+            if (uint8(command[4]) & FLAG_CALL_WITH_VALUE != 0) {
+                value = abi.decode(state[uint8(command[5])], (uint256));
+                first = 1;
+            }
+
+            (bool success, bytes memory returnData) = target.call{value: value}(buildInputs(command, first, state));
+            if (!success) revert CallFailed(i, returnData);
+
+            // Write the first word of the return data to the output slot
+            uint256 out = uint8(command[11]);
+            if (out != IDX_END) {
+                if (returnData.length < 32) revert CallFailed(i, returnData);
+                bytes memory word = new bytes(32);
+                assembly {
+                    mstore(add(word, 32), mload(add(returnData, 32)))
+                }
+                state[out] = word;
+            }
+
+            results[i] = Call3Result(success, returnData);
+            unchecked {
+                ++i;
+            }
+        }
+    }
+
+    /// @notice Abi encodes the calldata of a command from its input slots
+    /// @dev Static values are inlined in the head, dynamic values are appended and referenced by offset
+    function buildInputs(bytes32 command, uint256 first, bytes[] memory state) internal pure returns (bytes memory) {
+        uint256 headSize;
+        uint256 last = first;
+        for (; last < 6; ++last) {
+            uint256 idx = uint8(command[5 + last]);
+            if (idx == IDX_END) break;
+            headSize += idx & IDX_DYNAMIC == 0 ? state[idx].length : 32;
+        }
+
+        bytes memory heads;
+        bytes memory tails;
+        for (uint256 j = first; j < last; ++j) {
+            uint256 idx = uint8(command[5 + j]);
+            if (idx & IDX_DYNAMIC == 0) {
+                heads = bytes.concat(heads, state[idx]);
+            } else {
+                heads = bytes.concat(heads, bytes32(headSize + tails.length));
+                tails = bytes.concat(tails, state[idx & ~IDX_DYNAMIC]);
+            }
+        }
+        return bytes.concat(bytes4(command), heads, tails);
+    }
+}
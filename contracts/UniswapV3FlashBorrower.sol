@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "contracts/BaseFlashBorrower.sol";
+import "contracts/interfaces/IERC20.sol";
+import "contracts/interfaces/IUniswapV3Pool.sol";
+import "contracts/interfaces/IUniswapV3FlashCallback.sol";
+
+/// @title UniswapV3FlashBorrower
+/// @author asnared <https://github.com/abigger87>
+/// @notice A Minimal, Multicallable Uniswap V3 Flash Receiver
+contract UniswapV3FlashBorrower is BaseFlashBorrower, IUniswapV3FlashCallback {
+    /// @notice Receiver Construction
+    /// @param pool_ The Uniswap V3 Pool to borrow from
+    constructor(IUniswapV3Pool pool_, address owner_) BaseFlashBorrower(address(pool_), owner_) {}
+
+    /// @notice Executes the flashloan and middle calls
+    /// @notice This is the only contract entrypoint
+    /// @dev `token` must be one of the pool's two tokens
+    function flashBorrow(address token, uint256 amount, Call3[] calldata calls)
+        public payable
+        override
+        onlyOwner
+        returns (Call3Result[] memory)
+    {
+        bool zeroForOne = token == IUniswapV3Pool(lender).token0();
+        IUniswapV3Pool(lender).flash(
+            address(this), zeroForOne ? amount : 0, zeroForOne ? 0 : amount, abi.encode(token, amount, calls)
+        );
+        return takeResults();
+    }
+
+    /// @dev Uniswap V3 flash callback
+    /// @dev The pool only calls back the address that invoked `flash`, so the initiator is always this contract
+    function uniswapV3FlashCallback(uint256 fee0, uint256 fee1, bytes calldata data) external override onlyLender {
+        (address token, uint256 amount, Call3[] memory calls) = abi.decode(data, (address, uint256, Call3[]));
+
+        // Execute multicall
+        execute(calls);
+
+        // Only the borrowed side of the pool charges a fee
+        IERC20(token).transfer(lender, amount + fee0 + fee1);
+    }
+}
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+/// @title Aave V3 Simple Flashloan Receiver Interface
+/// @notice https://docs.aave.com/developers/guides/flash-loans
+interface IAaveFlashLoanSimpleReceiver {
+    /// @dev Receive a simple flashloan
+    /// @param asset The loan currency
+    /// @param amount The amount of tokens lent
+    /// @param premium The fee to approve on top of the principal
+    /// @param initiator The address that called `flashLoanSimple`
+    /// @param params Arbitrary data passed through by the initiator
+    /// @return True if the operation succeeded
+    function executeOperation(
+        address asset,
+        uint256 amount,
+        uint256 premium,
+        address initiator,
+        bytes calldata params
+    ) external returns (bool);
+}
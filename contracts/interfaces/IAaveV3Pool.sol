@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+/// @title Aave V3 Pool Interface
+/// @notice The subset of the Aave V3 Pool used for simple flashloans
+/// @notice https://docs.aave.com/developers/core-contracts/pool#flashloansimple
+interface IAaveV3Pool {
+    /// @dev The total premium charged on flashloans, expressed in bps
+    function FLASHLOAN_PREMIUM_TOTAL() external view returns (uint128);
+
+    /// @dev Borrow a single asset, calling `executeOperation` on the receiver
+    /// @param receiverAddress The contract receiving the funds and the callback
+    /// @param asset The loan currency
+    /// @param amount The amount of tokens lent
+    /// @param params Arbitrary data passed to the receiver
+    /// @param referralCode Aave referral code, 0 if unused
+    function flashLoanSimple(
+        address receiverAddress,
+        address asset,
+        uint256 amount,
+        bytes calldata params,
+        uint16 referralCode
+    ) external;
+}
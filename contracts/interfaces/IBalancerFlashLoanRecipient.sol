@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+/// @title Balancer V2 Flashloan Recipient Interface
+/// @notice https://docs.balancer.fi/reference/contracts/flash-loans.html
+interface IBalancerFlashLoanRecipient {
+    /// @dev Receive a flashloan, the recipient must transfer `amounts + feeAmounts` back to the vault
+    /// @param tokens The loan currencies
+    /// @param amounts The amount of each token lent
+    /// @param feeAmounts The fee owed for each token
+    /// @param userData Arbitrary data passed through by the initiator
+    function receiveFlashLoan(
+        address[] memory tokens,
+        uint256[] memory amounts,
+        uint256[] memory feeAmounts,
+        bytes memory userData
+    ) external;
+}
@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import "./IBalancerFlashLoanRecipient.sol";
+
+/// @title Balancer V2 Vault Interface
+/// @notice The subset of the Balancer Vault used for flashloans
+/// @notice https://docs.balancer.fi/reference/contracts/flash-loans.html
+interface IBalancerVault {
+    /// @dev The contract holding the flashloan fee configuration
+    function getProtocolFeesCollector() external view returns (address);
+
+    /// @dev Borrow tokens from the vault, calling `receiveFlashLoan` on the recipient
+    /// @param recipient The contract receiving the funds and the callback
+    /// @param tokens The loan currencies, sorted ascending
+    /// @param amounts The amount of each token lent
+    /// @param userData Arbitrary data passed to the recipient
+    function flashLoan(
+        IBalancerFlashLoanRecipient recipient,
+        address[] memory tokens,
+        uint256[] memory amounts,
+        bytes memory userData
+    ) external;
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+/// @title Uniswap V3 Flash Callback Interface
+/// @notice https://docs.uniswap.org/contracts/v3/reference/core/interfaces/callback/IUniswapV3FlashCallback
+interface IUniswapV3FlashCallback {
+    /// @dev Receive a flash, the caller must transfer the borrowed amounts plus fees back to the pool
+    /// @param fee0 The fee owed on token0
+    /// @param fee1 The fee owed on token1
+    /// @param data Arbitrary data passed through by the initiator
+    function uniswapV3FlashCallback(uint256 fee0, uint256 fee1, bytes calldata data) external;
+}
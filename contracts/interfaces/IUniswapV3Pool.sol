@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+/// @title Uniswap V3 Pool Interface
+/// @notice The subset of a Uniswap V3 Pool used for flash borrowing
+/// @notice https://docs.uniswap.org/contracts/v3/reference/core/interfaces/pool/IUniswapV3PoolActions#flash
+interface IUniswapV3Pool {
+    /// @dev The first of the two pool tokens, sorted by address
+    function token0() external view returns (address);
+
+    /// @dev The second of the two pool tokens, sorted by address
+    function token1() external view returns (address);
+
+    /// @dev The pool fee in hundredths of a bip
+    function fee() external view returns (uint24);
+
+    /// @dev Borrow pool tokens, calling `uniswapV3FlashCallback` on the msg.sender
+    /// @param recipient The address receiving the tokens
+    /// @param amount0 The amount of token0 lent
+    /// @param amount1 The amount of token1 lent
+    /// @param data Arbitrary data passed to the callback
+    function flash(address recipient, uint256 amount0, uint256 amount1, bytes calldata data) external;
+}
@@ -68,12 +68,9 @@ async fn main() -> Result<()> {
     println!();
 
     // Add calls to the flashloan builder
-    builder.add_call(Call3 {
-        target: mainnet_dai,
-        call_data: Bytes::from(hex::decode("0x095ea7b3").unwrap()),
-        value: 0.into(),
-        allow_failure: false,
-    });
+    // Let the Uniswap V2 router pull the borrowed DAI
+    let router = UniswapV2Router::default();
+    builder.add_call(Erc20Token(mainnet_dai).approve(router.0, max_amount).unwrap());
 
     // Then execute
     println!("Executing flashloan...");
@@ -68,17 +68,29 @@ async fn main() -> Result<()> {
     println!();
 
     // Add calls to the flashloan builder
+    // Let the Uniswap V2 router pull the borrowed DAI
+    let router = UniswapV2Router::default();
+    builder.add_call(Erc20Token(mainnet_dai).approve(router.0, max_amount).unwrap());
     // TODO: Swap DAI for ETH via Uniswap
     // TODO: Swap ETH back for DAI via balancer
-    // TODO: ensure profit > fee + gas
-    builder.add_call(Call3 {
-        target: mainnet_dai,
-        call_data: Bytes::from(hex::decode("0x095ea7b3").unwrap()),
-        value: 0.into(),
-        allow_failure: false,
-    });
-
-    // TODO: Estimate profitability + gas with a call
+
+    // Never land a loan that doesn't pay for its fee and gas
+    // The borrower reverts on-chain if its DAI balance doesn't grow by the minimum profit
+    let eth_price = U256::exp10(18) * 1500;
+    builder
+        .with_profit_guard(ProfitGuard::new(mainnet_dai, U256::zero()).with_eth_price(eth_price));
+
+    // TODO: Quote the expected DAI gain of the swaps
+    let expected_gain = I256::zero();
+    match builder.check_profit(expected_gain).await {
+        Ok(check) => {
+            println!("Expected net profit: {} DAI", format_ether(check.net_profit.into_raw()))
+        }
+        Err(e) => {
+            println!("Skipping flashloan: {}", e);
+            return Ok(())
+        }
+    }
 
     // Then execute
     println!("Executing flashloan...");
@@ -0,0 +1,27 @@
+#!/usr/bin/env bash
+# Rebuilds the bundled Solidity artifacts in src/ from contracts/ with forge
+#
+# The crate embeds these artifacts, so run this after changing a Solidity contract, then check
+# them with the ignored tests:
+#
+#   cargo test --all-features --test artifacts --test harness --test nested --test operators \
+#       --test program -- --include-ignored
+set -euo pipefail
+cd "$(dirname "$0")/.."
+
+forge build
+
+contracts=(
+    FlashBorrower
+    AaveFlashBorrower
+    BalancerFlashBorrower
+    UniswapV3FlashBorrower
+    MultiFlashBorrower
+    ProgramFlashBorrower
+    OperatedFlashBorrower
+)
+
+for contract in "${contracts[@]}"; do
+    jq '{abi, bytecode, deployedBytecode, methodIdentifiers}' \
+        "out/$contract.sol/$contract.json" > "src/$contract.json"
+done
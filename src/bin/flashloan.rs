@@ -0,0 +1,23 @@
+//! The `flashloan` command-line tool
+
+use flashloan_rs::cli::{Cli, USAGE};
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return
+    }
+    let cli = match Cli::parse(args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = cli.run().await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
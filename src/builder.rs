@@ -1,8 +1,14 @@
 use anyhow::Result;
-use ethers::prelude::*;
-use std::{str::FromStr, sync::Arc};
+use ethers::{abi::Detokenize, contract::builders::ContractCall, prelude::*};
+use futures::future::try_join_all;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use tracing::{debug, field, info, instrument, warn, Span};
 
-use crate::{contract::*, errors::*};
+use crate::{
+    bundle::*, cache::*, contract::*, create2::*, errors::*, gas::*, huff::*, lender::*, nested::*,
+    operators::*, outcome::*, plan::*, profit::*, program::*, registry::*, revert::*, router::*,
+    spec::*, sweep::*, value::*,
+};
 
 /// FlashloanBuilder
 ///
@@ -34,6 +40,14 @@ pub struct FlashloanBuilder<M> {
     pub owner: Option<Address>,
     /// Optional Flash Lender Address
     pub lender: Option<Address>,
+    /// The Flash Lender protocol, selecting which borrower contract is deployed
+    pub lender_kind: LenderKind,
+    /// The trusted lender the deployed borrower borrows from instead of its deployed lender
+    pub borrow_from: Option<Address>,
+    /// The language of the deployed borrower contract
+    pub borrower_flavor: BorrowerFlavor,
+    /// Whether the deployed borrower has operators and a transferable owner
+    pub operated_borrower: bool,
     /// A Middleware Client
     pub client: Arc<M>,
     /// The token to borrow
@@ -42,8 +56,38 @@ pub struct FlashloanBuilder<M> {
     pub amount: Option<U256>,
     /// Associated calls
     pub calls: Vec<Call3>,
+    /// Legs of a nested flashloan, borrowed instead of the token and amount when set
+    pub legs: Vec<FlashloanLeg>,
+    /// Command program run instead of the calls when set
+    pub program: Option<Program>,
     /// The chain id
     pub chain_id: u64,
+    /// Decodes contract reverts into typed errors
+    pub revert_decoder: RevertDecoder,
+    /// How the ether value of the calls is funded
+    pub call_value: CallValue,
+    /// Optional WETH wrap around the calls
+    pub weth_wrap: Option<WethWrap>,
+    /// Optional minimum profit required for the flashloan
+    pub profit_guard: Option<ProfitGuard>,
+    /// Optional cache of flash fees, passing the repayment to the borrower when fresh
+    pub fee_cache: Option<FeeCache>,
+    /// Optional private relay to submit the flashloan transaction through
+    pub bundle_relay: Option<BundleRelay>,
+    /// How the fees of the flashloan transaction are set
+    pub fee_strategy: FeeStrategy,
+    /// Padding added to the gas estimate of the flashloan transaction, in basis points
+    pub gas_padding_bps: u64,
+    /// Known lenders by chain, used when no lender is set
+    pub registry: ChainRegistry,
+    /// Borrower contracts deployed by the builder, by lender address
+    pub borrowers: HashMap<Address, Address>,
+    /// The factory deterministic borrowers are deployed through
+    pub create2_factory: Address,
+    /// Optional sweeps run after each successful execution
+    pub auto_sweep: Option<AutoSweep>,
+    /// The sweeps run after the last execution
+    pub sweeps: Vec<Sweep>,
 }
 
 impl<M: Middleware> FlashloanBuilder<M> {
@@ -77,11 +121,31 @@ impl<M: Middleware> FlashloanBuilder<M> {
             borrower: override_contract.map(|contract| Flashloan::new(contract, client.clone())),
             owner,
             lender,
+            lender_kind: LenderKind::default(),
+            borrow_from: None,
+            borrower_flavor: BorrowerFlavor::default(),
+            operated_borrower: false,
             client: Arc::clone(&client),
             token,
             amount,
             calls: vec![],
+            legs: vec![],
+            program: None,
             chain_id,
+            revert_decoder: RevertDecoder::default(),
+            call_value: CallValue::default(),
+            weth_wrap: None,
+            profit_guard: None,
+            fee_cache: None,
+            bundle_relay: None,
+            fee_strategy: FeeStrategy::default(),
+            gas_padding_bps: 0,
+            registry: ChainRegistry::builtin(),
+            borrowers: HashMap::new(),
+            // This won't panic since the address is checked
+            create2_factory: Address::from_str(CREATE2_FACTORY).unwrap(),
+            auto_sweep: None,
+            sweeps: vec![],
         }
     }
 
@@ -98,67 +162,628 @@ impl<M: Middleware> FlashloanBuilder<M> {
         self
     }
 
-    /// Set the lender of the deployed borrower contract
+    /// Set the flash lender
     ///
     /// ### Usage
     ///
-    /// This should be set **before** the borrower contract is deployed
-    /// by the associated [deploy](FlashBuilder::deploy) method.
+    /// Set **before** the borrower contract is deployed by the associated
+    /// [deploy](FlashBuilder::deploy) method, this is the lender the borrower is deployed with.
+    ///
+    /// Set once a borrower is deployed or attached, flashloans borrow from this lender instead of
+    /// the one the borrower was deployed with. The borrower owner must first trust the lender with
+    /// [trust_lender](FlashloanBuilder::trust_lender), and only the ERC-3156 borrower can borrow
+    /// from another lender.
     ///
     /// Returns a mutable reference to the builder for method chaining.
     pub fn with_lender(&mut self, lender: Address) -> &mut Self {
         self.lender = Some(lender);
+        self.borrow_from = self.borrower.as_ref().map(|_| lender);
+        self
+    }
+
+    /// Set the flash lender protocol and address
+    ///
+    /// ### Usage
+    ///
+    /// The [LenderKind](crate::lender::LenderKind) of the lender selects which borrower contract
+    /// implements the lender callback. This should be set **before** the borrower contract is
+    /// deployed by the associated [deploy](FlashBuilder::deploy) method.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_flash_lender<L: Lender<M>>(&mut self, lender: L) -> &mut Self
+    where
+        M: 'static,
+    {
+        self.lender = Some(lender.address());
+        self.lender_kind = lender.kind();
+        self
+    }
+
+    /// Set the language of the deployed borrower contract
+    ///
+    /// ### Usage
+    ///
+    /// The [Huff](crate::huff::BorrowerFlavor::Huff) borrower costs less gas than the Solidity
+    /// one, but only implements the ERC-3156 callback of a single-token flashloan. This should be
+    /// set **before** the borrower contract is deployed by the associated
+    /// [deploy](FlashBuilder::deploy) method.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_borrower_flavor(&mut self, flavor: BorrowerFlavor) -> &mut Self {
+        self.borrower_flavor = flavor;
+        self
+    }
+
+    /// Deploy the borrower with operators and a transferable owner
+    ///
+    /// ### Usage
+    ///
+    /// The owner of the operated borrower can let other accounts trigger flashloans with
+    /// [add_operator](FlashloanBuilder::add_operator), and hand the borrower over with
+    /// [transfer_ownership](FlashloanBuilder::transfer_ownership). Operators are fully trusted, as
+    /// the calls of their flashloans can move any of the borrower's funds. Only the Solidity
+    /// ERC-3156 borrower has operators. This should be set **before** the borrower contract is
+    /// deployed by the associated [deploy](FlashBuilder::deploy) method.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_operated_borrower(&mut self, operated: bool) -> &mut Self {
+        self.operated_borrower = operated;
         self
     }
 
     /// Deploy a new flashloan borrower contract
+    ///
+    /// The borrower contract deployed implements the callback of the configured
+    /// [LenderKind](crate::lender::LenderKind).
+    ///
+    /// ### Errors
+    ///
+    /// Returns an [UnsupportedChain](FlashloanError::UnsupportedChain) if no lender is configured
+    /// and the registry has no lenders for the chain. Returns a
+    /// [MissingLender](FlashloanError::MissingLender) if the chain has no lender of the configured
+    /// kind. Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the borrower contract for
+    /// the lender kind has no bundled bytecode.
+    #[instrument(skip_all, fields(chain_id = self.chain_id, borrower, tx_hash))]
     pub async fn deploy(
         &mut self,
         lender: Option<Address>,
         owner: Option<Address>,
     ) -> Result<&mut Self> {
         // Unpack the flash lender
-        let deploy_lender = lender.unwrap_or_else(|| {
-            self.lender.unwrap_or_else(|| {
-                // If none configured, use the MakerDAO Flash Lender
-                // This won't panic since the address is checked
-                // See: https://github.com/makerdao/dss-flash#deployment
-                Address::from_str("0x1eb4cf3a948e7d72a198fe073ccb8c7a948cd853").unwrap()
-            })
-        });
-        println!("Deploying with lender: {:?}", deploy_lender);
+        let (deploy_lender, lender_kind) = self.resolve_lender(lender)?;
+        self.lender_kind = lender_kind;
+
+        // Unpack the owner, defaulting to the first account if none is configured
+        let deploy_owner = self.resolve_owner(owner).await?;
+
+        // All borrower variants share the flashloan abi
+        let bytecode = self.borrower_bytecode()?;
+        let factory =
+            ContractFactory::new(FLASHLOAN_ABI.clone(), bytecode, Arc::clone(&self.client));
+        let contract_deployer = factory
+            .deploy((deploy_lender, deploy_owner))
+            .map_err(|_| FlashloanError::ContractDeployError)?;
+        debug!("Sending borrower deployment");
+        let (contract, receipt) = contract_deployer
+            .send_with_receipt()
+            .await
+            .map_err(|_| FlashloanError::ContractDeployError)?;
+        record_deployment(contract.address(), receipt.transaction_hash);
+        self.attach(deploy_lender, contract.address());
+        Ok(self)
+    }
 
-        // Unpack the owner
-        let mut optional_owner = owner;
-        if optional_owner.is_none() {
-            optional_owner = self.owner;
+    /// Deploy a borrower contract through the CREATE2 factory
+    ///
+    /// ### Usage
+    ///
+    /// The borrower is deployed at [address_for](FlashloanBuilder::address_for) the owner and
+    /// lender, so it can be found again without storing its address. Deploying the same borrower
+    /// twice fails, use [deploy_or_attach](FlashloanBuilder::deploy_or_attach) to reuse it.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the same errors as [deploy](FlashloanBuilder::deploy). Returns a
+    /// [MissingCreate2Factory](FlashloanError::MissingCreate2Factory) if the factory has no code on
+    /// the chain, and a [ContractDeployFailed](FlashloanError::ContractDeployFailed) if the
+    /// borrower has no code after the deployment.
+    #[instrument(skip_all, fields(chain_id = self.chain_id, borrower, tx_hash))]
+    pub async fn deploy_create2(
+        &mut self,
+        lender: Option<Address>,
+        owner: Option<Address>,
+    ) -> Result<&mut Self> {
+        let (deploy_lender, lender_kind) = self.resolve_lender(lender)?;
+        self.lender_kind = lender_kind;
+        let deploy_owner = self.resolve_owner(owner).await?;
+        let address = self.address_for(deploy_owner, deploy_lender)?;
+        Span::current().record("borrower", field::debug(address));
+
+        if !self.has_code(self.create2_factory).await? {
+            return Err(FlashloanError::MissingCreate2Factory(self.create2_factory).into())
         }
-        if optional_owner.is_none() {
-            // If none configured, use the first account
-            let accounts = self
-                .client
-                .get_accounts()
-                .await
-                .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
-            let first_account = *accounts.get(0).ok_or(FlashloanError::MissingOwner)?;
-            optional_owner = Some(first_account);
+        let init_code = borrower_init_code(&self.borrower_bytecode()?, deploy_lender, deploy_owner);
+        let tx = create2_deploy_tx(
+            self.create2_factory,
+            borrower_salt(deploy_owner, deploy_lender),
+            &init_code,
+        );
+        debug!(factory = ?self.create2_factory, "Sending borrower deployment");
+        let receipt = self
+            .client
+            .send_transaction(tx, None)
+            .await
+            .map_err(|_| FlashloanError::ContractDeployError)?
+            .await
+            .map_err(|_| FlashloanError::ContractDeployFailed)?
+            .ok_or(FlashloanError::ContractDeployFailed)?;
+        if !self.has_code(address).await? {
+            return Err(FlashloanError::ContractDeployFailed.into())
         }
-        let deploy_owner = optional_owner.unwrap();
-        println!("Deploying with owner: {:?}", deploy_owner);
-
-        let contract_deployer =
-            Flashloan::deploy(Arc::clone(&self.client), (deploy_lender, deploy_owner))
-                .map_err(|_| FlashloanError::ContractDeployError)?;
-        self.borrower =
-            Some(contract_deployer.send().await.map_err(|_| FlashloanError::ContractDeployError)?);
+        record_deployment(address, receipt.transaction_hash);
+        self.attach(deploy_lender, address);
         Ok(self)
     }
 
+    /// Attach to the owner's borrower for the lender, deploying it through the CREATE2 factory if
+    /// it has no code yet
+    ///
+    /// Only borrowers deployed with the crate's current bundled bytecode are found, a borrower
+    /// deployed by a release with other bytecode is deployed again at a new address.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the same errors as [deploy_create2](FlashloanBuilder::deploy_create2).
+    pub async fn deploy_or_attach(
+        &mut self,
+        lender: Option<Address>,
+        owner: Option<Address>,
+    ) -> Result<&mut Self> {
+        let (deploy_lender, lender_kind) = self.resolve_lender(lender)?;
+        self.lender_kind = lender_kind;
+        let deploy_owner = self.resolve_owner(owner).await?;
+        let address = self.address_for(deploy_owner, deploy_lender)?;
+        if self.has_code(address).await? {
+            info!(chain_id = self.chain_id, borrower = ?address, "Attached deployed borrower");
+            self.attach(deploy_lender, address);
+            return Ok(self)
+        }
+        self.deploy_create2(Some(deploy_lender), Some(deploy_owner)).await
+    }
+
+    /// The address of the borrower of `owner` for `lender`, deployed through the CREATE2 factory
+    ///
+    /// The borrower contract is the one [deploy](FlashloanBuilder::deploy) would deploy for the
+    /// builder's lender kind, legs and program. The address depends on the crate's bundled
+    /// bytecode, see [create2_borrower_address].
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the borrower contract has no
+    /// bundled bytecode.
+    pub fn address_for(&self, owner: Address, lender: Address) -> Result<Address> {
+        let bytecode = self.borrower_bytecode()?;
+        Ok(create2_borrower_address(self.create2_factory, &bytecode, lender, owner))
+    }
+
+    /// Set the CREATE2 factory deterministic borrowers are deployed through
+    ///
+    /// The factory must take the salt followed by the creation code as its calldata, as the
+    /// [deterministic deployment proxy](CREATE2_FACTORY) does.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_create2_factory(&mut self, factory: Address) -> &mut Self {
+        self.create2_factory = factory;
+        self
+    }
+
+    /// The creation bytecode of the borrower contract for the lender kind, flavor, legs and
+    /// program
+    fn borrower_bytecode(&self) -> Result<Bytes> {
+        check_program(&self.legs, &self.program)?;
+        let nested = !self.legs.is_empty();
+        if self.borrower_flavor == BorrowerFlavor::Huff && (nested || self.program.is_some()) {
+            let name = if nested { MULTI_BORROWER_NAME } else { PROGRAM_BORROWER_NAME };
+            return Err(FlashloanError::UnsupportedFlavor(name.to_string()).into())
+        }
+        if self.operated_borrower {
+            return self.operated_borrower_bytecode(nested)
+        }
+        if nested {
+            multi_borrower_bytecode()
+        } else if self.program.is_some() {
+            program_borrower_bytecode()
+        } else {
+            self.borrower_flavor.borrower_bytecode(self.lender_kind)
+        }
+    }
+
+    /// The creation bytecode of the operated borrower, which only extends the ERC-3156 borrower
+    fn operated_borrower_bytecode(&self, nested: bool) -> Result<Bytes> {
+        let unsupported = if nested {
+            Some(MULTI_BORROWER_NAME)
+        } else if self.program.is_some() {
+            Some(PROGRAM_BORROWER_NAME)
+        } else if self.lender_kind != LenderKind::Erc3156 {
+            Some(self.lender_kind.borrower_name())
+        } else {
+            None
+        };
+        if let Some(name) = unsupported {
+            return Err(FlashloanError::UnsupportedOperators(name.to_string()).into())
+        }
+        if self.borrower_flavor == BorrowerFlavor::Huff {
+            return Err(FlashloanError::UnsupportedFlavor(OPERATED_BORROWER_NAME.to_string()).into())
+        }
+        operated_borrower_bytecode()
+    }
+
+    /// Resolves the borrower owner, defaulting to the configured owner then the first account
+    async fn resolve_owner(&self, owner: Option<Address>) -> Result<Address> {
+        if let Some(owner) = owner.or(self.owner) {
+            debug!(owner = ?owner, "Resolved owner");
+            return Ok(owner)
+        }
+        let accounts = self
+            .client
+            .get_accounts()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        let owner = *accounts.first().ok_or(FlashloanError::MissingOwner)?;
+        debug!(owner = ?owner, "Resolved owner from the client's accounts");
+        Ok(owner)
+    }
+
+    /// Point the builder at a borrower for `lender`
+    fn attach(&mut self, lender: Address, borrower: Address) {
+        self.borrower = Some(Flashloan::new(borrower, Arc::clone(&self.client)));
+        self.borrowers.insert(lender, borrower);
+        self.borrow_from = None;
+    }
+
+    async fn has_code(&self, address: Address) -> Result<bool> {
+        let code = self
+            .client
+            .get_code(address, None)
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        Ok(!code.is_empty())
+    }
+
+    /// The token and amount to borrow, defaulting to the first leg's
+    pub(crate) fn loan(&self) -> Result<(Address, U256)> {
+        let first_leg = self.legs.first();
+        let token = self
+            .token
+            .or_else(|| first_leg.map(|leg| leg.token))
+            .ok_or(FlashloanError::MissingToken)?;
+        let amount = self
+            .amount
+            .or_else(|| first_leg.map(|leg| leg.amount))
+            .ok_or(FlashloanError::MissingAmount)?;
+        Ok((token, amount))
+    }
+
+    /// Resolves the flash lender address and protocol, preferring the given override
+    ///
+    /// Falls back to the registry's default lender for the chain.
+    pub(crate) fn resolve_lender(&self, lender: Option<Address>) -> Result<(Address, LenderKind)> {
+        match lender.or(self.lender) {
+            Some(lender) => {
+                debug!(lender = ?lender, kind = ?self.lender_kind, "Resolved lender");
+                Ok((lender, self.lender_kind))
+            }
+            None => {
+                let config = self.registry.default_lender(self.chain_id, self.lender_kind)?;
+                debug!(
+                    lender = ?config.address,
+                    kind = ?config.kind,
+                    name = %config.name,
+                    "Resolved lender from the registry"
+                );
+                Ok((config.address, config.kind))
+            }
+        }
+    }
+
+    /// The ERC-3156 lender whose cached fee repays the loan, or `None` for the on-chain path
+    fn fee_cache_lender(&self) -> Option<Address> {
+        let precomputable = self.legs.is_empty()
+            && self.program.is_none()
+            && self.profit_guard.is_none()
+            && self.fee_cache.is_some();
+        if !precomputable {
+            return None
+        }
+        match self.resolve_lender(None).ok()? {
+            (lender, LenderKind::Erc3156) => Some(lender),
+            _ => None,
+        }
+    }
+
+    /// The repayment of the loan from a fresh cached fee, without querying or updating the cache
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [RepaymentOverflow](FlashloanError::RepaymentOverflow) if the amount plus the
+    /// cached fee overflows.
+    #[cfg(feature = "simulate")]
+    pub(crate) fn fresh_repayment(&self, token: Address, amount: U256) -> Result<Option<U256>> {
+        let (lender, cache) = match (self.fee_cache_lender(), self.fee_cache.as_ref()) {
+            (Some(lender), Some(cache)) => (lender, cache),
+            _ => return Ok(None),
+        };
+        match cache.lookup(lender, token, amount) {
+            FeeLookup::Fresh(fee) => repayment(amount, fee).map(Some),
+            FeeLookup::Stale | FeeLookup::Missing => Ok(None),
+        }
+    }
+
+    /// [**Async**] The repayment of the loan from the fee cache, or `None` for the on-chain path
+    ///
+    /// A missing fee is queried from the lender and cached. A stale fee is dropped, so the next
+    /// loan queries it again.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [RepaymentOverflow](FlashloanError::RepaymentOverflow) if the amount plus the fee
+    /// overflows.
+    async fn cached_repayment(&mut self, token: Address, amount: U256) -> Result<Option<U256>> {
+        let lender = match self.fee_cache_lender() {
+            Some(lender) => lender,
+            None => return Ok(None),
+        };
+        let cache = match self.fee_cache.as_mut() {
+            Some(cache) => cache,
+            None => return Ok(None),
+        };
+        let fee = match cache.lookup(lender, token, amount) {
+            FeeLookup::Fresh(fee) => fee,
+            FeeLookup::Stale => {
+                debug!(lender = ?lender, token = ?token, "Cached flash fee is stale");
+                cache.remove(lender, token, amount);
+                return Ok(None)
+            }
+            FeeLookup::Missing => {
+                let query = IERC3156FlashLender::new(lender, Arc::clone(&self.client))
+                    .flash_fee(token, amount)
+                    .call()
+                    .await;
+                match query {
+                    Ok(fee) => {
+                        debug!(lender = ?lender, token = ?token, fee = %fee, "Cached flash fee");
+                        cache.insert(lender, token, amount, fee);
+                        fee
+                    }
+                    Err(e) => {
+                        warn!(lender = ?lender, error = %e, "Flash fee query failed");
+                        return Ok(None)
+                    }
+                }
+            }
+        };
+        repayment(amount, fee).map(Some)
+    }
+
+    /// Set how the ether value of the calls is funded
+    ///
+    /// ### Usage
+    ///
+    /// The borrower's flashloan entrypoints are payable. By default the summed value of the calls
+    /// is sent with the flashloan transaction. With [Borrower](CallValue::Borrower) the calls
+    /// spend ether parked in the borrower instead, and [call](FlashloanBuilder::call) and
+    /// [execute](FlashloanBuilder::execute) fail with
+    /// [InsufficientEth](FlashloanError::InsufficientEth) if its balance doesn't cover them.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_call_value(&mut self, call_value: CallValue) -> &mut Self {
+        self.call_value = call_value;
+        self
+    }
+
+    /// Wrap ether into WETH before the calls and unwrap WETH after them
+    ///
+    /// ### Usage
+    ///
+    /// Once set, the calls are preceded by a WETH `deposit` of the wrapped ether and followed by
+    /// a `withdraw` of the unwrapped WETH. The deposit's value is part of the value of the calls.
+    /// A [program](FlashloanBuilder::with_program) doesn't run the calls, so planning a program
+    /// with a WETH wrap returns a [CallConstructionError](FlashloanError::CallConstructionError).
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_weth_wrap(&mut self, weth_wrap: WethWrap) -> &mut Self {
+        self.weth_wrap = Some(weth_wrap);
+        self
+    }
+
+    /// Require the flashloan to return a minimum profit
+    ///
+    /// ### Usage
+    ///
+    /// Once set, the flashloan is executed through the borrower's `flashBorrowWithProfit`, which
+    /// reverts with [InsufficientProfit](FlashloanError::InsufficientProfit) if the borrower's
+    /// profit token balance did not grow by the minimum profit. Use
+    /// [check_profit](FlashloanBuilder::check_profit) to also check the expected profit covers
+    /// the flash fee and gas before sending the transaction.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_profit_guard(&mut self, guard: ProfitGuard) -> &mut Self {
+        self.profit_guard = Some(guard);
+        self
+    }
+
+    /// Cache flash fees to precompute the repayment of ERC-3156 flashloans
+    ///
+    /// ### Usage
+    ///
+    /// Once set, [call](FlashloanBuilder::call) and [execute](FlashloanBuilder::execute) query the
+    /// lender's `flashFee` once per lender, token and amount, or amount bucket for lenders with
+    /// [proportional fees](FeeCache::with_proportional_fees), and pass the repayment to the
+    /// borrower's `flashBorrowWithRepayment`, skipping its on-chain fee and allowance queries.
+    ///
+    /// A fee older than the cache's maximum age is dropped, and that flashloan falls back to the
+    /// on-chain path of `flashBorrow`. Flashloans with a profit guard, legs or a program always
+    /// take their own entrypoints.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_fee_cache(&mut self, cache: FeeCache) -> &mut Self {
+        self.fee_cache = Some(cache);
+        self
+    }
+
+    /// Submit the flashloan transaction through a private bundle relay
+    ///
+    /// ### Usage
+    ///
+    /// Once set, [execute](FlashloanBuilder::execute) signs the `flashBorrow` transaction with the
+    /// client and lands it through the relay instead of the public mempool. The client must be
+    /// able to sign transactions, e.g. a [SignerMiddleware].
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_bundle_relay(&mut self, relay: BundleRelay) -> &mut Self {
+        self.bundle_relay = Some(relay);
+        self
+    }
+
+    /// Set the fees of the flashloan transaction with a [FeeStrategy]
+    ///
+    /// ### Usage
+    ///
+    /// With a strategy other than [Provider](FeeStrategy::Provider), the `flashBorrow` transaction
+    /// is sent with the strategy's fees and the padded gas estimate as its gas limit.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_fee_strategy(&mut self, strategy: FeeStrategy) -> &mut Self {
+        self.fee_strategy = strategy;
+        self
+    }
+
+    /// Pad the gas limit of the flashloan transaction by `padding_bps` basis points
+    ///
+    /// ### Usage
+    ///
+    /// The gas used by the calls can change between the estimate and inclusion, so a padding of
+    /// 1000 bps (10%) or so keeps the transaction from running out of gas.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_gas_padding(&mut self, padding_bps: u64) -> &mut Self {
+        self.gas_padding_bps = padding_bps;
+        self
+    }
+
+    /// Sweep the borrower's profits after each successful [execute](FlashloanBuilder::execute)
+    ///
+    /// ### Usage
+    ///
+    /// The sweeps of the last execution are kept in [sweeps](FlashloanBuilder::sweeps).
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_auto_sweep(&mut self, sweep: AutoSweep) -> &mut Self {
+        self.auto_sweep = Some(sweep);
+        self
+    }
+
+    /// Set the registry of known lenders
+    ///
+    /// ### Usage
+    ///
+    /// When no lender is set, [deploy](FlashloanBuilder::deploy) uses the registry's default
+    /// lender for the chain. The [builtin](ChainRegistry::builtin) registry is used by default,
+    /// [merge](ChainRegistry::merge) a custom registry over it to add chains or lenders.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_registry(&mut self, registry: ChainRegistry) -> &mut Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Register the custom errors of a lender or call target abi
+    ///
+    /// Reverts with these errors are decoded into a
+    /// [CustomError](FlashloanError::CustomError).
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_error_abi(&mut self, abi: &ethers::abi::Abi) -> &mut Self {
+        self.revert_decoder.register_abi(abi);
+        self
+    }
+
+    /// Use an already deployed flashloan borrower contract
+    ///
+    /// The new borrower may not trust the lender set with
+    /// [with_lender](FlashloanBuilder::with_lender) for the previous one, so flashloans borrow from
+    /// the lender it was deployed with until [with_lender](FlashloanBuilder::with_lender) is called
+    /// again.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    pub fn with_borrower(&mut self, borrower: Address) -> &mut Self {
+        self.borrower = Some(Flashloan::new(borrower, Arc::clone(&self.client)));
+        self.borrow_from = None;
+        self
+    }
+
     /// Access the inner client
     pub fn inner(&self) -> Arc<M> {
         Arc::clone(&self.client)
     }
 
+    /// Load a [FlashloanSpec] into the builder
+    ///
+    /// ### Usage
+    ///
+    /// The spec's calls, legs, lender kind and gas padding replace the builder's. Its other
+    /// parameters replace the builder's when set, and leave them as they are otherwise.
+    ///
+    /// Returns a mutable reference to the builder for method chaining.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [PlanFormatError](FlashloanError::PlanFormatError) if the spec is for another
+    /// chain or one of its calls can't be encoded. The builder is left unchanged on error.
+    pub fn with_spec(&mut self, spec: &FlashloanSpec) -> Result<&mut Self> {
+        if let Some(chain_id) = spec.chain_id.filter(|chain_id| *chain_id != self.chain_id) {
+            return Err(FlashloanError::PlanFormatError(format!(
+                "The plan is for chain {}, not {}",
+                chain_id, self.chain_id
+            ))
+            .into());
+        }
+        let calls = spec.encode_calls()?;
+
+        if let Some(borrower) = spec.borrower {
+            self.with_borrower(borrower);
+        }
+        self.owner = spec.owner.or(self.owner);
+        self.lender = spec.lender.or(self.lender);
+        self.lender_kind = spec.lender_kind;
+        self.token = spec.token.or(self.token);
+        self.amount = spec.amount.or(self.amount);
+        self.calls = calls;
+        self.legs = spec.legs.clone();
+        self.profit_guard = spec.profit_guard.or(self.profit_guard);
+        self.gas_padding_bps = spec.gas_padding_bps;
+        Ok(self)
+    }
+
+    /// Snapshot the builder's parameters as a [FlashloanSpec]
+    ///
+    /// Calls are saved as raw calldata.
+    pub fn to_spec(&self) -> FlashloanSpec {
+        FlashloanSpec {
+            chain_id: Some(self.chain_id),
+            borrower: self.borrower.as_ref().map(|borrower| borrower.address()),
+            owner: self.owner,
+            lender: self.lender,
+            lender_kind: self.lender_kind,
+            token: self.token,
+            amount: self.amount,
+            calls: self.calls.iter().map(CallSpec::raw).collect(),
+            legs: self.legs.clone(),
+            profit_guard: self.profit_guard,
+            gas_padding_bps: self.gas_padding_bps,
+        }
+    }
+
     /// Appends a Call3 to the builder
     /// Returns a reference to the builder for method chaining
     pub fn add_call(&mut self, call: Call3) -> &mut Self {
@@ -166,6 +791,34 @@ impl<M: Middleware> FlashloanBuilder<M> {
         self
     }
 
+    /// Appends a leg to borrow in a nested flashloan
+    ///
+    /// ### Usage
+    ///
+    /// With legs, [deploy](FlashloanBuilder::deploy) deploys a `MultiFlashBorrower` implementing
+    /// every lender callback, and the flashloan borrows each leg in order instead of the
+    /// configured token and amount. The calls run once every leg is borrowed.
+    ///
+    /// Returns a reference to the builder for method chaining
+    pub fn add_leg(&mut self, leg: FlashloanLeg) -> &mut Self {
+        self.legs.push(leg);
+        self
+    }
+
+    /// Run a command program in the lender callback instead of the calls
+    ///
+    /// ### Usage
+    ///
+    /// Plan the program with a [CommandPlanner] to pipe return values between calls. With a
+    /// program, [deploy](FlashloanBuilder::deploy) deploys a `ProgramFlashBorrower`, which
+    /// borrows from an ERC-3156 lender. Programs can't be combined with nested legs.
+    ///
+    /// Returns a reference to the builder for method chaining
+    pub fn with_program(&mut self, program: Program) -> &mut Self {
+        self.program = Some(program);
+        self
+    }
+
     /// Specify the token address to borrow
     /// Returns a reference to the builder for method chaining
     pub fn with_token(&mut self, token: Address) -> &mut Self {
@@ -182,8 +835,8 @@ impl<M: Middleware> FlashloanBuilder<M> {
 
     /// [**Async**] Call the flashloan function on the borrower contract
     ///
-    /// Returns an empty result if successful since the flashloan function should have no return
-    /// value.
+    /// Returns the [Call3Result] of each call if successful, in order. Calls that allow failure
+    /// report whether they succeeded, and quoted outputs can be decoded from their return data.
     ///
     /// ### Errors
     ///
@@ -191,12 +844,17 @@ impl<M: Middleware> FlashloanBuilder<M> {
     /// not specified. Returns a [MissingAmount](FlashloanError::MissingAmount) if the amount to
     /// borrow is not specified. Returns a [MissingBorrower](FlashloanError::MissingBorrower) if
     /// the borrower contract is not specified.
-    /// Returns a [ContractError](FlashloanError::ContractError) if the call errors with a string
-    /// error message.
-    pub async fn call(&mut self) -> Result<()> {
+    /// Reverts are decoded by the builder's [RevertDecoder], returning
+    /// [Unauthorized](FlashloanError::Unauthorized),
+    /// [CallFailed](FlashloanError::CallFailed), [Revert](FlashloanError::Revert) and the like.
+    /// Returns a [ContractError](FlashloanError::ContractError) if the call errors without revert
+    /// data.
+    pub async fn call(&mut self) -> Result<Vec<Call3Result>>
+    where
+        M::Error: 'static,
+    {
         // Deconstruct the flash borrow parameters
-        let token = self.token.ok_or(FlashloanError::MissingToken)?;
-        let amount = self.amount.ok_or(FlashloanError::MissingAmount)?;
+        let (token, amount) = self.loan()?;
         self.inner_call(token, amount, &self.calls.clone()).await
     }
 
@@ -213,14 +871,12 @@ impl<M: Middleware> FlashloanBuilder<M> {
         token: Address,
         amount: U256,
         calls: &[Call3],
-    ) -> Result<()> {
-        let contract = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
-        contract
-            .flash_borrow(token, amount, calls.to_vec())
-            .call()
-            .await
-            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
-        Ok(())
+    ) -> Result<Vec<Call3Result>>
+    where
+        M::Error: 'static,
+    {
+        let repayment = self.cached_repayment(token, amount).await?;
+        self.plan_for(token, amount, calls)?.with_repayment(repayment).call().await
     }
 
     /// [**Async**] Execute the flashloan function on the borrower contract
@@ -233,12 +889,19 @@ impl<M: Middleware> FlashloanBuilder<M> {
     /// not specified. Returns a [MissingAmount](FlashloanError::MissingAmount) if the amount to
     /// borrow is not specified. Returns a [MissingBorrower](FlashloanError::MissingBorrower) if
     /// the borrower contract is not specified.
-    /// Returns a [ContractError](FlashloanError::ContractError) if the call errors with a string
-    /// error message.
-    pub async fn execute(&mut self) -> Result<Option<TransactionReceipt>> {
+    /// Reverts are decoded by the builder's [RevertDecoder] as in [call](FlashloanBuilder::call).
+    /// Returns a [ContractError](FlashloanError::ContractError) if the transaction errors without
+    /// revert data.
+    /// With a bundle relay, returns the errors of [submit](BundleRelay::submit).
+    /// With an [auto sweep](FlashloanBuilder::with_auto_sweep), returns the errors of
+    /// [sweep_all](FlashloanBuilder::sweep_all) and [sweep_eth](FlashloanBuilder::sweep_eth)
+    /// after the flashloan is executed.
+    pub async fn execute(&mut self) -> Result<Option<TransactionReceipt>>
+    where
+        M::Error: 'static,
+    {
         // Deconstruct the flash borrow parameters
-        let token = self.token.ok_or(FlashloanError::MissingToken)?;
-        let amount = self.amount.ok_or(FlashloanError::MissingAmount)?;
+        let (token, amount) = self.loan()?;
         self.inner_execute(token, amount, &self.calls.clone()).await
     }
 
@@ -255,16 +918,515 @@ impl<M: Middleware> FlashloanBuilder<M> {
         token: Address,
         amount: U256,
         calls: &[Call3],
-    ) -> Result<Option<TransactionReceipt>> {
-        let contract = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
-        let contract_call = contract.flash_borrow(token, amount, calls.to_vec());
-        let pending_transaction = contract_call
-            .send()
+    ) -> Result<Option<TransactionReceipt>>
+    where
+        M::Error: 'static,
+    {
+        self.sweeps.clear();
+        let repayment = self.cached_repayment(token, amount).await?;
+        let plan = self.plan_for(token, amount, calls)?.with_repayment(repayment);
+        let receipt = plan.execute().await?;
+        let succeeded = receipt.as_ref().map_or(false, |receipt| receipt.status == Some(1.into()));
+        if let (Some(auto_sweep), true) = (self.auto_sweep.clone(), succeeded) {
+            let mut tokens = auto_sweep.tokens;
+            if tokens.is_empty() {
+                tokens.extend(self.token);
+                tokens.extend(self.legs.iter().map(|leg| leg.token));
+                tokens.extend(self.profit_guard.map(|guard| guard.token));
+            }
+            let mut sweeps = self.sweep_all(&tokens, auto_sweep.to).await?;
+            if auto_sweep.eth {
+                sweeps.extend(self.sweep_eth(auto_sweep.to).await?);
+            }
+            self.sweeps = sweeps;
+        }
+        Ok(receipt)
+    }
+
+    /// Decode the outcome of an executed flashloan from its receipt
+    ///
+    /// ### Usage
+    ///
+    /// Reports the lender fees, the borrower's token transfers and net balance changes, and the
+    /// gas cost. With a profit guard, the realized profit is quoted in the profit token at the
+    /// guard's ether price.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified, and the errors of [from_receipt](FlashloanOutcome::from_receipt).
+    pub fn outcome(&self, receipt: &TransactionReceipt) -> Result<FlashloanOutcome> {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?.address();
+        let outcome = FlashloanOutcome::from_receipt(receipt, borrower)?;
+        Ok(match self.profit_guard {
+            Some(guard) => outcome.with_quote(guard.token, guard.eth_price),
+            None => outcome,
+        })
+    }
+
+    /// [**Async**] Withdraw the borrower's whole balance of `token`
+    ///
+    /// ### Usage
+    ///
+    /// The recipient defaults to the borrower owner. Returns `None` without sending a transaction
+    /// if the borrower holds none of the token.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified. Reverts are decoded by the builder's [RevertDecoder], returning
+    /// [Unauthorized](FlashloanError::Unauthorized) if the client isn't the borrower owner.
+    /// Returns a [ContractError](FlashloanError::ContractError) if the withdrawal errors without
+    /// revert data or is dropped.
+    pub async fn sweep_token(&self, token: Address, to: Option<Address>) -> Result<Option<Sweep>>
+    where
+        M::Error: 'static,
+    {
+        Ok(self.sweep_all(&[token], to).await?.into_iter().next())
+    }
+
+    /// [**Async**] Withdraw the borrower's whole balance of each of `tokens`
+    ///
+    /// ### Usage
+    ///
+    /// Balances are queried in parallel and tokens the borrower doesn't hold are skipped. The
+    /// withdrawals are then sent one after the other, so the client assigns them consecutive
+    /// nonces. Returns a [Sweep] for each withdrawal, with the amount moved decoded from its
+    /// `Transfer` events.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [sweep_token](FlashloanBuilder::sweep_token). Withdrawals sent before
+    /// an error are not rolled back.
+    pub async fn sweep_all(&self, tokens: &[Address], to: Option<Address>) -> Result<Vec<Sweep>>
+    where
+        M::Error: 'static,
+    {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
+        let mut unique: Vec<Address> = vec![];
+        for token in tokens {
+            if !unique.contains(token) {
+                unique.push(*token);
+            }
+        }
+        let balance_calls: Vec<_> = unique
+            .iter()
+            .map(|token| {
+                IERC20::new(*token, Arc::clone(&self.client)).balance_of(borrower.address())
+            })
+            .collect();
+        let balances = try_join_all(balance_calls.iter().map(|call| call.call()))
             .await
-            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
-        let optional_receipt = pending_transaction
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        if balances.iter().all(U256::is_zero) {
+            return Ok(vec![])
+        }
+
+        let to = self.resolve_owner(to).await?;
+        let mut sweeps = vec![];
+        for (token, _) in unique.into_iter().zip(balances).filter(|(_, balance)| !balance.is_zero())
+        {
+            let receipt = self
+                .send_borrower_tx(borrower.withdraw_token(token, to, U256::zero(), true))
+                .await?;
+            let amount = transferred_from(&receipt, token, borrower.address());
+            info!(
+                borrower = ?borrower.address(),
+                token = ?token,
+                amount = %amount,
+                tx_hash = ?receipt.transaction_hash,
+                "Swept token"
+            );
+            sweeps.push(Sweep {
+                token: Some(token),
+                to,
+                amount,
+                transaction_hash: receipt.transaction_hash,
+            });
+        }
+        Ok(sweeps)
+    }
+
+    /// [**Async**] Withdraw the borrower's ether
+    ///
+    /// Returns `None` without sending a transaction if the borrower has no ether.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [sweep_token](FlashloanBuilder::sweep_token), and a
+    /// [ClientFailure](FlashloanError::ClientFailure) if the balance can't be queried.
+    pub async fn sweep_eth(&self, to: Option<Address>) -> Result<Option<Sweep>>
+    where
+        M::Error: 'static,
+    {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
+        let balance = self
+            .client
+            .get_balance(borrower.address(), None)
             .await
-            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
-        Ok(optional_receipt)
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        if balance.is_zero() {
+            return Ok(None)
+        }
+        let to = self.resolve_owner(to).await?;
+        let receipt = self.send_borrower_tx(borrower.withdraw_eth(to)).await?;
+        info!(
+            borrower = ?borrower.address(),
+            amount = %balance,
+            tx_hash = ?receipt.transaction_hash,
+            "Swept ether"
+        );
+        Ok(Some(Sweep {
+            token: None,
+            to,
+            amount: balance,
+            transaction_hash: receipt.transaction_hash,
+        }))
+    }
+
+    /// [**Async**] Trust `lender` to be borrowed from by the borrower and to call it back
+    ///
+    /// ### Usage
+    ///
+    /// The borrower trusts the lender it was deployed with. Once another lender is trusted, set it
+    /// with [with_lender](FlashloanBuilder::with_lender) to borrow from it without redeploying
+    /// the borrower. The client must be the borrower owner.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified. Reverts are decoded by the builder's [RevertDecoder], returning
+    /// [Unauthorized](FlashloanError::Unauthorized) if the client isn't the borrower owner.
+    /// Returns a [ContractError](FlashloanError::ContractError) if the transaction errors without
+    /// revert data or is dropped, as it does if the borrower has a fixed lender.
+    pub async fn trust_lender(&self, lender: Address) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
+        let receipt = self.send_borrower_tx(borrower.trust_lender(lender)).await?;
+        info!(lender = ?lender, tx_hash = ?receipt.transaction_hash, "Trusted lender");
+        Ok(receipt)
     }
+
+    /// [**Async**] Stop trusting a lender, including the one the borrower was deployed with
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [trust_lender](FlashloanBuilder::trust_lender).
+    pub async fn untrust_lender(&self, lender: Address) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
+        let receipt = self.send_borrower_tx(borrower.untrust_lender(lender)).await?;
+        info!(lender = ?lender, tx_hash = ?receipt.transaction_hash, "Untrusted lender");
+        Ok(receipt)
+    }
+
+    /// [**Async**] Whether the borrower trusts `lender`
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified, and a [ContractError](FlashloanError::ContractError) if the query fails.
+    pub async fn is_trusted_lender(&self, lender: Address) -> Result<bool>
+    where
+        M::Error: 'static,
+    {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
+        Ok(borrower
+            .trusted_lenders(lender)
+            .call()
+            .await
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?)
+    }
+
+    /// [**Async**] Allow `operator` to trigger flashloans through the operated borrower
+    ///
+    /// ### Usage
+    ///
+    /// Operators can call `flashBorrow` and its variants, but can't manage operators or transfer
+    /// ownership. Only add accounts trusted with the borrower's funds: the calls of a flashloan run
+    /// as the borrower, so an operator can use them to transfer its tokens and ether anywhere. The
+    /// client must be the borrower owner.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified. Reverts are decoded by the builder's [RevertDecoder], returning
+    /// [Unauthorized](FlashloanError::Unauthorized) if the client isn't the borrower owner.
+    /// Returns a [ContractError](FlashloanError::ContractError) if the transaction errors without
+    /// revert data or is dropped, as it does if the borrower has no operators.
+    pub async fn add_operator(&self, operator: Address) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let receipt =
+            self.send_borrower_tx(self.operated_borrower()?.add_operator(operator)).await?;
+        info!(operator = ?operator, tx_hash = ?receipt.transaction_hash, "Added operator");
+        Ok(receipt)
+    }
+
+    /// [**Async**] Revoke an operator of the operated borrower
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [add_operator](FlashloanBuilder::add_operator).
+    pub async fn remove_operator(&self, operator: Address) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let call = self.operated_borrower()?.remove_operator(operator);
+        let receipt = self.send_borrower_tx(call).await?;
+        info!(operator = ?operator, tx_hash = ?receipt.transaction_hash, "Removed operator");
+        Ok(receipt)
+    }
+
+    /// [**Async**] Whether `account` can trigger flashloans through the operated borrower
+    ///
+    /// The owner is not an operator, but can always trigger flashloans.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified, and a [ContractError](FlashloanError::ContractError) if the query fails.
+    pub async fn is_operator(&self, account: Address) -> Result<bool>
+    where
+        M::Error: 'static,
+    {
+        let call = self.operated_borrower()?.is_operator(account);
+        Ok(call.call().await.map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?)
+    }
+
+    /// [**Async**] Offer the ownership of the operated borrower to `new_owner`
+    ///
+    /// ### Usage
+    ///
+    /// Ownership only moves once the new owner calls
+    /// [accept_ownership](FlashloanBuilder::accept_ownership), so a mistyped address can't lock the
+    /// borrower. Offering ownership to the zero address cancels a pending transfer.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [add_operator](FlashloanBuilder::add_operator).
+    pub async fn transfer_ownership(&self, new_owner: Address) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let call = self.operated_borrower()?.transfer_ownership(new_owner);
+        let receipt = self.send_borrower_tx(call).await?;
+        info!(
+            new_owner = ?new_owner,
+            tx_hash = ?receipt.transaction_hash,
+            "Started borrower ownership transfer"
+        );
+        Ok(receipt)
+    }
+
+    /// [**Async**] Accept the ownership offered to the client by
+    /// [transfer_ownership](FlashloanBuilder::transfer_ownership)
+    ///
+    /// The builder's owner is set to the new owner, so later sweeps default to it.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [add_operator](FlashloanBuilder::add_operator), returning
+    /// [Unauthorized](FlashloanError::Unauthorized) if the client isn't the pending owner.
+    pub async fn accept_ownership(&mut self) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let borrower = self.operated_borrower()?;
+        let new_owner = borrower
+            .pending_owner()
+            .call()
+            .await
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        let receipt = self.send_borrower_tx(borrower.accept_ownership()).await?;
+        info!(owner = ?new_owner, tx_hash = ?receipt.transaction_hash, "Accepted borrower ownership");
+        self.owner = Some(new_owner);
+        Ok(receipt)
+    }
+
+    /// The operator and ownership bindings of the borrower
+    fn operated_borrower(&self) -> Result<IOperatedFlashBorrower<M>> {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?.address();
+        Ok(IOperatedFlashBorrower::new(borrower, Arc::clone(&self.client)))
+    }
+
+    async fn send_borrower_tx<D: Detokenize>(
+        &self,
+        call: ContractCall<M, D>,
+    ) -> Result<TransactionReceipt>
+    where
+        M::Error: 'static,
+    {
+        let pending_transaction =
+            call.send().await.map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        let receipt = pending_transaction
+            .await
+            .map_err(|e| FlashloanError::ContractError(e.to_string()))?
+            .ok_or_else(|| {
+                FlashloanError::ContractError("The borrower transaction was dropped".to_string())
+            })?;
+        Ok(receipt)
+    }
+
+    /// [**Async**] Query the flash fee charged by the lender for the configured loan
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingToken](FlashloanError::MissingToken) or
+    /// [MissingAmount](FlashloanError::MissingAmount) if the respective parameter is not
+    /// specified. Returns the same lender errors as [deploy](FlashloanBuilder::deploy) if no lender
+    /// is configured.
+    pub async fn flash_fee(&self) -> Result<U256>
+    where
+        M: 'static,
+    {
+        let token = self.token.ok_or(FlashloanError::MissingToken)?;
+        let amount = self.amount.ok_or(FlashloanError::MissingAmount)?;
+        let (lender, lender_kind) = self.resolve_lender(None)?;
+        let lender = lender_kind.lender::<M>(lender);
+        lender.flash_fee(Arc::clone(&self.client), token, amount).await
+    }
+
+    /// [**Async**] Borrow from the cheapest lender able to lend the configured loan
+    ///
+    /// ### Usage
+    ///
+    /// The lender is selected by the [LenderRouter], and the builder is pointed at a borrower for
+    /// it. A borrower previously deployed by the builder for the lender is reused, otherwise a new
+    /// borrower is [deployed](FlashloanBuilder::deploy).
+    ///
+    /// Returns the selected lender's quote.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingToken](FlashloanError::MissingToken) or
+    /// [MissingAmount](FlashloanError::MissingAmount) if the respective parameter is not
+    /// specified. Returns an [InsufficientLiquidity](FlashloanError::InsufficientLiquidity) if no
+    /// lender can lend the amount, or the same errors as [deploy](FlashloanBuilder::deploy).
+    pub async fn auto_select_lender(&mut self, router: &LenderRouter<M>) -> Result<LenderQuote>
+    where
+        M: 'static,
+    {
+        let token = self.token.ok_or(FlashloanError::MissingToken)?;
+        let amount = self.amount.ok_or(FlashloanError::MissingAmount)?;
+        let quote = router.select(token, amount).await?;
+        self.lender = Some(quote.lender);
+        self.lender_kind = quote.kind;
+        match self.borrowers.get(&quote.lender) {
+            Some(borrower) => {
+                let borrower = *borrower;
+                self.with_borrower(borrower);
+            }
+            None => {
+                self.deploy(None, None).await?;
+            }
+        }
+        Ok(quote)
+    }
+
+    /// [**Async**] Estimate the gas limit of the flashloan transaction, including the gas padding
+    ///
+    /// ### Errors
+    ///
+    /// Returns the same errors as [call](FlashloanBuilder::call) if the gas estimation reverts.
+    pub async fn estimate_gas(&self) -> Result<U256>
+    where
+        M::Error: 'static,
+    {
+        self.plan()?.estimate_gas().await
+    }
+
+    /// [**Async**] Estimate the gas cost of executing the flashloan in wei
+    ///
+    /// The gas estimate is priced at the client's current gas price.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the same errors as [call](FlashloanBuilder::call) if the gas estimation reverts.
+    pub async fn estimate_gas_cost(&self) -> Result<U256>
+    where
+        M::Error: 'static,
+    {
+        self.plan()?.estimate_gas_cost().await
+    }
+
+    /// [**Async**] Check the expected gain of the flashloan against its costs
+    ///
+    /// The flash fee and gas cost are queried and checked against `gain` by the configured
+    /// [ProfitGuard]. `gain` is the expected change in the borrower's profit token balance
+    /// before repaying the fee. The fee is only deducted when the profit token is the borrowed
+    /// token, otherwise `gain` should already account for it.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingProfitGuard](FlashloanError::MissingProfitGuard) if no profit guard is
+    /// set. Returns an [Unprofitable](FlashloanError::Unprofitable) if the net profit is below the
+    /// minimum profit.
+    pub async fn check_profit(&self, gain: I256) -> Result<ProfitCheck>
+    where
+        M: 'static,
+    {
+        let gas_cost = self.estimate_gas_cost().await?;
+        self.check_profit_with_gas_cost(gain, gas_cost).await
+    }
+
+    /// [**Async**] Check the expected gain of the flashloan against the fee and a known gas cost
+    ///
+    /// See [check_profit](FlashloanBuilder::check_profit).
+    pub async fn check_profit_with_gas_cost(
+        &self,
+        gain: I256,
+        gas_cost: U256,
+    ) -> Result<ProfitCheck>
+    where
+        M: 'static,
+    {
+        let guard = self.profit_guard.ok_or(FlashloanError::MissingProfitGuard)?;
+        let fee = if !self.legs.is_empty() {
+            // Only the fees of legs borrowing the profit token are paid from the gain
+            let fees = leg_fees(Arc::clone(&self.client), &self.legs).await?;
+            self.legs
+                .iter()
+                .zip(fees)
+                .filter(|(leg, _)| leg.token == guard.token)
+                .fold(U256::zero(), |total, (_, fee)| total + fee)
+        } else if Some(guard.token) == self.token {
+            self.flash_fee().await?
+        } else {
+            U256::zero()
+        };
+        guard.check(gain, fee, gas_cost)
+    }
+
+    /// Decode the [Call3Result]s of an executed flashloan from its transaction receipt
+    ///
+    /// The borrower emits a `Call3Results` event from the lender callback. Returns an empty vector
+    /// if the receipt has no such event from the borrower contract.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBorrower](FlashloanError::MissingBorrower) if the borrower contract is not
+    /// specified.
+    pub fn decode_results(&self, receipt: &TransactionReceipt) -> Result<Vec<Call3Result>> {
+        let contract = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?;
+        Ok(crate::plan::decode_results(contract.address(), receipt))
+    }
+}
+
+/// Records a borrower deployment on the current span
+fn record_deployment(borrower: Address, tx_hash: H256) {
+    let span = Span::current();
+    span.record("borrower", field::debug(borrower));
+    span.record("tx_hash", field::debug(tx_hash));
+    info!(borrower = ?borrower, tx_hash = ?tx_hash, "Deployed borrower");
+}
+
+/// The repayment of a loan of `amount` with its flash `fee`
+fn repayment(amount: U256, fee: U256) -> Result<U256> {
+    amount.checked_add(fee).ok_or_else(|| FlashloanError::RepaymentOverflow { amount, fee }.into())
 }
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use ethers::{
+    prelude::*,
+    types::transaction::eip2718::TypedTransaction,
+    utils::{hex, keccak256},
+};
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{errors::*, revert::*};
+
+/// The Flashbots relay on mainnet
+///
+/// See: https://docs.flashbots.net/flashbots-auction/searchers/advanced/rpc-endpoint
+pub const FLASHBOTS_RELAY: &str = "https://relay.flashbots.net";
+
+/// A private bundle relay speaking the Flashbots `eth_callBundle` and `eth_sendBundle` api
+///
+/// ### Usage
+///
+/// Pass a relay to [with_bundle_relay](crate::builder::FlashloanBuilder::with_bundle_relay) to
+/// have [execute](crate::builder::FlashloanBuilder::execute) submit the signed `flashBorrow`
+/// transaction as a bundle instead of broadcasting it to the public mempool.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// // Requests are signed by a reputation key, which should not hold funds
+/// let auth = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+/// let relay = BundleRelay::flashbots(auth).with_max_blocks(5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BundleRelay {
+    /// The relay endpoint
+    url: Url,
+    /// Signs the `X-Flashbots-Signature` request header
+    auth: LocalWallet,
+    /// The number of consecutive blocks to target before giving up
+    max_blocks: u64,
+    /// How often the client is polled for new blocks
+    poll_interval: Duration,
+    /// How long to wait for each targeted block before giving up
+    block_timeout: Duration,
+    http: reqwest::Client,
+}
+
+/// The simulated result of a bundle
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSimulation {
+    /// The bundle hash
+    pub bundle_hash: H256,
+    /// The payment to the block builder in wei
+    #[serde(deserialize_with = "deserialize_decimal")]
+    pub coinbase_diff: U256,
+    /// The gas used by every transaction in the bundle
+    pub total_gas_used: u64,
+    /// The result of each transaction in the bundle, in order
+    pub results: Vec<BundleTransactionResult>,
+}
+
+/// The simulated result of a transaction in a bundle
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTransactionResult {
+    /// The transaction hash
+    pub tx_hash: H256,
+    /// The gas used by the transaction
+    pub gas_used: u64,
+    /// The execution error if the transaction failed
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The revert data or reason if the transaction reverted
+    #[serde(default)]
+    pub revert: Option<String>,
+}
+
+/// The response to an `eth_sendBundle` request
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendBundleResponse {
+    bundle_hash: H256,
+}
+
+impl BundleRelay {
+    /// Create a relay client for the endpoint at `url`
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [RelayError](FlashloanError::RelayError) if the url is invalid.
+    pub fn new(url: &str, auth: LocalWallet) -> Result<Self> {
+        let url = Url::parse(url).map_err(|e| FlashloanError::RelayError(e.to_string()))?;
+        Ok(Self {
+            url,
+            auth,
+            max_blocks: 3,
+            poll_interval: Duration::from_secs(1),
+            block_timeout: Duration::from_secs(30),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Create a relay client for the mainnet [Flashbots relay](FLASHBOTS_RELAY)
+    pub fn flashbots(auth: LocalWallet) -> Self {
+        // This won't panic since the url is checked
+        Self::new(FLASHBOTS_RELAY, auth).unwrap()
+    }
+
+    /// Set the number of consecutive blocks to target before giving up
+    pub fn with_max_blocks(mut self, max_blocks: u64) -> Self {
+        self.max_blocks = max_blocks;
+        self
+    }
+
+    /// Set how often the client is polled for new blocks
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Set how long to wait for each targeted block to be mined before giving up
+    ///
+    /// Defaults to 30 seconds, a few mainnet slots.
+    pub fn with_block_timeout(mut self, block_timeout: Duration) -> Self {
+        self.block_timeout = block_timeout;
+        self
+    }
+
+    /// The relay endpoint
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// [**Async**] Simulate a bundle of signed transactions on top of the latest block
+    pub async fn call_bundle(&self, txs: &[Bytes], block: U64) -> Result<BundleSimulation> {
+        let params = json!([{ "txs": txs, "blockNumber": block, "stateBlockNumber": "latest" }]);
+        self.request("eth_callBundle", params).await
+    }
+
+    /// [**Async**] Submit a bundle of signed transactions for inclusion in `block`
+    ///
+    /// Returns the bundle hash.
+    pub async fn send_bundle(&self, txs: &[Bytes], block: U64) -> Result<H256> {
+        let params = json!([{ "txs": txs, "blockNumber": block }]);
+        let response: SendBundleResponse = self.request("eth_sendBundle", params).await?;
+        Ok(response.bundle_hash)
+    }
+
+    /// [**Async**] Sign a transaction with the client and land it through the relay
+    ///
+    /// Missing transaction fields are filled by the client. Each attempt targets the next block:
+    /// the bundle is simulated with `eth_callBundle`, submitted with `eth_sendBundle`, and the
+    /// transaction receipt is checked once the target block is mined.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the revert decoded by `decoder` if the bundle simulation fails. Returns a
+    /// [BundleNotIncluded](FlashloanError::BundleNotIncluded) if the transaction is not included
+    /// after the configured number of blocks, or if a targeted block isn't mined within the block
+    /// timeout.
+    pub async fn submit<M: Middleware>(
+        &self,
+        client: Arc<M>,
+        mut tx: TypedTransaction,
+        decoder: &RevertDecoder,
+    ) -> Result<TransactionReceipt> {
+        // Sign the transaction with the client, filling the nonce if the client doesn't
+        client.fill_transaction(&mut tx, None).await.map_err(client_failure)?;
+        let from = *tx.from().ok_or(FlashloanError::MissingOwner)?;
+        if tx.nonce().is_none() {
+            let nonce = client.get_transaction_count(from, None).await.map_err(client_failure)?;
+            tx.set_nonce(nonce);
+        }
+        let signature = client.sign_transaction(&tx, from).await.map_err(client_failure)?;
+        let raw = tx.rlp_signed(&signature);
+        let tx_hash = H256::from(keccak256(&raw));
+        let txs = [raw];
+
+        for attempt in 1..=self.max_blocks {
+            let target = client.get_block_number().await.map_err(client_failure)? + 1;
+
+            // Don't submit a bundle that reverts
+            let simulation = self.call_bundle(&txs, target).await?;
+            if let Some(result) = simulation.results.iter().find(|r| r.error.is_some()) {
+                return Err(decode_bundle_revert(result, decoder).into())
+            }
+            self.send_bundle(&txs, target).await?;
+
+            // Wait for the target block, unless the chain stalls
+            let deadline = Instant::now() + self.block_timeout;
+            while client.get_block_number().await.map_err(client_failure)? < target {
+                if Instant::now() >= deadline {
+                    return Err(FlashloanError::BundleNotIncluded(attempt).into())
+                }
+                futures_timer::Delay::new(self.poll_interval).await;
+            }
+            if let Some(receipt) =
+                client.get_transaction_receipt(tx_hash).await.map_err(client_failure)?
+            {
+                return Ok(receipt)
+            }
+        }
+        Err(FlashloanError::BundleNotIncluded(self.max_blocks).into())
+    }
+
+    /// Sends a signed JSON-RPC request to the relay
+    async fn request<R: DeserializeOwned>(&self, method: &str, params: Value) -> Result<R> {
+        let body = serde_json::to_string(&Request { jsonrpc: "2.0", id: 1, method, params })?;
+
+        // The relay authenticates the request body signed as a hex message
+        let digest = format!("0x{}", hex::encode(keccak256(body.as_bytes())));
+        let signature = self
+            .auth
+            .sign_message(digest)
+            .await
+            .map_err(|e| FlashloanError::RelayError(e.to_string()))?;
+
+        let response: Value = self
+            .http
+            .post(self.url.clone())
+            .header("Content-Type", "application/json")
+            .header("X-Flashbots-Signature", format!("{:?}:0x{}", self.auth.address(), signature))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| FlashloanError::RelayError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| FlashloanError::RelayError(e.to_string()))?;
+        if let Some(error) = response.get("error") {
+            return Err(FlashloanError::RelayError(error.to_string()).into())
+        }
+        let result = serde_json::from_value(response["result"].clone())
+            .map_err(|e| FlashloanError::RelayError(e.to_string()))?;
+        Ok(result)
+    }
+}
+
+/// A JSON-RPC request
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'a str,
+    id: u64,
+    method: &'a str,
+    params: Value,
+}
+
+/// Decodes the revert of a failed bundle transaction
+///
+/// Relays report either the raw revert data or the revert reason.
+fn decode_bundle_revert(
+    result: &BundleTransactionResult,
+    decoder: &RevertDecoder,
+) -> FlashloanError {
+    match &result.revert {
+        Some(revert) => match Bytes::from_str(revert) {
+            Ok(data) => decoder.decode(&data),
+            Err(_) => FlashloanError::Revert(revert.clone()),
+        },
+        None => FlashloanError::RelayError(result.error.clone().unwrap_or_default()),
+    }
+}
+
+fn client_failure<E: ToString>(e: E) -> FlashloanError {
+    FlashloanError::ClientFailure(e.to_string())
+}
+
+/// Relays report wei amounts as decimal strings
+fn deserialize_decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    U256::from_dec_str(&value).map_err(serde::de::Error::custom)
+}
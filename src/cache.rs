@@ -0,0 +1,173 @@
+use ethers::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// How long a cached flash fee is trusted by default
+pub const DEFAULT_FEE_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// A flash fee quoted by a lender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedFee {
+    /// The quoted loan amount
+    pub amount: U256,
+    /// The fee charged for the quoted amount
+    pub fee: U256,
+    /// When the fee was quoted
+    pub quoted_at: Instant,
+}
+
+impl CachedFee {
+    /// Whether the fee was quoted less than `max_age` ago
+    pub fn is_fresh(&self, max_age: Duration) -> bool {
+        self.quoted_at.elapsed() < max_age
+    }
+
+    /// The fee for `amount`, scaled from the quoted amount and rounded up
+    ///
+    /// Only meaningful for fees proportional to the amount, flat or tiered fees don't scale.
+    pub fn fee_for(&self, amount: U256) -> U256 {
+        if amount == self.amount || self.amount.is_zero() {
+            return self.fee
+        }
+        match self.fee.checked_mul(amount) {
+            Some(product) => {
+                let (quotient, remainder) = product.div_mod(self.amount);
+                quotient + remainder.min(U256::one())
+            }
+            // Round the amount ratio up instead for amounts too large to multiply
+            None => self.fee.saturating_mul(amount / self.amount + 1),
+        }
+    }
+}
+
+/// The result of looking up a flash fee in a [FeeCache]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeLookup {
+    /// A fresh fee for the looked up amount
+    Fresh(U256),
+    /// The cached fee is older than the cache's maximum age
+    Stale,
+    /// No fee is cached for the lender, token and amount, or its amount bucket for lenders with
+    /// proportional fees
+    Missing,
+}
+
+/// Flash fees cached by lender, token and amount bucket
+///
+/// ### Usage
+///
+/// Set on a builder with [with_fee_cache](crate::builder::FlashloanBuilder::with_fee_cache), the
+/// cache lets ERC-3156 flashloans pass the repayment to the borrower's `flashBorrowWithRepayment`
+/// instead of querying the fee and allowance on-chain.
+///
+/// A quoted fee is only reused for the exact amount it was quoted for, since lenders may charge
+/// flat or tiered fees. Lenders opted in with
+/// [with_proportional_fees](FeeCache::with_proportional_fees) charge fees proportional to the
+/// amount, so their quotes are shared by amounts within a factor of two, bucketed by bit length,
+/// and scaled to the borrowed amount, rounding up.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// let (lender, dai) = (Address::random(), Address::random());
+/// let mut cache = FeeCache::new(Duration::from_secs(30));
+/// cache.insert(lender, dai, U256::from(3000), U256::from(27));
+/// assert_eq!(cache.lookup(lender, dai, U256::from(3000)), FeeLookup::Fresh(U256::from(27)));
+/// assert_eq!(cache.lookup(lender, dai, U256::from(2500)), FeeLookup::Missing);
+///
+/// // 2500 is in the same bucket as 3000, so the 0.9% fee is scaled
+/// let cache = cache.with_proportional_fees(lender);
+/// assert_eq!(cache.lookup(lender, dai, U256::from(2500)), FeeLookup::Fresh(U256::from(23)));
+/// assert_eq!(cache.lookup(lender, dai, U256::from(5000)), FeeLookup::Missing);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeCache {
+    /// How long a quoted fee is trusted
+    pub max_age: Duration,
+    fees: HashMap<(Address, Address, usize), CachedFee>,
+    proportional: HashSet<Address>,
+}
+
+impl Default for FeeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_FEE_MAX_AGE)
+    }
+}
+
+impl FeeCache {
+    /// Create an empty cache trusting fees for `max_age`
+    pub fn new(max_age: Duration) -> Self {
+        Self { max_age, fees: HashMap::new(), proportional: HashSet::new() }
+    }
+
+    /// Scale the fees quoted by `lender` to other amounts in the quoted amount's bucket
+    ///
+    /// Only opt in lenders whose flash fee is proportional to the amount.
+    pub fn with_proportional_fees(mut self, lender: Address) -> Self {
+        self.proportional.insert(lender);
+        self
+    }
+
+    /// Whether the fees quoted by `lender` are scaled to other amounts
+    pub fn has_proportional_fees(&self, lender: Address) -> bool {
+        self.proportional.contains(&lender)
+    }
+
+    /// The bucket of a loan amount, its bit length
+    pub fn amount_bucket(amount: U256) -> usize {
+        amount.bits()
+    }
+
+    /// Cache the `fee` quoted by `lender` to borrow `amount` of `token`
+    pub fn insert(&mut self, lender: Address, token: Address, amount: U256, fee: U256) {
+        let quoted_at = Instant::now();
+        let key = (lender, token, Self::amount_bucket(amount));
+        self.fees.insert(key, CachedFee { amount, fee, quoted_at });
+    }
+
+    /// The cached fee quoted for the bucket of `amount`, fresh or not
+    pub fn get(&self, lender: Address, token: Address, amount: U256) -> Option<&CachedFee> {
+        self.fees.get(&(lender, token, Self::amount_bucket(amount)))
+    }
+
+    /// Look up the fee charged by `lender` to borrow `amount` of `token`
+    ///
+    /// A fee quoted for another amount in the bucket is only scaled for lenders with proportional
+    /// fees, and is missing otherwise.
+    pub fn lookup(&self, lender: Address, token: Address, amount: U256) -> FeeLookup {
+        let cached = self
+            .get(lender, token, amount)
+            .filter(|cached| cached.amount == amount || self.has_proportional_fees(lender));
+        match cached {
+            Some(cached) if cached.is_fresh(self.max_age) => {
+                FeeLookup::Fresh(cached.fee_for(amount))
+            }
+            Some(_) => FeeLookup::Stale,
+            None => FeeLookup::Missing,
+        }
+    }
+
+    /// Remove the fee cached for the bucket of `amount`
+    pub fn remove(&mut self, lender: Address, token: Address, amount: U256) -> Option<CachedFee> {
+        self.fees.remove(&(lender, token, Self::amount_bucket(amount)))
+    }
+
+    /// Remove every cached fee
+    pub fn clear(&mut self) {
+        self.fees.clear();
+    }
+
+    /// The number of cached fees
+    pub fn len(&self) -> usize {
+        self.fees.len()
+    }
+
+    /// Whether no fees are cached
+    pub fn is_empty(&self) -> bool {
+        self.fees.is_empty()
+    }
+}
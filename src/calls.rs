@@ -0,0 +1,486 @@
+use anyhow::Result;
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+    utils::id,
+};
+use std::str::FromStr;
+
+use crate::{contract::*, errors::*};
+
+/// The Uniswap V2 Router02 on mainnet
+pub const UNISWAP_V2_ROUTER: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+
+/// The Uniswap V3 SwapRouter on mainnet, Optimism, Polygon and Arbitrum
+pub const UNISWAP_V3_ROUTER: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
+
+/// The Balancer V2 Vault, at the same address on every chain
+pub const BALANCER_VAULT: &str = "0xBA12222222228d8Ba445958a75a0704d566BF2C8";
+
+/// The largest Uniswap V3 fee, in hundredths of a bip
+const MAX_UNISWAP_V3_FEE: u32 = 1_000_000;
+
+/// An ERC-20 token
+///
+/// ### Usage
+///
+/// Each method builds a [Call3] to add to the flashloan, encoded with the function's abi.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// let dai = Erc20Token(Address::random());
+/// let router = UniswapV2Router::default();
+///
+/// // Let the router pull the borrowed tokens
+/// let approve = dai.approve(router.0, U256::exp10(24)).unwrap();
+/// assert_eq!(approve.target, dai.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc20Token(pub Address);
+
+impl Erc20Token {
+    /// Approve `spender` to transfer `amount` of the borrower's tokens
+    ///
+    /// A zero amount resets the allowance, which some tokens require before changing it.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the token or
+    /// the spender is the zero address.
+    pub fn approve(&self, spender: Address, amount: U256) -> Result<Call3> {
+        check_address("token", self.0)?;
+        check_address("spender", spender)?;
+        Ok(call3(
+            self.0,
+            "approve(address,uint256)",
+            &[Token::Address(spender), Token::Uint(amount)],
+            U256::zero(),
+        ))
+    }
+
+    /// Transfer `amount` of the borrower's tokens to `recipient`
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the token or
+    /// the recipient is the zero address, or the amount is zero.
+    pub fn transfer(&self, recipient: Address, amount: U256) -> Result<Call3> {
+        check_address("token", self.0)?;
+        check_address("recipient", recipient)?;
+        check_amount("amount", amount)?;
+        Ok(call3(
+            self.0,
+            "transfer(address,uint256)",
+            &[Token::Address(recipient), Token::Uint(amount)],
+            U256::zero(),
+        ))
+    }
+}
+
+/// A WETH9 wrapped ether contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weth(pub Address);
+
+impl Weth {
+    /// Wrap `amount` of the borrower's ether, sent as the call value
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the contract is
+    /// the zero address or the amount is zero.
+    pub fn deposit(&self, amount: U256) -> Result<Call3> {
+        check_address("weth", self.0)?;
+        check_amount("amount", amount)?;
+        Ok(call3(self.0, "deposit()", &[], amount))
+    }
+
+    /// Unwrap `amount` of the borrower's WETH into ether
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the contract is
+    /// the zero address or the amount is zero.
+    pub fn withdraw(&self, amount: U256) -> Result<Call3> {
+        check_address("weth", self.0)?;
+        check_amount("amount", amount)?;
+        Ok(call3(self.0, "withdraw(uint256)", &[Token::Uint(amount)], U256::zero()))
+    }
+}
+
+/// A Uniswap V2 Router02, or a fork sharing its abi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniswapV2Router(pub Address);
+
+impl Default for UniswapV2Router {
+    /// The Uniswap V2 Router02 on mainnet
+    fn default() -> Self {
+        // This won't panic since the address is checked
+        Self(Address::from_str(UNISWAP_V2_ROUTER).unwrap())
+    }
+}
+
+impl UniswapV2Router {
+    /// Swap exactly `amount_in` of the first token of `path` for at least `amount_out_min` of the
+    /// last
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the path is
+    /// invalid, the amount in is zero or the recipient is the zero address.
+    pub fn swap_exact_tokens_for_tokens(
+        &self,
+        amount_in: U256,
+        amount_out_min: U256,
+        path: &[Address],
+        to: Address,
+        deadline: U256,
+    ) -> Result<Call3> {
+        check_address("router", self.0)?;
+        check_amount("amount in", amount_in)?;
+        check_path(path)?;
+        check_address("recipient", to)?;
+        Ok(call3(
+            self.0,
+            "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+            &[
+                Token::Uint(amount_in),
+                Token::Uint(amount_out_min),
+                address_array(path),
+                Token::Address(to),
+                Token::Uint(deadline),
+            ],
+            U256::zero(),
+        ))
+    }
+
+    /// Swap at most `amount_in_max` of the first token of `path` for exactly `amount_out` of the
+    /// last
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the path is
+    /// invalid, the amount out is zero or the recipient is the zero address.
+    pub fn swap_tokens_for_exact_tokens(
+        &self,
+        amount_out: U256,
+        amount_in_max: U256,
+        path: &[Address],
+        to: Address,
+        deadline: U256,
+    ) -> Result<Call3> {
+        check_address("router", self.0)?;
+        check_amount("amount out", amount_out)?;
+        check_path(path)?;
+        check_address("recipient", to)?;
+        Ok(call3(
+            self.0,
+            "swapTokensForExactTokens(uint256,uint256,address[],address,uint256)",
+            &[
+                Token::Uint(amount_out),
+                Token::Uint(amount_in_max),
+                address_array(path),
+                Token::Address(to),
+                Token::Uint(deadline),
+            ],
+            U256::zero(),
+        ))
+    }
+}
+
+/// The parameters of a Uniswap V3 single pool swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UniswapV3SingleSwap {
+    /// The token sold
+    pub token_in: Address,
+    /// The token bought
+    pub token_out: Address,
+    /// The pool fee, in hundredths of a bip
+    pub fee: u32,
+    /// The recipient of the bought tokens
+    pub recipient: Address,
+    /// The timestamp after which the swap reverts
+    pub deadline: U256,
+    /// The amount of `token_in` sold
+    pub amount_in: U256,
+    /// The minimum amount of `token_out` bought
+    pub amount_out_minimum: U256,
+    /// The price limit of the swap, or zero for none
+    pub sqrt_price_limit_x96: U256,
+}
+
+/// A Uniswap V3 SwapRouter
+///
+/// The original SwapRouter takes a deadline in its swap parameters, unlike SwapRouter02.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniswapV3Router(pub Address);
+
+impl Default for UniswapV3Router {
+    /// The Uniswap V3 SwapRouter
+    fn default() -> Self {
+        // This won't panic since the address is checked
+        Self(Address::from_str(UNISWAP_V3_ROUTER).unwrap())
+    }
+}
+
+impl UniswapV3Router {
+    /// Swap exactly `amount_in` through a single pool
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the tokens are
+    /// the same, the fee is out of range, the amount in is zero or the recipient is the zero
+    /// address.
+    pub fn exact_input_single(&self, swap: UniswapV3SingleSwap) -> Result<Call3> {
+        check_address("router", self.0)?;
+        check_path(&[swap.token_in, swap.token_out])?;
+        check_fee(swap.fee)?;
+        check_address("recipient", swap.recipient)?;
+        check_amount("amount in", swap.amount_in)?;
+        if swap.sqrt_price_limit_x96.bits() > 160 {
+            return Err(invalid("The sqrt price limit doesn't fit a uint160".to_string()))
+        }
+        let params = Token::Tuple(vec![
+            Token::Address(swap.token_in),
+            Token::Address(swap.token_out),
+            Token::Uint(swap.fee.into()),
+            Token::Address(swap.recipient),
+            Token::Uint(swap.deadline),
+            Token::Uint(swap.amount_in),
+            Token::Uint(swap.amount_out_minimum),
+            Token::Uint(swap.sqrt_price_limit_x96),
+        ]);
+        Ok(call3(
+            self.0,
+            "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))",
+            &[params],
+            U256::zero(),
+        ))
+    }
+
+    /// Swap exactly `amount_in` of the first token of `path` along the path
+    ///
+    /// `fees` holds the fee of the pool between each pair of consecutive tokens, so it has one
+    /// entry less than `path`.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the path or a
+    /// fee is invalid, the amount in is zero or the recipient is the zero address.
+    pub fn exact_input(
+        &self,
+        path: &[Address],
+        fees: &[u32],
+        recipient: Address,
+        deadline: U256,
+        amount_in: U256,
+        amount_out_minimum: U256,
+    ) -> Result<Call3> {
+        check_address("router", self.0)?;
+        check_path(path)?;
+        if fees.len() + 1 != path.len() {
+            return Err(invalid(format!(
+                "A path of {} tokens needs {} fees, got {}",
+                path.len(),
+                path.len() - 1,
+                fees.len()
+            )))
+        }
+        fees.iter().try_for_each(|fee| check_fee(*fee))?;
+        check_address("recipient", recipient)?;
+        check_amount("amount in", amount_in)?;
+
+        // The path packs each token followed by the fee of the next pool as a uint24
+        let mut packed = path[0].as_bytes().to_vec();
+        for (token, fee) in path[1..].iter().zip(fees) {
+            packed.extend_from_slice(&fee.to_be_bytes()[1..]);
+            packed.extend_from_slice(token.as_bytes());
+        }
+        let params = Token::Tuple(vec![
+            Token::Bytes(packed),
+            Token::Address(recipient),
+            Token::Uint(deadline),
+            Token::Uint(amount_in),
+            Token::Uint(amount_out_minimum),
+        ]);
+        Ok(call3(
+            self.0,
+            "exactInput((bytes,address,uint256,uint256,uint256))",
+            &[params],
+            U256::zero(),
+        ))
+    }
+}
+
+/// A Curve pool with `int128` coin indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurvePool(pub Address);
+
+impl CurvePool {
+    /// Swap `dx` of coin `i` for at least `min_dy` of coin `j`
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if an index is
+    /// negative, the indices are the same or the amount is zero.
+    pub fn exchange(&self, i: i128, j: i128, dx: U256, min_dy: U256) -> Result<Call3> {
+        check_address("pool", self.0)?;
+        if i < 0 || j < 0 {
+            return Err(invalid(format!("Negative coin index in {} -> {}", i, j)))
+        }
+        if i == j {
+            return Err(invalid(format!("Can't exchange coin {} for itself", i)))
+        }
+        check_amount("dx", dx)?;
+        Ok(call3(
+            self.0,
+            "exchange(int128,int128,uint256,uint256)",
+            &[
+                Token::Int((i as u128).into()),
+                Token::Int((j as u128).into()),
+                Token::Uint(dx),
+                Token::Uint(min_dy),
+            ],
+            U256::zero(),
+        ))
+    }
+}
+
+/// The kind of a Balancer swap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwapKind {
+    /// The amount is the exact amount sold
+    #[default]
+    GivenIn,
+    /// The amount is the exact amount bought
+    GivenOut,
+}
+
+/// The parameters of a Balancer single pool swap
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BalancerSingleSwap {
+    /// The pool id
+    pub pool_id: H256,
+    /// Whether the amount is sold or bought
+    pub kind: SwapKind,
+    /// The token sold
+    pub asset_in: Address,
+    /// The token bought
+    pub asset_out: Address,
+    /// The exact amount sold or bought
+    pub amount: U256,
+    /// Pool specific data, usually empty
+    pub user_data: Bytes,
+}
+
+/// The Balancer V2 Vault, swapped through with `swap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalancerVault(pub Address);
+
+impl Default for BalancerVault {
+    /// The Balancer V2 Vault
+    fn default() -> Self {
+        // This won't panic since the address is checked
+        Self(Address::from_str(BALANCER_VAULT).unwrap())
+    }
+}
+
+impl BalancerVault {
+    /// Swap through a single pool, from the `sender`'s tokens to the `recipient`
+    ///
+    /// The `limit` is the minimum amount bought for [GivenIn](SwapKind::GivenIn) swaps, and the
+    /// maximum amount sold for [GivenOut](SwapKind::GivenOut) swaps. Both accounts use their token
+    /// balances rather than vault internal balances.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the assets are
+    /// the same, the amount is zero or an account is the zero address.
+    pub fn swap(
+        &self,
+        swap: BalancerSingleSwap,
+        sender: Address,
+        recipient: Address,
+        limit: U256,
+        deadline: U256,
+    ) -> Result<Call3> {
+        check_address("vault", self.0)?;
+        check_path(&[swap.asset_in, swap.asset_out])?;
+        check_amount("amount", swap.amount)?;
+        check_address("sender", sender)?;
+        check_address("recipient", recipient)?;
+        let kind = match swap.kind {
+            SwapKind::GivenIn => 0u8,
+            SwapKind::GivenOut => 1,
+        };
+        let single = Token::Tuple(vec![
+            Token::FixedBytes(swap.pool_id.as_bytes().to_vec()),
+            Token::Uint(kind.into()),
+            Token::Address(swap.asset_in),
+            Token::Address(swap.asset_out),
+            Token::Uint(swap.amount),
+            Token::Bytes(swap.user_data.to_vec()),
+        ]);
+        let funds = Token::Tuple(vec![
+            Token::Address(sender),
+            Token::Bool(false),
+            Token::Address(recipient),
+            Token::Bool(false),
+        ]);
+        Ok(call3(
+            self.0,
+            "swap((bytes32,uint8,address,address,uint256,bytes),(address,bool,address,bool),uint256,uint256)",
+            &[single, funds, Token::Uint(limit), Token::Uint(deadline)],
+            U256::zero(),
+        ))
+    }
+}
+
+/// Encodes a call of the function with the canonical `signature`
+fn call3(target: Address, signature: &str, args: &[Token], value: U256) -> Call3 {
+    let mut call_data = id(signature).to_vec();
+    call_data.extend(encode(args));
+    Call3 { target, allow_failure: false, value, call_data: call_data.into() }
+}
+
+fn address_array(path: &[Address]) -> Token {
+    Token::Array(path.iter().copied().map(Token::Address).collect())
+}
+
+fn check_address(name: &str, address: Address) -> Result<()> {
+    if address.is_zero() {
+        return Err(invalid(format!("The {} is the zero address", name)))
+    }
+    Ok(())
+}
+
+fn check_amount(name: &str, amount: U256) -> Result<()> {
+    if amount.is_zero() {
+        return Err(invalid(format!("The {} is zero", name)))
+    }
+    Ok(())
+}
+
+/// Checks a swap path has at least two tokens and doesn't swap a token for itself
+fn check_path(path: &[Address]) -> Result<()> {
+    if path.len() < 2 {
+        return Err(invalid(format!("A swap path needs at least 2 tokens, got {}", path.len())))
+    }
+    path.iter().try_for_each(|token| check_address("token", *token))?;
+    if let Some(pair) = path.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(invalid(format!("Can't swap {:?} for itself", pair[0])))
+    }
+    Ok(())
+}
+
+fn check_fee(fee: u32) -> Result<()> {
+    if fee >= MAX_UNISWAP_V3_FEE {
+        return Err(invalid(format!("The fee {} is not below {}", fee, MAX_UNISWAP_V3_FEE)))
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> anyhow::Error {
+    FlashloanError::CallConstructionError(message).into()
+}
@@ -0,0 +1,629 @@
+use anyhow::Result;
+use ethers::{contract::builders::ContractCall, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    builder::*, contract::*, errors::*, huff::*, lender::*, outcome::*, registry::*, revert::*,
+    spec::*,
+};
+
+/// The config file used when none is given
+pub const DEFAULT_CONFIG: &str = "flashloan.toml";
+
+/// The usage of the `flashloan` binary
+pub const USAGE: &str = "\
+Usage: flashloan [--config <path>] <command> [options]
+
+Commands:
+  deploy [--lender <address>] [--owner <address>] [--create2]
+      Deploy a borrower for the lender and owner
+  plan <plan>
+      Validate a JSON or TOML plan file
+  simulate <plan>
+      Call the plan's flashloan without sending a transaction
+  execute <plan>
+      Execute the plan's flashloan
+  withdraw-token --token <address> [--amount <amount>] [--to <address>] [--borrower <address>]
+      Withdraw a token from the borrower, its whole balance without an amount
+  withdraw-eth [--to <address>] [--borrower <address>]
+      Withdraw the borrower's ether
+  inspect [--borrower <address>] [--token <address>]...
+      Show the borrower's owner, lender and balances
+
+The config file defaults to flashloan.toml. The private key is read from the
+FLASHLOAN_PRIVATE_KEY environment variable, or from the config's keystore with
+its password in FLASHLOAN_KEYSTORE_PASSWORD.";
+
+/// The configuration of the `flashloan` binary
+///
+/// ### Usage
+///
+/// Loaded from a `.toml` or `.json` file. Every field has a default, so a missing default config
+/// file connects to a local node.
+///
+/// ```toml
+/// rpc_url = "https://eth.llamarpc.com"
+/// borrower = "0x5fbdb2315678afecb367f032d93f642f64180aa3"
+/// keystore = "keystores/deployer.json"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CliConfig {
+    /// The JSON-RPC endpoint
+    #[serde(default = "default_rpc_url")]
+    pub rpc_url: String,
+    /// The chain id, queried from the endpoint if unset
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// The environment variable holding the private key
+    #[serde(default = "default_private_key_env")]
+    pub private_key_env: String,
+    /// The keystore file, used if the private key variable is unset
+    #[serde(default)]
+    pub keystore: Option<PathBuf>,
+    /// The environment variable holding the keystore password
+    #[serde(default = "default_keystore_password_env")]
+    pub keystore_password_env: String,
+    /// The borrower contract
+    #[serde(default)]
+    pub borrower: Option<Address>,
+    /// The borrower owner, the signer if unset
+    #[serde(default)]
+    pub owner: Option<Address>,
+    /// The flash lender, the registry's default lender if unset
+    #[serde(default)]
+    pub lender: Option<Address>,
+    /// The flash lender protocol
+    #[serde(default)]
+    pub lender_kind: LenderKind,
+    /// The language of deployed borrowers
+    #[serde(default)]
+    pub borrower_flavor: BorrowerFlavor,
+    /// A registry merged over the builtin registry
+    #[serde(default)]
+    pub registry: Option<PathBuf>,
+    /// Whether borrowers are deployed through the CREATE2 factory
+    #[serde(default)]
+    pub create2: bool,
+}
+
+impl Default for CliConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: default_rpc_url(),
+            chain_id: None,
+            private_key_env: default_private_key_env(),
+            keystore: None,
+            keystore_password_env: default_keystore_password_env(),
+            borrower: None,
+            owner: None,
+            lender: None,
+            lender_kind: LenderKind::default(),
+            borrower_flavor: BorrowerFlavor::default(),
+            registry: None,
+            create2: false,
+        }
+    }
+}
+
+fn default_rpc_url() -> String {
+    "http://localhost:8545".to_string()
+}
+
+fn default_private_key_env() -> String {
+    "FLASHLOAN_PRIVATE_KEY".to_string()
+}
+
+fn default_keystore_password_env() -> String {
+    "FLASHLOAN_KEYSTORE_PASSWORD".to_string()
+}
+
+impl CliConfig {
+    /// Parse a config from a TOML document
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CliError](FlashloanError::CliError) if the document is invalid.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let document =
+            toml.parse::<toml_edit::DocumentMut>().map_err(|e| cli_error(e.to_string()))?;
+        let value = toml_table_to_json(document.as_table());
+        let config = serde_json::from_value(value).map_err(|e| cli_error(e.to_string()))?;
+        Ok(config)
+    }
+
+    /// Load a config from a `.toml` or `.json` file
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CliError](FlashloanError::CliError) if the file can't be read or is invalid.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| cli_error(format!("{}: {}", path.display(), e)))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => {
+                let config =
+                    serde_json::from_str(&contents).map_err(|e| cli_error(e.to_string()))?;
+                Ok(config)
+            }
+            _ => Self::from_toml(&contents),
+        }
+    }
+
+    /// The signing wallet, from the private key variable or the keystore
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CliError](FlashloanError::CliError) if neither is set, the key is invalid, or
+    /// the keystore can't be decrypted.
+    pub fn wallet(&self) -> Result<LocalWallet> {
+        if let Ok(key) = std::env::var(&self.private_key_env) {
+            let wallet = key
+                .trim()
+                .parse::<LocalWallet>()
+                .map_err(|e| cli_error(format!("Invalid {}: {}", self.private_key_env, e)))?;
+            return Ok(wallet)
+        }
+        let keystore = self.keystore.as_ref().ok_or_else(|| {
+            cli_error(format!("Set {} or a keystore to sign transactions", self.private_key_env))
+        })?;
+        let password = std::env::var(&self.keystore_password_env).map_err(|_| {
+            cli_error(format!("Set {} to decrypt the keystore", self.keystore_password_env))
+        })?;
+        let wallet = LocalWallet::decrypt_keystore(keystore, password)
+            .map_err(|e| cli_error(format!("{}: {}", keystore.display(), e)))?;
+        Ok(wallet)
+    }
+}
+
+/// A subcommand of the `flashloan` binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Deploy a borrower
+    Deploy {
+        /// The flash lender, the config's if unset
+        lender: Option<Address>,
+        /// The borrower owner, the config's or the signer if unset
+        owner: Option<Address>,
+        /// Deploy through the CREATE2 factory
+        create2: bool,
+    },
+    /// Validate a plan file
+    Plan(PathBuf),
+    /// Call a plan's flashloan without sending a transaction
+    Simulate(PathBuf),
+    /// Execute a plan's flashloan
+    Execute(PathBuf),
+    /// Withdraw a token from the borrower
+    WithdrawToken {
+        /// The borrower, the config's if unset
+        borrower: Option<Address>,
+        /// The token to withdraw
+        token: Address,
+        /// The recipient, the signer if unset
+        to: Option<Address>,
+        /// The amount to withdraw, the whole balance if unset
+        amount: Option<U256>,
+    },
+    /// Withdraw the borrower's ether
+    WithdrawEth {
+        /// The borrower, the config's if unset
+        borrower: Option<Address>,
+        /// The recipient, the signer if unset
+        to: Option<Address>,
+    },
+    /// Show a borrower's owner, lender and balances
+    Inspect {
+        /// The borrower, the config's if unset
+        borrower: Option<Address>,
+        /// Tokens to show the balances of, besides the registry's tokens for the chain
+        tokens: Vec<Address>,
+    },
+}
+
+/// The parsed command line of the `flashloan` binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cli {
+    /// The config file, if given
+    pub config: Option<PathBuf>,
+    /// The subcommand
+    pub command: Command,
+}
+
+impl Cli {
+    /// Parse the command line arguments, without the binary name
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CliError](FlashloanError::CliError) on unknown commands or options, missing
+    /// arguments, and invalid addresses or amounts.
+    pub fn parse<I, S>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args: Vec<String> = args.into_iter().map(Into::into).collect();
+        let mut config = None;
+        if args.first().map(String::as_str) == Some("--config") {
+            let path = args.get(1).ok_or_else(|| cli_error("--config takes a path".into()))?;
+            config = Some(PathBuf::from(path));
+            args.drain(..2);
+        }
+        let (name, args) =
+            args.split_first().ok_or_else(|| cli_error("Missing command".to_string()))?;
+        let command = match name.as_str() {
+            "deploy" => {
+                let options = Options::parse(args, &["--lender", "--owner"], &["--create2"])?;
+                options.no_arguments()?;
+                Command::Deploy {
+                    lender: options.address("--lender")?,
+                    owner: options.address("--owner")?,
+                    create2: options.switch("--create2"),
+                }
+            }
+            "plan" => Command::Plan(Options::parse(args, &[], &[])?.path()?),
+            "simulate" => Command::Simulate(Options::parse(args, &[], &[])?.path()?),
+            "execute" => Command::Execute(Options::parse(args, &[], &[])?.path()?),
+            "withdraw-token" => {
+                let flags = ["--borrower", "--token", "--to", "--amount"];
+                let options = Options::parse(args, &flags, &[])?;
+                options.no_arguments()?;
+                Command::WithdrawToken {
+                    borrower: options.address("--borrower")?,
+                    token: options
+                        .address("--token")?
+                        .ok_or_else(|| cli_error("withdraw-token takes a --token".to_string()))?,
+                    to: options.address("--to")?,
+                    amount: options
+                        .value("--amount")
+                        .map(|amount| parse_amount(&amount).map_err(cli_error))
+                        .transpose()?,
+                }
+            }
+            "withdraw-eth" => {
+                let options = Options::parse(args, &["--borrower", "--to"], &[])?;
+                options.no_arguments()?;
+                Command::WithdrawEth {
+                    borrower: options.address("--borrower")?,
+                    to: options.address("--to")?,
+                }
+            }
+            "inspect" => {
+                let options = Options::parse(args, &["--borrower", "--token"], &[])?;
+                options.no_arguments()?;
+                let tokens = options
+                    .values("--token")
+                    .iter()
+                    .map(|token| parse_address(token))
+                    .collect::<Result<_>>()?;
+                Command::Inspect { borrower: options.address("--borrower")?, tokens }
+            }
+            _ => return Err(cli_error(format!("Unknown command {:?}", name))),
+        };
+        Ok(Self { config, command })
+    }
+
+    /// Load the config file
+    ///
+    /// A missing [DEFAULT_CONFIG] file yields the default config, a missing given file is an error.
+    pub fn load_config(&self) -> Result<CliConfig> {
+        match &self.config {
+            Some(path) => CliConfig::load(path),
+            None if Path::new(DEFAULT_CONFIG).exists() => CliConfig::load(DEFAULT_CONFIG),
+            None => Ok(CliConfig::default()),
+        }
+    }
+
+    /// [**Async**] Run the command, printing its results
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of the config, the signer and the builder methods the command runs.
+    pub async fn run(&self) -> Result<()> {
+        run(&self.load_config()?, &self.command).await
+    }
+}
+
+/// [**Async**] Run a command with a config
+///
+/// Commands that send transactions sign them with the config's [wallet](CliConfig::wallet).
+pub async fn run(config: &CliConfig, command: &Command) -> Result<()> {
+    let provider = Provider::<Http>::try_from(config.rpc_url.as_str())
+        .map_err(|e| cli_error(format!("Invalid rpc url {:?}: {}", config.rpc_url, e)))?;
+    let provider = Arc::new(provider);
+    let chain_id = match config.chain_id {
+        Some(chain_id) => chain_id,
+        None => provider
+            .get_chainid()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?
+            .as_u64(),
+    };
+    match command {
+        Command::Plan(path) => {
+            let builder = plan_builder(config, provider, chain_id, path)?;
+            let plan = builder.plan()?;
+            println!("Borrower: {:?}", plan.borrower());
+            if let Some(lender) = plan.lender() {
+                println!("Lender: {:?} ({:?})", lender, plan.lender_kind());
+            }
+            for leg in plan.legs() {
+                println!("Leg: {} of {:?} from {:?}", leg.amount, leg.token, leg.lender);
+            }
+            if plan.legs().is_empty() {
+                println!("Loan: {} of {:?}", plan.amount(), plan.token());
+            }
+            println!("Calls: {}", plan.calls().len());
+            Ok(())
+        }
+        Command::Inspect { borrower, tokens } => {
+            inspect(config, provider, chain_id, *borrower, tokens).await
+        }
+        _ => {
+            let wallet = config.wallet()?.with_chain_id(chain_id);
+            let client = Arc::new(SignerMiddleware::new(provider, wallet));
+            run_signed(config, command, client, chain_id).await
+        }
+    }
+}
+
+/// Runs the commands sending transactions or calls from the signer
+async fn run_signed<M: Middleware + 'static>(
+    config: &CliConfig,
+    command: &Command,
+    client: Arc<M>,
+    chain_id: u64,
+) -> Result<()> {
+    match command {
+        Command::Deploy { lender, owner, create2 } => {
+            let mut builder = config_builder(config, client, chain_id)?;
+            if *create2 || config.create2 {
+                builder.deploy_create2(*lender, *owner).await?;
+            } else {
+                builder.deploy(*lender, *owner).await?;
+            }
+            // This won't panic since the borrower is set by a successful deployment
+            println!("Deployed borrower: {:?}", builder.borrower.as_ref().unwrap().address());
+            Ok(())
+        }
+        Command::Simulate(path) => {
+            let mut builder = plan_builder(config, client, chain_id, path)?;
+            let results = builder.call().await?;
+            print_results(&results);
+            println!("Gas estimate: {}", builder.estimate_gas().await?);
+            Ok(())
+        }
+        Command::Execute(path) => {
+            let mut builder = plan_builder(config, client, chain_id, path)?;
+            match builder.execute().await? {
+                Some(receipt) => {
+                    println!("Transaction: {:?}", receipt.transaction_hash);
+                    print_results(&builder.decode_results(&receipt)?);
+                    print_outcome(&builder.outcome(&receipt)?);
+                }
+                None => println!("The transaction was dropped"),
+            }
+            Ok(())
+        }
+        Command::WithdrawToken { borrower, token, to, amount } => {
+            let borrower =
+                Flashloan::new(borrower_address(config, *borrower)?, Arc::clone(&client));
+            let to = recipient(client.as_ref(), *to)?;
+            let call =
+                borrower.withdraw_token(*token, to, amount.unwrap_or_default(), amount.is_none());
+            send(call).await
+        }
+        Command::WithdrawEth { borrower, to } => {
+            let borrower =
+                Flashloan::new(borrower_address(config, *borrower)?, Arc::clone(&client));
+            let to = recipient(client.as_ref(), *to)?;
+            send(borrower.withdraw_eth(to)).await
+        }
+        Command::Plan(_) | Command::Inspect { .. } => unreachable!("read-only commands"),
+    }
+}
+
+/// The builder for the config's lender and borrower
+fn config_builder<M: Middleware>(
+    config: &CliConfig,
+    client: Arc<M>,
+    chain_id: u64,
+) -> Result<FlashloanBuilder<M>> {
+    let mut builder = FlashloanBuilder::new(
+        client,
+        chain_id,
+        config.owner,
+        config.lender,
+        None,
+        None,
+        config.borrower,
+    );
+    builder.lender_kind = config.lender_kind;
+    builder.borrower_flavor = config.borrower_flavor;
+    if let Some(path) = &config.registry {
+        let mut registry = ChainRegistry::builtin();
+        registry.merge(ChainRegistry::load(path)?);
+        builder.with_registry(registry);
+    }
+    Ok(builder)
+}
+
+/// The config's builder with a plan file loaded over it
+fn plan_builder<M: Middleware>(
+    config: &CliConfig,
+    client: Arc<M>,
+    chain_id: u64,
+    path: &Path,
+) -> Result<FlashloanBuilder<M>> {
+    let mut builder = config_builder(config, client, chain_id)?;
+    builder.with_spec(&FlashloanSpec::load(path)?)?;
+    Ok(builder)
+}
+
+async fn inspect<M: Middleware + 'static>(
+    config: &CliConfig,
+    client: Arc<M>,
+    chain_id: u64,
+    borrower: Option<Address>,
+    tokens: &[Address],
+) -> Result<()> {
+    let address = borrower_address(config, borrower)?;
+    let borrower = Flashloan::new(address, Arc::clone(&client));
+    let contract_error = |e: ContractError<M>| FlashloanError::ContractError(e.to_string());
+    println!("Borrower: {:?}", address);
+    println!("Owner: {:?}", borrower.owner().call().await.map_err(contract_error)?);
+    println!("Lender: {:?}", borrower.lender().call().await.map_err(contract_error)?);
+    let balance = client
+        .get_balance(address, None)
+        .await
+        .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+    println!("ETH: {}", balance);
+
+    // The registry's tokens for the chain, then the given tokens
+    let mut registry = ChainRegistry::builtin();
+    if let Some(path) = &config.registry {
+        registry.merge(ChainRegistry::load(path)?);
+    }
+    let mut named: Vec<(String, Address)> = registry
+        .chain(chain_id)
+        .map(|chain| chain.tokens.iter().map(|(symbol, token)| (symbol.clone(), *token)).collect())
+        .unwrap_or_default();
+    named.extend(tokens.iter().map(|token| (format!("{:?}", token), *token)));
+    for (name, token) in named {
+        let balance = IERC20::new(token, Arc::clone(&client))
+            .balance_of(address)
+            .call()
+            .await
+            .map_err(contract_error)?;
+        println!("{}: {}", name, balance);
+    }
+    Ok(())
+}
+
+async fn send<M: Middleware>(call: ContractCall<M, bool>) -> Result<()>
+where
+    M::Error: 'static,
+{
+    let pending =
+        call.send().await.map_err(|e| RevertDecoder::default().decode_contract_error(&e))?;
+    let receipt = pending.await.map_err(|e| FlashloanError::ContractError(e.to_string()))?;
+    match receipt {
+        Some(receipt) => println!("Transaction: {:?}", receipt.transaction_hash),
+        None => println!("The transaction was dropped"),
+    }
+    Ok(())
+}
+
+fn print_results(results: &[Call3Result]) {
+    for (index, result) in results.iter().enumerate() {
+        let status = if result.success { "ok" } else { "failed" };
+        println!("Call {}: {} {}", index, status, result.return_data);
+    }
+}
+
+fn print_outcome(outcome: &FlashloanOutcome) {
+    for loan in &outcome.loans {
+        println!("Borrowed {} of {:?} for a fee of {}", loan.amount, loan.token, loan.fee);
+    }
+    for (token, change) in &outcome.net_changes {
+        println!("Net change of {:?}: {}", token, change);
+    }
+    println!("Gas cost: {} wei ({} gas)", outcome.gas_cost, outcome.gas_used);
+    if let Some(profit) = outcome.profit {
+        println!("Realized profit: {} of {:?}", profit.net_profit, profit.token);
+    }
+}
+
+fn borrower_address(config: &CliConfig, borrower: Option<Address>) -> Result<Address> {
+    let borrower = borrower.or(config.borrower).ok_or(FlashloanError::MissingBorrower)?;
+    Ok(borrower)
+}
+
+/// The recipient of a withdrawal, the signer if unset
+fn recipient<M: Middleware>(client: &M, to: Option<Address>) -> Result<Address> {
+    let to = to
+        .or_else(|| client.default_sender())
+        .ok_or_else(|| cli_error("Missing withdrawal recipient".to_string()))?;
+    Ok(to)
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    let parsed = Address::from_str(address)
+        .map_err(|_| cli_error(format!("Invalid address {:?}", address)))?;
+    Ok(parsed)
+}
+
+fn cli_error(message: String) -> anyhow::Error {
+    FlashloanError::CliError(message).into()
+}
+
+/// The options and positional arguments of a command
+struct Options {
+    values: Vec<(String, String)>,
+    switches: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Options {
+    /// Splits `args` into the `flags` taking a value, the `switches`, and positional arguments
+    fn parse(args: &[String], flags: &[&str], switches: &[&str]) -> Result<Self> {
+        let mut options = Self { values: vec![], switches: vec![], positional: vec![] };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if flags.contains(&arg.as_str()) {
+                let value =
+                    args.next().ok_or_else(|| cli_error(format!("{} takes a value", arg)))?;
+                options.values.push((arg.clone(), value.clone()));
+            } else if switches.contains(&arg.as_str()) {
+                options.switches.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(cli_error(format!("Unknown option {}", arg)))
+            } else {
+                options.positional.push(arg.clone());
+            }
+        }
+        Ok(options)
+    }
+
+    fn values(&self, flag: &str) -> Vec<String> {
+        self.values
+            .iter()
+            .filter(|(name, _)| name == flag)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    /// The last value of `flag`
+    fn value(&self, flag: &str) -> Option<String> {
+        self.values(flag).pop()
+    }
+
+    fn address(&self, flag: &str) -> Result<Option<Address>> {
+        self.value(flag).map(|address| parse_address(&address)).transpose()
+    }
+
+    fn switch(&self, switch: &str) -> bool {
+        self.switches.iter().any(|name| name == switch)
+    }
+
+    fn no_arguments(&self) -> Result<()> {
+        match self.positional.first() {
+            Some(arg) => Err(cli_error(format!("Unexpected argument {:?}", arg))),
+            None => Ok(()),
+        }
+    }
+
+    /// The single positional path
+    fn path(&self) -> Result<PathBuf> {
+        match self.positional.as_slice() {
+            [path] => Ok(PathBuf::from(path)),
+            _ => Err(cli_error("Expected a single plan file".to_string())),
+        }
+    }
+}
@@ -4,4 +4,131 @@
 
 use ethers::prelude::*;
 
-abigen!(Flashloan, "src/FlashBorrower.json");
+// The nested entrypoints share the `Call3` and `Call3Result` types of the flashloan abi
+abigen!(
+    Flashloan,
+    "src/FlashBorrower.json";
+
+    IMultiFlashBorrower,
+    r#"[
+        {
+            "type": "function",
+            "name": "flashBorrowMulti",
+            "stateMutability": "payable",
+            "inputs": [
+                {
+                    "name": "legs", "type": "tuple[]", "internalType": "struct MultiFlashBorrower.Leg[]",
+                    "components": [
+                        { "name": "kind", "type": "uint8", "internalType": "enum MultiFlashBorrower.LenderKind" },
+                        { "name": "lender", "type": "address", "internalType": "address" },
+                        { "name": "token", "type": "address", "internalType": "address" },
+                        { "name": "amount", "type": "uint256", "internalType": "uint256" }
+                    ]
+                },
+                {
+                    "name": "calls", "type": "tuple[]", "internalType": "struct BaseFlashBorrower.Call3[]",
+                    "components": [
+                        { "name": "target", "type": "address", "internalType": "address" },
+                        { "name": "allowFailure", "type": "bool", "internalType": "bool" },
+                        { "name": "value", "type": "uint256", "internalType": "uint256" },
+                        { "name": "callData", "type": "bytes", "internalType": "bytes" }
+                    ]
+                }
+            ],
+            "outputs": [
+                {
+                    "name": "results", "type": "tuple[]", "internalType": "struct BaseFlashBorrower.Call3Result[]",
+                    "components": [
+                        { "name": "success", "type": "bool", "internalType": "bool" },
+                        { "name": "returnData", "type": "bytes", "internalType": "bytes" }
+                    ]
+                }
+            ]
+        },
+        {
+            "type": "function",
+            "name": "flashBorrowMultiWithProfit",
+            "stateMutability": "payable",
+            "inputs": [
+                {
+                    "name": "legs", "type": "tuple[]", "internalType": "struct MultiFlashBorrower.Leg[]",
+                    "components": [
+                        { "name": "kind", "type": "uint8", "internalType": "enum MultiFlashBorrower.LenderKind" },
+                        { "name": "lender", "type": "address", "internalType": "address" },
+                        { "name": "token", "type": "address", "internalType": "address" },
+                        { "name": "amount", "type": "uint256", "internalType": "uint256" }
+                    ]
+                },
+                {
+                    "name": "calls", "type": "tuple[]", "internalType": "struct BaseFlashBorrower.Call3[]",
+                    "components": [
+                        { "name": "target", "type": "address", "internalType": "address" },
+                        { "name": "allowFailure", "type": "bool", "internalType": "bool" },
+                        { "name": "value", "type": "uint256", "internalType": "uint256" },
+                        { "name": "callData", "type": "bytes", "internalType": "bytes" }
+                    ]
+                },
+                { "name": "profitToken", "type": "address", "internalType": "address" },
+                { "name": "minProfit", "type": "uint256", "internalType": "uint256" }
+            ],
+            "outputs": [
+                {
+                    "name": "results", "type": "tuple[]", "internalType": "struct BaseFlashBorrower.Call3Result[]",
+                    "components": [
+                        { "name": "success", "type": "bool", "internalType": "bool" },
+                        { "name": "returnData", "type": "bytes", "internalType": "bytes" }
+                    ]
+                }
+            ]
+        }
+    ]"#;
+);
+
+abigen!(
+    IERC20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function approve(address spender, uint256 amount) external returns (bool)
+        function transfer(address recipient, uint256 amount) external returns (bool)
+    ]"#;
+
+    IERC3156FlashLender,
+    r#"[
+        function maxFlashLoan(address token) external view returns (uint256)
+        function flashFee(address token, uint256 amount) external view returns (uint256)
+    ]"#;
+
+    IAaveV3Pool,
+    r#"[
+        function FLASHLOAN_PREMIUM_TOTAL() external view returns (uint128)
+        function getReserveData(address asset) external view returns (uint256, uint128, uint128, uint128, uint128, uint128, uint40, uint16, address, address, address, address, uint128, uint128, uint128)
+    ]"#;
+
+    IBalancerVault,
+    r#"[
+        function getProtocolFeesCollector() external view returns (address)
+    ]"#;
+
+    IBalancerProtocolFeesCollector,
+    r#"[
+        function getFlashLoanFeePercentage() external view returns (uint256)
+    ]"#;
+
+    IUniswapV3Pool,
+    r#"[
+        function token0() external view returns (address)
+        function token1() external view returns (address)
+        function fee() external view returns (uint24)
+    ]"#;
+
+    IOperatedFlashBorrower,
+    r#"[
+        function addOperator(address operator) external
+        function removeOperator(address operator) external
+        function isOperator(address account) external view returns (bool)
+        function transferOwnership(address newOwner) external
+        function acceptOwnership() external
+        function pendingOwner() external view returns (address)
+    ]"#;
+);
@@ -0,0 +1,73 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+    utils::{get_create2_address, keccak256},
+};
+
+/// The deterministic deployment proxy, at the same address on most chains
+///
+/// See: https://github.com/Arachnid/deterministic-deployment-proxy
+pub const CREATE2_FACTORY: &str = "0x4e59b44847b379578588920cA78FbF26c0B4956C";
+
+/// The CREATE2 salt of the borrower of `owner` for `lender`
+///
+/// ### Usage
+///
+/// The salt commits to the borrower's constructor arguments, so each owner has one borrower per
+/// lender and borrower contract.
+pub fn borrower_salt(owner: Address, lender: Address) -> H256 {
+    keccak256(encode(&[Token::Address(owner), Token::Address(lender)])).into()
+}
+
+/// The creation code of a borrower contract, with its `(lender, owner)` constructor arguments
+pub fn borrower_init_code(bytecode: &Bytes, lender: Address, owner: Address) -> Bytes {
+    let mut init_code = bytecode.to_vec();
+    init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+    init_code.into()
+}
+
+/// The address of a borrower deployed through `factory`
+///
+/// ### Usage
+///
+/// The address hashes the whole creation code, so it depends on the borrower bytecode as well as
+/// the owner and lender. The bytecode bundled with the crate changes between releases whenever a
+/// borrower contract is rebuilt, which moves the predicted address of every borrower. A borrower
+/// deployed by an earlier release is found again by passing that release's bytecode here, or by
+/// attaching it with [with_borrower](crate::builder::FlashloanBuilder::with_borrower).
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+/// use std::str::FromStr;
+///
+/// let factory = Address::from_str(CREATE2_FACTORY).unwrap();
+/// let (owner, lender) = (Address::random(), Address::random());
+/// let bytecode = LenderKind::Erc3156.borrower_bytecode().unwrap();
+///
+/// // The same owner and lender always map to the same borrower
+/// let borrower = create2_borrower_address(factory, &bytecode, lender, owner);
+/// assert_eq!(borrower, create2_borrower_address(factory, &bytecode, lender, owner));
+/// ```
+pub fn create2_borrower_address(
+    factory: Address,
+    bytecode: &Bytes,
+    lender: Address,
+    owner: Address,
+) -> Address {
+    let init_code = borrower_init_code(bytecode, lender, owner);
+    get_create2_address(factory, borrower_salt(owner, lender).as_bytes().to_vec(), init_code)
+}
+
+/// The transaction deploying `init_code` with `salt` through the deterministic deployment proxy
+///
+/// The proxy takes the salt followed by the creation code as its calldata.
+pub(crate) fn create2_deploy_tx(
+    factory: Address,
+    salt: H256,
+    init_code: &Bytes,
+) -> TransactionRequest {
+    let mut data = salt.as_bytes().to_vec();
+    data.extend_from_slice(init_code);
+    TransactionRequest::new().to(factory).data(data)
+}
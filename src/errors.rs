@@ -1,3 +1,7 @@
+use ethers::{
+    abi::Token,
+    types::{Address, Bytes, I256, U256},
+};
 use thiserror::Error;
 
 /// A Flashloan Builder Error
@@ -9,6 +13,9 @@ pub enum FlashloanError {
     /// Failed to deploy the contract
     #[error("Failed to deploy flashloan borrower contract!")]
     ContractDeployFailed,
+    /// The CREATE2 factory has no code on the chain
+    #[error("No CREATE2 factory deployed at {0:?}. Use the `FlashloanBuilder::with_create2_factory` method to set the factory")]
+    MissingCreate2Factory(Address),
     /// Missing the flashloan borrower contract
     #[error("Missing flashloan borrower contract definition. Use the `FlashloanBuilder::with_borrower` method to set the contract address or deploy a new instance with `FlashloanBuilder::deploy`")]
     MissingBorrower,
@@ -30,4 +37,140 @@ pub enum FlashloanError {
     /// Missing the flashloan borrower owner account
     #[error("Missing owner account. Use the `FlashloanBuilder::with_owner` method to set the owner account")]
     MissingOwner,
+    /// Missing the borrower contract bytecode for the lender kind
+    #[error("Missing bytecode for the {0} borrower contract. Compile the contracts with `forge build` and update the bundled artifact")]
+    MissingBytecode(String),
+    /// The borrower flavor has no implementation of the borrower contract
+    #[error("No Huff implementation of the {0} borrower contract. Use `BorrowerFlavor::Solidity` to deploy it")]
+    UnsupportedFlavor(String),
+    /// The borrower contract has no variant with operators
+    #[error("The {0} borrower contract has no operators. Only the ERC-3156 Solidity borrower can be deployed with operators")]
+    UnsupportedOperators(String),
+    /// The borrower contract can only borrow from the lender it was deployed with
+    #[error("The {0} borrower contract only borrows from the lender it was deployed with. Deploy a borrower for the lender instead")]
+    FixedLender(String),
+    /// Missing the flash lender address
+    #[error("Missing flash lender address. Use the `FlashloanBuilder::with_lender` or `FlashloanBuilder::with_flash_lender` method to set the lender")]
+    MissingLender,
+    /// No flash lenders are known for the chain
+    #[error("No flash lenders are configured for chain {0}. Use the `FlashloanBuilder::with_lender` method to set the lender or add the chain to the registry with `FlashloanBuilder::with_registry`")]
+    UnsupportedChain(u64),
+    /// Failed to load a chain registry
+    #[error("Invalid chain registry: {0}")]
+    RegistryError(String),
+    /// Failed to parse or encode a flashloan plan file
+    #[error("Invalid flashloan plan: {0}")]
+    PlanFormatError(String),
+    /// Invalid command line or config of the `flashloan` binary
+    #[error("{0}")]
+    CliError(String),
+    /// Failed to estimate the transaction fees
+    #[error("Fee estimation failed: {0}")]
+    FeeEstimationError(String),
+    /// Failed to execute a simulation
+    #[error("Simulation failed: {0}")]
+    SimulationError(String),
+    /// The borrower was called by an account without access to the function
+    #[error("Unauthorized: the caller is not the borrower owner or an operator")]
+    Unauthorized,
+    /// The borrower was asked to borrow from, or was called back by, a lender it doesn't trust
+    #[error("Untrusted lender: the lender is not trusted by the borrower. Use `FlashloanBuilder::trust_lender` to trust it")]
+    UntrustedLender,
+    /// The flashloan was not initiated by the borrower
+    #[error("Untrusted initiator: the flashloan was not initiated by the borrower")]
+    UntrustedInitiator,
+    /// The borrower has no ether to withdraw
+    #[error("The borrower has no ether balance to withdraw")]
+    EmptyBalance,
+    /// No lender can lend the amount
+    #[error("No lender can lend {amount} of token {token:?}")]
+    InsufficientLiquidity {
+        /// The token to borrow
+        token: Address,
+        /// The amount to borrow
+        amount: U256,
+    },
+    /// A call that does not allow failure failed inside the flashloan
+    #[error("Call {index} failed: {reason}")]
+    CallFailed {
+        /// The index of the failed call
+        index: usize,
+        /// The decoded revert of the failed call
+        reason: Box<FlashloanError>,
+    },
+    /// Missing the profit guard
+    #[error("Missing profit guard. Use the `FlashloanBuilder::with_profit_guard` method to set the minimum profit")]
+    MissingProfitGuard,
+    /// The borrower's profit token balance after the flashloan was below the threshold
+    #[error("Insufficient profit: balance {balance} is below the threshold {threshold}")]
+    InsufficientProfit {
+        /// The profit token balance after the flashloan
+        balance: U256,
+        /// The balance before the flashloan plus the minimum profit
+        threshold: U256,
+    },
+    /// The borrower holds less ether than the value of the calls
+    #[error("Insufficient ether: the borrower holds {balance} wei but the calls send {required} wei. Use `CallValue::Attach` to send the value with the flashloan")]
+    InsufficientEth {
+        /// The borrower's ether balance
+        balance: U256,
+        /// The summed value of the calls
+        required: U256,
+    },
+    /// The loan amount plus the flash fee doesn't fit in a uint256
+    #[error("Repayment overflow: the amount {amount} plus the flash fee {fee} exceeds uint256")]
+    RepaymentOverflow {
+        /// The loan amount
+        amount: U256,
+        /// The flash fee
+        fee: U256,
+    },
+    /// The change between two token amounts doesn't fit in an int256
+    #[error(
+        "Balance change overflow: the change from {before} to {after} doesn't fit in an int256"
+    )]
+    BalanceDeltaOverflow {
+        /// The amount before the change
+        before: U256,
+        /// The amount after the change
+        after: U256,
+    },
+    /// The expected net profit of the flashloan is below the minimum profit
+    #[error(
+        "Unprofitable flashloan: net profit {net_profit} is below the minimum profit {min_profit}"
+    )]
+    Unprofitable {
+        /// The gain less the flash fee and gas cost
+        net_profit: I256,
+        /// The minimum profit required
+        min_profit: U256,
+    },
+    /// A bundle relay request failed
+    #[error("Bundle relay error: {0}")]
+    RelayError(String),
+    /// The bundle was not included in any of the targeted blocks
+    #[error("Bundle was not included after targeting {0} blocks")]
+    BundleNotIncluded(u64),
+    /// Reverted with an `Error(string)` message
+    #[error("Reverted: {0}")]
+    Revert(String),
+    /// Reverted with a `Panic(uint256)` code
+    #[error("Panicked with code {code:#x}: {reason}")]
+    Panic {
+        /// The panic code
+        code: U256,
+        /// A description of the panic code
+        reason: String,
+    },
+    /// Reverted with a custom error registered in the [RevertDecoder](crate::revert::RevertDecoder)
+    #[error("Reverted with {name}{args:?}")]
+    CustomError {
+        /// The error name
+        name: String,
+        /// The decoded error arguments
+        args: Vec<Token>,
+    },
+    /// Reverted with data that could not be decoded
+    #[error("Reverted with unknown data: {0}")]
+    UnknownRevert(Bytes),
 }
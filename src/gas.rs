@@ -0,0 +1,158 @@
+use anyhow::Result;
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+
+use crate::errors::*;
+
+/// The basis of fee shares and gas padding
+const BPS: u64 = 10_000;
+
+/// How the EIP-1559 fees of the flashloan transaction are set
+///
+/// ### Usage
+///
+/// Set on a builder with [with_fee_strategy](crate::builder::FlashloanBuilder::with_fee_strategy).
+/// Every strategy but [Provider](FeeStrategy::Provider) caps the max fee at twice the next
+/// block's base fee plus the priority fee, so the transaction stays valid for a few blocks of
+/// rising base fees.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// // Tip the 60th percentile of the last 10 blocks' priority fees
+/// let strategy = FeeStrategy::FeeHistory { blocks: 10, percentile: 60.0 };
+///
+/// // Tip a fifth of the 0.1 ether expected profit
+/// let strategy = FeeStrategy::ProfitShare { expected_profit: U256::exp10(17), share_bps: 2_000 };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FeeStrategy {
+    /// Leave the fees to the middleware
+    #[default]
+    Provider,
+    /// Fixed fees
+    Fixed(Eip1559Fees),
+    /// A percentile of the priority fees paid in recent blocks, from `eth_feeHistory`
+    FeeHistory {
+        /// The number of recent blocks
+        blocks: u64,
+        /// The percentile of each block's priority fees, from 0 to 100
+        percentile: f64,
+    },
+    /// A share of the expected profit, spread over the gas limit as the priority fee
+    ProfitShare {
+        /// The expected profit in wei
+        expected_profit: U256,
+        /// The share of the profit paid to the block builder, in basis points
+        share_bps: u64,
+    },
+}
+
+/// The EIP-1559 fees of a transaction, in wei per gas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Eip1559Fees {
+    /// The maximum total fee
+    pub max_fee_per_gas: U256,
+    /// The maximum priority fee paid to the block builder
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl Eip1559Fees {
+    /// Fees tipping `priority_fee` on top of a `base_fee` that may double
+    pub fn from_base_fee(base_fee: U256, priority_fee: U256) -> Self {
+        Self {
+            max_fee_per_gas: base_fee * 2 + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+        }
+    }
+
+    /// Set the fees of a transaction
+    ///
+    /// Legacy transactions pay the max fee as their gas price.
+    pub fn apply(&self, tx: &mut TypedTransaction) {
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                inner.max_fee_per_gas = Some(self.max_fee_per_gas);
+                inner.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+            }
+            _ => {
+                tx.set_gas_price(self.max_fee_per_gas);
+            }
+        }
+    }
+}
+
+impl FeeStrategy {
+    /// [**Async**] The fees of a transaction with `gas_limit`, or `None` to leave them to the
+    /// middleware
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [FeeEstimationError](FlashloanError::FeeEstimationError) if the strategy is
+    /// invalid or the fee history is empty. Returns a [ClientFailure](FlashloanError::ClientFailure)
+    /// if the fee history can't be queried.
+    pub async fn fees<M: Middleware>(
+        &self,
+        client: &M,
+        gas_limit: U256,
+    ) -> Result<Option<Eip1559Fees>> {
+        let fees = match *self {
+            FeeStrategy::Provider => return Ok(None),
+            FeeStrategy::Fixed(fees) => fees,
+            FeeStrategy::FeeHistory { blocks, percentile } => {
+                if blocks == 0 || !(0.0..=100.0).contains(&percentile) {
+                    return Err(FlashloanError::FeeEstimationError(format!(
+                        "Invalid fee history of {} blocks at percentile {}",
+                        blocks, percentile
+                    ))
+                    .into());
+                }
+                let history = fee_history(client, blocks, &[percentile]).await?;
+                // The median of the blocks' percentiles, so a single outlier block doesn't set it
+                let mut rewards: Vec<U256> =
+                    history.reward.iter().filter_map(|reward| reward.first().copied()).collect();
+                rewards.sort();
+                let priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+                Eip1559Fees::from_base_fee(next_base_fee(&history)?, priority_fee)
+            }
+            FeeStrategy::ProfitShare { expected_profit, share_bps } => {
+                if share_bps > BPS || gas_limit.is_zero() {
+                    return Err(FlashloanError::FeeEstimationError(format!(
+                        "Invalid profit share of {} bps over {} gas",
+                        share_bps, gas_limit
+                    ))
+                    .into());
+                }
+                let history = fee_history(client, 1, &[]).await?;
+                let priority_fee = expected_profit * share_bps / BPS / gas_limit;
+                Eip1559Fees::from_base_fee(next_base_fee(&history)?, priority_fee)
+            }
+        };
+        Ok(Some(fees))
+    }
+}
+
+/// Pad a gas estimate by `padding_bps` basis points
+pub fn pad_gas(gas: U256, padding_bps: u64) -> U256 {
+    gas * (BPS + padding_bps) / BPS
+}
+
+async fn fee_history<M: Middleware>(
+    client: &M,
+    blocks: u64,
+    percentiles: &[f64],
+) -> Result<FeeHistory> {
+    let history = client
+        .fee_history(blocks, BlockNumber::Latest, percentiles)
+        .await
+        .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+    Ok(history)
+}
+
+/// The fee history ends with the base fee of the next block
+fn next_base_fee(history: &FeeHistory) -> Result<U256> {
+    let base_fee = history.base_fee_per_gas.last().copied().ok_or_else(|| {
+        FlashloanError::FeeEstimationError("The fee history has no base fees".to_string())
+    })?;
+    Ok(base_fee)
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::*, lender::*};
+
+/// The name of the Huff flashloan borrower contract
+pub const HUFF_BORROWER_NAME: &str = "HuffFlashBorrower";
+
+/// The bundled `HuffFlashBorrower` artifact, assembled from `contracts/FlashBorrower.huff`
+const HUFF_BORROWER_ARTIFACT: &str = include_str!("HuffFlashBorrower.json");
+
+/// The creation bytecode of the Huff `FlashBorrower` contract
+///
+/// The contract implements the [Flashloan](crate::contract::Flashloan) abi and takes the same
+/// `(lender, owner)` constructor arguments as the Solidity borrower.
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the bundled artifact has not
+/// been assembled.
+pub fn huff_borrower_bytecode() -> Result<Bytes> {
+    artifact_bytecode(HUFF_BORROWER_NAME, HUFF_BORROWER_ARTIFACT)
+}
+
+/// The language a borrower contract is written in
+///
+/// ### Usage
+///
+/// The Huff borrower is a cheaper drop-in for the Solidity ERC-3156 borrower, set it with
+/// [with_borrower_flavor](crate::builder::FlashloanBuilder::with_borrower_flavor) before
+/// deploying.
+///
+/// ```rust
+/// use flashloan_rs::prelude::*;
+///
+/// assert!(BorrowerFlavor::Huff.borrower_bytecode(LenderKind::Erc3156).is_ok());
+/// assert!(BorrowerFlavor::Huff.borrower_bytecode(LenderKind::Balancer).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorrowerFlavor {
+    /// The Solidity borrowers, available for every lender kind
+    #[default]
+    Solidity,
+    /// The gas-optimized Huff borrower, available for ERC-3156 lenders
+    Huff,
+}
+
+impl BorrowerFlavor {
+    /// The creation bytecode of this flavor's borrower for the lender kind
+    ///
+    /// ### Errors
+    ///
+    /// Returns an [UnsupportedFlavor](FlashloanError::UnsupportedFlavor) if the flavor has no
+    /// borrower for the lender kind, and a [MissingBytecode](FlashloanError::MissingBytecode) if
+    /// the borrower has no bundled bytecode.
+    pub fn borrower_bytecode(&self, kind: LenderKind) -> Result<Bytes> {
+        match (self, kind) {
+            (BorrowerFlavor::Solidity, kind) => kind.borrower_bytecode(),
+            (BorrowerFlavor::Huff, LenderKind::Erc3156) => huff_borrower_bytecode(),
+            (BorrowerFlavor::Huff, kind) => {
+                Err(FlashloanError::UnsupportedFlavor(kind.borrower_name().to_string()).into())
+            }
+        }
+    }
+}
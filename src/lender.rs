@@ -0,0 +1,302 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::{abi::Abi, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, str::FromStr, sync::Arc};
+
+use crate::{contract::*, errors::*};
+
+/// The MakerDAO DssFlash ERC-3156 lender on mainnet
+///
+/// See: https://github.com/makerdao/dss-flash#deployment
+pub const DSS_FLASH: &str = "0x1eb4cf3a948e7d72a198fe073ccb8c7a948cd853";
+
+/// The flash lender protocol
+///
+/// Each protocol calls back into the borrower through a different function, so the kind of lender
+/// determines which borrower contract is deployed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LenderKind {
+    /// An [ERC-3156](https://eips.ethereum.org/EIPS/eip-3156) lender, calling `onFlashLoan`
+    #[default]
+    Erc3156,
+    /// An Aave V3 Pool's `flashLoanSimple`, calling `executeOperation`
+    AaveV3,
+    /// The Balancer V2 Vault's `flashLoan`, calling `receiveFlashLoan`
+    Balancer,
+    /// A Uniswap V3 Pool's `flash`, calling `uniswapV3FlashCallback`
+    UniswapV3,
+}
+
+impl LenderKind {
+    /// The name of the borrower contract implementing this lender's callback
+    pub fn borrower_name(&self) -> &'static str {
+        match self {
+            LenderKind::Erc3156 => "FlashBorrower",
+            LenderKind::AaveV3 => "AaveFlashBorrower",
+            LenderKind::Balancer => "BalancerFlashBorrower",
+            LenderKind::UniswapV3 => "UniswapV3FlashBorrower",
+        }
+    }
+
+    /// The creation bytecode of the borrower contract for this lender
+    ///
+    /// All borrower variants share the [Flashloan](crate::contract::Flashloan) abi, only their
+    /// lender callback differs.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the bundled artifact has not
+    /// been compiled.
+    pub fn borrower_bytecode(&self) -> Result<Bytes> {
+        let artifact = match self {
+            LenderKind::Erc3156 => return Ok(FLASHLOAN_BYTECODE.clone()),
+            LenderKind::AaveV3 => include_str!("AaveFlashBorrower.json"),
+            LenderKind::Balancer => include_str!("BalancerFlashBorrower.json"),
+            LenderKind::UniswapV3 => include_str!("UniswapV3FlashBorrower.json"),
+        };
+        artifact_bytecode(self.borrower_name(), artifact)
+    }
+
+    /// The [Lender] of this kind deployed at `address`
+    pub fn lender<M: Middleware + 'static>(&self, address: Address) -> Box<dyn Lender<M>> {
+        match self {
+            LenderKind::Erc3156 => Box::new(Erc3156Lender(address)),
+            LenderKind::AaveV3 => Box::new(AaveV3Lender(address)),
+            LenderKind::Balancer => Box::new(BalancerLender(address)),
+            LenderKind::UniswapV3 => Box::new(UniswapV3Lender(address)),
+        }
+    }
+}
+
+/// Parses the creation bytecode of a bundled contract artifact
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the artifact has not been
+/// compiled.
+pub(crate) fn artifact_bytecode(name: &str, artifact: &str) -> Result<Bytes> {
+    let artifact: serde_json::Value = serde_json::from_str(artifact)?;
+    let bytecode = artifact["bytecode"]["object"]
+        .as_str()
+        .and_then(|object| Bytes::from_str(object).ok())
+        .filter(|bytecode| !bytecode.is_empty())
+        .ok_or_else(|| FlashloanError::MissingBytecode(name.to_string()))?;
+    Ok(bytecode)
+}
+
+/// Parses the abi of a bundled contract artifact
+pub(crate) fn artifact_abi(artifact: &str) -> Abi {
+    // This won't panic since the bundled artifacts are checked by the tests
+    let artifact: serde_json::Value = serde_json::from_str(artifact).unwrap();
+    serde_json::from_value(artifact["abi"].clone()).unwrap()
+}
+
+/// A Flash Lender
+///
+/// Describes a lending protocol deployment and how to query its liquidity and fees.
+///
+/// ### Usage
+///
+/// Pass a lender to [with_flash_lender](crate::builder::FlashloanBuilder::with_flash_lender) to
+/// have the builder deploy the matching borrower contract.
+#[async_trait]
+pub trait Lender<M: Middleware + 'static>: Debug + Send + Sync {
+    /// The address the borrower calls to initiate the flashloan
+    fn address(&self) -> Address;
+
+    /// The lender protocol
+    fn kind(&self) -> LenderKind;
+
+    /// [**Async**] The maximum amount of `token` available to borrow
+    async fn max_flash_loan(&self, client: Arc<M>, token: Address) -> Result<U256>;
+
+    /// [**Async**] The fee charged to borrow `amount` of `token`
+    async fn flash_fee(&self, client: Arc<M>, token: Address, amount: U256) -> Result<U256>;
+}
+
+/// An [ERC-3156](https://eips.ethereum.org/EIPS/eip-3156) Flash Lender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc3156Lender(pub Address);
+
+impl Default for Erc3156Lender {
+    /// The MakerDAO DssFlash lender
+    fn default() -> Self {
+        // This won't panic since the address is checked
+        Self(Address::from_str(DSS_FLASH).unwrap())
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> Lender<M> for Erc3156Lender {
+    fn address(&self) -> Address {
+        self.0
+    }
+
+    fn kind(&self) -> LenderKind {
+        LenderKind::Erc3156
+    }
+
+    async fn max_flash_loan(&self, client: Arc<M>, token: Address) -> Result<U256> {
+        let lender = IERC3156FlashLender::new(self.0, client);
+        let max = lender
+            .max_flash_loan(token)
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        Ok(max)
+    }
+
+    async fn flash_fee(&self, client: Arc<M>, token: Address, amount: U256) -> Result<U256> {
+        let lender = IERC3156FlashLender::new(self.0, client);
+        let fee = lender
+            .flash_fee(token, amount)
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        Ok(fee)
+    }
+}
+
+/// An Aave V3 Pool, borrowed from with `flashLoanSimple`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AaveV3Lender(pub Address);
+
+#[async_trait]
+impl<M: Middleware + 'static> Lender<M> for AaveV3Lender {
+    fn address(&self) -> Address {
+        self.0
+    }
+
+    fn kind(&self) -> LenderKind {
+        LenderKind::AaveV3
+    }
+
+    async fn max_flash_loan(&self, client: Arc<M>, token: Address) -> Result<U256> {
+        // The liquidity is held by the reserve's aToken
+        let pool = IAaveV3Pool::new(self.0, Arc::clone(&client));
+        let reserve = pool
+            .get_reserve_data(token)
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        let a_token = reserve.8;
+        if a_token.is_zero() {
+            return Ok(U256::zero())
+        }
+        let max = IERC20::new(token, client)
+            .balance_of(a_token)
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        Ok(max)
+    }
+
+    async fn flash_fee(&self, client: Arc<M>, _token: Address, amount: U256) -> Result<U256> {
+        let pool = IAaveV3Pool::new(self.0, client);
+        let premium = pool
+            .flashloan_premium_total()
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        // Aave's `percentMul` rounds half up over a 1e4 basis
+        Ok((amount * U256::from(premium) + 5_000) / 10_000)
+    }
+}
+
+/// The Balancer V2 Vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalancerLender(pub Address);
+
+#[async_trait]
+impl<M: Middleware + 'static> Lender<M> for BalancerLender {
+    fn address(&self) -> Address {
+        self.0
+    }
+
+    fn kind(&self) -> LenderKind {
+        LenderKind::Balancer
+    }
+
+    async fn max_flash_loan(&self, client: Arc<M>, token: Address) -> Result<U256> {
+        let max = IERC20::new(token, client)
+            .balance_of(self.0)
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        Ok(max)
+    }
+
+    async fn flash_fee(&self, client: Arc<M>, _token: Address, amount: U256) -> Result<U256> {
+        let vault = IBalancerVault::new(self.0, Arc::clone(&client));
+        let collector = vault
+            .get_protocol_fees_collector()
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        let percentage = IBalancerProtocolFeesCollector::new(collector, client)
+            .get_flash_loan_fee_percentage()
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        // Balancer's `mulUp` rounds up over a 1e18 basis
+        let product = amount * percentage;
+        if product.is_zero() {
+            return Ok(U256::zero())
+        }
+        let one = U256::exp10(18);
+        Ok((product - 1) / one + 1)
+    }
+}
+
+/// A Uniswap V3 Pool, borrowed from with `flash`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniswapV3Lender(pub Address);
+
+#[async_trait]
+impl<M: Middleware + 'static> Lender<M> for UniswapV3Lender {
+    fn address(&self) -> Address {
+        self.0
+    }
+
+    fn kind(&self) -> LenderKind {
+        LenderKind::UniswapV3
+    }
+
+    async fn max_flash_loan(&self, client: Arc<M>, token: Address) -> Result<U256> {
+        // Only the two pool tokens can be borrowed
+        let pool = IUniswapV3Pool::new(self.0, Arc::clone(&client));
+        let token0 = pool
+            .token_0()
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        let token1 = pool
+            .token_1()
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        if token != token0 && token != token1 {
+            return Ok(U256::zero())
+        }
+        let max = IERC20::new(token, client)
+            .balance_of(self.0)
+            .call()
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        Ok(max)
+    }
+
+    async fn flash_fee(&self, client: Arc<M>, _token: Address, amount: U256) -> Result<U256> {
+        let pool = IUniswapV3Pool::new(self.0, client);
+        let fee =
+            pool.fee().call().await.map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        // The pool fee is denominated in hundredths of a bip, rounded up
+        let product = amount * U256::from(fee);
+        if product.is_zero() {
+            return Ok(U256::zero())
+        }
+        Ok((product - 1) / 1_000_000 + 1)
+    }
+}
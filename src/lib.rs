@@ -2,7 +2,6 @@
 #![warn(missing_docs)]
 #![warn(unused_extern_crates)]
 #![forbid(unsafe_code)]
-#![forbid(where_clauses_object_safety)]
 
 /// Flashloan-rs errors
 pub mod errors;
@@ -13,7 +12,89 @@ pub mod builder;
 /// The flashloan contract
 pub mod contract;
 
+/// Flash fee caching
+pub mod cache;
+
+/// Typed calls for common DeFi actions
+pub mod calls;
+
+/// Deterministic CREATE2 borrower deployment
+pub mod create2;
+
+/// Private bundle submission
+pub mod bundle;
+
+/// Gas estimation and fee strategies
+pub mod gas;
+
+/// The gas-optimized Huff borrower
+pub mod huff;
+
+/// Flash lender protocols
+pub mod lender;
+
+/// Multi-token, multi-lender nested flashloans
+pub mod nested;
+
+/// The borrower with flashloan operators and a transferable owner
+pub mod operators;
+
+/// Flashloan outcomes decoded from receipts
+pub mod outcome;
+
+/// Validated, immutable flashloan plans
+pub mod plan;
+
+/// Weiroll-style command programs
+pub mod program;
+
+/// Profitability guards
+pub mod profit;
+
+/// Known lenders and tokens by chain
+pub mod registry;
+
+/// Revert data decoding
+pub mod revert;
+
+/// Lender selection by fee and liquidity
+pub mod router;
+
+/// Flashloan plans as JSON or TOML documents
+pub mod spec;
+
+/// Profit sweeping from the borrower
+pub mod sweep;
+
+/// Native ether value of call batches
+pub mod value;
+
+/// In-process EVM flashloan simulation
+#[cfg(feature = "simulate")]
+pub mod simulate;
+
+/// The `flashloan` command-line tool
+#[cfg(feature = "cli")]
+pub mod cli;
+
+/// Mock lender and token deployments for offline tests
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// Re-export a prelude
 pub mod prelude {
-    pub use super::{builder::*, contract::*, errors::*};
+    pub use super::{
+        builder::*, bundle::*, cache::*, calls::*, contract::*, create2::*, errors::*, gas::*,
+        huff::*, lender::*, nested::*, operators::*, outcome::*, plan::*, profit::*, program::*,
+        registry::*, revert::*, router::*, spec::*, sweep::*, value::*,
+    };
+
+    #[cfg(feature = "simulate")]
+    pub use super::simulate::*;
+
+    #[cfg(feature = "cli")]
+    pub use super::cli::*;
+
+    #[cfg(feature = "testing")]
+    pub use super::testing::*;
 }
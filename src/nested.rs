@@ -0,0 +1,168 @@
+use anyhow::Result;
+use ethers::{
+    abi::{Abi, InvalidOutputType, Token, Tokenizable, TokenizableItem},
+    contract::{builders::ContractCall, Lazy},
+    prelude::*,
+};
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{contract::*, lender::*, profit::*};
+
+/// The name of the nested flashloan borrower contract
+pub const MULTI_BORROWER_NAME: &str = "MultiFlashBorrower";
+
+/// The bundled `MultiFlashBorrower` artifact
+const MULTI_BORROWER_ARTIFACT: &str = include_str!("MultiFlashBorrower.json");
+
+/// The abi of the `MultiFlashBorrower` contract
+///
+/// Extends the [Flashloan] abi with the nested `flashBorrowMulti` entrypoints, which are called
+/// through the [IMultiFlashBorrower] bindings.
+pub static MULTI_FLASHLOAN_ABI: Lazy<Abi> = Lazy::new(|| artifact_abi(MULTI_BORROWER_ARTIFACT));
+
+/// The creation bytecode of the `MultiFlashBorrower` contract
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](crate::errors::FlashloanError::MissingBytecode) if the bundled
+/// artifact has not been compiled.
+pub fn multi_borrower_bytecode() -> Result<Bytes> {
+    artifact_bytecode(MULTI_BORROWER_NAME, MULTI_BORROWER_ARTIFACT)
+}
+
+/// A token borrowed from a lender in a nested flashloan
+///
+/// ### Usage
+///
+/// Legs are borrowed in order, each from the callback of the previous leg. The calls are executed
+/// in the innermost callback, once every token is borrowed, and each leg is repaid as the
+/// callbacks unwind.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// let dai = Address::random();
+/// let weth = Address::random();
+/// let vault = BalancerLender(Address::random());
+///
+/// let legs = vec![
+///     FlashloanLeg::new(LenderKind::Erc3156, Erc3156Lender::default().0, dai, U256::exp10(24)),
+///     FlashloanLeg::new(LenderKind::Balancer, vault.0, weth, U256::exp10(21)),
+/// ];
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashloanLeg {
+    /// The lender protocol
+    #[serde(default)]
+    pub kind: LenderKind,
+    /// The lender address
+    pub lender: Address,
+    /// The token to borrow
+    pub token: Address,
+    /// The amount to borrow
+    #[serde(with = "crate::spec::amount")]
+    pub amount: U256,
+}
+
+impl FlashloanLeg {
+    /// Borrow `amount` of `token` from the `kind` lender at `lender`
+    pub fn new(kind: LenderKind, lender: Address, token: Address, amount: U256) -> Self {
+        Self { kind, lender, token, amount }
+    }
+
+    /// Borrow `amount` of `token` from a [Lender]
+    pub fn from_lender<M: Middleware + 'static, L: Lender<M>>(
+        lender: &L,
+        token: Address,
+        amount: U256,
+    ) -> Self {
+        Self::new(lender.kind(), lender.address(), token, amount)
+    }
+
+    /// [**Async**] The fee charged by the lender for the leg
+    pub async fn flash_fee<M: Middleware + 'static>(&self, client: Arc<M>) -> Result<U256> {
+        self.kind.lender::<M>(self.lender).flash_fee(client, self.token, self.amount).await
+    }
+
+    /// The `MultiFlashBorrower.LenderKind` enum ordinal of the lender protocol
+    fn kind_ordinal(&self) -> u8 {
+        match self.kind {
+            LenderKind::Erc3156 => 0,
+            LenderKind::AaveV3 => 1,
+            LenderKind::Balancer => 2,
+            LenderKind::UniswapV3 => 3,
+        }
+    }
+}
+
+impl From<FlashloanLeg> for Leg {
+    fn from(leg: FlashloanLeg) -> Self {
+        Self { kind: leg.kind_ordinal(), lender: leg.lender, token: leg.token, amount: leg.amount }
+    }
+}
+
+impl Tokenizable for FlashloanLeg {
+    fn from_token(token: Token) -> Result<Self, InvalidOutputType> {
+        let invalid = || InvalidOutputType(format!("Expected a flashloan leg, got {:?}", token));
+        let (kind, lender, token_address, amount) = match &token {
+            Token::Tuple(tokens) => match tokens.as_slice() {
+                [Token::Uint(kind), Token::Address(lender), Token::Address(address), Token::Uint(amount)] => {
+                    (*kind, *lender, *address, *amount)
+                }
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+        let kind = match kind.as_u64() {
+            0 => LenderKind::Erc3156,
+            1 => LenderKind::AaveV3,
+            2 => LenderKind::Balancer,
+            3 => LenderKind::UniswapV3,
+            _ => return Err(invalid()),
+        };
+        Ok(Self::new(kind, lender, token_address, amount))
+    }
+
+    fn into_token(self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(self.kind_ordinal().into()),
+            Token::Address(self.lender),
+            Token::Address(self.token),
+            Token::Uint(self.amount),
+        ])
+    }
+}
+
+impl TokenizableItem for FlashloanLeg {}
+
+/// Builds the nested flashloan entrypoint call, guarded by the profit guard if set
+pub(crate) fn flash_borrow_multi<M: Middleware>(
+    borrower: Address,
+    client: Arc<M>,
+    legs: &[FlashloanLeg],
+    calls: &[Call3],
+    profit_guard: Option<ProfitGuard>,
+) -> ContractCall<M, Vec<Call3Result>> {
+    let contract = IMultiFlashBorrower::new(borrower, client);
+    let legs = legs.iter().copied().map(Leg::from).collect();
+    match profit_guard {
+        Some(guard) => contract.flash_borrow_multi_with_profit(
+            legs,
+            calls.to_vec(),
+            guard.token,
+            guard.min_profit,
+        ),
+        None => contract.flash_borrow_multi(legs, calls.to_vec()),
+    }
+}
+
+/// [**Async**] Query the fee of every leg in parallel
+pub(crate) async fn leg_fees<M: Middleware + 'static>(
+    client: Arc<M>,
+    legs: &[FlashloanLeg],
+) -> Result<Vec<U256>> {
+    try_join_all(legs.iter().map(|leg| leg.flash_fee(Arc::clone(&client)))).await
+}
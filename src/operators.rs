@@ -0,0 +1,31 @@
+use anyhow::Result;
+use ethers::{abi::Abi, contract::Lazy, prelude::*};
+
+use crate::lender::*;
+
+/// The name of the flashloan borrower contract with operators
+pub const OPERATED_BORROWER_NAME: &str = "OperatedFlashBorrower";
+
+/// The bundled `OperatedFlashBorrower` artifact
+const OPERATED_BORROWER_ARTIFACT: &str = include_str!("OperatedFlashBorrower.json");
+
+/// The abi of the `OperatedFlashBorrower` contract
+///
+/// Extends the [Flashloan](crate::contract::Flashloan) abi with the two-step ownership transfer
+/// and the operators allowed to trigger flashloans, which are called through the
+/// [IOperatedFlashBorrower](crate::contract::IOperatedFlashBorrower) bindings.
+pub static OPERATED_FLASHLOAN_ABI: Lazy<Abi> =
+    Lazy::new(|| artifact_abi(OPERATED_BORROWER_ARTIFACT));
+
+/// The creation bytecode of the `OperatedFlashBorrower` contract
+///
+/// The contract is an ERC-3156 borrower taking the same `(lender, owner)` constructor arguments
+/// as the [Flashloan](crate::contract::Flashloan) borrower.
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](crate::errors::FlashloanError::MissingBytecode) if the bundled
+/// artifact has not been compiled.
+pub fn operated_borrower_bytecode() -> Result<Bytes> {
+    artifact_bytecode(OPERATED_BORROWER_NAME, OPERATED_BORROWER_ARTIFACT)
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use ethers::{prelude::*, utils::keccak256};
+use std::collections::BTreeMap;
+
+use crate::{errors::*, profit::*, sweep::*};
+
+/// A lender's `FlashLoan` event
+///
+/// Decoded from the ERC-3156 mock lender, Aave V3 pool and Balancer V2 vault events. Uniswap V3
+/// pools log `Flash` events without the token addresses, so they are not decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashLoanEvent {
+    /// The lender contract that emitted the event
+    pub lender: Address,
+    /// The borrower the loan was sent to
+    pub receiver: Address,
+    /// The borrowed token
+    pub token: Address,
+    /// The amount borrowed
+    pub amount: U256,
+    /// The fee charged on top of the amount
+    pub fee: U256,
+}
+
+/// The profit of an executed flashloan in a quote token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RealizedProfit {
+    /// The quote token
+    pub token: Address,
+    /// The borrower's net change in the quote token, after repaying the loan and fee
+    pub gain: I256,
+    /// The gas cost of the transaction in quote token units
+    pub gas_cost_in_token: U256,
+    /// The gain less the gas cost
+    pub net_profit: I256,
+}
+
+/// The decoded result of an executed flashloan
+///
+/// ### Usage
+///
+/// Decode the receipt returned by [execute](crate::builder::FlashloanBuilder::execute) with
+/// [outcome](crate::builder::FlashloanBuilder::outcome), or from any receipt and borrower with
+/// [from_receipt](FlashloanOutcome::from_receipt).
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// let (borrower, weth) = (Address::random(), Address::random());
+/// let receipt = TransactionReceipt { status: Some(1.into()), ..Default::default() };
+///
+/// // Quote the realized profit in WETH, priced 1:1 with ether
+/// let outcome = FlashloanOutcome::from_receipt(&receipt, borrower).unwrap();
+/// let outcome = outcome.with_quote(weth, U256::exp10(18));
+/// assert!(outcome.success);
+/// assert_eq!(outcome.profit.unwrap().net_profit, I256::zero());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashloanOutcome {
+    /// The flashloan transaction
+    pub transaction_hash: H256,
+    /// Whether the transaction succeeded
+    pub success: bool,
+    /// The borrower contract
+    pub borrower: Address,
+    /// The lender `FlashLoan` events
+    pub loans: Vec<FlashLoanEvent>,
+    /// The ERC20 transfers into the borrower
+    pub transfers_in: Vec<Erc20Transfer>,
+    /// The ERC20 transfers out of the borrower
+    pub transfers_out: Vec<Erc20Transfer>,
+    /// The borrower's net balance change by token
+    pub net_changes: BTreeMap<Address, I256>,
+    /// The gas used by the transaction
+    pub gas_used: U256,
+    /// The effective gas price, zero if the node doesn't report it
+    pub effective_gas_price: U256,
+    /// The gas used times the effective gas price, in wei
+    pub gas_cost: U256,
+    /// The realized profit, once quoted with [with_quote](FlashloanOutcome::with_quote)
+    pub profit: Option<RealizedProfit>,
+}
+
+impl FlashloanOutcome {
+    /// Decode the outcome of a flashloan through `borrower` from its receipt
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [BalanceDeltaOverflow](FlashloanError::BalanceDeltaOverflow) if the transfers of
+    /// a token don't sum to a uint256, or their net change doesn't fit in an int256.
+    pub fn from_receipt(receipt: &TransactionReceipt, borrower: Address) -> Result<Self> {
+        let (transfers_in, transfers_out) = erc20_transfers(receipt)
+            .into_iter()
+            .filter(|transfer| transfer.from != transfer.to)
+            .filter(|transfer| transfer.to == borrower || transfer.from == borrower)
+            .partition::<Vec<_>, _>(|transfer| transfer.to == borrower);
+
+        // Sum the transfers in and out of each token, then take the signed difference
+        let mut flows = BTreeMap::<Address, (U256, U256)>::new();
+        for (transfer, inflow) in transfers_in
+            .iter()
+            .map(|transfer| (transfer, true))
+            .chain(transfers_out.iter().map(|transfer| (transfer, false)))
+        {
+            let (total_in, total_out) = flows.entry(transfer.token).or_default();
+            let total = if inflow { total_in } else { total_out };
+            *total =
+                total.checked_add(transfer.amount).ok_or(FlashloanError::BalanceDeltaOverflow {
+                    before: *total,
+                    after: transfer.amount,
+                })?;
+        }
+        let net_changes = flows
+            .into_iter()
+            .map(|(token, (total_in, total_out))| Ok((token, balance_delta(total_out, total_in)?)))
+            .collect::<Result<_>>()?;
+
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        let effective_gas_price = receipt.effective_gas_price.unwrap_or_default();
+        Ok(Self {
+            transaction_hash: receipt.transaction_hash,
+            success: receipt.status == Some(1.into()),
+            borrower,
+            loans: flash_loan_events(receipt),
+            transfers_in,
+            transfers_out,
+            net_changes,
+            gas_used,
+            effective_gas_price,
+            gas_cost: gas_used * effective_gas_price,
+            profit: None,
+        })
+    }
+
+    /// Quote the realized profit in `token`
+    ///
+    /// `eth_price` is the value of one ether in `token` units, used to convert the gas cost as a
+    /// [ProfitGuard] does.
+    pub fn with_quote(mut self, token: Address, eth_price: U256) -> Self {
+        let gain = self.net_change(token);
+        let gas_cost_in_token = ProfitGuard::new(token, U256::zero())
+            .with_eth_price(eth_price)
+            .gas_cost_in_token(self.gas_cost);
+        let net_profit = gain - I256::from_raw(gas_cost_in_token);
+        self.profit = Some(RealizedProfit { token, gain, gas_cost_in_token, net_profit });
+        self
+    }
+
+    /// The borrower's net balance change of `token`
+    pub fn net_change(&self, token: Address) -> I256 {
+        self.net_changes.get(&token).copied().unwrap_or_default()
+    }
+
+    /// The total fee paid to the lenders in `token`
+    pub fn fees(&self, token: Address) -> U256 {
+        self.loans
+            .iter()
+            .filter(|loan| loan.token == token)
+            .fold(U256::zero(), |total, loan| total + loan.fee)
+    }
+}
+
+/// Decode the lender `FlashLoan` events of a transaction receipt
+///
+/// The ERC-3156 mock lender and the Balancer vault share an event signature, and are told apart
+/// by which parameters are indexed.
+pub fn flash_loan_events(receipt: &TransactionReceipt) -> Vec<FlashLoanEvent> {
+    let erc3156_or_balancer = H256::from(keccak256("FlashLoan(address,address,uint256,uint256)"));
+    let aave =
+        H256::from(keccak256("FlashLoan(address,address,address,uint256,uint8,uint256,uint16)"));
+    let word =
+        |data: &[u8], index: usize| U256::from_big_endian(&data[32 * index..32 * index + 32]);
+    receipt
+        .logs
+        .iter()
+        .filter_map(|log| {
+            let (topics, data) = (&log.topics, &log.data);
+            match (topics.first(), topics.len(), data.len()) {
+                // FlashLoan(address indexed receiver, address token, uint256 amount, uint256 fee)
+                (Some(topic), 2, 96) if *topic == erc3156_or_balancer => Some(FlashLoanEvent {
+                    lender: log.address,
+                    receiver: Address::from(topics[1]),
+                    token: Address::from(H256::from_slice(&data[..32])),
+                    amount: word(data, 1),
+                    fee: word(data, 2),
+                }),
+                // FlashLoan(address indexed recipient, address indexed token, uint256 amount,
+                // uint256 feeAmount)
+                (Some(topic), 3, 64) if *topic == erc3156_or_balancer => Some(FlashLoanEvent {
+                    lender: log.address,
+                    receiver: Address::from(topics[1]),
+                    token: Address::from(topics[2]),
+                    amount: word(data, 0),
+                    fee: word(data, 1),
+                }),
+                // FlashLoan(address indexed target, address initiator, address indexed asset,
+                // uint256 amount, uint8 interestRateMode, uint256 premium, uint16 indexed referral)
+                (Some(topic), 4, 128) if *topic == aave => Some(FlashLoanEvent {
+                    lender: log.address,
+                    receiver: Address::from(topics[1]),
+                    token: Address::from(topics[2]),
+                    amount: word(data, 1),
+                    fee: word(data, 3),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The signed change from `before` to `after`
+///
+/// ### Errors
+///
+/// Returns a [BalanceDeltaOverflow](FlashloanError::BalanceDeltaOverflow) if the change doesn't
+/// fit in an int256.
+pub(crate) fn balance_delta(before: U256, after: U256) -> Result<I256> {
+    let (delta, decreased) = after.overflowing_sub(before);
+    let delta = if decreased {
+        // The wrapped difference is the two's complement of the decrease
+        Some(I256::from_raw(delta)).filter(|delta| delta.is_negative())
+    } else {
+        I256::try_from(delta).ok()
+    };
+    delta.ok_or_else(|| FlashloanError::BalanceDeltaOverflow { before, after }.into())
+}
@@ -0,0 +1,989 @@
+use anyhow::Result;
+use ethers::{abi::RawLog, contract::builders::ContractCall, prelude::*};
+use std::{fmt, sync::Arc};
+use tracing::{debug, field, info, instrument, warn, Span};
+
+use crate::{
+    builder::*, bundle::*, contract::*, errors::*, gas::*, lender::*, nested::*, outcome::*,
+    profit::*, program::*, registry::*, revert::*, value::*,
+};
+
+/// A validated, immutable flashloan
+///
+/// ### Usage
+///
+/// A plan is built with [FlashloanBuilder::with_client], or from a configured builder with
+/// [plan](FlashloanBuilder::plan). The token, amount and borrower are checked when the plan is
+/// built, so a plan can always be executed. Plans are cheap to clone and only take `&self`, so
+/// the same plan can be called and executed many times concurrently.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+/// let plan = FlashloanBuilder::with_client(client)
+///     .borrower(Address::random())
+///     .token(Address::random())
+///     .amount(U256::exp10(18))
+///     .build()
+///     .unwrap();
+/// assert_eq!(plan.amount(), U256::exp10(18));
+/// ```
+pub struct FlashloanPlan<M> {
+    client: Arc<M>,
+    chain_id: Option<u64>,
+    borrower: Address,
+    owner: Option<Address>,
+    lender: Option<Address>,
+    lender_kind: LenderKind,
+    borrow_from: Option<Address>,
+    token: Address,
+    amount: U256,
+    calls: Vec<Call3>,
+    legs: Vec<FlashloanLeg>,
+    program: Option<Program>,
+    repayment: Option<U256>,
+    call_value: CallValue,
+    revert_decoder: RevertDecoder,
+    profit_guard: Option<ProfitGuard>,
+    bundle_relay: Option<BundleRelay>,
+    fee_strategy: FeeStrategy,
+    gas_padding_bps: u64,
+}
+
+/// Builds a [FlashloanPlan], checking required parameters up front
+///
+/// Created with [FlashloanBuilder::with_client]. Each method consumes and returns the builder.
+pub struct FlashloanPlanBuilder<M> {
+    client: Arc<M>,
+    chain_id: Option<u64>,
+    borrower: Option<Address>,
+    owner: Option<Address>,
+    lender: Option<Address>,
+    lender_kind: LenderKind,
+    borrow_from: Option<Address>,
+    token: Option<Address>,
+    amount: Option<U256>,
+    calls: Vec<Call3>,
+    legs: Vec<FlashloanLeg>,
+    program: Option<Program>,
+    repayment: Option<U256>,
+    call_value: CallValue,
+    weth_wrap: Option<WethWrap>,
+    revert_decoder: RevertDecoder,
+    profit_guard: Option<ProfitGuard>,
+    bundle_relay: Option<BundleRelay>,
+    fee_strategy: FeeStrategy,
+    gas_padding_bps: u64,
+}
+
+impl<M: Middleware> FlashloanBuilder<M> {
+    /// Start building a [FlashloanPlan] with a client
+    pub fn with_client(client: Arc<M>) -> FlashloanPlanBuilder<M> {
+        FlashloanPlanBuilder {
+            client,
+            chain_id: None,
+            borrower: None,
+            owner: None,
+            lender: None,
+            lender_kind: LenderKind::default(),
+            borrow_from: None,
+            token: None,
+            amount: None,
+            calls: vec![],
+            legs: vec![],
+            program: None,
+            repayment: None,
+            call_value: CallValue::default(),
+            weth_wrap: None,
+            revert_decoder: RevertDecoder::default(),
+            profit_guard: None,
+            bundle_relay: None,
+            fee_strategy: FeeStrategy::default(),
+            gas_padding_bps: 0,
+        }
+    }
+
+    /// Snapshot the builder into an immutable [FlashloanPlan]
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingToken](FlashloanError::MissingToken),
+    /// [MissingAmount](FlashloanError::MissingAmount) or
+    /// [MissingBorrower](FlashloanError::MissingBorrower) if the respective parameter is not
+    /// specified, and a [CallConstructionError](FlashloanError::CallConstructionError) if the
+    /// WETH wrap can't be encoded or is combined with a program.
+    pub fn plan(&self) -> Result<FlashloanPlan<M>> {
+        let (token, amount) = self.loan()?;
+        self.plan_for(token, amount, &self.calls)
+    }
+
+    /// Snapshot the builder into a plan borrowing `amount` of `token` with `calls`
+    pub(crate) fn plan_for(
+        &self,
+        token: Address,
+        amount: U256,
+        calls: &[Call3],
+    ) -> Result<FlashloanPlan<M>> {
+        let borrower = self.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?.address();
+        check_program(&self.legs, &self.program)?;
+        // The lender is only needed to quote fees, so an unknown chain isn't an error here
+        let (lender, lender_kind) = match self.resolve_lender(None) {
+            Ok((lender, lender_kind)) => (Some(lender), lender_kind),
+            Err(_) => (None, self.lender_kind),
+        };
+        check_borrow_from(self.borrow_from, lender_kind, &self.legs, &self.program)?;
+        check_weth_wrap(&self.weth_wrap, &self.program)?;
+        let calls = match &self.weth_wrap {
+            Some(weth_wrap) => weth_wrap.around(calls)?,
+            None => calls.to_vec(),
+        };
+        Ok(FlashloanPlan {
+            client: Arc::clone(&self.client),
+            chain_id: Some(self.chain_id),
+            borrower,
+            owner: self.owner,
+            lender,
+            lender_kind,
+            borrow_from: self.borrow_from,
+            token,
+            amount,
+            calls,
+            legs: self.legs.clone(),
+            program: self.program.clone(),
+            repayment: None,
+            call_value: self.call_value,
+            revert_decoder: self.revert_decoder.clone(),
+            profit_guard: self.profit_guard,
+            bundle_relay: self.bundle_relay.clone(),
+            fee_strategy: self.fee_strategy,
+            gas_padding_bps: self.gas_padding_bps,
+        })
+    }
+}
+
+impl<M: Middleware> FlashloanPlanBuilder<M> {
+    /// Set the chain id, used to sign transactions submitted through a bundle relay
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Set the deployed flashloan borrower contract
+    pub fn borrower(mut self, borrower: Address) -> Self {
+        self.borrower = Some(borrower);
+        self
+    }
+
+    /// Set the borrower owner, which simulations are sent from
+    pub fn owner(mut self, owner: Address) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Set the flash lender address
+    pub fn lender(mut self, lender: Address) -> Self {
+        self.lender = Some(lender);
+        self
+    }
+
+    /// Set the flash lender protocol and address
+    pub fn flash_lender<L: Lender<M>>(mut self, lender: L) -> Self
+    where
+        M: 'static,
+    {
+        self.lender = Some(lender.address());
+        self.lender_kind = lender.kind();
+        self
+    }
+
+    /// Borrow from a lender trusted by the borrower instead of the lender it was deployed with
+    ///
+    /// The loan is borrowed through the `flashBorrow` overloads taking the lender, which revert
+    /// with [UntrustedLender](FlashloanError::UntrustedLender) unless the borrower owner trusted
+    /// the lender with [trust_lender](FlashloanBuilder::trust_lender). Only the ERC-3156 borrower
+    /// can borrow from another lender.
+    pub fn borrow_from(mut self, lender: Address) -> Self {
+        self.borrow_from = Some(lender);
+        self
+    }
+
+    /// Set the token to borrow
+    pub fn token(mut self, token: Address) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Set the amount to borrow
+    pub fn amount(mut self, amount: U256) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Append a call to execute in the lender callback
+    pub fn call(mut self, call: Call3) -> Self {
+        self.calls.push(call);
+        self
+    }
+
+    /// Append calls to execute in the lender callback
+    pub fn calls<I: IntoIterator<Item = Call3>>(mut self, calls: I) -> Self {
+        self.calls.extend(calls);
+        self
+    }
+
+    /// Append a leg to borrow in a nested flashloan
+    ///
+    /// See [add_leg](FlashloanBuilder::add_leg).
+    pub fn leg(mut self, leg: FlashloanLeg) -> Self {
+        self.legs.push(leg);
+        self
+    }
+
+    /// Append legs to borrow in a nested flashloan
+    pub fn legs<I: IntoIterator<Item = FlashloanLeg>>(mut self, legs: I) -> Self {
+        self.legs.extend(legs);
+        self
+    }
+
+    /// Run a command program instead of the calls in the lender callback
+    ///
+    /// See [with_program](FlashloanBuilder::with_program).
+    pub fn program(mut self, program: Program) -> Self {
+        self.program = Some(program);
+        self
+    }
+
+    /// Repay the lender the given amount plus fee, computed off-chain
+    ///
+    /// The loan is borrowed through the ERC-3156 borrower's `flashBorrowWithRepayment`, which skips
+    /// the on-chain fee and allowance queries. Ignored with a profit guard, legs or a program, and
+    /// rejected by [build](FlashloanPlanBuilder::build) for other lender kinds.
+    pub fn repayment(mut self, repayment: U256) -> Self {
+        self.repayment = Some(repayment);
+        self
+    }
+
+    /// Set how the ether value of the calls is funded
+    ///
+    /// See [with_call_value](FlashloanBuilder::with_call_value).
+    pub fn call_value(mut self, call_value: CallValue) -> Self {
+        self.call_value = call_value;
+        self
+    }
+
+    /// Wrap ether into WETH before the calls and unwrap WETH after them
+    ///
+    /// See [with_weth_wrap](FlashloanBuilder::with_weth_wrap).
+    pub fn weth_wrap(mut self, weth_wrap: WethWrap) -> Self {
+        self.weth_wrap = Some(weth_wrap);
+        self
+    }
+
+    /// Require the flashloan to return a minimum profit
+    ///
+    /// See [with_profit_guard](FlashloanBuilder::with_profit_guard).
+    pub fn profit_guard(mut self, guard: ProfitGuard) -> Self {
+        self.profit_guard = Some(guard);
+        self
+    }
+
+    /// Submit the flashloan transaction through a private bundle relay
+    ///
+    /// See [with_bundle_relay](FlashloanBuilder::with_bundle_relay).
+    pub fn bundle_relay(mut self, relay: BundleRelay) -> Self {
+        self.bundle_relay = Some(relay);
+        self
+    }
+
+    /// Set the fees of the flashloan transaction
+    ///
+    /// See [with_fee_strategy](FlashloanBuilder::with_fee_strategy).
+    pub fn fee_strategy(mut self, strategy: FeeStrategy) -> Self {
+        self.fee_strategy = strategy;
+        self
+    }
+
+    /// Pad the gas limit of the flashloan transaction
+    ///
+    /// See [with_gas_padding](FlashloanBuilder::with_gas_padding).
+    pub fn gas_padding(mut self, padding_bps: u64) -> Self {
+        self.gas_padding_bps = padding_bps;
+        self
+    }
+
+    /// Register the custom errors of a lender or call target abi
+    pub fn error_abi(mut self, abi: &ethers::abi::Abi) -> Self {
+        self.revert_decoder.register_abi(abi);
+        self
+    }
+
+    /// Validate the parameters and build the [FlashloanPlan]
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingToken](FlashloanError::MissingToken),
+    /// [MissingAmount](FlashloanError::MissingAmount) or
+    /// [MissingBorrower](FlashloanError::MissingBorrower) if the respective parameter is not
+    /// specified. With legs, the token and amount default to the first leg's.
+    ///
+    /// If no lender is set, the [builtin](ChainRegistry::builtin) default lender for the chain
+    /// is used when the chain id is set.
+    ///
+    /// Returns a [FixedLender](FlashloanError::FixedLender) if the plan borrows from a trusted
+    /// lender with legs, a program or a lender kind other than ERC-3156, and a
+    /// [CallConstructionError](FlashloanError::CallConstructionError) if the WETH wrap can't be
+    /// encoded or is combined with a program, or if a repayment is set for a lender kind other
+    /// than ERC-3156.
+    pub fn build(self) -> Result<FlashloanPlan<M>> {
+        let first_leg = self.legs.first();
+        let token = self
+            .token
+            .or_else(|| first_leg.map(|leg| leg.token))
+            .ok_or(FlashloanError::MissingToken)?;
+        let amount = self
+            .amount
+            .or_else(|| first_leg.map(|leg| leg.amount))
+            .ok_or(FlashloanError::MissingAmount)?;
+        let borrower = self.borrower.ok_or(FlashloanError::MissingBorrower)?;
+        check_program(&self.legs, &self.program)?;
+        let (lender, lender_kind) = match (self.lender, self.chain_id) {
+            (None, Some(chain_id)) => ChainRegistry::builtin()
+                .default_lender(chain_id, self.lender_kind)
+                .map(|config| (Some(config.address), config.kind))
+                .unwrap_or((None, self.lender_kind)),
+            _ => (self.lender, self.lender_kind),
+        };
+        check_borrow_from(self.borrow_from, lender_kind, &self.legs, &self.program)?;
+        check_weth_wrap(&self.weth_wrap, &self.program)?;
+        check_repayment(self.repayment, lender_kind, &self.legs, &self.program)?;
+        let calls = match &self.weth_wrap {
+            Some(weth_wrap) => weth_wrap.around(&self.calls)?,
+            None => self.calls,
+        };
+        Ok(FlashloanPlan {
+            client: self.client,
+            chain_id: self.chain_id,
+            borrower,
+            owner: self.owner,
+            lender,
+            lender_kind,
+            borrow_from: self.borrow_from,
+            token,
+            amount,
+            calls,
+            legs: self.legs,
+            program: self.program,
+            repayment: self.repayment,
+            call_value: self.call_value,
+            revert_decoder: self.revert_decoder,
+            profit_guard: self.profit_guard,
+            bundle_relay: self.bundle_relay,
+            fee_strategy: self.fee_strategy,
+            gas_padding_bps: self.gas_padding_bps,
+        })
+    }
+}
+
+impl<M: Middleware> FlashloanPlan<M> {
+    /// The client
+    pub fn client(&self) -> Arc<M> {
+        Arc::clone(&self.client)
+    }
+
+    /// The chain id, if specified
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    /// The flashloan borrower contract
+    pub fn borrower(&self) -> Address {
+        self.borrower
+    }
+
+    /// The borrower owner, if specified
+    pub fn owner(&self) -> Option<Address> {
+        self.owner
+    }
+
+    /// The flash lender address, if specified
+    pub fn lender(&self) -> Option<Address> {
+        self.lender
+    }
+
+    /// The flash lender protocol
+    pub fn lender_kind(&self) -> LenderKind {
+        self.lender_kind
+    }
+
+    /// The trusted lender borrowed from instead of the borrower's deployed lender, if set
+    pub fn borrow_from(&self) -> Option<Address> {
+        self.borrow_from
+    }
+
+    /// The token to borrow
+    pub fn token(&self) -> Address {
+        self.token
+    }
+
+    /// The amount to borrow
+    pub fn amount(&self) -> U256 {
+        self.amount
+    }
+
+    /// The calls executed in the lender callback
+    pub fn calls(&self) -> &[Call3] {
+        &self.calls
+    }
+
+    /// The legs of a nested flashloan, empty for a single loan
+    pub fn legs(&self) -> &[FlashloanLeg] {
+        &self.legs
+    }
+
+    /// The command program run instead of the calls, if set
+    pub fn program(&self) -> Option<&Program> {
+        self.program.as_ref()
+    }
+
+    /// The repayment computed off-chain, if set
+    pub fn repayment(&self) -> Option<U256> {
+        self.repayment
+    }
+
+    /// How the ether value of the calls is funded
+    pub fn call_value(&self) -> CallValue {
+        self.call_value
+    }
+
+    /// The ether sent with the flashloan transaction
+    ///
+    /// With [Attach](CallValue::Attach), the summed value of the calls. Programs don't run the
+    /// calls, so send no ether.
+    pub fn value(&self) -> U256 {
+        match self.call_value {
+            CallValue::Attach => self.required_value(),
+            CallValue::Borrower => U256::zero(),
+        }
+    }
+
+    /// The minimum profit guard, if set
+    pub fn profit_guard(&self) -> Option<ProfitGuard> {
+        self.profit_guard
+    }
+
+    /// The private bundle relay, if set
+    pub fn bundle_relay(&self) -> Option<&BundleRelay> {
+        self.bundle_relay.as_ref()
+    }
+
+    /// The fee strategy of the flashloan transaction
+    pub fn fee_strategy(&self) -> FeeStrategy {
+        self.fee_strategy
+    }
+
+    /// The gas padding of the flashloan transaction, in basis points
+    pub fn gas_padding(&self) -> u64 {
+        self.gas_padding_bps
+    }
+
+    /// The revert decoder
+    pub fn revert_decoder(&self) -> &RevertDecoder {
+        &self.revert_decoder
+    }
+
+    /// [**Async**] Call the flashloan function on the borrower contract
+    ///
+    /// Returns the [Call3Result] of each call if successful, in order.
+    ///
+    /// ### Errors
+    ///
+    /// Reverts are decoded by the plan's [RevertDecoder]. Returns a
+    /// [ContractError](FlashloanError::ContractError) if the call errors without revert data.
+    #[instrument(
+        name = "flashloan_call",
+        skip_all,
+        fields(
+            chain_id = self.chain_id,
+            borrower = ?self.borrower,
+            token = ?self.token,
+            amount = %self.amount
+        )
+    )]
+    pub async fn call(&self) -> Result<Vec<Call3Result>>
+    where
+        M::Error: 'static,
+    {
+        self.check_call_value().await?;
+        let results = self
+            .flash_borrow()
+            .call()
+            .await
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        let failed = results.iter().filter(|result| !result.success).count();
+        debug!(calls = results.len(), failed, "Flashloan call succeeded");
+        Ok(results)
+    }
+
+    /// [**Async**] Execute the flashloan function on the borrower contract
+    ///
+    /// Returns the transaction receipt if successful. With a bundle relay, the transaction is
+    /// signed by the client and submitted through the relay.
+    ///
+    /// ### Errors
+    ///
+    /// Reverts are decoded by the plan's [RevertDecoder]. Returns a
+    /// [ContractError](FlashloanError::ContractError) if the transaction errors without revert
+    /// data. With a bundle relay, returns the errors of [submit](BundleRelay::submit).
+    #[instrument(
+        name = "flashloan_execute",
+        skip_all,
+        fields(
+            chain_id = self.chain_id,
+            borrower = ?self.borrower,
+            token = ?self.token,
+            amount = %self.amount,
+            tx_hash
+        )
+    )]
+    pub async fn execute(&self) -> Result<Option<TransactionReceipt>>
+    where
+        M::Error: 'static,
+    {
+        self.check_call_value().await?;
+        let contract_call = self.prepared_flash_borrow().await?;
+        if let Some(relay) = &self.bundle_relay {
+            let mut tx = contract_call.tx;
+            if let (None, Some(chain_id)) = (tx.chain_id(), self.chain_id) {
+                tx.set_chain_id(chain_id);
+            }
+            debug!("Submitting flashloan bundle");
+            let receipt = relay.submit(Arc::clone(&self.client), tx, &self.revert_decoder).await?;
+            record_receipt(&receipt);
+            return Ok(Some(receipt))
+        }
+        debug!("Sending flashloan transaction");
+        let pending_transaction = contract_call
+            .send()
+            .await
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        let tx_hash = pending_transaction.tx_hash();
+        Span::current().record("tx_hash", field::debug(tx_hash));
+        info!(tx_hash = ?tx_hash, "Sent flashloan transaction");
+        let optional_receipt = pending_transaction
+            .await
+            .map_err(|ce| FlashloanError::ContractError(ce.to_string()))?;
+        match &optional_receipt {
+            Some(receipt) => record_receipt(receipt),
+            None => warn!(tx_hash = ?tx_hash, "Flashloan transaction dropped"),
+        }
+        Ok(optional_receipt)
+    }
+
+    /// [**Async**] Check the borrower holds the ether the calls send
+    ///
+    /// Only ether spent from the borrower with [Borrower](CallValue::Borrower) is checked, ether
+    /// sent with the transaction is checked by the client.
+    ///
+    /// ### Errors
+    ///
+    /// Returns an [InsufficientEth](FlashloanError::InsufficientEth) if the borrower's balance is
+    /// below the value of the calls, and a [ClientFailure](FlashloanError::ClientFailure) if the
+    /// balance can't be queried.
+    pub async fn check_call_value(&self) -> Result<()> {
+        let required = self.required_value();
+        if self.call_value == CallValue::Attach || required.is_zero() {
+            return Ok(())
+        }
+        let balance = self
+            .client
+            .get_balance(self.borrower, None)
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        if balance < required {
+            return Err(FlashloanError::InsufficientEth { balance, required }.into())
+        }
+        Ok(())
+    }
+
+    /// The summed value of the calls the borrower runs
+    fn required_value(&self) -> U256 {
+        match self.program {
+            Some(_) => U256::zero(),
+            None => calls_value(&self.calls),
+        }
+    }
+
+    /// Replace the repayment computed off-chain
+    pub(crate) fn with_repayment(mut self, repayment: Option<U256>) -> Self {
+        self.repayment = repayment;
+        self
+    }
+
+    /// Decode the outcome of an executed flashloan from its receipt
+    ///
+    /// With a profit guard, the realized profit is quoted in the profit token at the guard's
+    /// ether price.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [from_receipt](FlashloanOutcome::from_receipt).
+    pub fn outcome(&self, receipt: &TransactionReceipt) -> Result<FlashloanOutcome> {
+        let outcome = FlashloanOutcome::from_receipt(receipt, self.borrower)?;
+        Ok(match self.profit_guard {
+            Some(guard) => outcome.with_quote(guard.token, guard.eth_price),
+            None => outcome,
+        })
+    }
+
+    /// [**Async**] Builds the borrower entrypoint call with the gas limit and fees of the
+    /// configured gas settings
+    ///
+    /// Without a fee strategy or gas padding, both are left to the middleware.
+    pub(crate) async fn prepared_flash_borrow(&self) -> Result<ContractCall<M, Vec<Call3Result>>>
+    where
+        M::Error: 'static,
+    {
+        let mut contract_call = self.flash_borrow();
+        if self.fee_strategy == FeeStrategy::Provider && self.gas_padding_bps == 0 {
+            return Ok(contract_call)
+        }
+        let gas = self.estimate_gas().await?;
+        contract_call.tx.set_gas(gas);
+        let fees = self.fee_strategy.fees(self.client.as_ref(), gas).await?;
+        if let Some(fees) = fees {
+            fees.apply(&mut contract_call.tx);
+        }
+        debug!(gas = %gas, fees = ?fees, "Prepared flashloan transaction");
+        Ok(contract_call)
+    }
+
+    /// Builds the borrower entrypoint call, guarded by the profit guard if set
+    ///
+    /// Plans with legs call the nested `flashBorrowMulti` entrypoint, and plans with a program call
+    /// the `flashBorrowProgram` entrypoint. Otherwise plans with a precomputed repayment call the
+    /// `flashBorrowWithRepayment` entrypoint, and plans borrowing from a trusted lender call the
+    /// overloads taking the lender. The [value](FlashloanPlan::value) is sent with the call.
+    pub(crate) fn flash_borrow(&self) -> ContractCall<M, Vec<Call3Result>> {
+        let value = self.value();
+        let contract_call = self.entrypoint();
+        if value.is_zero() {
+            return contract_call
+        }
+        contract_call.value(value)
+    }
+
+    /// Builds the call of the borrower entrypoint for the plan
+    fn entrypoint(&self) -> ContractCall<M, Vec<Call3Result>> {
+        if let Some(program) = &self.program {
+            return flash_borrow_program(
+                self.borrower,
+                Arc::clone(&self.client),
+                self.token,
+                self.amount,
+                program,
+                self.profit_guard,
+            )
+        }
+        if !self.legs.is_empty() {
+            return flash_borrow_multi(
+                self.borrower,
+                Arc::clone(&self.client),
+                &self.legs,
+                &self.calls,
+                self.profit_guard,
+            )
+        }
+        let contract = Flashloan::new(self.borrower, Arc::clone(&self.client));
+        let calls = self.calls.clone();
+        match (self.profit_guard, self.repayment, self.borrow_from) {
+            (Some(guard), _, None) => contract.flash_borrow_with_profit(
+                self.token,
+                self.amount,
+                calls,
+                guard.token,
+                guard.min_profit,
+            ),
+            (Some(guard), _, Some(lender)) => contract.flash_borrow_with_profit_with_lender(
+                self.token,
+                self.amount,
+                calls,
+                guard.token,
+                guard.min_profit,
+                lender,
+            ),
+            (None, Some(repayment), None) => {
+                contract.flash_borrow_with_repayment(self.token, self.amount, repayment, calls)
+            }
+            (None, Some(repayment), Some(lender)) => contract
+                .flash_borrow_with_repayment_with_lender(
+                    self.token,
+                    self.amount,
+                    repayment,
+                    calls,
+                    lender,
+                ),
+            (None, None, None) => contract.flash_borrow(self.token, self.amount, calls),
+            (None, None, Some(lender)) => {
+                contract.flash_borrow_with_lender(self.token, self.amount, calls, lender)
+            }
+        }
+    }
+
+    /// [**Async**] Query the flash fee charged by the lender
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingLender](FlashloanError::MissingLender) if no lender is specified.
+    pub async fn flash_fee(&self) -> Result<U256>
+    where
+        M: 'static,
+    {
+        let lender =
+            self.lender_kind.lender::<M>(self.lender.ok_or(FlashloanError::MissingLender)?);
+        lender.flash_fee(Arc::clone(&self.client), self.token, self.amount).await
+    }
+
+    /// [**Async**] Query the fee charged by the lender of each leg, in parallel
+    pub async fn leg_fees(&self) -> Result<Vec<U256>>
+    where
+        M: 'static,
+    {
+        leg_fees(Arc::clone(&self.client), &self.legs).await
+    }
+
+    /// [**Async**] Estimate the gas limit of the flashloan transaction, including the gas padding
+    ///
+    /// ### Errors
+    ///
+    /// Returns the decoded revert if the gas estimation reverts.
+    pub async fn estimate_gas(&self) -> Result<U256>
+    where
+        M::Error: 'static,
+    {
+        let gas = self
+            .flash_borrow()
+            .estimate_gas()
+            .await
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        Ok(pad_gas(gas, self.gas_padding_bps))
+    }
+
+    /// [**Async**] Estimate the gas cost of executing the flashloan in wei
+    ///
+    /// The gas estimate is priced at the client's current gas price.
+    ///
+    /// ### Errors
+    ///
+    /// Returns the decoded revert if the gas estimation reverts.
+    pub async fn estimate_gas_cost(&self) -> Result<U256>
+    where
+        M::Error: 'static,
+    {
+        let gas = self
+            .flash_borrow()
+            .estimate_gas()
+            .await
+            .map_err(|ce| self.revert_decoder.decode_contract_error(&ce))?;
+        let gas_price = self
+            .client
+            .get_gas_price()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        Ok(gas * gas_price)
+    }
+
+    /// [**Async**] Check the expected gain of the flashloan against its costs
+    ///
+    /// See [FlashloanBuilder::check_profit].
+    pub async fn check_profit(&self, gain: I256) -> Result<ProfitCheck>
+    where
+        M: 'static,
+    {
+        let gas_cost = self.estimate_gas_cost().await?;
+        self.check_profit_with_gas_cost(gain, gas_cost).await
+    }
+
+    /// [**Async**] Check the expected gain of the flashloan against the fee and a known gas cost
+    ///
+    /// See [FlashloanBuilder::check_profit].
+    pub async fn check_profit_with_gas_cost(
+        &self,
+        gain: I256,
+        gas_cost: U256,
+    ) -> Result<ProfitCheck>
+    where
+        M: 'static,
+    {
+        let guard = self.profit_guard.ok_or(FlashloanError::MissingProfitGuard)?;
+        let fee = if !self.legs.is_empty() {
+            // Only the fees of legs borrowing the profit token are paid from the gain
+            let fees = self.leg_fees().await?;
+            self.legs
+                .iter()
+                .zip(fees)
+                .filter(|(leg, _)| leg.token == guard.token)
+                .fold(U256::zero(), |total, (_, fee)| total + fee)
+        } else if guard.token == self.token {
+            self.flash_fee().await?
+        } else {
+            U256::zero()
+        };
+        guard.check(gain, fee, gas_cost)
+    }
+
+    /// Decode the [Call3Result]s of an executed flashloan from its transaction receipt
+    ///
+    /// See [FlashloanBuilder::decode_results].
+    pub fn decode_results(&self, receipt: &TransactionReceipt) -> Vec<Call3Result> {
+        decode_results(self.borrower, receipt)
+    }
+}
+
+/// Command programs are run by a single loan borrower, so can't be combined with legs
+pub(crate) fn check_program(legs: &[FlashloanLeg], program: &Option<Program>) -> Result<()> {
+    if program.is_some() && !legs.is_empty() {
+        return Err(FlashloanError::CallConstructionError(
+            "A command program can't be combined with nested legs".to_string(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Programs don't run the calls, so there are no calls to wrap WETH around
+pub(crate) fn check_weth_wrap(
+    weth_wrap: &Option<WethWrap>,
+    program: &Option<Program>,
+) -> Result<()> {
+    if weth_wrap.is_some() && program.is_some() {
+        return Err(FlashloanError::CallConstructionError(
+            "A WETH wrap can't be combined with a command program".to_string(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Only the ERC-3156 borrower has the `flashBorrowWithRepayment` entrypoint
+fn check_repayment(
+    repayment: Option<U256>,
+    lender_kind: LenderKind,
+    legs: &[FlashloanLeg],
+    program: &Option<Program>,
+) -> Result<()> {
+    if repayment.is_none() || !legs.is_empty() || program.is_some() {
+        return Ok(())
+    }
+    if lender_kind != LenderKind::Erc3156 {
+        return Err(FlashloanError::CallConstructionError(format!(
+            "A precomputed repayment needs the ERC-3156 borrower, not the {} borrower",
+            lender_kind.borrower_name()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Only the ERC-3156 borrower has the overloads borrowing from a trusted lender
+pub(crate) fn check_borrow_from(
+    borrow_from: Option<Address>,
+    lender_kind: LenderKind,
+    legs: &[FlashloanLeg],
+    program: &Option<Program>,
+) -> Result<()> {
+    if borrow_from.is_none() {
+        return Ok(())
+    }
+    let name = if !legs.is_empty() {
+        MULTI_BORROWER_NAME
+    } else if program.is_some() {
+        PROGRAM_BORROWER_NAME
+    } else if lender_kind != LenderKind::Erc3156 {
+        lender_kind.borrower_name()
+    } else {
+        return Ok(())
+    };
+    Err(FlashloanError::FixedLender(name.to_string()).into())
+}
+
+/// Decodes the `Call3Results` event emitted by `borrower` in a transaction receipt
+pub(crate) fn decode_results(borrower: Address, receipt: &TransactionReceipt) -> Vec<Call3Result> {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == borrower)
+        .find_map(|log| {
+            let raw = RawLog::from((log.topics.clone(), log.data.to_vec()));
+            <Call3ResultsFilter as EthEvent>::decode_log(&raw).ok()
+        })
+        .map(|event| event.results)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(success, return_data)| Call3Result { success, return_data })
+        .collect()
+}
+
+impl<M> Clone for FlashloanPlan<M> {
+    fn clone(&self) -> Self {
+        Self {
+            client: Arc::clone(&self.client),
+            chain_id: self.chain_id,
+            borrower: self.borrower,
+            owner: self.owner,
+            lender: self.lender,
+            lender_kind: self.lender_kind,
+            borrow_from: self.borrow_from,
+            token: self.token,
+            amount: self.amount,
+            calls: self.calls.clone(),
+            legs: self.legs.clone(),
+            program: self.program.clone(),
+            repayment: self.repayment,
+            call_value: self.call_value,
+            revert_decoder: self.revert_decoder.clone(),
+            profit_guard: self.profit_guard,
+            bundle_relay: self.bundle_relay.clone(),
+            fee_strategy: self.fee_strategy,
+            gas_padding_bps: self.gas_padding_bps,
+        }
+    }
+}
+
+impl<M> fmt::Debug for FlashloanPlan<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlashloanPlan")
+            .field("chain_id", &self.chain_id)
+            .field("borrower", &self.borrower)
+            .field("owner", &self.owner)
+            .field("lender", &self.lender)
+            .field("lender_kind", &self.lender_kind)
+            .field("borrow_from", &self.borrow_from)
+            .field("token", &self.token)
+            .field("amount", &self.amount)
+            .field("calls", &self.calls)
+            .field("legs", &self.legs)
+            .field("program", &self.program)
+            .field("repayment", &self.repayment)
+            .field("call_value", &self.call_value)
+            .field("profit_guard", &self.profit_guard)
+            .field("bundle_relay", &self.bundle_relay)
+            .field("fee_strategy", &self.fee_strategy)
+            .field("gas_padding_bps", &self.gas_padding_bps)
+            .finish()
+    }
+}
+
+/// Records a mined flashloan transaction on the current span
+fn record_receipt(receipt: &TransactionReceipt) {
+    Span::current().record("tx_hash", field::debug(receipt.transaction_hash));
+    info!(
+        tx_hash = ?receipt.transaction_hash,
+        block_number = receipt.block_number.map(|block| block.as_u64()),
+        gas_used = receipt.gas_used.map(|gas| gas.as_u64()),
+        status = receipt.status.map(|status| status.as_u64()),
+        "Flashloan transaction mined"
+    );
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::*;
+
+/// A minimum profit requirement for a flashloan
+///
+/// ### Usage
+///
+/// Set on a builder with [with_profit_guard](crate::builder::FlashloanBuilder::with_profit_guard),
+/// the guard is enforced twice:
+/// - Off-chain, [check](ProfitGuard::check) compares the expected gain against the flash fee and
+///   the gas cost before a transaction is sent.
+/// - On-chain, the borrower's `flashBorrowWithProfit` reverts with `InsufficientProfit` if its
+///   profit token balance did not grow by at least [min_profit](ProfitGuard::min_profit).
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// // Require at least 0.1 DAI of profit, pricing ether at 1500 DAI
+/// let dai = Address::random();
+/// let guard = ProfitGuard::new(dai, U256::exp10(17)).with_eth_price(U256::exp10(18) * 1500);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfitGuard {
+    /// The token the profit is realized in
+    pub token: Address,
+    /// The minimum net profit, in profit token units
+    #[serde(with = "crate::spec::amount")]
+    pub min_profit: U256,
+    /// The value of one ether in profit token units, used to convert the gas cost
+    #[serde(with = "crate::spec::amount", default = "one_ether")]
+    pub eth_price: U256,
+}
+
+/// The outcome of a passed [ProfitGuard] check
+///
+/// All amounts are denominated in the profit token, except for the gas cost in wei.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfitCheck {
+    /// The gain of the flashloan calls before repaying the fee
+    pub gain: I256,
+    /// The flash fee paid to the lender
+    pub fee: U256,
+    /// The gas cost of the flashloan transaction in wei
+    pub gas_cost: U256,
+    /// The gas cost of the flashloan transaction in profit token units
+    pub gas_cost_in_token: U256,
+    /// The gain less the fee and gas cost
+    pub net_profit: I256,
+}
+
+impl ProfitGuard {
+    /// Create a guard requiring `min_profit` of `token`
+    ///
+    /// The profit token is assumed to be priced 1:1 with ether, as is WETH. Use
+    /// [with_eth_price](ProfitGuard::with_eth_price) for other tokens.
+    pub fn new(token: Address, min_profit: U256) -> Self {
+        Self { token, min_profit, eth_price: one_ether() }
+    }
+
+    /// Set the value of one ether in profit token units
+    pub fn with_eth_price(mut self, eth_price: U256) -> Self {
+        self.eth_price = eth_price;
+        self
+    }
+
+    /// Convert a gas cost in wei to profit token units
+    pub fn gas_cost_in_token(&self, gas_cost: U256) -> U256 {
+        gas_cost * self.eth_price / U256::exp10(18)
+    }
+
+    /// Check that a flashloan's gain covers the fee, the gas cost and the minimum profit
+    ///
+    /// ### Arguments
+    ///
+    /// - `gain`: The change in the borrower's profit token balance before repaying the fee
+    /// - `fee`: The flash fee, in profit token units
+    /// - `gas_cost`: The gas cost of the flashloan transaction in wei
+    ///
+    /// ### Errors
+    ///
+    /// Returns an [Unprofitable](FlashloanError::Unprofitable) if the net profit is below the
+    /// minimum profit.
+    pub fn check(&self, gain: I256, fee: U256, gas_cost: U256) -> Result<ProfitCheck> {
+        let gas_cost_in_token = self.gas_cost_in_token(gas_cost);
+        let net_profit = gain - I256::from_raw(fee) - I256::from_raw(gas_cost_in_token);
+        if net_profit < I256::from_raw(self.min_profit) {
+            return Err(
+                FlashloanError::Unprofitable { net_profit, min_profit: self.min_profit }.into()
+            )
+        }
+        Ok(ProfitCheck { gain, fee, gas_cost, gas_cost_in_token, net_profit })
+    }
+}
+
+fn one_ether() -> U256 {
+    U256::exp10(18)
+}
@@ -0,0 +1,378 @@
+use anyhow::Result;
+use ethers::{
+    abi::{encode, Abi, Function, ParamType, Token},
+    contract::{builders::ContractCall, Contract, Lazy},
+    prelude::*,
+};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{contract::*, errors::*, lender::*, profit::*};
+
+/// The name of the command program borrower contract
+pub const PROGRAM_BORROWER_NAME: &str = "ProgramFlashBorrower";
+
+/// The bundled `ProgramFlashBorrower` artifact
+const PROGRAM_BORROWER_ARTIFACT: &str = include_str!("ProgramFlashBorrower.json");
+
+/// The abi of the `ProgramFlashBorrower` contract
+///
+/// Extends the [Flashloan] abi with the `flashBorrowProgram` entrypoints.
+pub static PROGRAM_FLASHLOAN_ABI: Lazy<Abi> = Lazy::new(|| artifact_abi(PROGRAM_BORROWER_ARTIFACT));
+
+/// The creation bytecode of the `ProgramFlashBorrower` contract
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the bundled artifact has not
+/// been compiled.
+pub fn program_borrower_bytecode() -> Result<Bytes> {
+    artifact_bytecode(PROGRAM_BORROWER_NAME, PROGRAM_BORROWER_ARTIFACT)
+}
+
+/// Command flag sending the value of the first input slot with the call
+pub const FLAG_CALL_WITH_VALUE: u8 = 0x01;
+
+/// Input slot flag marking an abi encoded dynamic value, passed by offset
+pub const IDX_DYNAMIC: u8 = 0x80;
+
+/// Ends the input slots, or discards the return value as the output slot
+pub const IDX_END: u8 = 0xff;
+
+/// The maximum number of inputs of a command, including the value of a call with value
+pub const MAX_INPUTS: usize = 6;
+
+/// The maximum number of state slots, leaving the dynamic flag and end marker free
+pub const MAX_STATE: usize = 0x7f;
+
+/// The return value of a planned command
+///
+/// Pass it as an argument of a later command to pipe the value on-chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnValue {
+    /// The index of the command
+    command: usize,
+    /// The type of the first return value, if it fits a single state slot
+    kind: Option<ParamType>,
+}
+
+impl ReturnValue {
+    /// The index of the command returning the value
+    pub fn command(&self) -> usize {
+        self.command
+    }
+}
+
+/// An argument of a planned command
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandArg {
+    /// A value known when planning
+    Literal(Token),
+    /// The return value of an earlier command
+    Return(ReturnValue),
+}
+
+impl From<Token> for CommandArg {
+    fn from(token: Token) -> Self {
+        CommandArg::Literal(token)
+    }
+}
+
+impl From<ReturnValue> for CommandArg {
+    fn from(value: ReturnValue) -> Self {
+        CommandArg::Return(value)
+    }
+}
+
+impl From<&ReturnValue> for CommandArg {
+    fn from(value: &ReturnValue) -> Self {
+        CommandArg::Return(value.clone())
+    }
+}
+
+/// A planned call
+#[derive(Debug, Clone)]
+struct PlannedCommand {
+    target: Address,
+    selector: [u8; 4],
+    value: Option<CommandArg>,
+    args: Vec<(CommandArg, ParamType)>,
+}
+
+/// Plans weiroll-style command programs
+///
+/// ### Usage
+///
+/// `Call3` calldata is fixed when the flashloan is built, so a call can't use the output of an
+/// earlier call. A planned command instead reads its arguments from a state of abi encoded
+/// values, which the `ProgramFlashBorrower` updates with each command's return value as it runs
+/// the program in the lender callback.
+///
+/// ```rust
+/// use ethers::{abi::{HumanReadableParser, Token}, prelude::*};
+/// use flashloan_rs::prelude::*;
+///
+/// let balance_of = HumanReadableParser::parse_function(
+///     "function balanceOf(address) external view returns (uint256)"
+/// ).unwrap();
+/// let transfer = HumanReadableParser::parse_function(
+///     "function transfer(address,uint256) external returns (bool)"
+/// ).unwrap();
+/// let (token, borrower, recipient) = (Address::random(), Address::random(), Address::random());
+///
+/// // Transfer the borrower's whole balance, as read mid-flashloan
+/// let mut planner = CommandPlanner::new();
+/// let balance = planner.call(token, &balance_of, vec![Token::Address(borrower).into()]).unwrap();
+/// planner.call(token, &transfer, vec![Token::Address(recipient).into(), balance.into()]).unwrap();
+/// let program = planner.plan().unwrap();
+/// assert_eq!(program.commands.len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CommandPlanner {
+    commands: Vec<PlannedCommand>,
+}
+
+/// A command program, interpreted on-chain by the `ProgramFlashBorrower`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    /// The packed commands: `selector (4) | flags (1) | input slots (6) | output slot (1) | target
+    /// (20)`
+    pub commands: Vec<[u8; 32]>,
+    /// The initial state, each slot an abi encoded static value or the abi tail of a dynamic value
+    pub state: Vec<Bytes>,
+}
+
+impl CommandPlanner {
+    /// Create an empty planner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of planned commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Whether no commands are planned
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Plan a call of `function` on `target`
+    ///
+    /// Returns the call's return value, which can be passed to later commands.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the arguments
+    /// don't match the function inputs.
+    pub fn call(
+        &mut self,
+        target: Address,
+        function: &Function,
+        args: Vec<CommandArg>,
+    ) -> Result<ReturnValue> {
+        self.add(target, function, None, args)
+    }
+
+    /// Plan a call of `function` on `target` sending `value` wei
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the value isn't
+    /// a uint or the arguments don't match the function inputs.
+    pub fn call_with_value(
+        &mut self,
+        target: Address,
+        function: &Function,
+        value: CommandArg,
+        args: Vec<CommandArg>,
+    ) -> Result<ReturnValue> {
+        self.add(target, function, Some(value), args)
+    }
+
+    fn add(
+        &mut self,
+        target: Address,
+        function: &Function,
+        value: Option<CommandArg>,
+        args: Vec<CommandArg>,
+    ) -> Result<ReturnValue> {
+        let name = &function.name;
+        if args.len() != function.inputs.len() {
+            return Err(construction_error(format!(
+                "{} takes {} arguments, got {}",
+                name,
+                function.inputs.len(),
+                args.len()
+            )))
+        }
+        if args.len() + value.is_some() as usize > MAX_INPUTS {
+            return Err(construction_error(format!("{} has more than {} inputs", name, MAX_INPUTS)))
+        }
+        if let Some(value) = &value {
+            self.check_arg(name, value, &ParamType::Uint(256))?;
+        }
+        let args = args
+            .into_iter()
+            .zip(function.inputs.iter())
+            .map(|(arg, input)| {
+                self.check_arg(name, &arg, &input.kind)?;
+                Ok((arg, input.kind.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.commands.push(PlannedCommand {
+            target,
+            selector: function.short_signature(),
+            value,
+            args,
+        });
+        let kind = function.outputs.first().map(|output| output.kind.clone()).filter(is_word);
+        Ok(ReturnValue { command: self.commands.len() - 1, kind })
+    }
+
+    /// Checks an argument can be passed as a `kind` input
+    fn check_arg(&self, name: &str, arg: &CommandArg, kind: &ParamType) -> Result<()> {
+        match arg {
+            CommandArg::Literal(token) if !token.type_check(kind) => {
+                Err(construction_error(format!("{} expects {}, got {:?}", name, kind, token)))
+            }
+            CommandArg::Return(value) if value.command >= self.commands.len() => {
+                Err(construction_error(format!("{} uses an unplanned return value", name)))
+            }
+            CommandArg::Return(ReturnValue { kind: None, command }) => Err(construction_error(
+                format!("{} uses command {}, which has no single word return value", name, command),
+            )),
+            CommandArg::Return(ReturnValue { kind: Some(output), command }) if output != kind => {
+                Err(construction_error(format!(
+                    "{} expects {}, command {} returns {}",
+                    name, kind, command, output
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Encode the planned commands into a [Program]
+    ///
+    /// Equal literals share a state slot, and a slot is allocated for each return value used by a
+    /// later command.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](FlashloanError::CallConstructionError) if the program
+    /// needs more than [MAX_STATE] state slots.
+    pub fn plan(&self) -> Result<Program> {
+        let mut state: Vec<Bytes> = vec![];
+        let mut literals: HashMap<Bytes, u8> = HashMap::new();
+        let mut outputs: HashMap<usize, u8> = HashMap::new();
+
+        let mut commands = Vec::with_capacity(self.commands.len());
+        for (index, command) in self.commands.iter().enumerate() {
+            let mut inputs = [IDX_END; MAX_INPUTS];
+            let value = command.value.iter().map(|value| (value, ParamType::Uint(256)));
+            let args = command.args.iter().map(|(arg, kind)| (arg, kind.clone()));
+            for (input, (arg, kind)) in inputs.iter_mut().zip(value.chain(args)) {
+                *input = match arg {
+                    CommandArg::Literal(token) => {
+                        let (slot, flag) = encode_literal(token, &kind);
+                        let idx = match literals.get(&slot) {
+                            Some(idx) => *idx,
+                            None => {
+                                let idx = allocate_slot(&mut state, slot.clone())?;
+                                literals.insert(slot, idx);
+                                idx
+                            }
+                        };
+                        idx | flag
+                    }
+                    CommandArg::Return(value) => match outputs.get(&value.command) {
+                        Some(idx) => *idx,
+                        None => {
+                            let idx = allocate_slot(&mut state, Bytes::default())?;
+                            outputs.insert(value.command, idx);
+                            idx
+                        }
+                    },
+                };
+            }
+            commands.push((index, command, inputs));
+        }
+
+        let commands = commands
+            .into_iter()
+            .map(|(index, command, inputs)| {
+                let mut packed = [0u8; 32];
+                packed[..4].copy_from_slice(&command.selector);
+                packed[4] = if command.value.is_some() { FLAG_CALL_WITH_VALUE } else { 0 };
+                packed[5..11].copy_from_slice(&inputs);
+                packed[11] = outputs.get(&index).copied().unwrap_or(IDX_END);
+                packed[12..].copy_from_slice(command.target.as_bytes());
+                packed
+            })
+            .collect();
+        Ok(Program { commands, state })
+    }
+}
+
+/// Appends a state slot, returning its index
+fn allocate_slot(state: &mut Vec<Bytes>, slot: Bytes) -> Result<u8> {
+    if state.len() >= MAX_STATE {
+        return Err(construction_error(format!(
+            "The program needs more than {} state slots",
+            MAX_STATE
+        )))
+    }
+    state.push(slot);
+    Ok((state.len() - 1) as u8)
+}
+
+/// Whether a type is encoded in a single word, and so can be written to a state slot
+fn is_word(kind: &ParamType) -> bool {
+    matches!(
+        kind,
+        ParamType::Address
+            | ParamType::Bool
+            | ParamType::Int(_)
+            | ParamType::Uint(_)
+            | ParamType::FixedBytes(_)
+    )
+}
+
+/// Encodes a literal into a state slot, returning the slot and its input flag
+fn encode_literal(token: &Token, kind: &ParamType) -> (Bytes, u8) {
+    let encoded = encode(std::slice::from_ref(token));
+    if kind.is_dynamic() {
+        // Dynamic values are passed by offset, so the slot holds the tail without the offset
+        (encoded[32..].to_vec().into(), IDX_DYNAMIC)
+    } else {
+        (encoded.into(), 0)
+    }
+}
+
+fn construction_error(message: String) -> anyhow::Error {
+    FlashloanError::CallConstructionError(message).into()
+}
+
+/// Builds the command program entrypoint call, guarded by the profit guard if set
+pub(crate) fn flash_borrow_program<M: Middleware>(
+    borrower: Address,
+    client: Arc<M>,
+    token: Address,
+    amount: U256,
+    program: &Program,
+    profit_guard: Option<ProfitGuard>,
+) -> ContractCall<M, Vec<Call3Result>> {
+    let contract = Contract::new(borrower, PROGRAM_FLASHLOAN_ABI.clone(), client);
+    let (commands, state) = (program.commands.clone(), program.state.clone());
+    // This won't panic since the functions are in the abi and the arguments match their types
+    match profit_guard {
+        Some(guard) => contract
+            .method(
+                "flashBorrowProgramWithProfit",
+                (token, amount, commands, state, guard.token, guard.min_profit),
+            )
+            .unwrap(),
+        None => contract.method("flashBorrowProgram", (token, amount, commands, state)).unwrap(),
+    }
+}
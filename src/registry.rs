@@ -0,0 +1,271 @@
+use anyhow::Result;
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{errors::*, lender::*};
+
+/// A registry of known flash lenders and tokens by chain id
+///
+/// ### Usage
+///
+/// The [builtin](ChainRegistry::builtin) registry covers mainnet, Optimism, Polygon and
+/// Arbitrum. Registries can be loaded from JSON or TOML and [merged](ChainRegistry::merge) over
+/// the builtin one to add chains or override lenders.
+///
+/// ```toml
+/// [chains.31337]
+/// name = "anvil"
+/// tokens = { DAI = "0x6B175474E89094C44Da98b954EedeAC495271d0F" }
+///
+/// [[chains.31337.lenders]]
+/// name = "mock-lender"
+/// kind = "erc3156"
+/// address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+/// fee = { bps = 9 }
+/// default = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainRegistry {
+    /// The configuration of each chain by chain id
+    #[serde(default)]
+    pub chains: BTreeMap<u64, ChainConfig>,
+}
+
+/// The known flash lenders and tokens of a chain
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// The chain name
+    #[serde(default)]
+    pub name: String,
+    /// Known token addresses by symbol
+    #[serde(default)]
+    pub tokens: BTreeMap<String, Address>,
+    /// Known flash lenders
+    #[serde(default)]
+    pub lenders: Vec<LenderConfig>,
+}
+
+/// A flash lender deployment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LenderConfig {
+    /// A name unique within the chain
+    pub name: String,
+    /// The lender protocol
+    pub kind: LenderKind,
+    /// The lender address
+    pub address: Address,
+    /// The tokens that can be borrowed, any token if empty
+    #[serde(default)]
+    pub tokens: Vec<Address>,
+    /// How the lender charges fees
+    #[serde(default)]
+    pub fee: FeeModel,
+    /// Whether this is the chain's default lender
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// How a flash lender charges fees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeModel {
+    /// The fee must be queried from the lender
+    #[default]
+    OnChain,
+    /// The lender charges no fee
+    Free,
+    /// A fixed fee in basis points of the amount, rounded up
+    Bps(u32),
+    /// A fixed fee in hundredths of a basis point of the amount, rounded up
+    Pips(u32),
+}
+
+impl FeeModel {
+    /// The fee to borrow `amount`, or `None` if it must be queried on-chain
+    pub fn fee(&self, amount: U256) -> Option<U256> {
+        let (rate, basis) = match self {
+            FeeModel::OnChain => return None,
+            FeeModel::Free => return Some(U256::zero()),
+            FeeModel::Bps(bps) => (*bps, 10_000u64),
+            FeeModel::Pips(pips) => (*pips, 1_000_000u64),
+        };
+        let product = amount * U256::from(rate);
+        if product.is_zero() {
+            return Some(U256::zero())
+        }
+        Some((product - 1) / basis + 1)
+    }
+}
+
+impl LenderConfig {
+    /// Whether the lender can lend `token`
+    pub fn supports(&self, token: Address) -> bool {
+        self.tokens.is_empty() || self.tokens.contains(&token)
+    }
+
+    /// The [Lender] for this deployment
+    pub fn lender<M: Middleware + 'static>(&self) -> Box<dyn Lender<M>> {
+        self.kind.lender(self.address)
+    }
+}
+
+impl ChainConfig {
+    /// The lender flagged as default, or the first lender
+    pub fn default_lender(&self) -> Option<&LenderConfig> {
+        self.lenders.iter().find(|lender| lender.default).or_else(|| self.lenders.first())
+    }
+}
+
+impl ChainRegistry {
+    /// The builtin registry
+    pub fn builtin() -> Self {
+        // This won't panic since the bundled registry is checked by the tests
+        Self::from_json(include_str!("registry.json")).unwrap()
+    }
+
+    /// Parse a registry from JSON
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [RegistryError](FlashloanError::RegistryError) if the registry is malformed.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let registry =
+            serde_json::from_str(json).map_err(|e| FlashloanError::RegistryError(e.to_string()))?;
+        Ok(registry)
+    }
+
+    /// Parse a registry from TOML
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [RegistryError](FlashloanError::RegistryError) if the registry is malformed.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let document = toml
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| FlashloanError::RegistryError(e.to_string()))?;
+        let value = toml_table_to_json(document.as_table());
+        let registry = serde_json::from_value(value)
+            .map_err(|e| FlashloanError::RegistryError(e.to_string()))?;
+        Ok(registry)
+    }
+
+    /// Load a registry from a `.toml` or `.json` file
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [RegistryError](FlashloanError::RegistryError) if the file can't be read or the
+    /// registry is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| FlashloanError::RegistryError(format!("{}: {}", path.display(), e)))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            _ => Self::from_json(&contents),
+        }
+    }
+
+    /// Merge another registry over this one
+    ///
+    /// Lenders are replaced by name and tokens by symbol, other lenders and tokens are kept.
+    ///
+    /// Returns a mutable reference to the registry for method chaining.
+    pub fn merge(&mut self, other: ChainRegistry) -> &mut Self {
+        for (chain_id, config) in other.chains {
+            let chain = self.chains.entry(chain_id).or_default();
+            if !config.name.is_empty() {
+                chain.name = config.name;
+            }
+            chain.tokens.extend(config.tokens);
+            for lender in config.lenders {
+                // A new default replaces the previous one
+                if lender.default {
+                    chain.lenders.iter_mut().for_each(|l| l.default = false);
+                }
+                match chain.lenders.iter_mut().find(|l| l.name == lender.name) {
+                    Some(existing) => *existing = lender,
+                    None => chain.lenders.push(lender),
+                }
+            }
+        }
+        self
+    }
+
+    /// The configuration of a chain
+    pub fn chain(&self, chain_id: u64) -> Option<&ChainConfig> {
+        self.chains.get(&chain_id)
+    }
+
+    /// The address of a known token by symbol
+    pub fn token(&self, chain_id: u64, symbol: &str) -> Option<Address> {
+        self.chain(chain_id)?.tokens.get(symbol).copied()
+    }
+
+    /// The lenders of a chain that can lend `token`
+    pub fn lenders_for(&self, chain_id: u64, token: Address) -> Vec<&LenderConfig> {
+        self.chain(chain_id)
+            .map(|chain| chain.lenders.iter().filter(|lender| lender.supports(token)).collect())
+            .unwrap_or_default()
+    }
+
+    /// The default lender of a protocol on a chain
+    ///
+    /// Since [Erc3156](LenderKind::Erc3156) is the default lender kind, chains without an
+    /// ERC-3156 lender fall back to their default lender of any kind.
+    ///
+    /// ### Errors
+    ///
+    /// Returns an [UnsupportedChain](FlashloanError::UnsupportedChain) if the chain has no
+    /// lenders configured. Returns a [MissingLender](FlashloanError::MissingLender) if the chain
+    /// has no lender of the given kind.
+    pub fn default_lender(&self, chain_id: u64, kind: LenderKind) -> Result<&LenderConfig> {
+        let chain = self
+            .chain(chain_id)
+            .filter(|chain| !chain.lenders.is_empty())
+            .ok_or(FlashloanError::UnsupportedChain(chain_id))?;
+        let mut of_kind = chain.lenders.iter().filter(|lender| lender.kind == kind);
+        let lender = match of_kind.clone().find(|lender| lender.default).or_else(|| of_kind.next())
+        {
+            Some(lender) => lender,
+            None if kind == LenderKind::Erc3156 => chain.default_lender().unwrap(),
+            None => return Err(FlashloanError::MissingLender.into()),
+        };
+        Ok(lender)
+    }
+}
+
+/// Converts a parsed TOML table into a JSON value
+pub(crate) fn toml_table_to_json(table: &toml_edit::Table) -> Value {
+    Value::Object(
+        table.iter().map(|(key, item)| (key.to_string(), toml_item_to_json(item))).collect(),
+    )
+}
+
+fn toml_item_to_json(item: &toml_edit::Item) -> Value {
+    match item {
+        toml_edit::Item::None => Value::Null,
+        toml_edit::Item::Value(value) => toml_value_to_json(value),
+        toml_edit::Item::Table(table) => toml_table_to_json(table),
+        toml_edit::Item::ArrayOfTables(tables) => {
+            Value::Array(tables.iter().map(toml_table_to_json).collect())
+        }
+    }
+}
+
+fn toml_value_to_json(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(s) => Value::from(s.value().as_str()),
+        toml_edit::Value::Integer(i) => Value::from(*i.value()),
+        toml_edit::Value::Float(f) => Value::from(*f.value()),
+        toml_edit::Value::Boolean(b) => Value::from(*b.value()),
+        toml_edit::Value::Datetime(d) => Value::from(d.value().to_string()),
+        toml_edit::Value::Array(array) => {
+            Value::Array(array.iter().map(toml_value_to_json).collect())
+        }
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table.iter().map(|(key, value)| (key.to_string(), toml_value_to_json(value))).collect(),
+        ),
+    }
+}
@@ -0,0 +1,197 @@
+use ethers::{
+    abi::{ethabi::AbiError, Abi, ParamType, Token},
+    middleware::signer::SignerMiddlewareError,
+    prelude::*,
+};
+use std::{collections::HashMap, error::Error};
+
+use crate::{contract::*, errors::*};
+
+/// The `Error(string)` selector
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// The `Panic(uint256)` selector
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Decodes revert data into a [FlashloanError]
+///
+/// ### Usage
+///
+/// A decoder understands `Error(string)`, `Panic(uint256)` and the borrower's custom errors out of
+/// the box. Custom errors of lenders and call targets can be registered with
+/// [register](RevertDecoder::register) or [register_abi](RevertDecoder::register_abi).
+///
+/// ```rust
+/// use ethers::abi::{ethabi::AbiError, Param, ParamType};
+/// use flashloan_rs::prelude::*;
+///
+/// let mut decoder = RevertDecoder::default();
+/// decoder.register(AbiError {
+///     name: "InsufficientLiquidity".to_string(),
+///     inputs: vec![Param { name: "available".to_string(), kind: ParamType::Uint(256), internal_type: None }],
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct RevertDecoder {
+    /// Registered custom errors by selector
+    errors: HashMap<[u8; 4], AbiError>,
+}
+
+impl Default for RevertDecoder {
+    fn default() -> Self {
+        let mut decoder = Self { errors: HashMap::new() };
+        decoder.register_abi(&FLASHLOAN_ABI);
+        decoder
+    }
+}
+
+impl RevertDecoder {
+    /// Register a custom error
+    ///
+    /// Returns a mutable reference to the decoder for method chaining.
+    pub fn register(&mut self, error: AbiError) -> &mut Self {
+        let mut selector = [0u8; 4];
+        selector.copy_from_slice(&error.signature().as_bytes()[..4]);
+        self.errors.insert(selector, error);
+        self
+    }
+
+    /// Register every custom error defined in an abi
+    ///
+    /// Returns a mutable reference to the decoder for method chaining.
+    pub fn register_abi(&mut self, abi: &Abi) -> &mut Self {
+        for error in abi.errors() {
+            self.register(error.clone());
+        }
+        self
+    }
+
+    /// Decode revert data into a [FlashloanError]
+    ///
+    /// Data that can't be decoded is returned as an
+    /// [UnknownRevert](FlashloanError::UnknownRevert).
+    pub fn decode(&self, data: &[u8]) -> FlashloanError {
+        let unknown = || FlashloanError::UnknownRevert(Bytes::from(data.to_vec()));
+        if data.len() < 4 {
+            return unknown()
+        }
+        let (selector, args) = data.split_at(4);
+
+        if selector == ERROR_SELECTOR {
+            return match ethers::abi::decode(&[ParamType::String], args).as_deref() {
+                Ok([Token::String(message)]) => FlashloanError::Revert(message.clone()),
+                _ => unknown(),
+            }
+        }
+        if selector == PANIC_SELECTOR {
+            return match ethers::abi::decode(&[ParamType::Uint(256)], args).as_deref() {
+                Ok([Token::Uint(code)]) => {
+                    FlashloanError::Panic { code: *code, reason: panic_reason(*code).to_string() }
+                }
+                _ => unknown(),
+            }
+        }
+
+        let mut key = [0u8; 4];
+        key.copy_from_slice(selector);
+        let error = match self.errors.get(&key) {
+            Some(error) => error,
+            None => return unknown(),
+        };
+        let tokens = match error.decode(args) {
+            Ok(tokens) => tokens,
+            Err(_) => return unknown(),
+        };
+        match (error.name.as_str(), tokens.as_slice()) {
+            ("Unauthorized", []) => FlashloanError::Unauthorized,
+            ("UntrustedLender", []) => FlashloanError::UntrustedLender,
+            ("UntrustedInitiator", []) => FlashloanError::UntrustedInitiator,
+            ("EmptyBalance", []) => FlashloanError::EmptyBalance,
+            ("InsufficientProfit", [Token::Uint(balance), Token::Uint(threshold)]) => {
+                FlashloanError::InsufficientProfit { balance: *balance, threshold: *threshold }
+            }
+            ("CallFailed", [Token::Uint(index), Token::Bytes(inner)]) => {
+                FlashloanError::CallFailed {
+                    index: index.low_u64() as usize,
+                    reason: Box::new(self.decode(inner)),
+                }
+            }
+            _ => FlashloanError::CustomError { name: error.name.clone(), args: tokens },
+        }
+    }
+
+    /// Decode the revert of a failed contract call or transaction
+    ///
+    /// Falls back to a [ContractError](FlashloanError::ContractError) with the error message if
+    /// the error carries no revert data.
+    pub fn decode_contract_error<M: Middleware>(&self, error: &ContractError<M>) -> FlashloanError
+    where
+        M::Error: 'static,
+    {
+        match revert_data(error) {
+            Some(data) => self.decode(&data),
+            None => FlashloanError::ContractError(error.to_string()),
+        }
+    }
+}
+
+/// Extracts the revert data from the JSON-RPC error of a failed contract call or transaction
+///
+/// Nodes return revert data as a hex string in the `data` field of the error. It is read from
+/// errors of a [Provider] over HTTP, directly or through a [SignerMiddleware] with a
+/// [LocalWallet].
+pub fn revert_data<M: Middleware>(error: &ContractError<M>) -> Option<Bytes>
+where
+    M::Error: 'static,
+{
+    match error {
+        ContractError::ProviderError(error) => provider_revert_data(error),
+        ContractError::MiddlewareError(error) => middleware_revert_data(error),
+        _ => None,
+    }
+}
+
+/// Extracts the revert data from the error of a middleware
+fn middleware_revert_data(error: &(dyn Error + 'static)) -> Option<Bytes> {
+    if let Some(error) = error.downcast_ref::<ProviderError>() {
+        return provider_revert_data(error)
+    }
+    match error.downcast_ref::<SignerMiddlewareError<Provider<Http>, LocalWallet>>()? {
+        SignerMiddlewareError::MiddlewareError(error) => provider_revert_data(error),
+        _ => None,
+    }
+}
+
+/// Extracts the revert data from the error of a provider
+fn provider_revert_data(error: &ProviderError) -> Option<Bytes> {
+    let error = match error {
+        ProviderError::JsonRpcClientError(error) => error.downcast_ref::<HttpClientError>()?,
+        _ => return None,
+    };
+    match error {
+        HttpClientError::JsonRpcError(error) => error.data.as_ref()?.as_str()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Describes a Solidity panic code
+///
+/// See: https://docs.soliditylang.org/en/latest/control-structures.html#panic-via-assert-and-error-via-require
+pub fn panic_reason(code: U256) -> &'static str {
+    if code > U256::from(u8::MAX) {
+        return "unknown panic code"
+    }
+    match code.low_u32() {
+        0x00 => "generic compiler inserted panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "memory allocation overflow",
+        0x51 => "call to an uninitialized internal function",
+        _ => "unknown panic code",
+    }
+}
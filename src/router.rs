@@ -0,0 +1,179 @@
+use anyhow::Result;
+use ethers::prelude::*;
+use futures::future::join_all;
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use crate::{errors::*, lender::*, registry::*};
+
+/// Ranks flash lenders by fee and liquidity
+///
+/// ### Usage
+///
+/// A router queries the liquidity and fee of every configured lender for a loan in parallel.
+/// Lenders that can't lend the full amount, or fail to respond, are skipped. The remaining lenders
+/// are ranked by cheapest fee, then by deepest liquidity.
+///
+/// Pass a router to [auto_select_lender](crate::builder::FlashloanBuilder::auto_select_lender) to
+/// have the builder borrow from the best lender, deploying or reusing the matching borrower.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+/// let router = LenderRouter::from_registry(client, &ChainRegistry::builtin(), 1);
+/// assert_eq!(router.len(), 3);
+/// ```
+pub struct LenderRouter<M> {
+    client: Arc<M>,
+    routes: Vec<Route<M>>,
+}
+
+/// A lender known to the router
+struct Route<M> {
+    lender: Arc<dyn Lender<M>>,
+    /// The tokens that can be borrowed, any token if empty
+    tokens: Vec<Address>,
+    /// Fees known without querying the lender
+    fee: FeeModel,
+}
+
+/// A lender's quote for a loan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LenderQuote {
+    /// The lender address
+    pub lender: Address,
+    /// The lender protocol
+    pub kind: LenderKind,
+    /// The amount of the token available to borrow
+    pub liquidity: U256,
+    /// The fee charged for the loan
+    pub fee: U256,
+}
+
+impl<M: Middleware + 'static> LenderRouter<M> {
+    /// Create a router without lenders
+    pub fn new(client: Arc<M>) -> Self {
+        Self { client, routes: vec![] }
+    }
+
+    /// Create a router over every lender of a chain in the registry
+    pub fn from_registry(client: Arc<M>, registry: &ChainRegistry, chain_id: u64) -> Self {
+        let mut router = Self::new(client);
+        for config in
+            registry.chain(chain_id).map(|chain| chain.lenders.iter()).into_iter().flatten()
+        {
+            router.routes.push(Route {
+                lender: Arc::from(config.lender::<M>()),
+                tokens: config.tokens.clone(),
+                fee: config.fee,
+            });
+        }
+        router
+    }
+
+    /// Add a lender able to lend any token
+    pub fn with_lender<L: Lender<M> + 'static>(mut self, lender: L) -> Self {
+        self.routes.push(Route {
+            lender: Arc::new(lender),
+            tokens: vec![],
+            fee: FeeModel::OnChain,
+        });
+        self
+    }
+
+    /// The number of lenders
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Whether the router has no lenders
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// [**Async**] Quote a loan of `amount` of `token` from every lender
+    ///
+    /// Returns the lenders able to lend the full amount, cheapest first.
+    pub async fn quote(&self, token: Address, amount: U256) -> Vec<LenderQuote> {
+        let quotes = self
+            .routes
+            .iter()
+            .filter(|route| route.tokens.is_empty() || route.tokens.contains(&token))
+            .map(|route| self.quote_route(route, token, amount));
+        let mut quotes = join_all(quotes)
+            .await
+            .into_iter()
+            .filter_map(|quote| quote.ok())
+            .filter(|quote| quote.liquidity >= amount)
+            .collect::<Vec<_>>();
+        quotes.sort_by(rank);
+        quotes
+    }
+
+    /// [**Async**] The cheapest lender able to lend `amount` of `token`
+    ///
+    /// ### Errors
+    ///
+    /// Returns an [InsufficientLiquidity](FlashloanError::InsufficientLiquidity) if no lender can
+    /// lend the full amount.
+    pub async fn select(&self, token: Address, amount: U256) -> Result<LenderQuote> {
+        let quote = self
+            .quote(token, amount)
+            .await
+            .into_iter()
+            .next()
+            .ok_or(FlashloanError::InsufficientLiquidity { token, amount })?;
+        Ok(quote)
+    }
+
+    /// Queries the liquidity and fee of a lender concurrently
+    async fn quote_route(
+        &self,
+        route: &Route<M>,
+        token: Address,
+        amount: U256,
+    ) -> Result<LenderQuote> {
+        let client = Arc::clone(&self.client);
+        let liquidity = route.lender.max_flash_loan(Arc::clone(&client), token);
+        let fee = async {
+            match route.fee.fee(amount) {
+                Some(fee) => Ok(fee),
+                None => route.lender.flash_fee(client, token, amount).await,
+            }
+        };
+        let (liquidity, fee) = futures::join!(liquidity, fee);
+        Ok(LenderQuote {
+            lender: route.lender.address(),
+            kind: route.lender.kind(),
+            liquidity: liquidity?,
+            fee: fee?,
+        })
+    }
+}
+
+/// Orders quotes by cheapest fee, then deepest liquidity
+fn rank(a: &LenderQuote, b: &LenderQuote) -> Ordering {
+    a.fee.cmp(&b.fee).then_with(|| b.liquidity.cmp(&a.liquidity))
+}
+
+impl<M> Clone for LenderRouter<M> {
+    fn clone(&self) -> Self {
+        Self { client: Arc::clone(&self.client), routes: self.routes.clone() }
+    }
+}
+
+impl<M> Clone for Route<M> {
+    fn clone(&self) -> Self {
+        Self { lender: Arc::clone(&self.lender), tokens: self.tokens.clone(), fee: self.fee }
+    }
+}
+
+impl<M: Middleware + 'static> fmt::Debug for LenderRouter<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LenderRouter")
+            .field("lenders", &self.routes.iter().map(|route| &route.lender).collect::<Vec<_>>())
+            .finish()
+    }
+}
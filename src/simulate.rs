@@ -0,0 +1,565 @@
+use anyhow::Result;
+use ethers::{
+    abi::{encode, AbiDecode, AbiEncode, Token},
+    prelude::*,
+    utils::get_contract_address,
+};
+use revm::{
+    db::{CacheDB, EmptyDB},
+    inspector_handle_register,
+    interpreter::{CallInputs, CallOutcome},
+    primitives::{
+        self as rtypes, AccountInfo, Bytecode, ExecutionResult, ResultAndState, State, TransactTo,
+    },
+    Database, DatabaseCommit, Evm, EvmContext, Inspector,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{contract::*, errors::*, lender::*, outcome::*, plan::*, profit::*};
+
+/// The gas limit simulated transactions are executed with
+pub const SIMULATION_GAS_LIMIT: u64 = 30_000_000;
+
+/// The state of a single account in a [StateSnapshot]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountState {
+    /// The account ether balance
+    #[serde(default)]
+    pub balance: U256,
+    /// The account nonce
+    #[serde(default)]
+    pub nonce: u64,
+    /// The deployed bytecode, empty for externally owned accounts
+    #[serde(default)]
+    pub code: Bytes,
+    /// Non-zero storage slots
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// The block environment simulated transactions are executed in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockState {
+    /// The block number
+    #[serde(default)]
+    pub number: u64,
+    /// The block timestamp
+    #[serde(default)]
+    pub timestamp: u64,
+    /// The block coinbase
+    #[serde(default)]
+    pub coinbase: Address,
+}
+
+/// A caller-supplied state snapshot to simulate flashloans over
+///
+/// ### Usage
+///
+/// A snapshot can be built up in code with [insert_account](StateSnapshot::insert_account) and
+/// [deploy](StateSnapshot::deploy), or deserialized from a JSON fixture so strategies can be tested
+/// without a node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// The chain id
+    #[serde(default)]
+    pub chain_id: u64,
+    /// The block environment
+    #[serde(default)]
+    pub block: BlockState,
+    /// Account states by address
+    #[serde(default)]
+    pub accounts: BTreeMap<Address, AccountState>,
+}
+
+/// The result of a single [Call3] executed during a simulation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimulatedCall {
+    /// The call target
+    pub target: Address,
+    /// Whether the call succeeded
+    pub success: bool,
+    /// The call return data, or revert data if the call failed
+    pub return_data: Bytes,
+    /// The gas used by the call
+    pub gas_used: u64,
+}
+
+/// The result of a simulated flashloan
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Simulation {
+    /// Whether the `flashBorrow` transaction succeeded
+    pub success: bool,
+    /// The gas used by the transaction
+    pub gas_used: u64,
+    /// The transaction return data, or revert data if the transaction failed
+    pub output: Bytes,
+    /// The result of each executed [Call3], in order
+    ///
+    /// Calls after a failing call that disallowed failure are not executed and are omitted.
+    pub calls: Vec<SimulatedCall>,
+    /// The logs emitted by the transaction
+    pub logs: Vec<Log>,
+    /// The change in the borrower's balance of each tracked token
+    ///
+    /// The borrower's ether balance change is keyed by the zero address.
+    pub balance_deltas: HashMap<Address, I256>,
+}
+
+impl Simulation {
+    /// Decode the [Call3Result]s returned by the borrower entrypoint
+    ///
+    /// Every entrypoint, including the nested and program ones, returns the results as a
+    /// `Call3Result[]`.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [SimulationError](FlashloanError::SimulationError) if the flashloan reverted.
+    pub fn results(&self) -> Result<Vec<Call3Result>> {
+        if !self.success {
+            return Err(FlashloanError::SimulationError("flashBorrow reverted".to_string()).into())
+        }
+        Ok(Vec::<Call3Result>::decode(&self.output)?)
+    }
+}
+
+impl StateSnapshot {
+    /// Create an empty snapshot for the given chain id
+    pub fn new(chain_id: u64) -> Self {
+        Self { chain_id, ..Default::default() }
+    }
+
+    /// Insert or replace an account
+    pub fn insert_account(&mut self, address: Address, account: AccountState) -> &mut Self {
+        self.accounts.insert(address, account);
+        self
+    }
+
+    /// Set the ether balance of an account
+    pub fn set_balance(&mut self, address: Address, balance: U256) -> &mut Self {
+        self.accounts.entry(address).or_default().balance = balance;
+        self
+    }
+
+    /// Set a storage slot of an account
+    pub fn set_storage(&mut self, address: Address, slot: U256, value: U256) -> &mut Self {
+        self.accounts.entry(address).or_default().storage.insert(slot, value);
+        self
+    }
+
+    /// Deploy a contract from the `deployer` account
+    ///
+    /// `init_code` is the creation bytecode with any abi encoded constructor arguments appended.
+    /// Returns the address of the deployed contract.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [SimulationError](FlashloanError::SimulationError) if the deployment reverts.
+    pub fn deploy(&mut self, deployer: Address, init_code: Bytes) -> Result<Address> {
+        let nonce = self.accounts.get(&deployer).map(|account| account.nonce).unwrap_or_default();
+        let ResultAndState { result, state } =
+            self.transact(deployer, None, U256::zero(), init_code, &mut NoopTracer)?;
+        if !result.is_success() {
+            return Err(FlashloanError::SimulationError(format!(
+                "Deployment from {:?} failed: {:?}",
+                deployer, result
+            ))
+            .into());
+        }
+        self.commit(state);
+        Ok(get_contract_address(deployer, nonce))
+    }
+
+    /// Deploy a flashloan borrower contract for the given lender kind
+    ///
+    /// The `owner` is used as the deployer. Returns the address of the deployed borrower.
+    pub fn deploy_borrower(
+        &mut self,
+        kind: LenderKind,
+        lender: Address,
+        owner: Address,
+    ) -> Result<Address> {
+        let mut init_code = kind.borrower_bytecode()?.to_vec();
+        init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+        self.deploy(owner, init_code.into())
+    }
+
+    /// Execute a call against the snapshot without modifying it
+    ///
+    /// Returns the call return data if successful.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [SimulationError](FlashloanError::SimulationError) if the call reverts.
+    pub fn call(&self, from: Address, to: Address, data: Bytes) -> Result<Bytes> {
+        let ResultAndState { result, .. } =
+            self.transact(from, Some(to), U256::zero(), data, &mut NoopTracer)?;
+        match result {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data().to_vec().into()),
+            result => Err(FlashloanError::SimulationError(format!(
+                "Call to {:?} failed: {:?}",
+                to, result
+            ))
+            .into()),
+        }
+    }
+
+    /// The ERC20 `token` balance of `account`, or its ether balance for the zero address
+    pub fn balance_of(&self, token: Address, account: Address) -> Result<U256> {
+        if token.is_zero() {
+            return Ok(self.accounts.get(&account).map(|a| a.balance).unwrap_or_default())
+        }
+        let data = ierc20::BalanceOfCall { account }.encode();
+        let output = self.call(Address::zero(), token, data.into())?;
+        Ok(U256::from_big_endian(output.get(..32).unwrap_or(&[0u8; 32])))
+    }
+
+    /// Simulate `flashBorrow` on a borrower deployed in the snapshot
+    ///
+    /// The transaction is sent from `caller`, which should be the borrower owner. The borrower's
+    /// balance of each token in `tracked_tokens` is compared before and after the transaction.
+    pub fn simulate_flashloan(
+        &self,
+        caller: Address,
+        borrower: Address,
+        token: Address,
+        amount: U256,
+        calls: &[Call3],
+        tracked_tokens: &[Address],
+    ) -> Result<Simulation> {
+        let data = FlashBorrowCall { token, amount, calls: calls.to_vec() }.encode();
+        self.simulate_entrypoint(caller, borrower, U256::zero(), data.into(), calls, tracked_tokens)
+    }
+
+    /// Simulate a call of any entrypoint of a borrower deployed in the snapshot
+    ///
+    /// `data` is the entrypoint calldata, and `value` the ether sent with it from `caller`. The
+    /// `calls` the borrower makes are traced into [calls](Simulation::calls), and the borrower's
+    /// balance of each token in `tracked_tokens` is compared before and after the transaction.
+    pub fn simulate_entrypoint(
+        &self,
+        caller: Address,
+        borrower: Address,
+        value: U256,
+        data: Bytes,
+        calls: &[Call3],
+        tracked_tokens: &[Address],
+    ) -> Result<Simulation> {
+        // Snapshot balances before the flashloan
+        let mut tokens = vec![Address::zero()];
+        for token in tracked_tokens {
+            if !tokens.contains(token) {
+                tokens.push(*token);
+            }
+        }
+        let before =
+            tokens.iter().map(|t| self.balance_of(*t, borrower)).collect::<Result<Vec<_>>>()?;
+
+        // Execute the flashloan, tracing the calls made from the borrower callback
+        let mut tracer = CallTracer::new(borrower, calls);
+        let ResultAndState { result, state } =
+            self.transact(caller, Some(borrower), value, data, &mut tracer)?;
+
+        let mut after_state = self.clone();
+        after_state.commit(state);
+        let mut balance_deltas = HashMap::new();
+        for (token, before) in tokens.iter().zip(before) {
+            let after = after_state.balance_of(*token, borrower)?;
+            balance_deltas.insert(*token, balance_delta(before, after)?);
+        }
+
+        let (success, gas_used, output, logs) = match result {
+            ExecutionResult::Success { gas_used, logs, output, .. } => {
+                (true, gas_used, output.into_data(), logs)
+            }
+            ExecutionResult::Revert { gas_used, output } => (false, gas_used, output, vec![]),
+            ExecutionResult::Halt { gas_used, .. } => (false, gas_used, Default::default(), vec![]),
+        };
+        Ok(Simulation {
+            success,
+            gas_used,
+            output: output.to_vec().into(),
+            calls: tracer.results,
+            logs: logs.into_iter().map(to_ethers_log).collect(),
+            balance_deltas,
+        })
+    }
+
+    /// Builds an in-memory database from the snapshot
+    fn database(&self) -> CacheDB<EmptyDB> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        for (address, account) in &self.accounts {
+            let code = Bytecode::new_raw(account.code.to_vec().into());
+            let info =
+                AccountInfo::new(to_u256(account.balance), account.nonce, code.hash_slow(), code);
+            db.insert_account_info(to_address(*address), info);
+            for (slot, value) in &account.storage {
+                // The account was just inserted so this can't fail
+                let _ = db.insert_account_storage(
+                    to_address(*address),
+                    to_u256(*slot),
+                    to_u256(*value),
+                );
+            }
+        }
+        db
+    }
+
+    /// Executes a transaction over the snapshot without committing it
+    fn transact<I: Inspector<CacheDB<EmptyDB>>>(
+        &self,
+        from: Address,
+        to: Option<Address>,
+        value: U256,
+        data: Bytes,
+        inspector: &mut I,
+    ) -> Result<ResultAndState> {
+        let mut evm = Evm::builder()
+            .with_db(self.database())
+            .with_external_context(inspector)
+            .modify_cfg_env(|cfg| cfg.chain_id = self.chain_id)
+            .modify_block_env(|block| {
+                block.number = rtypes::U256::from(self.block.number);
+                block.timestamp = rtypes::U256::from(self.block.timestamp);
+                block.coinbase = to_address(self.block.coinbase);
+                block.basefee = rtypes::U256::ZERO;
+                block.gas_limit = rtypes::U256::from(SIMULATION_GAS_LIMIT);
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = to_address(from);
+                tx.transact_to = match to {
+                    Some(to) => TransactTo::call(to_address(to)),
+                    None => TransactTo::create(),
+                };
+                tx.value = to_u256(value);
+                tx.data = data.to_vec().into();
+                tx.gas_limit = SIMULATION_GAS_LIMIT;
+                tx.gas_price = rtypes::U256::ZERO;
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let result =
+            evm.transact().map_err(|e| FlashloanError::SimulationError(format!("{:?}", e)))?;
+        Ok(result)
+    }
+
+    /// Folds the changed accounts of an executed transaction into the snapshot
+    fn commit(&mut self, state: State) {
+        let mut db = self.database();
+        db.commit(state);
+        for (address, account) in db.accounts {
+            let address = Address::from(address.0 .0);
+            if account.info.is_empty() && account.storage.values().all(|v| v.is_zero()) {
+                self.accounts.remove(&address);
+                continue
+            }
+            let code =
+                account.info.code.map(|code| code.original_bytes().to_vec()).unwrap_or_default();
+            self.accounts.insert(
+                address,
+                AccountState {
+                    balance: from_u256(account.info.balance),
+                    nonce: account.info.nonce,
+                    code: code.into(),
+                    storage: account
+                        .storage
+                        .into_iter()
+                        .filter(|(_, value)| !value.is_zero())
+                        .map(|(slot, value)| (from_u256(slot), from_u256(value)))
+                        .collect(),
+                },
+            );
+        }
+    }
+}
+
+impl<M: Middleware> crate::builder::FlashloanBuilder<M> {
+    /// Simulate the flashloan in an embedded EVM over a caller-supplied state snapshot
+    ///
+    /// See [FlashloanPlan::simulate]. A fresh flash fee in the fee cache is passed as the
+    /// repayment, as [execute](crate::builder::FlashloanBuilder::execute) does, but a missing fee
+    /// is not queried.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingToken](FlashloanError::MissingToken),
+    /// [MissingAmount](FlashloanError::MissingAmount),
+    /// [MissingBorrower](FlashloanError::MissingBorrower) or
+    /// [MissingOwner](FlashloanError::MissingOwner) if the respective parameter is not specified.
+    /// A reverting flashloan is not an error, it is reported through
+    /// [success](Simulation::success).
+    pub fn simulate(
+        &self,
+        snapshot: &StateSnapshot,
+        tracked_tokens: &[Address],
+    ) -> Result<Simulation> {
+        let plan = self.plan()?;
+        let repayment = self.fresh_repayment(plan.token(), plan.amount())?;
+        plan.with_repayment(repayment).simulate(snapshot, tracked_tokens)
+    }
+
+    /// [**Async**] Check a simulated flashloan against its costs
+    ///
+    /// See [FlashloanPlan::check_simulated_profit].
+    pub async fn check_simulated_profit(&self, simulation: &Simulation) -> Result<ProfitCheck>
+    where
+        M: 'static,
+    {
+        self.plan()?.check_simulated_profit(simulation).await
+    }
+}
+
+impl<M: Middleware> FlashloanPlan<M> {
+    /// Simulate the flashloan in an embedded EVM over a caller-supplied state snapshot
+    ///
+    /// The borrower contract must already be deployed in the snapshot (see
+    /// [deploy_borrower](StateSnapshot::deploy_borrower)) and is called from the configured owner,
+    /// with the calldata and [value](FlashloanPlan::value) of the entrypoint
+    /// [execute](FlashloanPlan::execute) calls. The owner must hold the value in the snapshot.
+    /// The borrowed token, the profit guard token and each token in `tracked_tokens` are tracked
+    /// in the returned [balance_deltas](Simulation::balance_deltas).
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingOwner](FlashloanError::MissingOwner) if the owner is not specified.
+    /// A reverting flashloan is not an error, it is reported through
+    /// [success](Simulation::success).
+    pub fn simulate(
+        &self,
+        snapshot: &StateSnapshot,
+        tracked_tokens: &[Address],
+    ) -> Result<Simulation> {
+        let owner = self.owner().ok_or(FlashloanError::MissingOwner)?;
+        let mut tokens = vec![self.token()];
+        tokens.extend(self.profit_guard().map(|guard| guard.token));
+        tokens.extend_from_slice(tracked_tokens);
+
+        // The same entrypoint call as execute, so legs, programs, guards, repayments, trusted
+        // lenders and the call value are all simulated
+        let flash_borrow = self.flash_borrow();
+        let data = flash_borrow.tx.data().cloned().unwrap_or_default();
+        let value = flash_borrow.tx.value().copied().unwrap_or_default();
+        // Program commands are reported through the results, not traced as calls
+        let calls = if self.program().is_some() { &[] } else { self.calls() };
+        snapshot.simulate_entrypoint(owner, self.borrower(), value, data, calls, &tokens)
+    }
+
+    /// [**Async**] Check a simulated flashloan against its costs
+    ///
+    /// The simulated gas used is priced at the client's current gas price. The simulation repays
+    /// the lender, so the flash fee is already deducted from the simulated balance change.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingProfitGuard](FlashloanError::MissingProfitGuard) if no profit guard is
+    /// set. Returns the decoded revert if the simulated flashloan reverted, and an
+    /// [Unprofitable](FlashloanError::Unprofitable) if the net profit is below the minimum profit.
+    pub async fn check_simulated_profit(&self, simulation: &Simulation) -> Result<ProfitCheck>
+    where
+        M: 'static,
+    {
+        let guard = self.profit_guard().ok_or(FlashloanError::MissingProfitGuard)?;
+        if !simulation.success {
+            return Err(self.revert_decoder().decode(&simulation.output).into())
+        }
+        let delta = simulation.balance_deltas.get(&guard.token).copied().ok_or_else(|| {
+            FlashloanError::SimulationError("profit token balance was not tracked".to_string())
+        })?;
+        let gas_price = self
+            .client()
+            .get_gas_price()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        let gas_cost = U256::from(simulation.gas_used) * gas_price;
+        let fee = if guard.token == self.token() { self.flash_fee().await? } else { U256::zero() };
+        guard.check(delta + I256::from_raw(fee), fee, gas_cost)
+    }
+}
+
+/// Records the result of each [Call3] the borrower makes from the lender callback
+struct CallTracer {
+    borrower: rtypes::Address,
+    expected: Vec<(rtypes::Address, rtypes::Bytes)>,
+    in_callback: bool,
+    stack: Vec<Option<usize>>,
+    results: Vec<SimulatedCall>,
+}
+
+impl CallTracer {
+    fn new(borrower: Address, calls: &[Call3]) -> Self {
+        Self {
+            borrower: to_address(borrower),
+            expected: calls
+                .iter()
+                .map(|call| (to_address(call.target), call.call_data.to_vec().into()))
+                .collect(),
+            in_callback: false,
+            stack: vec![],
+            results: vec![],
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        // The lender calling back into the borrower starts the multicall
+        if inputs.contract == self.borrower && context.journaled_state.depth() > 0 {
+            self.in_callback = true;
+        }
+        let next = self.results.len() + self.stack.iter().flatten().count();
+        let matched = self.in_callback
+            && inputs.context.caller == self.borrower
+            && self.expected.get(next).map_or(false, |(target, data)| {
+                *target == inputs.contract && *data == inputs.input
+            });
+        self.stack.push(if matched { Some(next) } else { None });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        if let Some(Some(_)) = self.stack.pop() {
+            self.results.push(SimulatedCall {
+                target: Address::from(inputs.contract.0 .0),
+                success: outcome.result.is_ok(),
+                return_data: outcome.result.output.to_vec().into(),
+                gas_used: outcome.result.gas.spend(),
+            });
+        }
+        outcome
+    }
+}
+
+/// An inspector that records nothing
+struct NoopTracer;
+
+impl<DB: Database> Inspector<DB> for NoopTracer {}
+
+fn to_address(address: Address) -> rtypes::Address {
+    rtypes::Address::from(address.0)
+}
+
+fn to_u256(value: U256) -> rtypes::U256 {
+    rtypes::U256::from_limbs(value.0)
+}
+
+fn from_u256(value: rtypes::U256) -> U256 {
+    U256(value.into_limbs())
+}
+
+fn to_ethers_log(log: rtypes::Log) -> Log {
+    Log {
+        address: Address::from(log.address.0 .0),
+        topics: log.data.topics().iter().map(|topic| H256::from(topic.0)).collect(),
+        data: log.data.data.to_vec().into(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,404 @@
+use anyhow::Result;
+use ethers::{
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        HumanReadableParser, ParamType, Token,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{fmt::Write, path::Path};
+
+use crate::{contract::*, errors::*, lender::*, nested::*, profit::*, registry::*};
+
+/// A flashloan plan as a JSON or TOML document
+///
+/// ### Usage
+///
+/// Specs hold the parameters of a [FlashloanBuilder](crate::builder::FlashloanBuilder), so a
+/// strategy can be saved, diffed, reviewed and loaded back into a builder with
+/// [with_spec](crate::builder::FlashloanBuilder::with_spec). Addresses are hex strings, and
+/// amounts are decimal or `0x` prefixed hex strings. Calls are either raw calldata or a function
+/// signature with its arguments.
+///
+/// ```toml
+/// chain_id = 1
+/// lender = "0x1eb4cf3a948e7d72a198fe073ccb8c7a948cd853"
+/// token = "0x6b175474e89094c44da98b954eedeac495271d0f"
+/// amount = "1000000000000000000000"
+///
+/// [[calls]]
+/// target = "0x6b175474e89094c44da98b954eedeac495271d0f"
+/// function = "approve(address spender, uint256 amount)"
+/// args = ["0x7a250d5630b4cf539739df2c5dacb4c659f2488d", "0xffffffffffffffff"]
+/// ```
+///
+/// The client, bundle relay, fee strategy and command program aren't part of the spec.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlashloanSpec {
+    /// The chain the plan is for, checked against the builder's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>,
+    /// The flashloan borrower contract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub borrower: Option<Address>,
+    /// The borrower owner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Address>,
+    /// The flash lender
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lender: Option<Address>,
+    /// The flash lender protocol
+    #[serde(default)]
+    pub lender_kind: LenderKind,
+    /// The token to borrow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<Address>,
+    /// The amount to borrow
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "option_amount")]
+    pub amount: Option<U256>,
+    /// The calls made with the borrowed tokens
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallSpec>,
+    /// The legs of a nested flashloan
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legs: Vec<FlashloanLeg>,
+    /// The minimum profit required
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profit_guard: Option<ProfitGuard>,
+    /// The gas padding, in basis points
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub gas_padding_bps: u64,
+}
+
+/// A call of a [FlashloanSpec]
+///
+/// The calldata is either given as raw `data`, or encoded from a `function` signature and its
+/// `args`. Arguments are parsed by type, with uints as decimal or `0x` prefixed hex strings, and
+/// arrays and tuples as `[a,b]` and `(a,b)`. A call with neither sends no calldata.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CallSpec {
+    /// The call target
+    pub target: Address,
+    /// Whether the flashloan continues if the call fails
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub allow_failure: bool,
+    /// The ether sent with the call
+    #[serde(default, skip_serializing_if = "U256::is_zero", with = "amount")]
+    pub value: U256,
+    /// The raw calldata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+    /// The function signature, e.g. `transfer(address to, uint256 amount)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    /// The function arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+impl CallSpec {
+    /// A call spec with the raw calldata of `call`
+    pub fn raw(call: &Call3) -> Self {
+        Self {
+            target: call.target,
+            allow_failure: call.allow_failure,
+            value: call.value,
+            data: Some(call.call_data.clone()),
+            function: None,
+            args: vec![],
+        }
+    }
+
+    /// A call of `function` on `target` with `args`, encoded when the spec is loaded
+    pub fn abi(target: Address, function: &str, args: Vec<String>) -> Self {
+        Self { target, function: Some(function.to_string()), args, ..Default::default() }
+    }
+
+    /// Encode the call
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [PlanFormatError](FlashloanError::PlanFormatError) if both raw data and a
+    /// function are given, the function signature is invalid, or the arguments don't match it.
+    pub fn to_call3(&self) -> Result<Call3> {
+        let call_data = match (&self.data, &self.function) {
+            (Some(_), Some(_)) => {
+                return Err(format_error(format!(
+                    "The call to {:?} has both data and a function",
+                    self.target
+                )))
+            }
+            (Some(data), None) => data.clone(),
+            (None, Some(function)) => self.encode_function(function)?,
+            (None, None) if self.args.is_empty() => Bytes::default(),
+            (None, None) => {
+                return Err(format_error(format!(
+                    "The call to {:?} has arguments but no function",
+                    self.target
+                )))
+            }
+        };
+        Ok(Call3 {
+            target: self.target,
+            allow_failure: self.allow_failure,
+            value: self.value,
+            call_data,
+        })
+    }
+
+    fn encode_function(&self, signature: &str) -> Result<Bytes> {
+        let function = HumanReadableParser::parse_function(signature)
+            .map_err(|e| format_error(format!("Invalid function {:?}: {}", signature, e)))?;
+        if function.inputs.len() != self.args.len() {
+            return Err(format_error(format!(
+                "{} takes {} arguments, got {}",
+                function.name,
+                function.inputs.len(),
+                self.args.len()
+            )))
+        }
+        let tokens = function
+            .inputs
+            .iter()
+            .zip(&self.args)
+            .map(|(input, arg)| match input.kind {
+                // Uints also take hex amounts
+                ParamType::Uint(_) => parse_amount(arg).map(Token::Uint),
+                _ => LenientTokenizer::tokenize(&input.kind, arg).map_err(|e| e.to_string()),
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| format_error(format!("Invalid {} argument: {}", function.name, e)))?;
+        let data = function.encode_input(&tokens).map_err(|e| format_error(e.to_string()))?;
+        Ok(data.into())
+    }
+}
+
+impl FlashloanSpec {
+    /// Parse a spec from a JSON document
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [PlanFormatError](FlashloanError::PlanFormatError) if the document is invalid.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let spec = serde_json::from_str(json).map_err(|e| format_error(e.to_string()))?;
+        Ok(spec)
+    }
+
+    /// Parse a spec from a TOML document
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [PlanFormatError](FlashloanError::PlanFormatError) if the document is invalid.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let document =
+            toml.parse::<toml_edit::DocumentMut>().map_err(|e| format_error(e.to_string()))?;
+        let value = toml_table_to_json(document.as_table());
+        let spec = serde_json::from_value(value).map_err(|e| format_error(e.to_string()))?;
+        Ok(spec)
+    }
+
+    /// Load a spec from a `.toml` or `.json` file
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [PlanFormatError](FlashloanError::PlanFormatError) if the file can't be read,
+    /// has another extension, or is invalid.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| format_error(e.to_string()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(format_error(format!("Unsupported plan file {}", path.display()))),
+        }
+    }
+
+    /// Serialize the spec to pretty JSON
+    pub fn to_json(&self) -> Result<String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| format_error(e.to_string()))?;
+        Ok(json)
+    }
+
+    /// Serialize the spec to TOML
+    pub fn to_toml(&self) -> Result<String> {
+        let value = serde_json::to_value(self).map_err(|e| format_error(e.to_string()))?;
+        let mut toml = String::new();
+        match value {
+            Value::Object(table) => write_toml_table(&mut toml, &[], &table, false),
+            _ => unreachable!("specs serialize to objects"),
+        }
+        Ok(toml)
+    }
+
+    /// Save the spec to a `.toml` or `.json` file
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [PlanFormatError](FlashloanError::PlanFormatError) if the file has another
+    /// extension or can't be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => self.to_toml()?,
+            Some("json") => self.to_json()? + "\n",
+            _ => return Err(format_error(format!("Unsupported plan file {}", path.display()))),
+        };
+        std::fs::write(path, contents).map_err(|e| format_error(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Encode the calls of the spec
+    ///
+    /// ### Errors
+    ///
+    /// Returns the errors of [CallSpec::to_call3], with the index of the invalid call.
+    pub fn encode_calls(&self) -> Result<Vec<Call3>> {
+        self.calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| {
+                call.to_call3().map_err(|e| format_error(format!("Call {}: {}", index, e)))
+            })
+            .collect()
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hex amount
+pub(crate) fn parse_amount(amount: &str) -> std::result::Result<U256, String> {
+    let amount = amount.trim();
+    match amount.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).map_err(|e| e.to_string()),
+        None => U256::from_dec_str(amount).map_err(|e| e.to_string()),
+    }
+}
+
+/// Serializes amounts as decimal strings, and deserializes decimal or hex strings and integers
+pub(crate) mod amount {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Amount {
+        Integer(u64),
+        String(String),
+    }
+
+    pub fn serialize<S: Serializer>(amount: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&amount.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        match Amount::deserialize(deserializer)? {
+            Amount::Integer(amount) => Ok(amount.into()),
+            Amount::String(amount) => parse_amount(&amount).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// [amount] for optional amounts
+pub(crate) mod option_amount {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        amount: &Option<U256>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match amount {
+            Some(amount) => amount::serialize(amount, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<U256>, D::Error> {
+        amount::deserialize(deserializer).map(Some)
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+fn format_error(message: String) -> anyhow::Error {
+    FlashloanError::PlanFormatError(message).into()
+}
+
+/// Writes a table's values, then its subtables and arrays of tables under their headers
+fn write_toml_table(out: &mut String, path: &[String], table: &Map<String, Value>, header: bool) {
+    let is_table = |value: &Value| value.is_object();
+    let is_array_of_tables = |value: &Value| match value {
+        Value::Array(items) => !items.is_empty() && items.iter().all(is_table),
+        _ => false,
+    };
+    for (key, value) in table {
+        if value.is_null() || is_table(value) || is_array_of_tables(value) {
+            continue
+        }
+        let _ = writeln!(out, "{} = {}", toml_key(key), toml_value(value));
+    }
+    for (key, value) in table {
+        let mut child = path.to_vec();
+        child.push(toml_key(key));
+        match value {
+            Value::Object(subtable) => {
+                if header || !out.is_empty() {
+                    out.push('\n');
+                }
+                let _ = writeln!(out, "[{}]", child.join("."));
+                write_toml_table(out, &child, subtable, true);
+            }
+            Value::Array(items) if is_array_of_tables(value) => {
+                for item in items.iter().filter_map(Value::as_object) {
+                    if !out.is_empty() {
+                        out.push('\n');
+                    }
+                    let _ = writeln!(out, "[[{}]]", child.join("."));
+                    write_toml_table(out, &child, item, true);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Formats an inline TOML value
+fn toml_value(value: &Value) -> String {
+    match value {
+        // JSON string escapes are valid TOML basic string escapes
+        Value::String(_) | Value::Number(_) | Value::Bool(_) => value.to_string(),
+        Value::Array(items) => {
+            format!("[{}]", items.iter().map(toml_value).collect::<Vec<_>>().join(", "))
+        }
+        Value::Object(table) => format!(
+            "{{ {} }}",
+            table
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| format!("{} = {}", toml_key(key), toml_value(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Null => unreachable!("nulls are skipped"),
+    }
+}
+
+fn toml_key(key: &str) -> String {
+    let bare =
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        Value::from(key).to_string()
+    }
+}
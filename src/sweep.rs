@@ -0,0 +1,85 @@
+use ethers::{prelude::*, utils::keccak256};
+
+/// A withdrawal of a token or ether from the borrower
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sweep {
+    /// The swept token, or `None` for ether
+    pub token: Option<Address>,
+    /// The recipient
+    pub to: Address,
+    /// The amount moved
+    pub amount: U256,
+    /// The withdrawal transaction
+    pub transaction_hash: H256,
+}
+
+/// The sweeps run after each successful [execute](crate::builder::FlashloanBuilder::execute)
+///
+/// ### Usage
+///
+/// Set on a builder with [with_auto_sweep](crate::builder::FlashloanBuilder::with_auto_sweep).
+/// Without tokens, the borrowed tokens and the profit guard token are swept.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// // Sweep the borrowed tokens and the borrower's ether to the owner
+/// let sweep = AutoSweep { eth: true, ..Default::default() };
+///
+/// // Sweep WETH to a cold wallet
+/// let (weth, cold_wallet) = (Address::random(), Address::random());
+/// let sweep = AutoSweep { tokens: vec![weth], eth: false, to: Some(cold_wallet) };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutoSweep {
+    /// The tokens to sweep
+    pub tokens: Vec<Address>,
+    /// Whether the borrower's ether is swept too
+    pub eth: bool,
+    /// The recipient, the borrower owner if unset
+    pub to: Option<Address>,
+}
+
+/// An ERC20 `Transfer` event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Erc20Transfer {
+    /// The token contract
+    pub token: Address,
+    /// The sender
+    pub from: Address,
+    /// The recipient
+    pub to: Address,
+    /// The amount transferred
+    pub amount: U256,
+}
+
+/// Decode the ERC20 `Transfer` events of a transaction receipt
+///
+/// ERC721 transfers, which index the token id instead of logging an amount, are skipped.
+pub fn erc20_transfers(receipt: &TransactionReceipt) -> Vec<Erc20Transfer> {
+    let topic = H256::from(keccak256("Transfer(address,address,uint256)"));
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.topics.len() == 3 && log.topics[0] == topic && log.data.len() == 32)
+        .map(|log| Erc20Transfer {
+            token: log.address,
+            from: Address::from(log.topics[1]),
+            to: Address::from(log.topics[2]),
+            amount: U256::from_big_endian(&log.data),
+        })
+        .collect()
+}
+
+/// The amount of `token` moved out of `from` in a receipt
+pub(crate) fn transferred_from(
+    receipt: &TransactionReceipt,
+    token: Address,
+    from: Address,
+) -> U256 {
+    erc20_transfers(receipt)
+        .iter()
+        .filter(|transfer| transfer.token == token && transfer.from == from)
+        .fold(U256::zero(), |total, transfer| total + transfer.amount)
+}
@@ -0,0 +1,261 @@
+use anyhow::Result;
+use ethers::{
+    abi::{encode, Abi, Token, Tokenize},
+    contract::{Contract, ContractFactory, Lazy},
+    prelude::*,
+    utils::keccak256,
+};
+use std::sync::Arc;
+
+use crate::{builder::*, contract::*, errors::*, huff::*, lender::*};
+
+#[cfg(feature = "simulate")]
+use crate::simulate::*;
+
+/// The name of the mock ERC-3156 lender contract
+pub const MOCK_LENDER_NAME: &str = "FlashLender";
+
+/// The name of the mock ERC20 token contract
+pub const MOCK_TOKEN_NAME: &str = "MockERC20";
+
+/// The bundled `FlashLender` artifact, assembled from `contracts/mocks/FlashLender.huff`
+const MOCK_LENDER_ARTIFACT: &str = include_str!("FlashLender.json");
+
+/// The bundled `MockERC20` artifact, assembled from `contracts/mocks/MockERC20.huff`
+const MOCK_TOKEN_ARTIFACT: &str = include_str!("MockERC20.json");
+
+/// The abi of the mock `FlashLender` contract
+pub static MOCK_LENDER_ABI: Lazy<Abi> = Lazy::new(|| artifact_abi(MOCK_LENDER_ARTIFACT));
+
+/// The abi of the `MockERC20` contract
+pub static MOCK_TOKEN_ABI: Lazy<Abi> = Lazy::new(|| artifact_abi(MOCK_TOKEN_ARTIFACT));
+
+/// The storage slot of the `balanceOf` mapping, as in solmate's ERC20
+const BALANCE_OF_SLOT: u64 = 3;
+
+/// The storage slot of `totalSupply`, as in solmate's ERC20
+const TOTAL_SUPPLY_SLOT: u64 = 2;
+
+/// The creation bytecode of the mock `FlashLender` contract
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](crate::errors::FlashloanError::MissingBytecode) if the bundled
+/// artifact has not been assembled.
+pub fn mock_lender_bytecode() -> Result<Bytes> {
+    artifact_bytecode(MOCK_LENDER_NAME, MOCK_LENDER_ARTIFACT)
+}
+
+/// The creation bytecode of the `MockERC20` contract
+///
+/// The token's name, symbol and decimals are constant, so the constructor takes no arguments.
+///
+/// ### Errors
+///
+/// Returns a [MissingBytecode](crate::errors::FlashloanError::MissingBytecode) if the bundled
+/// artifact has not been assembled.
+pub fn mock_token_bytecode() -> Result<Bytes> {
+    artifact_bytecode(MOCK_TOKEN_NAME, MOCK_TOKEN_ARTIFACT)
+}
+
+/// The fee charged by the mock lender, 1% of the loan
+pub fn mock_flash_fee(amount: U256) -> U256 {
+    amount / 100
+}
+
+/// A mock lender and token with a borrower deployed against them
+///
+/// The harness deploys the [Huff](BorrowerFlavor::Huff) borrower, which is assembled from the
+/// source in the repository and implements the whole [Flashloan] abi.
+///
+/// ### Usage
+///
+/// Deploy the harness onto a local dev chain such as anvil, or into a [StateSnapshot] with the
+/// `simulate` feature, then borrow from the mock lender with a ready [FlashloanBuilder].
+///
+/// ```rust,ignore
+/// let harness = TestHarness::deploy(client, None, U256::exp10(24)).await?;
+///
+/// // The builder mints the flash fee to the borrower, so the loan repays
+/// let mut builder = harness.builder(U256::exp10(18));
+/// builder.execute().await?;
+/// ```
+#[derive(Debug)]
+pub struct TestHarness<M> {
+    /// The client the mocks were deployed with
+    pub client: Arc<M>,
+    /// The chain id
+    pub chain_id: u64,
+    /// The borrower owner
+    pub owner: Address,
+    /// The mock lender
+    pub lender: Address,
+    /// The mock token, lent by the mock lender
+    pub token: Address,
+    /// The borrower deployed against the mock lender
+    pub borrower: Address,
+}
+
+impl<M: Middleware + 'static> TestHarness<M> {
+    /// [**Async**] Deploy the mocks and a borrower onto a local dev chain
+    ///
+    /// The mock token and lender are deployed from the client's default sender, `liquidity` mock
+    /// tokens are minted to the lender, and a Huff borrower owned by `owner` is deployed against it.
+    /// Without an owner, the first client account owns the borrower.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the mock artifacts have not
+    /// been assembled, and a [ContractDeployError](FlashloanError::ContractDeployError) if a
+    /// deployment fails.
+    pub async fn deploy(client: Arc<M>, owner: Option<Address>, liquidity: U256) -> Result<Self> {
+        let lender_bytecode = mock_lender_bytecode()?;
+        let token_bytecode = mock_token_bytecode()?;
+        let chain_id = client
+            .get_chainid()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?
+            .as_u64();
+
+        let token = deploy_mock(&client, &MOCK_TOKEN_ABI, token_bytecode, ()).await?;
+        let lender = deploy_mock(&client, &MOCK_LENDER_ABI, lender_bytecode, ()).await?;
+
+        let mut builder =
+            FlashloanBuilder::new(Arc::clone(&client), chain_id, owner, None, None, None, None);
+        builder.with_borrower_flavor(BorrowerFlavor::Huff).deploy(Some(lender), None).await?;
+        let borrower = builder.borrower.as_ref().ok_or(FlashloanError::MissingBorrower)?.address();
+        let owner = match owner {
+            Some(owner) => owner,
+            None => Flashloan::new(borrower, Arc::clone(&client))
+                .owner()
+                .call()
+                .await
+                .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?,
+        };
+
+        let harness = Self { client, chain_id, owner, lender, token, borrower };
+        harness.mint(lender, liquidity).await?;
+        Ok(harness)
+    }
+
+    /// A builder borrowing `amount` of the mock token through the harness borrower
+    ///
+    /// The first call mints the flash fee to the borrower, so the loan is repaid without any
+    /// further calls.
+    pub fn builder(&self, amount: U256) -> FlashloanBuilder<M> {
+        let mut builder = FlashloanBuilder::new(
+            Arc::clone(&self.client),
+            self.chain_id,
+            Some(self.owner),
+            Some(self.lender),
+            Some(self.token),
+            Some(amount),
+            Some(self.borrower),
+        );
+        builder.add_call(self.mint_call(self.borrower, mock_flash_fee(amount)));
+        builder
+    }
+
+    /// The mock token contract
+    pub fn token_contract(&self) -> Contract<M> {
+        Contract::new(self.token, MOCK_TOKEN_ABI.clone(), Arc::clone(&self.client))
+    }
+
+    /// A [Call3] minting `amount` mock tokens to `to` from inside the flashloan
+    pub fn mint_call(&self, to: Address, amount: U256) -> Call3 {
+        // This won't panic since the bundled abi is checked by the tests
+        let call_data = MOCK_TOKEN_ABI
+            .function("mint")
+            .and_then(|mint| mint.encode_input(&[Token::Address(to), Token::Uint(amount)]))
+            .unwrap();
+        Call3 {
+            target: self.token,
+            allow_failure: false,
+            value: U256::zero(),
+            call_data: call_data.into(),
+        }
+    }
+
+    /// [**Async**] Mint `amount` mock tokens to `to`
+    ///
+    /// Anyone can mint the mock token, so tests can fund any account.
+    pub async fn mint(&self, to: Address, amount: U256) -> Result<()> {
+        let call = self
+            .token_contract()
+            .method::<_, ()>("mint", (to, amount))
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        call.send()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        Ok(())
+    }
+
+    /// [**Async**] The mock token balance of `account`
+    pub async fn balance_of(&self, account: Address) -> Result<U256> {
+        let balance = self
+            .token_contract()
+            .method::<_, U256>("balanceOf", account)
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?
+            .call()
+            .await
+            .map_err(|e| FlashloanError::ClientFailure(e.to_string()))?;
+        Ok(balance)
+    }
+}
+
+#[cfg(feature = "simulate")]
+impl<M: Middleware + 'static> TestHarness<M> {
+    /// Deploy the mocks and a borrower into an in-process [StateSnapshot]
+    ///
+    /// Everything is deployed from `owner`, and the lender's `liquidity` is written straight to
+    /// the token's storage. The `client` is only used by the returned builders, simulations of
+    /// which never reach it.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [MissingBytecode](FlashloanError::MissingBytecode) if the mock artifacts have not
+    /// been assembled, and a [SimulationError](FlashloanError::SimulationError) if a deployment
+    /// reverts.
+    pub fn deploy_in_snapshot(
+        client: Arc<M>,
+        snapshot: &mut StateSnapshot,
+        owner: Address,
+        liquidity: U256,
+    ) -> Result<Self> {
+        let token_bytecode = mock_token_bytecode()?;
+        let lender_bytecode = mock_lender_bytecode()?;
+
+        let token = snapshot.deploy(owner, token_bytecode)?;
+        let lender = snapshot.deploy(owner, lender_bytecode)?;
+        let mut borrower_init_code = huff_borrower_bytecode()?.to_vec();
+        borrower_init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+        let borrower = snapshot.deploy(owner, borrower_init_code.into())?;
+
+        // Mint to the lender by writing its balance and the total supply
+        let balance_slot =
+            keccak256(encode(&[Token::Address(lender), Token::Uint(BALANCE_OF_SLOT.into())]));
+        snapshot.set_storage(token, U256::from_big_endian(&balance_slot), liquidity);
+        snapshot.set_storage(token, TOTAL_SUPPLY_SLOT.into(), liquidity);
+
+        let chain_id = snapshot.chain_id;
+        Ok(Self { client, chain_id, owner, lender, token, borrower })
+    }
+}
+
+/// Deploy a mock contract from the client's default sender
+async fn deploy_mock<M: Middleware + 'static, T: Tokenize>(
+    client: &Arc<M>,
+    abi: &Abi,
+    bytecode: Bytes,
+    args: T,
+) -> Result<Address> {
+    let contract = ContractFactory::new(abi.clone(), bytecode, Arc::clone(client))
+        .deploy(args)
+        .map_err(|_| FlashloanError::ContractDeployError)?
+        .send()
+        .await
+        .map_err(|_| FlashloanError::ContractDeployError)?;
+    Ok(contract.address())
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use ethers::prelude::*;
+
+use crate::{calls::*, contract::*};
+
+/// How the ether value of the calls is funded
+///
+/// ### Usage
+///
+/// Set on a builder with [with_call_value](crate::builder::FlashloanBuilder::with_call_value).
+/// The borrower's flashloan entrypoints are payable, and the calls spend the ether sent along
+/// on top of the ether the borrower holds.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// // Wrap 1 ether twice, sending 2 ether with the flashloan transaction
+/// let deposit = Weth(Address::random()).deposit(U256::exp10(18)).unwrap();
+/// assert_eq!(calls_value(&[deposit.clone(), deposit]), U256::exp10(18) * 2);
+/// assert_eq!(CallValue::default(), CallValue::Attach);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CallValue {
+    /// Send the summed value of the calls with the flashloan transaction
+    #[default]
+    Attach,
+    /// Spend ether parked in the borrower, checking its balance covers the calls before sending
+    Borrower,
+}
+
+/// The summed ether value of the calls
+pub fn calls_value(calls: &[Call3]) -> U256 {
+    calls.iter().fold(U256::zero(), |total, call| total.saturating_add(call.value))
+}
+
+/// Wraps ether into WETH before the calls and unwraps WETH after them
+///
+/// ### Usage
+///
+/// Set on a builder with [with_weth_wrap](crate::builder::FlashloanBuilder::with_weth_wrap).
+/// The wrapped ether is the value of a WETH `deposit` call, so with
+/// [Attach](CallValue::Attach) it is sent with the flashloan transaction. The unwrapped ether
+/// stays in the borrower until it is swept.
+///
+/// ```rust
+/// use ethers::prelude::*;
+/// use flashloan_rs::prelude::*;
+///
+/// // Wrap 1 ether for the calls and unwrap 1.1 WETH of profit
+/// let weth = WethWrap::new(Address::random())
+///     .with_wrap(U256::exp10(18))
+///     .with_unwrap(U256::exp10(17) * 11);
+/// let calls = weth.around(&[]).unwrap();
+/// assert_eq!(calls.len(), 2);
+/// assert_eq!(calls_value(&calls), U256::exp10(18));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WethWrap {
+    /// The WETH9 contract
+    pub weth: Weth,
+    /// The ether wrapped before the calls
+    pub wrap: U256,
+    /// The WETH unwrapped after the calls
+    pub unwrap: U256,
+}
+
+impl WethWrap {
+    /// Wrap and unwrap nothing through the WETH9 contract at `weth`
+    pub fn new(weth: Address) -> Self {
+        Self { weth: Weth(weth), wrap: U256::zero(), unwrap: U256::zero() }
+    }
+
+    /// Wrap `amount` of ether before the calls
+    pub fn with_wrap(mut self, amount: U256) -> Self {
+        self.wrap = amount;
+        self
+    }
+
+    /// Unwrap `amount` of WETH after the calls
+    pub fn with_unwrap(mut self, amount: U256) -> Self {
+        self.unwrap = amount;
+        self
+    }
+
+    /// The calls preceded by the WETH deposit and followed by the withdrawal
+    ///
+    /// A zero amount skips its call.
+    ///
+    /// ### Errors
+    ///
+    /// Returns a [CallConstructionError](crate::errors::FlashloanError::CallConstructionError) if
+    /// the WETH contract is the zero address.
+    pub fn around(&self, calls: &[Call3]) -> Result<Vec<Call3>> {
+        let mut wrapped = Vec::with_capacity(calls.len() + 2);
+        if !self.wrap.is_zero() {
+            wrapped.push(self.weth.deposit(self.wrap)?);
+        }
+        wrapped.extend_from_slice(calls);
+        if !self.unwrap.is_zero() {
+            wrapped.push(self.weth.withdraw(self.unwrap)?);
+        }
+        Ok(wrapped)
+    }
+}
@@ -4,68 +4,12 @@ pragma solidity ^0.8.15;
 import { Test } from "forge-std/Test.sol";
 import { MockERC20 } from "solmate/test/utils/mocks/MockERC20.sol";
 
+import { BaseFlashBorrower } from "contracts/BaseFlashBorrower.sol";
 import { FlashBorrower } from "contracts/FlashBorrower.sol";
 
 import { IERC20 } from "contracts/interfaces/IERC20.sol";
-import { IERC3156FlashLender } from "contracts/interfaces/IERC3156FlashLender.sol";
-import { IERC3156FlashBorrower } from "contracts/interfaces/IERC3156FlashBorrower.sol";
 
-
-/// @notice Example Flash Lender
-/// @notice Conforms to the EIP-3156: Flash Loans
-contract FlashLender is IERC3156FlashLender {
-
-    bytes32 public constant CALLBACK_SUCCESS = keccak256("ERC3156FlashBorrower.onFlashLoan");
-
-    event FlashLoan(address indexed receiver, address token, uint256 amount, uint256 fee);
-
-    error FlashLoanCallbackFailed();
-
-    /// @dev The amount of currency available to be lent.
-    /// @param token The loan currency.
-    // @return The amount of `token` that can be borrowed.
-    function maxFlashLoan(address token) external view returns (uint256) {
-        return IERC20(token).balanceOf(address(this));
-    }
-
-    /// @dev The fee to be charged for a given loan.
-    /// @param token The loan currency.
-    /// @param amount The amount of tokens lent.
-    /// @return The amount of `token` to be charged for the loan, on top of the returned principal.
-    function flashFee(address token, uint256 amount) external view returns (uint256) {
-        // The flash fee is 1% of the loan amount
-        return amount / 100;
-    }
-
-    /// @dev Initiate a flash loan.
-    /// @param receiver The receiver of the tokens in the loan, and the receiver of the callback.
-    /// @param token The loan currency.
-    /// @param amount The amount of tokens lent.
-    /// @param data Arbitrary data structure, intended to contain user-defined parameters.
-    function flashLoan(
-        IERC3156FlashBorrower receiver,
-        address token,
-        uint256 amount,
-        bytes calldata data
-    ) external returns (bool) {
-        uint256 fee = amount / 100;
-
-        // Transfer the token to the receiver
-        IERC20(token).transfer(address(receiver), amount);
-        emit FlashLoan(address(receiver), token, amount, fee);
-
-        // Call the receiver flashloan callback
-        if (receiver.onFlashLoan(msg.sender, token, amount, fee, data) != CALLBACK_SUCCESS) {
-            revert FlashLoanCallbackFailed();
-        }
-
-        // Transfer the token back to this lender contract
-        IERC20(token).transferFrom(address(receiver), address(this), amount + fee);
-
-        // Wowwee, great success!
-        return true;
-    }
-}
+import { FlashLender } from "contracts/mocks/FlashLender.sol";
 
 contract FlashBorrowerTest is Test {
     FlashBorrower public instance;
@@ -112,4 +56,142 @@ contract FlashBorrowerTest is Test {
         assertEq(token.balanceOf(address(instance)), 990);
         assertEq(token.balanceOf(address(lender)), 1010);
     }
-}
\ No newline at end of file
+
+    function testFlashLoanResults() public {
+        // Read the borrowed balance and make an allowed failing call
+        FlashBorrower.Call3[] memory calls = new FlashBorrower.Call3[](2);
+        calls[0] = BaseFlashBorrower.Call3(
+            address(token), false, 0, abi.encodeWithSelector(IERC20.balanceOf.selector, address(instance))
+        );
+        calls[1] = BaseFlashBorrower.Call3(
+            address(token), true, 0, abi.encodeWithSelector(IERC20.transfer.selector, address(0xdead), 1e18)
+        );
+
+        BaseFlashBorrower.Call3Result[] memory expected = new BaseFlashBorrower.Call3Result[](2);
+        expected[0] = BaseFlashBorrower.Call3Result(true, abi.encode(uint256(2000)));
+        expected[1] = BaseFlashBorrower.Call3Result(false, abi.encodeWithSignature("Panic(uint256)", 0x11));
+
+        vm.expectEmit(false, false, false, true, address(instance));
+        emit Call3Results(expected);
+        vm.prank(owner);
+        BaseFlashBorrower.Call3Result[] memory results = instance.flashBorrow(address(token), 1000, calls);
+
+        assertEq(results.length, 2);
+        assertTrue(results[0].success);
+        assertEq(abi.decode(results[0].returnData, (uint256)), 2000);
+        assertTrue(!results[1].success);
+    }
+
+    function testFlashLoanWithProfit() public {
+        FlashBorrower.Call3[] memory no_calls;
+
+        // Paying the fee without a gain is unprofitable
+        vm.expectRevert(abi.encodeWithSignature("InsufficientProfit(uint256,uint256)", 990, 1000));
+        vm.prank(owner);
+        instance.flashBorrowWithProfit(address(token), 1000, no_calls, address(token), 0);
+
+        // A gain covering the fee and minimum profit succeeds
+        FlashBorrower.Call3[] memory calls = new FlashBorrower.Call3[](1);
+        calls[0] = BaseFlashBorrower.Call3(
+            address(token), false, 0, abi.encodeWithSelector(MockERC20.mint.selector, address(instance), 20)
+        );
+        vm.prank(owner);
+        instance.flashBorrowWithProfit(address(token), 1000, calls, address(token), 10);
+        assertEq(token.balanceOf(address(instance)), 1010);
+    }
+
+    function testFlashLoanWithRepayment() public {
+        FlashBorrower.Call3[] memory no_calls;
+
+        // A repayment below the amount plus fee can't be pulled by the lender
+        vm.expectRevert();
+        vm.prank(owner);
+        instance.flashBorrowWithRepayment(address(token), 1000, 1009, no_calls);
+
+        vm.prank(owner);
+        instance.flashBorrowWithRepayment(address(token), 1000, 1010, no_calls);
+        assertEq(token.balanceOf(address(instance)), 990);
+        assertEq(token.balanceOf(address(lender)), 1010);
+        assertEq(token.allowance(address(instance), address(lender)), 0);
+    }
+
+    function testFlashLoanWithValue() public {
+        address recipient = address(0xC0FFEE);
+        FlashBorrower.Call3[] memory calls = new FlashBorrower.Call3[](1);
+        calls[0] = BaseFlashBorrower.Call3(recipient, false, 1 ether, "");
+        vm.deal(owner, 1 ether);
+
+        // The borrower holds no ether to fund the call value
+        vm.expectRevert();
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, calls);
+
+        // Ether sent with the flashloan funds it
+        vm.prank(owner);
+        instance.flashBorrow{value: 1 ether}(address(token), 1000, calls);
+        assertEq(recipient.balance, 1 ether);
+        assertEq(address(instance).balance, 0);
+    }
+
+    function testTrustedLenders(address leonardo) public {
+        vm.assume(leonardo != owner);
+        FlashLender other = new FlashLender();
+        token.mint(address(other), 1000);
+        FlashBorrower.Call3[] memory no_calls;
+        assertTrue(instance.trustedLenders(address(lender)));
+
+        // An untrusted lender can't be borrowed from or call back
+        vm.expectRevert(abi.encodeWithSignature("UntrustedLender()"));
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, no_calls, other);
+        vm.expectRevert(abi.encodeWithSignature("UntrustedLender()"));
+        vm.prank(address(other));
+        instance.onFlashLoan(address(instance), address(token), 1000, 10, abi.encode(no_calls));
+
+        // Only the owner can trust lenders
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(leonardo);
+        instance.trustLender(address(other));
+
+        vm.expectEmit(true, false, false, true, address(instance));
+        emit LenderUpdated(address(other), true);
+        vm.prank(owner);
+        instance.trustLender(address(other));
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, no_calls, other);
+        assertEq(token.balanceOf(address(other)), 1010);
+        vm.prank(owner);
+        instance.flashBorrowWithRepayment(address(token), 1000, 1010, no_calls, other);
+        assertEq(token.balanceOf(address(other)), 1020);
+
+        // The default lender can be untrusted too
+        vm.prank(owner);
+        instance.untrustLender(address(lender));
+        vm.expectRevert(abi.encodeWithSignature("UntrustedLender()"));
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 100, no_calls);
+    }
+
+    function testFlashLoanWithProfitFromLender() public {
+        FlashLender other = new FlashLender();
+        token.mint(address(other), 1000);
+        vm.prank(owner);
+        instance.trustLender(address(other));
+
+        FlashBorrower.Call3[] memory calls = new FlashBorrower.Call3[](1);
+        calls[0] = BaseFlashBorrower.Call3(
+            address(token), false, 0, abi.encodeWithSelector(MockERC20.mint.selector, address(instance), 20)
+        );
+        vm.expectRevert(abi.encodeWithSignature("InsufficientProfit(uint256,uint256)", 1010, 1020));
+        vm.prank(owner);
+        instance.flashBorrowWithProfit(address(token), 1000, calls, address(token), 20, other);
+
+        vm.prank(owner);
+        instance.flashBorrowWithProfit(address(token), 1000, calls, address(token), 10, other);
+        assertEq(token.balanceOf(address(other)), 1010);
+    }
+
+    /// @notice Mirror the borrower events for `expectEmit`
+    event Call3Results(BaseFlashBorrower.Call3Result[] results);
+    event LenderUpdated(address indexed lender, bool trusted);
+}
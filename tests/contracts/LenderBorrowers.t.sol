@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import { Test } from "forge-std/Test.sol";
+import { MockERC20 } from "solmate/test/utils/mocks/MockERC20.sol";
+
+import { BaseFlashBorrower } from "contracts/BaseFlashBorrower.sol";
+import { AaveFlashBorrower } from "contracts/AaveFlashBorrower.sol";
+import { BalancerFlashBorrower } from "contracts/BalancerFlashBorrower.sol";
+import { UniswapV3FlashBorrower } from "contracts/UniswapV3FlashBorrower.sol";
+
+import { IERC20 } from "contracts/interfaces/IERC20.sol";
+import { IAaveV3Pool } from "contracts/interfaces/IAaveV3Pool.sol";
+import { IAaveFlashLoanSimpleReceiver } from "contracts/interfaces/IAaveFlashLoanSimpleReceiver.sol";
+import { IBalancerVault } from "contracts/interfaces/IBalancerVault.sol";
+import { IBalancerFlashLoanRecipient } from "contracts/interfaces/IBalancerFlashLoanRecipient.sol";
+import { IUniswapV3Pool } from "contracts/interfaces/IUniswapV3Pool.sol";
+import { IUniswapV3FlashCallback } from "contracts/interfaces/IUniswapV3FlashCallback.sol";
+
+/// @notice Example Aave V3 Pool charging a 5 bps premium
+contract MockAavePool is IAaveV3Pool {
+    function FLASHLOAN_PREMIUM_TOTAL() external pure returns (uint128) {
+        return 5;
+    }
+
+    function flashLoanSimple(address receiver, address asset, uint256 amount, bytes calldata params, uint16)
+        external
+    {
+        uint256 premium = amount * 5 / 10_000;
+        IERC20(asset).transfer(receiver, amount);
+        require(IAaveFlashLoanSimpleReceiver(receiver).executeOperation(asset, amount, premium, msg.sender, params));
+        IERC20(asset).transferFrom(receiver, address(this), amount + premium);
+    }
+}
+
+/// @notice Example Balancer Vault charging no fee
+contract MockBalancerVault is IBalancerVault {
+    function getProtocolFeesCollector() external pure returns (address) {
+        return address(0);
+    }
+
+    function flashLoan(
+        IBalancerFlashLoanRecipient recipient,
+        address[] memory tokens,
+        uint256[] memory amounts,
+        bytes memory userData
+    ) external {
+        uint256[] memory fees = new uint256[](tokens.length);
+        uint256[] memory before = new uint256[](tokens.length);
+        for (uint256 i = 0; i < tokens.length; i++) {
+            before[i] = IERC20(tokens[i]).balanceOf(address(this));
+            IERC20(tokens[i]).transfer(address(recipient), amounts[i]);
+        }
+        recipient.receiveFlashLoan(tokens, amounts, fees, userData);
+        for (uint256 i = 0; i < tokens.length; i++) {
+            require(IERC20(tokens[i]).balanceOf(address(this)) >= before[i], "BAL#602");
+        }
+    }
+}
+
+/// @notice Example Uniswap V3 Pool charging a 0.3% fee
+contract MockUniswapV3Pool is IUniswapV3Pool {
+    address public immutable token0;
+    address public immutable token1;
+
+    constructor(address token0_, address token1_) {
+        token0 = token0_;
+        token1 = token1_;
+    }
+
+    function fee() external pure returns (uint24) {
+        return 3000;
+    }
+
+    function flash(address recipient, uint256 amount0, uint256 amount1, bytes calldata data) external {
+        uint256 fee0 = amount0 * 3000 / 1e6;
+        uint256 fee1 = amount1 * 3000 / 1e6;
+        uint256 balance0 = IERC20(token0).balanceOf(address(this));
+        uint256 balance1 = IERC20(token1).balanceOf(address(this));
+        if (amount0 > 0) IERC20(token0).transfer(recipient, amount0);
+        if (amount1 > 0) IERC20(token1).transfer(recipient, amount1);
+        IUniswapV3FlashCallback(msg.sender).uniswapV3FlashCallback(fee0, fee1, data);
+        require(IERC20(token0).balanceOf(address(this)) >= balance0 + fee0, "F0");
+        require(IERC20(token1).balanceOf(address(this)) >= balance1 + fee1, "F1");
+    }
+}
+
+contract LenderBorrowersTest is Test {
+    MockERC20 public token;
+    MockERC20 public other;
+
+    /// @notice Use a constant owner
+    address constant owner = address(0xBA5EBA11BAD);
+
+    BaseFlashBorrower.Call3[] no_calls;
+
+    function setUp() public {
+        token = new MockERC20("Mock", "MCK", 18);
+        other = new MockERC20("Other", "OTH", 18);
+    }
+
+    function testAaveFlashLoan() public {
+        MockAavePool pool = new MockAavePool();
+        AaveFlashBorrower instance = new AaveFlashBorrower(pool, owner);
+        token.mint(address(instance), 1000);
+        token.mint(address(pool), 20_000);
+
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        instance.flashBorrow(address(token), 20_000, no_calls);
+
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 20_000, no_calls);
+        assertEq(token.balanceOf(address(instance)), 990);
+        assertEq(token.balanceOf(address(pool)), 20_010);
+
+        // Only the pool can call back
+        vm.expectRevert(abi.encodeWithSignature("UntrustedLender()"));
+        instance.executeOperation(address(token), 1, 0, address(instance), abi.encode(no_calls));
+    }
+
+    function testBalancerFlashLoan() public {
+        MockBalancerVault vault = new MockBalancerVault();
+        BalancerFlashBorrower instance = new BalancerFlashBorrower(vault, owner);
+        token.mint(address(vault), 1000);
+
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, no_calls);
+        assertEq(token.balanceOf(address(instance)), 0);
+        assertEq(token.balanceOf(address(vault)), 1000);
+
+        // Loans not initiated by the borrower are rejected
+        address[] memory tokens = new address[](1);
+        tokens[0] = address(token);
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 1000;
+        vm.expectRevert(abi.encodeWithSignature("UntrustedInitiator()"));
+        vault.flashLoan(instance, tokens, amounts, abi.encode(no_calls));
+    }
+
+    function testBalancerCraftedLoan() public {
+        MockBalancerVault vault = new MockBalancerVault();
+        BalancerFlashBorrower instance = new BalancerFlashBorrower(vault, owner);
+        token.mint(address(vault), 2000);
+
+        // A loan to the borrower taken while its own loan is in flight carries other calls
+        address[] memory tokens = new address[](1);
+        tokens[0] = address(token);
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 1000;
+        BaseFlashBorrower.Call3[] memory crafted = new BaseFlashBorrower.Call3[](1);
+        crafted[0] = BaseFlashBorrower.Call3(
+            address(token), false, 0, abi.encodeWithSelector(IERC20.transfer.selector, address(0xdead), 1000)
+        );
+        bytes memory loan =
+            abi.encodeWithSelector(IBalancerVault.flashLoan.selector, instance, tokens, amounts, abi.encode(crafted));
+        BaseFlashBorrower.Call3[] memory calls = new BaseFlashBorrower.Call3[](1);
+        calls[0] = BaseFlashBorrower.Call3(address(vault), false, 0, loan);
+
+        // The callback only runs the calls the borrower passed to the vault
+        vm.expectRevert(
+            abi.encodeWithSignature(
+                "CallFailed(uint256,bytes)", 0, abi.encodeWithSignature("UntrustedInitiator()")
+            )
+        );
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, calls);
+    }
+
+    function testUniswapV3Flash() public {
+        MockUniswapV3Pool pool = new MockUniswapV3Pool(address(token), address(other));
+        UniswapV3FlashBorrower instance = new UniswapV3FlashBorrower(pool, owner);
+        other.mint(address(instance), 1000);
+        other.mint(address(pool), 10_000);
+
+        // Borrow the pool's token1
+        vm.prank(owner);
+        instance.flashBorrow(address(other), 10_000, no_calls);
+        assertEq(other.balanceOf(address(instance)), 970);
+        assertEq(other.balanceOf(address(pool)), 10_030);
+    }
+}
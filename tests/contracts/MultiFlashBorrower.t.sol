@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import { Test } from "forge-std/Test.sol";
+import { MockERC20 } from "solmate/test/utils/mocks/MockERC20.sol";
+
+import { BaseFlashBorrower } from "contracts/BaseFlashBorrower.sol";
+import { MultiFlashBorrower } from "contracts/MultiFlashBorrower.sol";
+
+import { IERC20 } from "contracts/interfaces/IERC20.sol";
+import { IBalancerVault } from "contracts/interfaces/IBalancerVault.sol";
+
+import { FlashLender } from "contracts/mocks/FlashLender.sol";
+import { MockBalancerVault, MockUniswapV3Pool } from "./LenderBorrowers.t.sol";
+
+contract MultiFlashBorrowerTest is Test {
+    MultiFlashBorrower public instance;
+    FlashLender public lender;
+    MockBalancerVault public vault;
+    MockUniswapV3Pool public pool;
+
+    MockERC20 public token;
+    MockERC20 public other;
+
+    /// @notice Use a constant owner
+    address constant owner = address(0xBA5EBA11BAD);
+
+    BaseFlashBorrower.Call3[] no_calls;
+
+    function setUp() public {
+        token = new MockERC20("Mock", "MCK", 18);
+        other = new MockERC20("Other", "OTH", 18);
+        lender = new FlashLender();
+        vault = new MockBalancerVault();
+        pool = new MockUniswapV3Pool(address(token), address(other));
+        instance = new MultiFlashBorrower(lender, owner);
+
+        token.mint(address(instance), 1000);
+        token.mint(address(lender), 1000);
+        other.mint(address(vault), 5000);
+        other.mint(address(pool), 5000);
+    }
+
+    function testNestedFlashLoan() public {
+        MultiFlashBorrower.Leg[] memory legs = new MultiFlashBorrower.Leg[](2);
+        legs[0] = MultiFlashBorrower.Leg(MultiFlashBorrower.LenderKind.Erc3156, address(lender), address(token), 1000);
+        legs[1] = MultiFlashBorrower.Leg(MultiFlashBorrower.LenderKind.Balancer, address(vault), address(other), 5000);
+
+        // Both tokens are held when the calls run
+        BaseFlashBorrower.Call3[] memory calls = new BaseFlashBorrower.Call3[](2);
+        calls[0] = BaseFlashBorrower.Call3(
+            address(token), false, 0, abi.encodeWithSelector(IERC20.balanceOf.selector, address(instance))
+        );
+        calls[1] = BaseFlashBorrower.Call3(
+            address(other), false, 0, abi.encodeWithSelector(IERC20.balanceOf.selector, address(instance))
+        );
+
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        instance.flashBorrowMulti(legs, calls);
+
+        vm.prank(owner);
+        BaseFlashBorrower.Call3Result[] memory results = instance.flashBorrowMulti(legs, calls);
+        assertEq(abi.decode(results[0].returnData, (uint256)), 2000);
+        assertEq(abi.decode(results[1].returnData, (uint256)), 5000);
+
+        // Every leg is repaid with its fee
+        assertEq(token.balanceOf(address(instance)), 990);
+        assertEq(token.balanceOf(address(lender)), 1010);
+        assertEq(other.balanceOf(address(instance)), 0);
+        assertEq(other.balanceOf(address(vault)), 5000);
+    }
+
+    function testNestedFlashLoanSameToken() public {
+        MultiFlashBorrower.Leg[] memory legs = new MultiFlashBorrower.Leg[](2);
+        legs[0] = MultiFlashBorrower.Leg(MultiFlashBorrower.LenderKind.UniswapV3, address(pool), address(other), 5000);
+        legs[1] = MultiFlashBorrower.Leg(MultiFlashBorrower.LenderKind.Balancer, address(vault), address(other), 5000);
+        other.mint(address(instance), 15);
+
+        vm.prank(owner);
+        instance.flashBorrowMulti(legs, no_calls);
+        assertEq(other.balanceOf(address(instance)), 0);
+        assertEq(other.balanceOf(address(pool)), 5015);
+    }
+
+    function testSingleFlashLoan() public {
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, no_calls);
+        assertEq(token.balanceOf(address(instance)), 990);
+    }
+
+    function testUntrustedCallback() public {
+        MultiFlashBorrower.Leg[] memory legs = new MultiFlashBorrower.Leg[](1);
+        legs[0] = MultiFlashBorrower.Leg(MultiFlashBorrower.LenderKind.Erc3156, address(this), address(token), 1);
+
+        // Callbacks are rejected outside a flashloan, even from a lender named in the legs
+        vm.expectRevert(abi.encodeWithSignature("UntrustedLender()"));
+        instance.onFlashLoan(address(instance), address(token), 1, 0, abi.encode(legs, 0, no_calls));
+    }
+
+    function testCraftedCallback() public {
+        MultiFlashBorrower.Leg[] memory legs = new MultiFlashBorrower.Leg[](1);
+        legs[0] = MultiFlashBorrower.Leg(MultiFlashBorrower.LenderKind.Balancer, address(vault), address(other), 1000);
+
+        // A loan from the active lender taken by a call carries other calls
+        BaseFlashBorrower.Call3[] memory crafted = new BaseFlashBorrower.Call3[](1);
+        crafted[0] = BaseFlashBorrower.Call3(
+            address(other), false, 0, abi.encodeWithSelector(IERC20.transfer.selector, address(0xdead), 1000)
+        );
+        address[] memory tokens = new address[](1);
+        tokens[0] = address(other);
+        uint256[] memory amounts = new uint256[](1);
+        amounts[0] = 1000;
+        bytes memory loan = abi.encodeWithSelector(
+            IBalancerVault.flashLoan.selector, instance, tokens, amounts, abi.encode(legs, 0, crafted)
+        );
+        BaseFlashBorrower.Call3[] memory calls = new BaseFlashBorrower.Call3[](1);
+        calls[0] = BaseFlashBorrower.Call3(address(vault), false, 0, loan);
+
+        // The callback only borrows for the legs and calls the borrower committed to
+        vm.expectRevert(
+            abi.encodeWithSignature(
+                "CallFailed(uint256,bytes)", 0, abi.encodeWithSignature("UntrustedInitiator()")
+            )
+        );
+        vm.prank(owner);
+        instance.flashBorrowMulti(legs, calls);
+    }
+}
@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import { Test } from "forge-std/Test.sol";
+import { MockERC20 } from "solmate/test/utils/mocks/MockERC20.sol";
+
+import { BaseFlashBorrower } from "contracts/BaseFlashBorrower.sol";
+import { OperatedFlashBorrower } from "contracts/OperatedFlashBorrower.sol";
+
+import { FlashLender } from "contracts/mocks/FlashLender.sol";
+
+contract OperatedFlashBorrowerTest is Test {
+    OperatedFlashBorrower public instance;
+    FlashLender public lender;
+
+    MockERC20 public token;
+
+    /// @notice Use a constant owner and operator
+    address constant owner = address(0xBA5EBA11BAD);
+    address constant operator = address(0x0BE7A70B);
+
+    BaseFlashBorrower.Call3[] no_calls;
+
+    function setUp() public {
+        lender = new FlashLender();
+        instance = new OperatedFlashBorrower(lender, owner);
+        token = new MockERC20("Mock", "MCK", 18);
+        token.mint(address(instance), 1000);
+        token.mint(address(lender), 1000);
+    }
+
+    function testOperators() public {
+        // Operators can't flash loan until the owner adds them
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(operator);
+        instance.flashBorrow(address(token), 1000, no_calls);
+
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(operator);
+        instance.addOperator(operator);
+
+        vm.expectEmit(true, false, false, true, address(instance));
+        emit OperatorUpdated(operator, true);
+        vm.prank(owner);
+        instance.addOperator(operator);
+        assertTrue(instance.isOperator(operator));
+
+        vm.prank(operator);
+        instance.flashBorrow(address(token), 1000, no_calls);
+        vm.prank(operator);
+        instance.flashBorrowWithRepayment(address(token), 1000, 1010, no_calls);
+        assertEq(token.balanceOf(address(instance)), 980);
+
+        // Operators can't call the withdrawals themselves
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(operator);
+        instance.withdrawToken(token, operator, 0, true);
+
+        vm.prank(owner);
+        instance.removeOperator(operator);
+        assertTrue(!instance.isOperator(operator));
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(operator);
+        instance.flashBorrow(address(token), 1000, no_calls);
+    }
+
+    function testOperatorsAreTrusted() public {
+        vm.prank(owner);
+        instance.addOperator(operator);
+
+        // The flashloan calls run as the borrower, so an operator can move its funds
+        BaseFlashBorrower.Call3[] memory calls = new BaseFlashBorrower.Call3[](1);
+        calls[0] = BaseFlashBorrower.Call3(
+            address(token), false, 0, abi.encodeWithSelector(token.transfer.selector, operator, 990)
+        );
+        vm.prank(operator);
+        instance.flashBorrow(address(token), 1000, calls);
+        assertEq(token.balanceOf(address(instance)), 0);
+        assertEq(token.balanceOf(operator), 990);
+    }
+
+    function testTransferOwnership(address newOwner) public {
+        vm.assume(newOwner != owner && newOwner != address(0));
+
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(newOwner);
+        instance.transferOwnership(newOwner);
+
+        vm.expectEmit(true, true, false, false, address(instance));
+        emit OwnershipTransferStarted(owner, newOwner);
+        vm.prank(owner);
+        instance.transferOwnership(newOwner);
+        assertEq(instance.pendingOwner(), newOwner);
+
+        // Ownership only moves once accepted
+        assertEq(instance.owner(), owner);
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(owner);
+        instance.acceptOwnership();
+
+        vm.expectEmit(true, true, false, false, address(instance));
+        emit OwnershipTransferred(owner, newOwner);
+        vm.prank(newOwner);
+        instance.acceptOwnership();
+        assertEq(instance.owner(), newOwner);
+        assertEq(instance.pendingOwner(), address(0));
+
+        // The previous owner loses access
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        vm.prank(owner);
+        instance.withdrawToken(token, owner, 0, true);
+        vm.prank(newOwner);
+        instance.withdrawToken(token, newOwner, 0, true);
+        assertEq(token.balanceOf(newOwner), 1000);
+    }
+
+    /// @notice Mirror the borrower events for `expectEmit`
+    event OwnershipTransferStarted(address indexed previousOwner, address indexed newOwner);
+    event OwnershipTransferred(address indexed previousOwner, address indexed newOwner);
+    event OperatorUpdated(address indexed operator, bool allowed);
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+import { Test } from "forge-std/Test.sol";
+import { MockERC20 } from "solmate/test/utils/mocks/MockERC20.sol";
+
+import { BaseFlashBorrower } from "contracts/BaseFlashBorrower.sol";
+import { ProgramFlashBorrower } from "contracts/ProgramFlashBorrower.sol";
+
+import { IERC20 } from "contracts/interfaces/IERC20.sol";
+
+import { FlashLender } from "contracts/mocks/FlashLender.sol";
+
+contract ProgramFlashBorrowerTest is Test {
+    ProgramFlashBorrower public instance;
+    FlashLender public lender;
+
+    MockERC20 public token;
+    MockERC20 public other;
+
+    /// @notice Use a constant owner
+    address constant owner = address(0xBA5EBA11BAD);
+
+    BaseFlashBorrower.Call3[] no_calls;
+
+    function setUp() public {
+        lender = new FlashLender();
+        instance = new ProgramFlashBorrower(lender, owner);
+        token = new MockERC20("Mock", "MCK", 18);
+        other = new MockERC20("Other", "OTH", 18);
+        token.mint(address(instance), 1000);
+        token.mint(address(lender), 1000);
+    }
+
+    /// @notice Packs a command
+    function command(bytes4 selector, uint8 flags, bytes6 inputs, uint8 out, address target)
+        internal
+        pure
+        returns (bytes32)
+    {
+        return bytes32(abi.encodePacked(selector, flags, inputs, out, target));
+    }
+
+    function testProgramPipesReturnValues() public {
+        // Mint as many `other` tokens as the borrower holds of `token` mid-flashloan
+        bytes[] memory state = new bytes[](2);
+        state[0] = abi.encode(address(instance));
+        bytes32[] memory commands = new bytes32[](2);
+        commands[0] = command(IERC20.balanceOf.selector, 0, 0x00ffffffffff, 1, address(token));
+        commands[1] = command(MockERC20.mint.selector, 0, 0x0001ffffffff, 0xff, address(other));
+
+        vm.expectRevert(abi.encodeWithSignature("Unauthorized()"));
+        instance.flashBorrowProgram(address(token), 1000, commands, state);
+
+        vm.prank(owner);
+        BaseFlashBorrower.Call3Result[] memory results =
+            instance.flashBorrowProgram(address(token), 1000, commands, state);
+        assertEq(results.length, 2);
+        assertEq(abi.decode(results[0].returnData, (uint256)), 2000);
+        assertEq(other.balanceOf(address(instance)), 2000);
+        assertEq(token.balanceOf(address(instance)), 990);
+    }
+
+    function testProgramDynamicInputs() public {
+        // Pass a dynamic value by offset after a static one
+        bytes[] memory state = new bytes[](2);
+        state[0] = abi.encode(address(token));
+        bytes memory balanceOf = abi.encodeWithSelector(IERC20.balanceOf.selector, address(instance));
+        // Dynamic slots hold the abi tail, the length and the padded data
+        state[1] = abi.encodePacked(balanceOf.length, balanceOf, bytes28(0));
+
+        bytes32[] memory commands = new bytes32[](1);
+        commands[0] = command(this.staticCallTarget.selector, 0, 0x0081ffffffff, 0xff, address(this));
+
+        vm.prank(owner);
+        BaseFlashBorrower.Call3Result[] memory results =
+            instance.flashBorrowProgram(address(token), 1000, commands, state);
+        assertEq(abi.decode(results[0].returnData, (uint256)), 2000);
+    }
+
+    function testProgramCommandFailed() public {
+        bytes[] memory state = new bytes[](2);
+        state[0] = abi.encode(address(0xdead));
+        state[1] = abi.encode(uint256(1e18));
+        bytes32[] memory commands = new bytes32[](1);
+        commands[0] = command(IERC20.transfer.selector, 0, 0x0001ffffffff, 0xff, address(token));
+
+        vm.expectRevert(
+            abi.encodeWithSignature("CallFailed(uint256,bytes)", 0, abi.encodeWithSignature("Panic(uint256)", 0x11))
+        );
+        vm.prank(owner);
+        instance.flashBorrowProgram(address(token), 1000, commands, state);
+    }
+
+    function testFlashBorrowWithoutProgram() public {
+        vm.prank(owner);
+        instance.flashBorrow(address(token), 1000, no_calls);
+        assertEq(token.balanceOf(address(instance)), 990);
+    }
+
+    /// @notice Calls `target` with `data`, returning the first word of the result
+    function staticCallTarget(address target, bytes calldata data) external view returns (uint256) {
+        (, bytes memory result) = target.staticcall(data);
+        return abi.decode(result, (uint256));
+    }
+}
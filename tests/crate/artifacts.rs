@@ -0,0 +1,151 @@
+use ethers::{
+    abi::{encode, Abi, AbiDecode, AbiEncode, Token},
+    prelude::*,
+};
+use std::collections::HashSet;
+
+use flashloan_rs::prelude::*;
+
+/// The abi of a bundled artifact
+fn artifact_abi(artifact: &str) -> Abi {
+    let artifact: serde_json::Value = serde_json::from_str(artifact).unwrap();
+    serde_json::from_value(artifact["abi"].clone()).unwrap()
+}
+
+/// Runtime code returning 32 zero bytes for any call
+const RETURN_ZERO: &str = "0x60206000f3";
+
+/// The selectors pushed by the code, the ones its dispatcher compares the calldata against
+fn pushed_selectors(code: &[u8]) -> HashSet<[u8; 4]> {
+    let mut selectors = HashSet::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        if (0x60..=0x7f).contains(&op) {
+            let width = (op - 0x5f) as usize;
+            let operand = code.get(pc + 1..pc + 1 + width).unwrap_or_default();
+            // Selectors with leading zero bytes are pushed with a narrower push
+            if width <= 4 && operand.len() == width {
+                let mut selector = [0u8; 4];
+                selector[4 - width..].copy_from_slice(operand);
+                selectors.insert(selector);
+            }
+            pc += width;
+        }
+        pc += 1;
+    }
+    selectors
+}
+
+/// Deploy the borrower into a snapshot and check its runtime code dispatches every function
+fn assert_dispatches(abi: &Abi, bytecode: Bytes) -> (StateSnapshot, Address, Address) {
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut snapshot = StateSnapshot::new(1);
+    let mut init_code = bytecode.to_vec();
+    init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+    let borrower = snapshot.deploy(owner, init_code.into()).unwrap();
+
+    let selectors = pushed_selectors(&snapshot.accounts[&borrower].code);
+    for function in abi.functions() {
+        assert!(
+            selectors.contains(&function.short_signature()),
+            "{} {} is not dispatched",
+            function.name,
+            Bytes::from(function.short_signature().to_vec())
+        );
+    }
+    (snapshot, owner, borrower)
+}
+
+/// Every flashloan entrypoint returns the results of the calls
+fn assert_returns_results(mut snapshot: StateSnapshot, owner: Address, borrower: Address) {
+    let output = snapshot.call(owner, borrower, LenderCall.encode().into()).unwrap();
+    let lender = Address::decode(output).unwrap();
+    let token = Address::random();
+    for stub in [lender, token] {
+        snapshot.insert_account(
+            stub,
+            AccountState { code: RETURN_ZERO.parse().unwrap(), ..Default::default() },
+        );
+    }
+
+    let amount = U256::exp10(18);
+    let entrypoints = [
+        FlashloanCalls::FlashBorrow(FlashBorrowCall { token, amount, calls: vec![] }),
+        FlashloanCalls::FlashBorrowWithRepayment(FlashBorrowWithRepaymentCall {
+            token,
+            amount,
+            repayment: amount,
+            calls: vec![],
+        }),
+        FlashloanCalls::FlashBorrowWithProfit(FlashBorrowWithProfitCall {
+            token,
+            amount,
+            calls: vec![],
+            profit_token: token,
+            min_profit: U256::zero(),
+        }),
+        FlashloanCalls::FlashBorrowWithLender(FlashBorrowWithLenderCall {
+            token,
+            amount,
+            calls: vec![],
+            lender,
+        }),
+        FlashloanCalls::FlashBorrowWithRepaymentWithLender(
+            FlashBorrowWithRepaymentWithLenderCall {
+                token,
+                amount,
+                repayment: amount,
+                calls: vec![],
+                lender,
+            },
+        ),
+        FlashloanCalls::FlashBorrowWithProfitWithLender(FlashBorrowWithProfitWithLenderCall {
+            token,
+            amount,
+            calls: vec![],
+            profit_token: token,
+            min_profit: U256::zero(),
+            lender,
+        }),
+    ];
+    for entrypoint in entrypoints {
+        let output = snapshot.call(owner, borrower, entrypoint.encode().into()).unwrap();
+        assert!(Vec::<(bool, Bytes)>::decode(output).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn test_huff_borrower_artifact() {
+    let bytecode = huff_borrower_bytecode().unwrap();
+    let (snapshot, owner, borrower) = assert_dispatches(&FLASHLOAN_ABI, bytecode);
+    assert_returns_results(snapshot, owner, borrower);
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_flash_borrower_artifact() {
+    let bytecode = LenderKind::Erc3156.borrower_bytecode().unwrap();
+    let (snapshot, owner, borrower) = assert_dispatches(&FLASHLOAN_ABI, bytecode);
+    assert_returns_results(snapshot, owner, borrower);
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_lender_borrower_artifacts() {
+    let artifacts = [
+        (LenderKind::AaveV3, include_str!("../../src/AaveFlashBorrower.json")),
+        (LenderKind::Balancer, include_str!("../../src/BalancerFlashBorrower.json")),
+        (LenderKind::UniswapV3, include_str!("../../src/UniswapV3FlashBorrower.json")),
+    ];
+    for (kind, artifact) in artifacts {
+        let bytecode = kind.borrower_bytecode().unwrap();
+        let (snapshot, owner, borrower) = assert_dispatches(&artifact_abi(artifact), bytecode);
+
+        // The lender is the pool or vault the borrower calls
+        let output = snapshot.call(owner, borrower, OwnerCall.encode().into()).unwrap();
+        assert_eq!(Address::decode(output).unwrap(), owner);
+        let output = snapshot.call(owner, borrower, LenderCall.encode().into()).unwrap();
+        assert!(!Address::decode(output).unwrap().is_zero());
+    }
+}
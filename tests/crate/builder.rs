@@ -1,4 +1,5 @@
 use ethers::{
+    abi::Token,
     prelude::*,
     utils::{format_ether, Anvil},
 };
@@ -123,3 +124,37 @@ async fn test_deploy_flashloan_borrower() {
     println!("[Gas Used] {:?}", tx_receipt.gas_used.unwrap());
     println!();
 }
+
+#[test]
+fn test_decode_results() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let borrower = Address::random();
+    let builder =
+        FlashloanBuilder::new(Arc::clone(&client), 1, None, None, None, None, Some(borrower));
+
+    // Build a receipt with the borrower's results event
+    let results = vec![
+        Call3Result { success: true, return_data: Bytes::from(vec![1u8; 32]) },
+        Call3Result { success: false, return_data: Bytes::default() },
+    ];
+    let data = ethers::abi::encode(&[Token::Array(
+        results
+            .iter()
+            .map(|r| {
+                Token::Tuple(vec![Token::Bool(r.success), Token::Bytes(r.return_data.to_vec())])
+            })
+            .collect(),
+    )]);
+    let log = Log {
+        address: borrower,
+        topics: vec![Call3ResultsFilter::signature()],
+        data: data.into(),
+        ..Default::default()
+    };
+    let mut receipt = TransactionReceipt { logs: vec![log.clone()], ..Default::default() };
+    assert_eq!(builder.decode_results(&receipt).unwrap(), results);
+
+    // Events from other contracts are ignored
+    receipt.logs[0].address = Address::random();
+    assert!(builder.decode_results(&receipt).unwrap().is_empty());
+}
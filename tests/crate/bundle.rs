@@ -0,0 +1,248 @@
+use ethers::{
+    abi::AbiEncode,
+    prelude::*,
+    types::transaction::eip2718::TypedTransaction,
+    utils::{hex, id, keccak256},
+};
+use serde_json::{json, Value};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use flashloan_rs::prelude::*;
+
+/// A mock node and bundle relay
+///
+/// Every `eth_blockNumber` request mines a block, unless the chain is stalled. Bundles are
+/// included once `include_after` bundles have been sent.
+#[derive(Default)]
+struct MockRelay {
+    block: u64,
+    stalled: bool,
+    include_after: usize,
+    revert: Option<String>,
+    requests: Vec<(String, Value, Option<String>)>,
+    sent_blocks: Vec<U64>,
+}
+
+impl MockRelay {
+    fn handle(&mut self, method: &str, params: &Value) -> Value {
+        match method {
+            "eth_blockNumber" => {
+                if !self.stalled {
+                    self.block += 1;
+                }
+                json!(U64::from(self.block))
+            }
+            "eth_callBundle" => {
+                let tx_hash = H256::from(keccak256(
+                    params[0]["txs"][0].as_str().unwrap().parse::<Bytes>().unwrap(),
+                ));
+                let mut result = json!({ "txHash": tx_hash, "gasUsed": 21000, "value": "0x" });
+                if let Some(revert) = &self.revert {
+                    result["error"] = json!("execution reverted");
+                    result["revert"] = json!(revert);
+                }
+                json!({
+                    "bundleHash": H256::random(),
+                    "coinbaseDiff": "1000000000000",
+                    "totalGasUsed": 21000,
+                    "results": [result],
+                })
+            }
+            "eth_sendBundle" => {
+                self.sent_blocks
+                    .push(serde_json::from_value(params[0]["blockNumber"].clone()).unwrap());
+                json!({ "bundleHash": H256::random() })
+            }
+            "eth_getTransactionReceipt" if self.sent_blocks.len() > self.include_after => {
+                let receipt = TransactionReceipt {
+                    transaction_hash: serde_json::from_value(params[0].clone()).unwrap(),
+                    block_number: Some(U64::from(self.block)),
+                    ..Default::default()
+                };
+                serde_json::to_value(receipt).unwrap()
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Serves JSON-RPC requests to a [MockRelay] on a local port
+async fn spawn_relay(relay: MockRelay) -> (String, Arc<Mutex<MockRelay>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let relay = Arc::new(Mutex::new(relay));
+    let state = Arc::clone(&relay);
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let state = Arc::clone(&state);
+            tokio::spawn(async move {
+                // Read the headers and the body
+                let mut buf = vec![];
+                let mut chunk = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| {
+                                l.to_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or_default();
+                        if body.len() >= length {
+                            break (head.to_string(), body.to_string());
+                        }
+                    }
+                };
+                let signature = head.lines().find_map(|l| {
+                    l.to_lowercase()
+                        .starts_with("x-flashbots-signature:")
+                        .then(|| l.split_once(':').unwrap().1.trim().to_string())
+                });
+
+                let request: Value = serde_json::from_str(&body).unwrap();
+                let method = request["method"].as_str().unwrap().to_string();
+                let result = {
+                    let mut relay = state.lock().unwrap();
+                    relay.requests.push((method.clone(), request["params"].clone(), signature));
+                    relay.handle(&method, &request["params"])
+                };
+                let response =
+                    json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }).to_string();
+                let http = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                );
+                stream.write_all(http.as_bytes()).await.unwrap();
+            });
+        }
+    });
+    (url, relay)
+}
+
+/// A signing client against the mock relay and a prefilled legacy transaction
+fn client_and_tx(
+    url: &str,
+) -> (Arc<SignerMiddleware<Provider<Http>, LocalWallet>>, TypedTransaction) {
+    let wallet = LocalWallet::new(&mut ethers::core::rand::thread_rng()).with_chain_id(1u64);
+    let provider = Provider::<Http>::try_from(url).unwrap();
+    let client = Arc::new(SignerMiddleware::new(provider, wallet));
+    let tx = TransactionRequest::new()
+        .to(Address::random())
+        .data(
+            FlashBorrowCall { token: Address::random(), amount: U256::one(), calls: vec![] }
+                .encode(),
+        )
+        .nonce(0)
+        .gas(500_000)
+        .gas_price(1_000_000_000)
+        .chain_id(1);
+    (client, tx.into())
+}
+
+#[tokio::test]
+async fn test_submit_bundle_with_retries() {
+    let (url, state) = spawn_relay(MockRelay { include_after: 1, ..Default::default() }).await;
+    let (client, tx) = client_and_tx(&url);
+    let auth = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+    let relay = BundleRelay::new(&url, auth.clone())
+        .unwrap()
+        .with_max_blocks(3)
+        .with_poll_interval(Duration::from_millis(10));
+
+    let receipt = relay.submit(client, tx, &RevertDecoder::default()).await.unwrap();
+
+    // The first bundle is missed, so the next block is targeted
+    let state = state.lock().unwrap();
+    assert_eq!(state.sent_blocks, vec![U64::from(2), U64::from(4)]);
+    let raw: Bytes = state
+        .requests
+        .iter()
+        .find(|(method, ..)| method == "eth_sendBundle")
+        .map(|(_, params, _)| params[0]["txs"][0].as_str().unwrap().parse().unwrap())
+        .unwrap();
+    assert_eq!(receipt.transaction_hash, H256::from(keccak256(raw)));
+
+    // Every bundle is simulated before it is sent, with signed requests
+    let methods = state
+        .requests
+        .iter()
+        .map(|(method, ..)| method.as_str())
+        .filter(|method| method.contains("Bundle"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        vec!["eth_callBundle", "eth_sendBundle", "eth_callBundle", "eth_sendBundle"]
+    );
+    for (method, _, signature) in &state.requests {
+        if method.contains("Bundle") {
+            let signature = signature.as_ref().unwrap();
+            assert!(signature.starts_with(&format!("{:?}:0x", auth.address())));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_submit_bundle_not_included() {
+    let (url, state) = spawn_relay(MockRelay { include_after: 5, ..Default::default() }).await;
+    let (client, tx) = client_and_tx(&url);
+    let auth = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+    let relay = BundleRelay::new(&url, auth)
+        .unwrap()
+        .with_max_blocks(2)
+        .with_poll_interval(Duration::from_millis(10));
+
+    let err = relay.submit(client, tx, &RevertDecoder::default()).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::BundleNotIncluded(2))
+    ));
+    assert_eq!(state.lock().unwrap().sent_blocks.len(), 2);
+}
+
+#[tokio::test]
+async fn test_submit_bundle_stalled_chain() {
+    // The target block is never mined, so the relay gives up after the block timeout
+    let (url, state) = spawn_relay(MockRelay { stalled: true, ..Default::default() }).await;
+    let (client, tx) = client_and_tx(&url);
+    let auth = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+    let relay = BundleRelay::new(&url, auth)
+        .unwrap()
+        .with_poll_interval(Duration::from_millis(10))
+        .with_block_timeout(Duration::from_millis(50));
+
+    let err = relay.submit(client, tx, &RevertDecoder::default()).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::BundleNotIncluded(1))
+    ));
+    let state = state.lock().unwrap();
+    assert_eq!(state.sent_blocks, vec![U64::from(1)]);
+    assert!(!state.requests.iter().any(|(method, ..)| method == "eth_getTransactionReceipt"));
+}
+
+#[tokio::test]
+async fn test_submit_bundle_reverts() {
+    // A reverting bundle is never sent
+    let revert = format!("0x{}", hex::encode(id("Unauthorized()")));
+    let (url, state) = spawn_relay(MockRelay { revert: Some(revert), ..Default::default() }).await;
+    let (client, tx) = client_and_tx(&url);
+    let auth = LocalWallet::new(&mut ethers::core::rand::thread_rng());
+    let relay = BundleRelay::new(&url, auth).unwrap();
+
+    let err = relay.submit(client, tx, &RevertDecoder::default()).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::Unauthorized)));
+    assert!(state.lock().unwrap().sent_blocks.is_empty());
+}
@@ -0,0 +1,159 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+};
+use std::{sync::Arc, time::Duration};
+
+use flashloan_rs::prelude::*;
+
+#[test]
+fn test_fee_cache() {
+    let (lender, token) = (Address::random(), Address::random());
+    let mut cache = FeeCache::default();
+    assert_eq!(cache.max_age, DEFAULT_FEE_MAX_AGE);
+    assert!(cache.is_empty());
+    assert_eq!(cache.lookup(lender, token, U256::exp10(18)), FeeLookup::Missing);
+
+    // Fees are only reused for the quoted amount by default, they may be flat or tiered
+    cache.insert(lender, token, U256::from(3000), U256::from(27));
+    assert_eq!(FeeCache::amount_bucket(U256::from(3000)), 12);
+    assert_eq!(cache.lookup(lender, token, U256::from(3000)), FeeLookup::Fresh(U256::from(27)));
+    assert_eq!(cache.lookup(lender, token, U256::from(4000)), FeeLookup::Missing);
+    assert!(!cache.has_proportional_fees(lender));
+
+    // Proportional fees are scaled within the amount bucket, rounding up
+    let mut cache = cache.with_proportional_fees(lender);
+    assert!(cache.has_proportional_fees(lender));
+    assert_eq!(cache.lookup(lender, token, U256::from(4000)), FeeLookup::Fresh(U256::from(36)));
+    assert_eq!(cache.lookup(lender, token, U256::from(2500)), FeeLookup::Fresh(U256::from(23)));
+    assert_eq!(cache.lookup(lender, token, U256::from(2047)), FeeLookup::Missing);
+    assert_eq!(cache.lookup(lender, token, U256::from(4096)), FeeLookup::Missing);
+    assert_eq!(cache.lookup(Address::random(), token, U256::from(3000)), FeeLookup::Missing);
+
+    // Fees older than the maximum age are stale
+    cache.max_age = Duration::ZERO;
+    assert_eq!(cache.lookup(lender, token, U256::from(3000)), FeeLookup::Stale);
+    assert_eq!(cache.get(lender, token, U256::from(3000)).unwrap().fee, U256::from(27));
+
+    cache.insert(lender, token, U256::exp10(18), U256::exp10(15));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.remove(lender, token, U256::from(3000)).is_some());
+    assert!(cache.remove(lender, token, U256::from(3000)).is_none());
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[tokio::test]
+async fn test_builder_fee_cache() {
+    let (provider, mock) = Provider::mocked();
+    let (owner, lender, token) = (Address::random(), Address::random(), Address::random());
+    let mut builder = FlashloanBuilder::new(
+        Arc::new(provider),
+        1,
+        Some(owner),
+        Some(lender),
+        Some(token),
+        Some(U256::exp10(18)),
+        Some(Address::random()),
+    );
+    builder.with_fee_cache(FeeCache::default());
+    let results = Bytes::from(encode(&[Token::Array(vec![])]));
+    let fee = U256::exp10(15);
+
+    // The first loan queries the fee, responses are popped last in first out
+    mock.push::<Bytes, _>(results.clone()).unwrap();
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Uint(fee)]))).unwrap();
+    builder.call().await.unwrap();
+    let cache = builder.fee_cache.as_ref().unwrap();
+    assert_eq!(cache.lookup(lender, token, U256::exp10(18)), FeeLookup::Fresh(fee));
+
+    // Later loans of the amount reuse the cached fee
+    mock.push::<Bytes, _>(results.clone()).unwrap();
+    builder.call().await.unwrap();
+    assert_eq!(builder.fee_cache.as_ref().unwrap().len(), 1);
+
+    // Other amounts query their own fee, replacing the quote in the bucket
+    let amount = U256::exp10(18) + 1;
+    mock.push::<Bytes, _>(results.clone()).unwrap();
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Uint(fee + 1)]))).unwrap();
+    builder.with_amount(amount);
+    builder.call().await.unwrap();
+    let cache = builder.fee_cache.as_ref().unwrap();
+    assert_eq!(cache.lookup(lender, token, amount), FeeLookup::Fresh(fee + 1));
+    assert_eq!(cache.len(), 1);
+
+    // Unless the lender's fees are proportional to the amount
+    let cache = builder.fee_cache.take().unwrap().with_proportional_fees(lender);
+    builder.with_fee_cache(cache);
+    mock.push::<Bytes, _>(results.clone()).unwrap();
+    builder.with_amount(U256::exp10(18));
+    builder.call().await.unwrap();
+
+    // A stale fee takes the on-chain path and is dropped
+    builder.fee_cache.as_mut().unwrap().max_age = Duration::ZERO;
+    mock.push::<Bytes, _>(results).unwrap();
+    builder.call().await.unwrap();
+    assert!(builder.fee_cache.as_ref().unwrap().is_empty());
+}
+
+#[test]
+fn test_plan_repayment() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let plan = FlashloanBuilder::with_client(Arc::clone(&client))
+        .borrower(Address::random())
+        .token(Address::random())
+        .amount(U256::exp10(18))
+        .repayment(U256::exp10(18) + U256::exp10(15))
+        .build()
+        .unwrap();
+    assert_eq!(plan.repayment(), Some(U256::exp10(18) + U256::exp10(15)));
+
+    // The other borrowers repay the fee queried on-chain, so can't take a repayment
+    let plan = |kind: LenderKind| {
+        let lender = Address::random();
+        let builder = FlashloanBuilder::with_client(Arc::clone(&client))
+            .borrower(Address::random())
+            .token(Address::random())
+            .amount(U256::exp10(18));
+        match kind {
+            LenderKind::AaveV3 => builder.flash_lender(AaveV3Lender(lender)),
+            LenderKind::Balancer => builder.flash_lender(BalancerLender(lender)),
+            _ => builder.flash_lender(UniswapV3Lender(lender)),
+        }
+    };
+    for kind in [LenderKind::AaveV3, LenderKind::Balancer, LenderKind::UniswapV3] {
+        let err = plan(kind).repayment(U256::exp10(18)).build().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FlashloanError>(),
+            Some(FlashloanError::CallConstructionError(message))
+                if message.contains(kind.borrower_name())
+        ));
+        assert_eq!(plan(kind).build().unwrap().lender_kind(), kind);
+    }
+}
+
+#[tokio::test]
+async fn test_repayment_overflow() {
+    let (provider, _mock) = Provider::mocked();
+    let (lender, token) = (Address::random(), Address::random());
+    let mut builder = FlashloanBuilder::new(
+        Arc::new(provider),
+        1,
+        Some(Address::random()),
+        Some(lender),
+        Some(token),
+        Some(U256::MAX),
+        Some(Address::random()),
+    );
+    let mut cache = FeeCache::default();
+    cache.insert(lender, token, U256::MAX, U256::one());
+    builder.with_fee_cache(cache);
+
+    // The repayment doesn't fit in a uint256, so nothing is sent
+    let err = builder.call().await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::RepaymentOverflow { amount, fee })
+            if *amount == U256::MAX && *fee == U256::one()
+    ));
+}
@@ -0,0 +1,261 @@
+use ethers::{
+    abi::{Function, HumanReadableParser, Token},
+    prelude::*,
+};
+
+use flashloan_rs::prelude::*;
+
+fn function(signature: &str) -> Function {
+    HumanReadableParser::parse_function(signature).unwrap()
+}
+
+fn is_construction_error(err: anyhow::Error) -> bool {
+    matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::CallConstructionError(_)))
+}
+
+#[test]
+fn test_erc20_calls() {
+    let token = Erc20Token(Address::random());
+    let spender = Address::random();
+    let amount = U256::exp10(18);
+
+    let approve = token.approve(spender, amount).unwrap();
+    assert_eq!(approve.target, token.0);
+    assert!(!approve.allow_failure);
+    assert_eq!(approve.value, U256::zero());
+    assert_eq!(approve.call_data[..4], [0x09, 0x5e, 0xa7, 0xb3]);
+    let expected = function("function approve(address,uint256) returns (bool)")
+        .encode_input(&[Token::Address(spender), Token::Uint(amount)])
+        .unwrap();
+    assert_eq!(approve.call_data.to_vec(), expected);
+
+    // Allowances can be reset, but nothing can be sent to the zero address
+    assert!(token.approve(spender, U256::zero()).is_ok());
+    assert!(is_construction_error(token.approve(Address::zero(), amount).unwrap_err()));
+    assert!(is_construction_error(token.transfer(Address::zero(), amount).unwrap_err()));
+    assert!(is_construction_error(token.transfer(spender, U256::zero()).unwrap_err()));
+    assert!(is_construction_error(
+        Erc20Token(Address::zero()).approve(spender, amount).unwrap_err()
+    ));
+
+    let transfer = token.transfer(spender, amount).unwrap();
+    assert_eq!(transfer.call_data[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+}
+
+#[test]
+fn test_weth_calls() {
+    let weth = Weth(Address::random());
+    let amount = U256::exp10(18);
+
+    // Deposits send the amount as the call value
+    let deposit = weth.deposit(amount).unwrap();
+    assert_eq!(deposit.value, amount);
+    assert_eq!(deposit.call_data.to_vec(), vec![0xd0, 0xe3, 0x0d, 0xb0]);
+
+    let withdraw = weth.withdraw(amount).unwrap();
+    assert_eq!(withdraw.value, U256::zero());
+    let expected =
+        function("function withdraw(uint256)").encode_input(&[Token::Uint(amount)]).unwrap();
+    assert_eq!(withdraw.call_data.to_vec(), expected);
+
+    assert!(is_construction_error(weth.deposit(U256::zero()).unwrap_err()));
+    assert!(is_construction_error(weth.withdraw(U256::zero()).unwrap_err()));
+}
+
+#[test]
+fn test_uniswap_v2_calls() {
+    let router = UniswapV2Router::default();
+    let (dai, weth, to) = (Address::random(), Address::random(), Address::random());
+    let (amount_in, amount_out, deadline) =
+        (U256::exp10(21), U256::exp10(18), U256::from(1u64 << 40));
+
+    let swap = router
+        .swap_exact_tokens_for_tokens(amount_in, amount_out, &[dai, weth], to, deadline)
+        .unwrap();
+    assert_eq!(swap.target, router.0);
+    assert_eq!(swap.call_data[..4], [0x38, 0xed, 0x17, 0x39]);
+    let expected = function(
+        "function swapExactTokensForTokens(uint256,uint256,address[],address,uint256) returns (uint256[])",
+    )
+    .encode_input(&[
+        Token::Uint(amount_in),
+        Token::Uint(amount_out),
+        Token::Array(vec![Token::Address(dai), Token::Address(weth)]),
+        Token::Address(to),
+        Token::Uint(deadline),
+    ])
+    .unwrap();
+    assert_eq!(swap.call_data.to_vec(), expected);
+
+    let swap = router
+        .swap_tokens_for_exact_tokens(amount_out, amount_in, &[dai, weth], to, deadline)
+        .unwrap();
+    assert_eq!(swap.call_data[..4], [0x88, 0x03, 0xdb, 0xee]);
+
+    // Paths need two distinct consecutive tokens
+    for path in [vec![dai], vec![dai, dai], vec![dai, Address::zero()]] {
+        let err = router
+            .swap_exact_tokens_for_tokens(amount_in, amount_out, &path, to, deadline)
+            .unwrap_err();
+        assert!(is_construction_error(err));
+    }
+    let err = router
+        .swap_exact_tokens_for_tokens(U256::zero(), amount_out, &[dai, weth], to, deadline)
+        .unwrap_err();
+    assert!(is_construction_error(err));
+}
+
+#[test]
+fn test_uniswap_v3_calls() {
+    let router = UniswapV3Router::default();
+    let (dai, usdc, weth) = (Address::random(), Address::random(), Address::random());
+    let swap = UniswapV3SingleSwap {
+        token_in: dai,
+        token_out: weth,
+        fee: 3000,
+        recipient: Address::random(),
+        deadline: U256::from(1u64 << 40),
+        amount_in: U256::exp10(21),
+        amount_out_minimum: U256::exp10(17),
+        sqrt_price_limit_x96: U256::zero(),
+    };
+
+    let call = router.exact_input_single(swap).unwrap();
+    assert_eq!(call.call_data[..4], [0x41, 0x4b, 0xf3, 0x89]);
+    let expected = function(
+        "function exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160)) returns (uint256)",
+    )
+    .encode_input(&[Token::Tuple(vec![
+        Token::Address(dai),
+        Token::Address(weth),
+        Token::Uint(3000.into()),
+        Token::Address(swap.recipient),
+        Token::Uint(swap.deadline),
+        Token::Uint(swap.amount_in),
+        Token::Uint(swap.amount_out_minimum),
+        Token::Uint(U256::zero()),
+    ])])
+    .unwrap();
+    assert_eq!(call.call_data.to_vec(), expected);
+
+    let invalid = [
+        UniswapV3SingleSwap { token_out: dai, ..swap },
+        UniswapV3SingleSwap { fee: 1_000_000, ..swap },
+        UniswapV3SingleSwap { amount_in: U256::zero(), ..swap },
+        UniswapV3SingleSwap { sqrt_price_limit_x96: U256::MAX, ..swap },
+    ];
+    for swap in invalid {
+        assert!(is_construction_error(router.exact_input_single(swap).unwrap_err()));
+    }
+
+    // Multi-hop paths pack each token with the fee of the next pool
+    let call = router
+        .exact_input(
+            &[dai, usdc, weth],
+            &[100, 500],
+            swap.recipient,
+            swap.deadline,
+            swap.amount_in,
+            U256::zero(),
+        )
+        .unwrap();
+    assert_eq!(call.call_data[..4], [0xc0, 0x4b, 0x8d, 0x59]);
+    let mut path = dai.as_bytes().to_vec();
+    path.extend([0, 0, 100]);
+    path.extend(usdc.as_bytes());
+    path.extend([0, 1, 0xf4]);
+    path.extend(weth.as_bytes());
+    let expected =
+        function("function exactInput((bytes,address,uint256,uint256,uint256)) returns (uint256)")
+            .encode_input(&[Token::Tuple(vec![
+                Token::Bytes(path),
+                Token::Address(swap.recipient),
+                Token::Uint(swap.deadline),
+                Token::Uint(swap.amount_in),
+                Token::Uint(U256::zero()),
+            ])])
+            .unwrap();
+    assert_eq!(call.call_data.to_vec(), expected);
+
+    let err = router
+        .exact_input(
+            &[dai, usdc, weth],
+            &[100],
+            swap.recipient,
+            swap.deadline,
+            swap.amount_in,
+            U256::zero(),
+        )
+        .unwrap_err();
+    assert!(is_construction_error(err));
+}
+
+#[test]
+fn test_curve_calls() {
+    let pool = CurvePool(Address::random());
+    let (dx, min_dy) = (U256::exp10(21), U256::exp10(9));
+
+    let call = pool.exchange(0, 1, dx, min_dy).unwrap();
+    assert_eq!(call.call_data[..4], [0x3d, 0xf0, 0x21, 0x24]);
+    let expected = function("function exchange(int128,int128,uint256,uint256) returns (uint256)")
+        .encode_input(&[
+            Token::Int(0.into()),
+            Token::Int(1.into()),
+            Token::Uint(dx),
+            Token::Uint(min_dy),
+        ])
+        .unwrap();
+    assert_eq!(call.call_data.to_vec(), expected);
+
+    assert!(is_construction_error(pool.exchange(1, 1, dx, min_dy).unwrap_err()));
+    assert!(is_construction_error(pool.exchange(-1, 1, dx, min_dy).unwrap_err()));
+    assert!(is_construction_error(pool.exchange(0, 1, U256::zero(), min_dy).unwrap_err()));
+}
+
+#[test]
+fn test_balancer_calls() {
+    let vault = BalancerVault::default();
+    let account = Address::random();
+    let swap = BalancerSingleSwap {
+        pool_id: H256::random(),
+        kind: SwapKind::GivenOut,
+        asset_in: Address::random(),
+        asset_out: Address::random(),
+        amount: U256::exp10(18),
+        user_data: Bytes::default(),
+    };
+
+    let call = vault.swap(swap.clone(), account, account, U256::exp10(21), U256::MAX).unwrap();
+    assert_eq!(call.target, vault.0);
+    assert_eq!(call.call_data[..4], [0x52, 0xbb, 0xbe, 0x29]);
+    let expected = function(
+        "function swap((bytes32,uint8,address,address,uint256,bytes),(address,bool,address,bool),uint256,uint256) returns (uint256)",
+    )
+    .encode_input(&[
+        Token::Tuple(vec![
+            Token::FixedBytes(swap.pool_id.as_bytes().to_vec()),
+            Token::Uint(1.into()),
+            Token::Address(swap.asset_in),
+            Token::Address(swap.asset_out),
+            Token::Uint(swap.amount),
+            Token::Bytes(vec![]),
+        ]),
+        Token::Tuple(vec![
+            Token::Address(account),
+            Token::Bool(false),
+            Token::Address(account),
+            Token::Bool(false),
+        ]),
+        Token::Uint(U256::exp10(21)),
+        Token::Uint(U256::MAX),
+    ])
+    .unwrap();
+    assert_eq!(call.call_data.to_vec(), expected);
+
+    let same = BalancerSingleSwap { asset_out: swap.asset_in, ..swap.clone() };
+    assert!(is_construction_error(
+        vault.swap(same, account, account, U256::zero(), U256::MAX).unwrap_err()
+    ));
+    let err = vault.swap(swap, Address::zero(), account, U256::zero(), U256::MAX).unwrap_err();
+    assert!(is_construction_error(err));
+}
@@ -0,0 +1,160 @@
+use ethers::{core::rand::thread_rng, prelude::*};
+use std::{path::PathBuf, str::FromStr};
+
+use flashloan_rs::prelude::*;
+
+fn assert_cli_error(err: anyhow::Error) {
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::CliError(_))));
+}
+
+/// A config that never touches the network or the user's environment
+fn offline_config(name: &str) -> CliConfig {
+    CliConfig {
+        chain_id: Some(1),
+        private_key_env: format!("FLASHLOAN_TEST_{}_KEY", name),
+        keystore_password_env: format!("FLASHLOAN_TEST_{}_PASSWORD", name),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_parse_commands() {
+    let (lender, token) = (Address::random(), Address::random());
+
+    let cli = Cli::parse(["--config", "mainnet.toml", "deploy", "--create2"]).unwrap();
+    assert_eq!(cli.config, Some(PathBuf::from("mainnet.toml")));
+    assert_eq!(cli.command, Command::Deploy { lender: None, owner: None, create2: true });
+    let cli =
+        Cli::parse(vec!["deploy".to_string(), "--lender".to_string(), format!("{:?}", lender)])
+            .unwrap();
+    assert_eq!(cli.config, None);
+    assert_eq!(cli.command, Command::Deploy { lender: Some(lender), owner: None, create2: false });
+
+    let cli = Cli::parse(["execute", "plans/arb.toml"]).unwrap();
+    assert_eq!(cli.command, Command::Execute(PathBuf::from("plans/arb.toml")));
+
+    // Amounts are decimal or hex, without an amount the whole balance is withdrawn
+    let token_arg = format!("{:?}", token);
+    let cli = Cli::parse(["withdraw-token", "--token", &token_arg, "--amount", "0x10"]).unwrap();
+    let expected =
+        Command::WithdrawToken { borrower: None, token, to: None, amount: Some(16.into()) };
+    assert_eq!(cli.command, expected);
+    let cli = Cli::parse(["withdraw-token", "--token", &token_arg]).unwrap();
+    assert!(matches!(cli.command, Command::WithdrawToken { amount: None, .. }));
+
+    let other = Address::random();
+    let other_arg = format!("{:?}", other);
+    let cli = Cli::parse(["inspect", "--token", &token_arg, "--token", &other_arg]).unwrap();
+    assert_eq!(cli.command, Command::Inspect { borrower: None, tokens: vec![token, other] });
+}
+
+#[test]
+fn test_parse_invalid_commands() {
+    let invalid: &[&[&str]] = &[
+        &[],
+        &["borrow"],
+        &["deploy", "--lender"],
+        &["deploy", "--lender", "0x1234"],
+        &["deploy", "--lendr", "0x0000000000000000000000000000000000000001"],
+        &["deploy", "extra"],
+        &["plan"],
+        &["simulate", "a.toml", "b.toml"],
+        &["withdraw-token"],
+        &[
+            "withdraw-token",
+            "--token",
+            "0x0000000000000000000000000000000000000001",
+            "--amount",
+            "ten",
+        ],
+        &["--config"],
+    ];
+    for args in invalid {
+        assert_cli_error(Cli::parse(args.iter().copied()).unwrap_err());
+    }
+}
+
+#[test]
+fn test_config_file() {
+    let borrower = Address::random();
+    let config = CliConfig::from_toml(&format!(
+        "rpc_url = \"https://rpc.example\"\nborrower = \"{:?}\"\nlender_kind = \"aave_v3\"\ncreate2 = true",
+        borrower
+    ))
+    .unwrap();
+    assert_eq!(config.rpc_url, "https://rpc.example");
+    assert_eq!(config.borrower, Some(borrower));
+    assert_eq!(config.lender_kind, LenderKind::AaveV3);
+    assert!(config.create2);
+    // Unset fields have defaults
+    assert_eq!(config.private_key_env, "FLASHLOAN_PRIVATE_KEY");
+    assert_eq!(config.chain_id, None);
+
+    assert_cli_error(CliConfig::from_toml("rpc = \"http://localhost:8545\"").unwrap_err());
+
+    // A given config file must exist
+    let cli =
+        Cli { config: Some(PathBuf::from("missing.toml")), command: Command::Plan("p".into()) };
+    assert_cli_error(cli.load_config().unwrap_err());
+}
+
+#[test]
+fn test_config_wallet() {
+    let mut config = offline_config("WALLET");
+    assert_cli_error(config.wallet().unwrap_err());
+
+    // From a keystore, with the password from the environment
+    let dir = std::env::temp_dir();
+    let name = "flashloan-cli-test-keystore";
+    let (wallet, _) =
+        LocalWallet::new_keystore(&dir, &mut thread_rng(), "hunter2", Some(name)).unwrap();
+    config.keystore = Some(dir.join(name));
+    assert_cli_error(config.wallet().unwrap_err());
+    std::env::set_var(&config.keystore_password_env, "hunter2");
+    assert_eq!(config.wallet().unwrap().address(), wallet.address());
+    std::fs::remove_file(dir.join(name)).unwrap();
+
+    // The private key variable takes precedence over the keystore
+    let key = LocalWallet::new(&mut thread_rng());
+    std::env::set_var(&config.private_key_env, hex::encode(key.signer().to_bytes()));
+    assert_eq!(config.wallet().unwrap().address(), key.address());
+    std::env::set_var(&config.private_key_env, "0xnope");
+    assert_cli_error(config.wallet().unwrap_err());
+}
+
+#[tokio::test]
+async fn test_run_offline_commands() {
+    let path = std::env::temp_dir().join("flashloan-cli-test-plan.json");
+    let dai = Address::from_str("0x6b175474e89094c44da98b954eedeac495271d0f").unwrap();
+    let spec = FlashloanSpec {
+        chain_id: Some(1),
+        token: Some(dai),
+        amount: Some(U256::exp10(21)),
+        calls: vec![CallSpec::abi(
+            dai,
+            "approve(address,uint256)",
+            vec![format!("{:?}", Address::random()), "1000".to_string()],
+        )],
+        ..Default::default()
+    };
+    spec.save(&path).unwrap();
+
+    // The plan needs a borrower, from the config or the plan
+    let mut config = offline_config("RUN");
+    let err = run(&config, &Command::Plan(path.clone())).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingBorrower)));
+    config.borrower = Some(Address::random());
+    run(&config, &Command::Plan(path.clone())).await.unwrap();
+
+    // Plans for another chain are rejected
+    config.chain_id = Some(10);
+    let err = run(&config, &Command::Plan(path.clone())).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::PlanFormatError(_))
+    ));
+
+    // Sending commands need a signer
+    assert_cli_error(run(&config, &Command::Execute(path.clone())).await.unwrap_err());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,285 @@
+//! A minimal assembler for the Huff subset used by the bundled contracts
+//!
+//! It reproduces the bundled Huff artifacts from their sources, so the tests can check the
+//! artifacts were built from the contracts in the repository. It supports opcodes, literals,
+//! constants, `FREE_STORAGE_POINTER()`, `__FUNC_SIG`, `__EVENT_HASH`, `__ERROR`, `__codesize`,
+//! labels and nested macros, whose labels are scoped to the macro invocation. Jump labels are
+//! always pushed with `PUSH2`.
+
+use ethers::utils::keccak256;
+use std::collections::HashMap;
+
+/// The opcodes the contracts use
+const OPCODES: &[(&str, u8)] = &[
+    ("stop", 0x00),
+    ("add", 0x01),
+    ("mul", 0x02),
+    ("sub", 0x03),
+    ("div", 0x04),
+    ("lt", 0x10),
+    ("gt", 0x11),
+    ("eq", 0x14),
+    ("iszero", 0x15),
+    ("and", 0x16),
+    ("or", 0x17),
+    ("not", 0x19),
+    ("shl", 0x1b),
+    ("shr", 0x1c),
+    ("sha3", 0x20),
+    ("address", 0x30),
+    ("caller", 0x33),
+    ("callvalue", 0x34),
+    ("calldataload", 0x35),
+    ("calldatasize", 0x36),
+    ("calldatacopy", 0x37),
+    ("codesize", 0x38),
+    ("codecopy", 0x39),
+    ("returndatasize", 0x3d),
+    ("returndatacopy", 0x3e),
+    ("selfbalance", 0x47),
+    ("pop", 0x50),
+    ("mload", 0x51),
+    ("mstore", 0x52),
+    ("sload", 0x54),
+    ("sstore", 0x55),
+    ("jump", 0x56),
+    ("jumpi", 0x57),
+    ("gas", 0x5a),
+    ("jumpdest", 0x5b),
+    ("log1", 0xa1),
+    ("log2", 0xa2),
+    ("log3", 0xa3),
+    ("call", 0xf1),
+    ("return", 0xf3),
+    ("staticcall", 0xfa),
+    ("revert", 0xfd),
+];
+
+fn opcode(token: &str) -> Option<u8> {
+    if let Some(n) = token.strip_prefix("dup").and_then(|n| n.parse::<u8>().ok()) {
+        return Some(0x7f + n)
+    }
+    if let Some(n) = token.strip_prefix("swap").and_then(|n| n.parse::<u8>().ok()) {
+        return Some(0x8f + n)
+    }
+    OPCODES.iter().find(|(name, _)| *name == token).map(|(_, op)| *op)
+}
+
+enum Item {
+    Label(String),
+    Op(u8),
+    Push(Vec<u8>),
+    Ref(String),
+}
+
+#[derive(Default)]
+struct Source {
+    functions: HashMap<String, String>,
+    events: HashMap<String, String>,
+    errors: HashMap<String, String>,
+    constants: HashMap<String, Vec<u8>>,
+    macros: HashMap<String, Vec<String>>,
+}
+
+/// Remove the line and block comments
+fn strip_comments(source: &str) -> String {
+    let mut out = String::new();
+    let mut rest = source;
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix("/*") {
+            rest = tail.find("*/").map(|end| &tail[end + 2..]).unwrap_or_default();
+        } else if rest.starts_with("//") {
+            rest = rest.find('\n').map(|end| &rest[end..]).unwrap_or_default();
+        } else {
+            let c = rest.chars().next().unwrap();
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// The `name(args)` signature of a definition, without `indexed` markers
+fn signature(definition: &str) -> (String, String) {
+    let open = definition.find('(').unwrap();
+    let close = open + definition[open..].find(')').unwrap();
+    let name = definition[..open].trim().to_string();
+    let args = definition[open + 1..close].replace(" indexed", "");
+    (name.clone(), format!("{}({})", name, args.trim()))
+}
+
+/// Big endian bytes of a hex literal, without leading zero bytes
+fn literal(hex: &str) -> Vec<u8> {
+    let digits = hex.trim_start_matches("0x");
+    let digits = if digits.len() % 2 == 1 { format!("0{}", digits) } else { digits.to_string() };
+    let bytes = hex::decode(digits).unwrap();
+    let first = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len() - 1);
+    bytes[first..].to_vec()
+}
+
+fn parse(source: &str) -> Source {
+    let source = strip_comments(source);
+    let mut parsed = Source::default();
+    let mut free_slot = 0u8;
+    for definition in source.split("#define").skip(1) {
+        let definition = definition.trim();
+        let (kind, rest) = definition.split_once(char::is_whitespace).unwrap();
+        match kind {
+            "function" => {
+                let (name, signature) = signature(rest);
+                parsed.functions.insert(name, signature);
+            }
+            "event" => {
+                let (name, signature) = signature(rest);
+                parsed.events.insert(name, signature);
+            }
+            "error" => {
+                let (name, signature) = signature(rest);
+                parsed.errors.insert(name, signature);
+            }
+            "constant" => {
+                let (name, value) = rest.split_once('=').unwrap();
+                let value = value.split_whitespace().next().unwrap();
+                let value = if value == "FREE_STORAGE_POINTER()" {
+                    free_slot += 1;
+                    vec![free_slot - 1]
+                } else {
+                    literal(value)
+                };
+                parsed.constants.insert(name.trim().to_string(), value);
+            }
+            "macro" => {
+                let name = rest[..rest.find('(').unwrap()].trim().to_string();
+                let body = &rest[rest.find('{').unwrap() + 1..rest.rfind('}').unwrap()];
+                parsed.macros.insert(name, body.split_whitespace().map(String::from).collect());
+            }
+            kind => panic!("unsupported definition {}", kind),
+        }
+    }
+    parsed
+}
+
+/// The argument of a builtin call such as `__FUNC_SIG(name)`
+fn argument<'a>(token: &'a str, builtin: &str) -> Option<&'a str> {
+    token.strip_prefix(builtin)?.strip_prefix('(')?.strip_suffix(')')
+}
+
+fn selector(signature: &str) -> Vec<u8> {
+    keccak256(signature.as_bytes())[..4].to_vec()
+}
+
+fn expand(
+    source: &Source,
+    name: &str,
+    scope: &str,
+    sizes: &HashMap<&str, usize>,
+    counter: &mut usize,
+) -> Vec<Item> {
+    let mut items = vec![];
+    for token in &source.macros[name] {
+        let token = token.as_str();
+        if let Some(label) = token.strip_suffix(':') {
+            items.push(Item::Label(format!("{}{}", scope, label)));
+        } else if let Some(op) = opcode(token) {
+            items.push(Item::Op(op));
+        } else if token.starts_with("0x") {
+            items.push(Item::Push(literal(token)));
+        } else if let Some(constant) = token.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            items.push(Item::Push(source.constants[constant].clone()));
+        } else if let Some(function) = argument(token, "__FUNC_SIG") {
+            let signature = match function.strip_prefix('"').and_then(|f| f.strip_suffix('"')) {
+                Some(signature) => signature,
+                None => &source.functions[function],
+            };
+            items.push(Item::Push(selector(signature)));
+        } else if let Some(event) = argument(token, "__EVENT_HASH") {
+            items.push(Item::Push(keccak256(source.events[event].as_bytes()).to_vec()));
+        } else if let Some(error) = argument(token, "__ERROR") {
+            let mut word = selector(&source.errors[error]);
+            word.resize(32, 0);
+            items.push(Item::Push(word));
+        } else if let Some(code) = argument(token, "__codesize") {
+            items.push(Item::Push((sizes[code] as u16).to_be_bytes().to_vec()));
+        } else if let Some(inner) =
+            token.strip_suffix("()").filter(|m| source.macros.contains_key(*m))
+        {
+            *counter += 1;
+            let scope = format!("{}{}#{}/", scope, inner, counter);
+            items.extend(expand(source, inner, &scope, sizes, counter));
+        } else {
+            items.push(Item::Ref(format!("{}{}", scope, token)));
+        }
+    }
+    items
+}
+
+/// Assemble a macro, resolving each label reference to the innermost scope defining it
+fn assemble(source: &Source, name: &str, sizes: &HashMap<&str, usize>) -> Vec<u8> {
+    let items = expand(source, name, "", sizes, &mut 0);
+    let mut labels = HashMap::new();
+    let mut pc = 0;
+    for item in &items {
+        match item {
+            Item::Label(label) => {
+                labels.insert(label.clone(), pc);
+                pc += 1;
+            }
+            Item::Op(_) => pc += 1,
+            Item::Push(bytes) => pc += 1 + bytes.len(),
+            Item::Ref(_) => pc += 3,
+        }
+    }
+
+    let mut code = vec![];
+    for item in items {
+        match item {
+            Item::Label(_) => code.push(0x5b),
+            Item::Op(op) => code.push(op),
+            Item::Push(bytes) => {
+                code.push(0x5f + bytes.len() as u8);
+                code.extend(bytes);
+            }
+            Item::Ref(reference) => {
+                let (mut scope, label) = reference.rsplit_once('/').unwrap_or(("", &reference));
+                let offset = loop {
+                    let key = if scope.is_empty() {
+                        label.to_string()
+                    } else {
+                        format!("{}/{}", scope, label)
+                    };
+                    if let Some(offset) = labels.get(&key) {
+                        break *offset;
+                    }
+                    assert!(!scope.is_empty(), "unknown label {}", reference);
+                    scope = scope.rsplit_once('/').map(|(outer, _)| outer).unwrap_or("");
+                };
+                code.push(0x61);
+                code.extend((offset as u16).to_be_bytes());
+            }
+        }
+    }
+    code
+}
+
+/// Assemble a Huff contract into its creation and runtime bytecode
+///
+/// The creation code is the `CONSTRUCTOR` macro, if any, followed by a bootstrap copying the
+/// `MAIN` macro into memory and returning it.
+pub fn assemble_contract(source: &str) -> (Vec<u8>, Vec<u8>) {
+    let source = parse(source);
+    let runtime = assemble(&source, "MAIN", &HashMap::new());
+    let constructor = if source.macros.contains_key("CONSTRUCTOR") {
+        assemble(&source, "CONSTRUCTOR", &HashMap::from([("MAIN", runtime.len())]))
+    } else {
+        vec![]
+    };
+
+    let mut creation = constructor.clone();
+    creation.push(0x61);
+    creation.extend((runtime.len() as u16).to_be_bytes());
+    creation.extend([0x80, 0x61]);
+    creation.extend((constructor.len() as u16 + 11).to_be_bytes());
+    creation.extend([0x3d, 0x39, 0x3d, 0xf3]);
+    creation.extend(&runtime);
+    (creation, runtime)
+}
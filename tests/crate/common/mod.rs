@@ -0,0 +1,32 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use ethers::prelude::*;
+use serde_json::Value;
+
+pub mod huff;
+
+/// Queue the responses to sending a transaction and waiting for its receipt
+///
+/// Responses are popped last in first out, so they are pushed in reverse order.
+pub fn push_transaction(mock: &MockProvider, transaction_hash: H256) {
+    let receipt =
+        TransactionReceipt { transaction_hash, block_number: Some(1.into()), ..Default::default() };
+    let transaction =
+        Transaction { hash: transaction_hash, block_number: Some(1.into()), ..Default::default() };
+    let fee_history = FeeHistory {
+        base_fee_per_gas: vec![U256::from(10)],
+        gas_used_ratio: vec![0.5],
+        oldest_block: U256::one(),
+        reward: vec![vec![U256::one()]],
+    };
+    let block = Block::<H256> { base_fee_per_gas: Some(U256::from(10)), ..Default::default() };
+    mock.push(receipt).unwrap();
+    mock.push(transaction).unwrap();
+    mock.push(transaction_hash).unwrap();
+    // The access list can't be decoded, so the gas estimate is used
+    mock.push(Value::Null).unwrap();
+    mock.push(U256::from(50_000)).unwrap();
+    mock.push(fee_history).unwrap();
+    mock.push(block).unwrap();
+}
@@ -0,0 +1,88 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+    utils::keccak256,
+};
+use std::{str::FromStr, sync::Arc};
+
+use flashloan_rs::prelude::*;
+
+fn builder<M: Middleware>(client: Arc<M>, owner: Address, lender: Address) -> FlashloanBuilder<M> {
+    FlashloanBuilder::new(client, 1, Some(owner), Some(lender), None, None, None)
+}
+
+#[test]
+fn test_address_for() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let (owner, lender) = (Address::random(), Address::random());
+    let builder = builder(client, owner, lender);
+
+    // CREATE2 addresses hash the factory, the salt and the creation code
+    let factory = Address::from_str(CREATE2_FACTORY).unwrap();
+    let salt = keccak256(encode(&[Token::Address(owner), Token::Address(lender)]));
+    assert_eq!(borrower_salt(owner, lender), H256::from(salt));
+    let mut init_code = FLASHLOAN_BYTECODE.to_vec();
+    init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+    let mut preimage = vec![0xff];
+    preimage.extend(factory.as_bytes());
+    preimage.extend(salt);
+    preimage.extend(keccak256(&init_code));
+    let expected = Address::from_slice(&keccak256(preimage)[12..]);
+    assert_eq!(builder.address_for(owner, lender).unwrap(), expected);
+
+    // Each owner has one borrower per lender
+    assert_ne!(builder.address_for(lender, owner).unwrap(), expected);
+    assert_ne!(builder.address_for(owner, Address::random()).unwrap(), expected);
+}
+
+#[test]
+fn test_address_for_borrower_contract() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder = builder(client, owner, lender);
+    let erc3156 = builder.address_for(owner, lender).unwrap();
+
+    // The factory and borrower contract change the address
+    builder.with_create2_factory(Address::random());
+    assert_ne!(builder.address_for(owner, lender).unwrap(), erc3156);
+
+    // So does the bundled bytecode, as a rebuilt borrower has other creation code
+    let factory = Address::from_str(CREATE2_FACTORY).unwrap();
+    let bytecode = FLASHLOAN_BYTECODE.clone();
+    assert_eq!(create2_borrower_address(factory, &bytecode, lender, owner), erc3156);
+    let mut rebuilt = bytecode.to_vec();
+    rebuilt.push(0x00);
+    assert_ne!(create2_borrower_address(factory, &rebuilt.into(), lender, owner), erc3156);
+}
+
+#[tokio::test]
+async fn test_deploy_or_attach_existing_borrower() {
+    let (provider, mock) = Provider::mocked();
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder = builder(Arc::new(provider), owner, lender);
+    let address = builder.address_for(owner, lender).unwrap();
+
+    // The borrower already has code, so it is attached without deploying
+    mock.push::<Bytes, _>(Bytes::from(vec![0x60, 0x80])).unwrap();
+    builder.deploy_or_attach(None, None).await.unwrap();
+    assert_eq!(builder.borrower.as_ref().unwrap().address(), address);
+    assert_eq!(builder.borrowers.get(&lender), Some(&address));
+    mock.assert_request("eth_getCode", (address, "latest")).unwrap();
+}
+
+#[tokio::test]
+async fn test_deploy_create2_without_factory() {
+    let (provider, mock) = Provider::mocked();
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder = builder(Arc::new(provider), owner, lender);
+
+    // Neither the borrower nor the factory have code
+    mock.push::<Bytes, _>(Bytes::default()).unwrap();
+    mock.push::<Bytes, _>(Bytes::default()).unwrap();
+    let err = builder.deploy_or_attach(None, None).await.err().unwrap();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::MissingCreate2Factory(factory)) if *factory == builder.create2_factory
+    ));
+    assert!(builder.borrower.is_none());
+}
@@ -0,0 +1,112 @@
+use ethers::{prelude::*, types::transaction::eip2718::TypedTransaction};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+fn fee_history(base_fees: &[u64], rewards: &[u64]) -> FeeHistory {
+    FeeHistory {
+        base_fee_per_gas: base_fees.iter().map(|fee| U256::from(*fee)).collect(),
+        gas_used_ratio: vec![0.5; rewards.len()],
+        oldest_block: U256::from(100),
+        reward: rewards.iter().map(|reward| vec![U256::from(*reward)]).collect(),
+    }
+}
+
+#[tokio::test]
+async fn test_fee_history_strategy() {
+    let (provider, mock) = Provider::mocked();
+    // The last base fee is the next block's
+    mock.push(fee_history(&[10, 12, 14, 20], &[1, 100, 3])).unwrap();
+
+    let strategy = FeeStrategy::FeeHistory { blocks: 3, percentile: 60.0 };
+    let fees = strategy.fees(&provider, U256::from(100_000)).await.unwrap().unwrap();
+    // The median reward ignores the outlier block
+    assert_eq!(fees, Eip1559Fees::from_base_fee(20.into(), 3.into()));
+    assert_eq!(fees.max_fee_per_gas, U256::from(43));
+    mock.assert_request("eth_feeHistory", (U256::from(3), BlockNumber::Latest, [60.0])).unwrap();
+
+    // Invalid percentiles are rejected before querying
+    let strategy = FeeStrategy::FeeHistory { blocks: 3, percentile: 101.0 };
+    let err = strategy.fees(&provider, U256::from(100_000)).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::FeeEstimationError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_profit_share_strategy() {
+    let (provider, mock) = Provider::mocked();
+    mock.push(fee_history(&[10, 30], &[])).unwrap();
+
+    // A fifth of 1 ether over 200k gas
+    let strategy = FeeStrategy::ProfitShare { expected_profit: U256::exp10(18), share_bps: 2_000 };
+    let fees = strategy.fees(&provider, U256::from(200_000)).await.unwrap().unwrap();
+    assert_eq!(fees.max_priority_fee_per_gas, U256::exp10(12));
+    assert_eq!(fees.max_fee_per_gas, U256::exp10(12) + 60);
+
+    let strategy = FeeStrategy::ProfitShare { expected_profit: U256::exp10(18), share_bps: 10_001 };
+    assert!(strategy.fees(&provider, U256::from(200_000)).await.is_err());
+
+    // Fixed fees and the provider's don't query the client
+    let fixed = Eip1559Fees { max_fee_per_gas: 5.into(), max_priority_fee_per_gas: 1.into() };
+    let fees = FeeStrategy::Fixed(fixed).fees(&provider, U256::zero()).await.unwrap();
+    assert_eq!(fees, Some(fixed));
+    assert_eq!(FeeStrategy::Provider.fees(&provider, U256::zero()).await.unwrap(), None);
+}
+
+#[test]
+fn test_apply_fees() {
+    let fees = Eip1559Fees { max_fee_per_gas: 50.into(), max_priority_fee_per_gas: 2.into() };
+
+    let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest::new());
+    fees.apply(&mut tx);
+    match &tx {
+        TypedTransaction::Eip1559(inner) => {
+            assert_eq!(inner.max_fee_per_gas, Some(50.into()));
+            assert_eq!(inner.max_priority_fee_per_gas, Some(2.into()));
+        }
+        _ => unreachable!(),
+    }
+
+    // Legacy transactions pay the max fee
+    let mut tx = TypedTransaction::Legacy(TransactionRequest::new());
+    fees.apply(&mut tx);
+    assert_eq!(tx.gas_price(), Some(50.into()));
+
+    assert_eq!(pad_gas(U256::from(100_000), 1_500), U256::from(115_000));
+    assert_eq!(pad_gas(U256::from(100_000), 0), U256::from(100_000));
+}
+
+#[test]
+fn test_plan_gas_settings() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let strategy = FeeStrategy::FeeHistory { blocks: 5, percentile: 50.0 };
+
+    let plan = FlashloanBuilder::with_client(Arc::clone(&client))
+        .borrower(Address::random())
+        .token(Address::random())
+        .amount(U256::exp10(18))
+        .fee_strategy(strategy)
+        .gas_padding(2_000)
+        .build()
+        .unwrap();
+    assert_eq!(plan.fee_strategy(), strategy);
+    assert_eq!(plan.gas_padding(), 2_000);
+
+    // The builder's gas settings carry over to its plans
+    let mut builder = FlashloanBuilder::new(
+        client,
+        1,
+        None,
+        None,
+        Some(Address::random()),
+        Some(U256::exp10(18)),
+        Some(Address::random()),
+    );
+    assert_eq!(builder.plan().unwrap().fee_strategy(), FeeStrategy::Provider);
+    builder.with_fee_strategy(strategy).with_gas_padding(1_000);
+    let plan = builder.plan().unwrap();
+    assert_eq!(plan.fee_strategy(), strategy);
+    assert_eq!(plan.gas_padding(), 1_000);
+}
@@ -0,0 +1,535 @@
+use ethers::{
+    abi::{encode, AbiDecode, AbiEncode, Token},
+    prelude::*,
+    utils::{id, keccak256},
+};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+/// The harness deployed into a fresh snapshot, with a client the simulations never reach
+fn snapshot_harness() -> (TestHarness<Provider<MockProvider>>, StateSnapshot) {
+    let (provider, _mock) = Provider::mocked();
+    let mut snapshot = StateSnapshot::new(31337);
+    let harness = TestHarness::deploy_in_snapshot(
+        Arc::new(provider),
+        &mut snapshot,
+        Address::random(),
+        U256::exp10(24),
+    )
+    .unwrap();
+    (harness, snapshot)
+}
+
+/// Call a mock token view function in the snapshot
+fn token_view(snapshot: &StateSnapshot, token: Address, name: &str, args: &[Token]) -> Bytes {
+    let data = MOCK_TOKEN_ABI.function(name).unwrap().encode_input(args).unwrap();
+    snapshot.call(Address::random(), token, data.into()).unwrap()
+}
+
+#[test]
+fn test_deploy_in_snapshot() {
+    let (harness, snapshot) = snapshot_harness();
+    assert_eq!(harness.chain_id, 31337);
+    let token = harness.token;
+    assert_eq!(snapshot.balance_of(token, harness.lender).unwrap(), U256::exp10(24));
+    assert_eq!(
+        U256::decode(token_view(&snapshot, token, "totalSupply", &[])).unwrap(),
+        U256::exp10(24)
+    );
+    assert_eq!(
+        U256::decode(token_view(&snapshot, token, "decimals", &[])).unwrap(),
+        U256::from(18)
+    );
+    assert_eq!(String::decode(token_view(&snapshot, token, "name", &[])).unwrap(), "Mock");
+    assert_eq!(String::decode(token_view(&snapshot, token, "symbol", &[])).unwrap(), "MCK");
+
+    // The lender lends its whole balance for a 1% fee
+    let view = |name: &str, args: &[Token]| {
+        let data = MOCK_LENDER_ABI.function(name).unwrap().encode_input(args).unwrap();
+        U256::decode(snapshot.call(harness.owner, harness.lender, data.into()).unwrap()).unwrap()
+    };
+    assert_eq!(view("maxFlashLoan", &[Token::Address(token)]), U256::exp10(24));
+    let fee = view("flashFee", &[Token::Address(token), Token::Uint(U256::exp10(18))]);
+    assert_eq!(fee, mock_flash_fee(U256::exp10(18)));
+}
+
+#[test]
+fn test_harness_flashloan() {
+    let (harness, snapshot) = snapshot_harness();
+    let amount = U256::exp10(18);
+    let fee = mock_flash_fee(amount);
+
+    // The borrower mints the fee inside the callback and repays the loan with it
+    let simulation = harness.builder(amount).simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    assert_eq!(simulation.calls.len(), 1);
+    assert!(simulation.calls[0].success);
+    assert_eq!(simulation.results().unwrap().len(), 1);
+    assert_eq!(simulation.balance_deltas[&harness.token], I256::zero());
+
+    // The lender lent the amount, then pulled back the amount and the fee
+    let flash_loan = MOCK_LENDER_ABI.event("FlashLoan").unwrap().signature();
+    let log = simulation.logs.iter().find(|log| log.topics[0] == flash_loan).unwrap();
+    assert_eq!(log.address, harness.lender);
+    assert_eq!(log.topics[1], H256::from(harness.borrower));
+    let transfer = MOCK_TOKEN_ABI.event("Transfer").unwrap().signature();
+    let transfers: Vec<_> = simulation
+        .logs
+        .iter()
+        .filter(|log| log.topics[0] == transfer)
+        .map(|log| (log.topics[1], log.topics[2], U256::from_big_endian(&log.data)))
+        .collect();
+    let (lender, borrower) = (H256::from(harness.lender), H256::from(harness.borrower));
+    assert_eq!(
+        transfers,
+        vec![
+            (lender, borrower, amount),
+            (H256::zero(), borrower, fee),
+            (borrower, lender, amount + fee)
+        ]
+    );
+}
+
+#[test]
+fn test_simulated_balance_deltas() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let amount = U256::exp10(18);
+    let fee = mock_flash_fee(amount);
+    let half = U256::one() << 255;
+    let simulate = |snapshot: &StateSnapshot, minted: U256| {
+        let mut builder = harness.builder(amount);
+        builder.calls = vec![harness.mint_call(harness.borrower, fee + minted)];
+        builder.simulate(snapshot, &[])
+    };
+
+    // Gains up to the int256 bounds are exact
+    let simulation = simulate(&snapshot, half - 1).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    assert_eq!(simulation.balance_deltas[&harness.token], I256::MAX);
+
+    // Larger ones error instead of reading as a loss
+    let err = simulate(&snapshot, half).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::BalanceDeltaOverflow { before, after })
+            if before.is_zero() && *after == half
+    ));
+
+    // A balance above the int256 bounds still changes by the minted amount
+    let slot = keccak256(encode(&[Token::Address(harness.borrower), Token::Uint(U256::from(3))]));
+    snapshot.set_storage(harness.token, U256::from(slot), half);
+    let simulation = simulate(&snapshot, U256::one()).unwrap();
+    assert_eq!(simulation.balance_deltas[&harness.token], I256::one());
+}
+
+#[test]
+fn test_harness_flashloan_unpaid_fee() {
+    let (harness, snapshot) = snapshot_harness();
+    let mut builder = harness.builder(U256::exp10(18));
+    builder.calls.clear();
+
+    // Without the minted fee the repayment underflows the borrower's balance
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert_eq!(simulation.output, overflow_panic());
+}
+
+/// The revert data of a checked arithmetic overflow or underflow
+fn overflow_panic() -> Bytes {
+    let mut panic = id("Panic(uint256)").to_vec();
+    panic.extend(encode(&[Token::Uint(0x11.into())]));
+    panic.into()
+}
+
+#[test]
+fn test_huff_borrower_callback() {
+    let (harness, snapshot) = snapshot_harness();
+    let (token, amount) = (harness.token, U256::exp10(18));
+    let fee = mock_flash_fee(amount);
+    let calls = vec![harness.mint_call(harness.borrower, fee)];
+
+    // The lender calls back into each entrypoint, which approves the repayment it pulls
+    let entrypoints = [
+        FlashloanCalls::FlashBorrow(FlashBorrowCall { token, amount, calls: calls.clone() }),
+        FlashloanCalls::FlashBorrowWithRepayment(FlashBorrowWithRepaymentCall {
+            token,
+            amount,
+            repayment: amount + fee,
+            calls: calls.clone(),
+        }),
+        FlashloanCalls::FlashBorrowWithProfit(FlashBorrowWithProfitCall {
+            token,
+            amount,
+            calls: vec![harness.mint_call(harness.borrower, fee * 2)],
+            profit_token: token,
+            min_profit: fee,
+        }),
+    ];
+    for entrypoint in entrypoints {
+        let output =
+            snapshot.call(harness.owner, harness.borrower, entrypoint.encode().into()).unwrap();
+        let results = Vec::<(bool, Bytes)>::decode(output).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].0);
+    }
+
+    // An approval short of the fee fails the lender's repayment
+    let short = FlashBorrowWithRepaymentCall { token, amount, repayment: amount, calls };
+    let err = snapshot.call(harness.owner, harness.borrower, short.encode().into()).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FlashloanError::SimulationError(_))));
+}
+
+#[test]
+fn test_huff_borrower_overflow() {
+    let (harness, snapshot) = snapshot_harness();
+
+    // The amount plus the flash fee overflows before anything is approved
+    let simulation = harness.builder(U256::MAX).simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert_eq!(simulation.output, overflow_panic());
+
+    // So does the profit threshold
+    let profit = FlashBorrowWithProfitCall {
+        token: harness.token,
+        amount: U256::exp10(18),
+        calls: vec![],
+        profit_token: harness.token,
+        min_profit: U256::MAX,
+    };
+    let err = snapshot.call(harness.owner, harness.borrower, profit.encode().into()).unwrap_err();
+    assert!(err.to_string().contains(&hex::encode(overflow_panic())), "{}", err);
+}
+
+/// Deploy a Solidity borrower against the harness lender into the snapshot
+fn deploy_borrower(
+    harness: &TestHarness<Provider<MockProvider>>,
+    snapshot: &mut StateSnapshot,
+    bytecode: Bytes,
+) -> Address {
+    let mut init_code = bytecode.to_vec();
+    init_code.extend(encode(&[Token::Address(harness.lender), Token::Address(harness.owner)]));
+    snapshot.deploy(harness.owner, init_code.into()).unwrap()
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_registry_default_borrowers() {
+    let registry = ChainRegistry::builtin();
+    let owner = Address::random();
+
+    // Chains without an ERC-3156 lender deploy the borrower of their default lender
+    for chain_id in [10, 137, 42161] {
+        let mut snapshot = StateSnapshot::new(chain_id);
+        let lender = registry.default_lender(chain_id, LenderKind::Erc3156).unwrap();
+        let bytecode = BorrowerFlavor::Solidity.borrower_bytecode(lender.kind).unwrap();
+        let mut init_code = bytecode.to_vec();
+        init_code.extend(encode(&[Token::Address(lender.address), Token::Address(owner)]));
+        assert!(snapshot.deploy(owner, init_code.into()).is_ok(), "chain {}", chain_id);
+    }
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_multi_borrower_nesting() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let borrower = deploy_borrower(&harness, &mut snapshot, multi_borrower_bytecode().unwrap());
+    let (first, second) = (U256::exp10(18), U256::exp10(20));
+    let simulate = |fee: U256| {
+        let mut builder = harness.builder(first);
+        builder.calls = vec![harness.mint_call(borrower, fee)];
+        builder.with_borrower(borrower);
+        for amount in [first, second] {
+            builder.add_leg(FlashloanLeg::new(
+                LenderKind::Erc3156,
+                harness.lender,
+                harness.token,
+                amount,
+            ));
+        }
+        builder.simulate(&snapshot, &[]).unwrap()
+    };
+
+    // The calls run inside both loans, then each leg is repaid with its fee
+    let simulation = simulate(mock_flash_fee(first) + mock_flash_fee(second));
+    assert!(simulation.success, "{:?}", simulation.output);
+    let results = simulation.results().unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success);
+
+    // Without the second fee, the outer leg can't be repaid
+    assert!(!simulate(mock_flash_fee(first)).success);
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_program_borrower_execution() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let borrower = deploy_borrower(&harness, &mut snapshot, program_borrower_bytecode().unwrap());
+    let amount = U256::exp10(18);
+
+    // The program queries the flash fee and mints it to the borrower with the returned value
+    let mut planner = CommandPlanner::new();
+    let flash_fee = MOCK_LENDER_ABI.function("flashFee").unwrap();
+    let args = vec![Token::Address(harness.token).into(), Token::Uint(amount).into()];
+    let fee = planner.call(harness.lender, flash_fee, args).unwrap();
+    let mint = MOCK_TOKEN_ABI.function("mint").unwrap();
+    planner.call(harness.token, mint, vec![Token::Address(borrower).into(), fee.into()]).unwrap();
+
+    let mut builder = harness.builder(amount);
+    builder.calls.clear();
+    builder.with_borrower(borrower).with_program(planner.plan().unwrap());
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    let results = simulation.results().unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.success));
+    assert_eq!(U256::decode(&results[0].return_data).unwrap(), mock_flash_fee(amount));
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_operated_borrower_operator() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let borrower = deploy_borrower(&harness, &mut snapshot, operated_borrower_bytecode().unwrap());
+    let amount = U256::exp10(18);
+
+    // Add the operator, `isOperator` follows the lender, trusted lenders, owner and pending owner
+    let operator = Address::random();
+    let slot = keccak256(encode(&[Token::Address(operator), Token::Uint(U256::from(4))]));
+    snapshot.set_storage(borrower, U256::from(slot), U256::one());
+    let data = OPERATED_FLASHLOAN_ABI
+        .function("isOperator")
+        .and_then(|function| function.encode_input(&[Token::Address(operator)]))
+        .unwrap();
+    assert!(bool::decode(snapshot.call(harness.owner, borrower, data.into()).unwrap()).unwrap());
+
+    // The operator's calls run as the borrower, so they can move its funds to the operator
+    let mut builder = harness.builder(amount);
+    builder.calls = vec![harness.mint_call(borrower, mock_flash_fee(amount) + 100)];
+    let transfer = MOCK_TOKEN_ABI
+        .function("transfer")
+        .and_then(|function| {
+            function.encode_input(&[Token::Address(operator), Token::Uint(U256::from(100))])
+        })
+        .unwrap();
+    builder.add_call(Call3 {
+        target: harness.token,
+        allow_failure: false,
+        value: U256::zero(),
+        call_data: transfer.into(),
+    });
+    builder.with_borrower(borrower).with_owner(operator);
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    assert!(simulation.results().unwrap().iter().all(|result| result.success));
+
+    // Other accounts can't trigger flashloans
+    builder.with_owner(Address::random());
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert_eq!(simulation.output.to_vec(), id("Unauthorized()").to_vec());
+}
+
+/// Simulate a flashloan wrapping ether into a stubbed WETH through the borrower
+fn simulate_weth_wrap(
+    harness: &TestHarness<Provider<MockProvider>>,
+    snapshot: &mut StateSnapshot,
+    borrower: Address,
+) {
+    let weth = Address::random();
+    snapshot.insert_account(
+        weth,
+        AccountState { code: "0x60206000f3".parse().unwrap(), ..Default::default() },
+    );
+    let wrap = U256::exp10(17);
+    let mut builder = harness.builder(U256::exp10(18));
+    builder.calls = vec![harness.mint_call(borrower, mock_flash_fee(U256::exp10(18)))];
+    builder.with_borrower(borrower).with_weth_wrap(WethWrap::new(weth).with_wrap(wrap));
+
+    // The wrapped ether is sent by the owner to the payable entrypoint, then deposited
+    snapshot.set_balance(harness.owner, U256::zero());
+    assert!(builder.simulate(snapshot, &[]).is_err());
+    snapshot.set_balance(harness.owner, wrap);
+    let simulation = builder.simulate(snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    let results = simulation.results().unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.success));
+}
+
+#[test]
+fn test_simulate_weth_wrap() {
+    let (harness, mut snapshot) = snapshot_harness();
+    simulate_weth_wrap(&harness, &mut snapshot, harness.borrower);
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_solidity_borrower_weth_wrap() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let bytecode = BorrowerFlavor::Solidity.borrower_bytecode(LenderKind::Erc3156).unwrap();
+    let borrower = deploy_borrower(&harness, &mut snapshot, bytecode);
+    simulate_weth_wrap(&harness, &mut snapshot, borrower);
+}
+
+/// Simulate a flashloan through the borrower's profit guarded entrypoint
+fn simulate_profit_guard(
+    harness: &TestHarness<Provider<MockProvider>>,
+    snapshot: &StateSnapshot,
+    borrower: Address,
+) {
+    let amount = U256::exp10(18);
+    let profit = U256::exp10(15);
+    let simulate = |min_profit: U256| {
+        let mut builder = harness.builder(amount);
+        builder.calls = vec![harness.mint_call(borrower, mock_flash_fee(amount) + profit)];
+        builder.with_borrower(borrower);
+        builder.with_profit_guard(ProfitGuard::new(harness.token, min_profit));
+        builder.simulate(snapshot, &[]).unwrap()
+    };
+
+    // The minted profit clears the guard and stays with the borrower
+    let simulation = simulate(profit);
+    assert!(simulation.success, "{:?}", simulation.output);
+    assert_eq!(simulation.balance_deltas[&harness.token], I256::try_from(profit).unwrap());
+
+    // A higher minimum reverts the whole flashloan
+    let simulation = simulate(profit + 1);
+    assert!(!simulation.success);
+    let selector = id("InsufficientProfit(uint256,uint256)");
+    assert_eq!(simulation.output[..4], selector);
+}
+
+#[test]
+fn test_simulate_profit_guard() {
+    let (harness, snapshot) = snapshot_harness();
+    simulate_profit_guard(&harness, &snapshot, harness.borrower);
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_solidity_borrower_profit_guard() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let bytecode = BorrowerFlavor::Solidity.borrower_bytecode(LenderKind::Erc3156).unwrap();
+    let borrower = deploy_borrower(&harness, &mut snapshot, bytecode);
+    simulate_profit_guard(&harness, &snapshot, borrower);
+}
+
+/// Simulate flashloans through the borrower's entrypoint taking a precomputed repayment
+fn simulate_repayment(
+    harness: &TestHarness<Provider<MockProvider>>,
+    snapshot: &StateSnapshot,
+    borrower: Address,
+) {
+    let amount = U256::exp10(18);
+    let fee = mock_flash_fee(amount);
+
+    // A precomputed repayment is approved instead of the queried fee
+    let plan = |repayment: U256| {
+        FlashloanBuilder::with_client(Arc::clone(&harness.client))
+            .owner(harness.owner)
+            .borrower(borrower)
+            .token(harness.token)
+            .amount(amount)
+            .call(harness.mint_call(borrower, fee))
+            .repayment(repayment)
+            .build()
+            .unwrap()
+    };
+    let simulation = plan(amount + fee).simulate(snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    let simulation = plan(amount).simulate(snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert_eq!(simulation.output, overflow_panic());
+
+    // So is a fresh fee from the builder's fee cache
+    let mut builder = harness.builder(amount);
+    builder.calls = vec![harness.mint_call(borrower, fee)];
+    builder.with_borrower(borrower);
+    let mut cache = FeeCache::new(std::time::Duration::from_secs(60));
+    cache.insert(harness.lender, harness.token, amount, U256::zero());
+    builder.with_fee_cache(cache);
+    assert_eq!(builder.simulate(snapshot, &[]).unwrap().output, overflow_panic());
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_solidity_borrower_repayment() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let bytecode = BorrowerFlavor::Solidity.borrower_bytecode(LenderKind::Erc3156).unwrap();
+    let borrower = deploy_borrower(&harness, &mut snapshot, bytecode);
+    simulate_repayment(&harness, &snapshot, borrower);
+}
+
+/// Simulate flashloans through the borrower's overloads taking a trusted lender
+fn simulate_trusted_lender(
+    harness: &TestHarness<Provider<MockProvider>>,
+    snapshot: &StateSnapshot,
+    borrower: Address,
+) {
+    let call = |from: Address, name: &str, lender: Address| {
+        let data = FLASHLOAN_ABI
+            .function(name)
+            .and_then(|function| function.encode_input(&[Token::Address(lender)]))
+            .unwrap();
+        snapshot.call(from, borrower, data.into())
+    };
+
+    // The lender the borrower is deployed with is trusted, and only the owner trusts others
+    let other = Address::random();
+    let trusted =
+        |lender: Address| bool::decode(call(harness.owner, "trustedLenders", lender).unwrap());
+    assert!(trusted(harness.lender).unwrap());
+    assert!(!trusted(other).unwrap());
+    assert!(call(harness.owner, "trustLender", other).is_ok());
+    assert!(call(Address::random(), "trustLender", other).is_err());
+
+    // Borrowing from another lender calls the overload taking the lender
+    let mut builder = harness.builder(U256::exp10(18));
+    builder.calls = vec![harness.mint_call(borrower, mock_flash_fee(U256::exp10(18)))];
+    builder.with_borrower(borrower).with_lender(harness.lender);
+    assert_eq!(builder.borrow_from, Some(harness.lender));
+    let simulation = builder.simulate(snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    builder.with_lender(other);
+    let simulation = builder.simulate(snapshot, &[]).unwrap();
+    assert_eq!(simulation.output.to_vec(), id("UntrustedLender()").to_vec());
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_solidity_borrower_trusted_lender() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let bytecode = BorrowerFlavor::Solidity.borrower_bytecode(LenderKind::Erc3156).unwrap();
+    let borrower = deploy_borrower(&harness, &mut snapshot, bytecode);
+    simulate_trusted_lender(&harness, &snapshot, borrower);
+}
+
+#[test]
+fn test_simulate_entrypoints() {
+    let (harness, mut snapshot) = snapshot_harness();
+    let amount = U256::exp10(18);
+    simulate_repayment(&harness, &snapshot, harness.borrower);
+    simulate_trusted_lender(&harness, &snapshot, harness.borrower);
+
+    // The value of the calls is sent by the owner and forwarded by the borrower
+    let recipient = Address::random();
+    let value = U256::exp10(17);
+    let mut builder = harness.builder(amount);
+    builder.add_call(Call3 {
+        target: recipient,
+        allow_failure: false,
+        value,
+        call_data: Bytes::default(),
+    });
+    assert!(builder.simulate(&snapshot, &[]).is_err());
+    snapshot.set_balance(harness.owner, value);
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success, "{:?}", simulation.output);
+    assert_eq!(simulation.balance_deltas[&Address::zero()], I256::zero());
+
+    // Unless the borrower pays for the calls, which it can't
+    builder.with_call_value(CallValue::Borrower);
+    assert!(!builder.simulate(&snapshot, &[]).unwrap().success);
+}
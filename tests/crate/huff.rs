@@ -0,0 +1,65 @@
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+mod common;
+use common::huff::assemble_contract;
+
+#[test]
+fn test_huff_artifact() {
+    // The Huff borrower ships with assembled bytecode and the flashloan abi
+    let bytecode = huff_borrower_bytecode().unwrap();
+    assert!(!bytecode.is_empty());
+    assert_ne!(bytecode, FLASHLOAN_BYTECODE.clone());
+    assert_eq!(BorrowerFlavor::default(), BorrowerFlavor::Solidity);
+    assert_eq!(BorrowerFlavor::Huff.borrower_bytecode(LenderKind::Erc3156).unwrap(), bytecode);
+    assert_eq!(
+        BorrowerFlavor::Solidity.borrower_bytecode(LenderKind::Erc3156).unwrap(),
+        FLASHLOAN_BYTECODE.clone()
+    );
+
+    // Only the ERC-3156 borrower has a Huff implementation
+    for kind in [LenderKind::AaveV3, LenderKind::Balancer, LenderKind::UniswapV3] {
+        let err = BorrowerFlavor::Huff.borrower_bytecode(kind).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FlashloanError>(),
+            Some(FlashloanError::UnsupportedFlavor(name)) if name == kind.borrower_name()
+        ));
+    }
+}
+
+#[test]
+fn test_huff_artifact_source() {
+    // The bundled artifact is assembled from the contract in the repository
+    let (creation, runtime) = assemble_contract(include_str!("../../contracts/FlashBorrower.huff"));
+    assert_eq!(huff_borrower_bytecode().unwrap().to_vec(), creation);
+    let artifact: serde_json::Value =
+        serde_json::from_str(include_str!("../../src/HuffFlashBorrower.json")).unwrap();
+    let deployed: Bytes = artifact["deployedBytecode"]["object"].as_str().unwrap().parse().unwrap();
+    assert_eq!(deployed.to_vec(), runtime);
+}
+
+#[test]
+fn test_builder_borrower_flavor() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder = FlashloanBuilder::new(client, 1, Some(owner), Some(lender), None, None, None);
+    assert_eq!(builder.borrower_flavor, BorrowerFlavor::Solidity);
+    let solidity = builder.address_for(owner, lender).unwrap();
+
+    // The flavor selects the deployed bytecode
+    builder.with_borrower_flavor(BorrowerFlavor::Huff);
+    let huff = builder.address_for(owner, lender).unwrap();
+    assert_ne!(huff, solidity);
+    let bytecode = huff_borrower_bytecode().unwrap();
+    assert_eq!(huff, create2_borrower_address(builder.create2_factory, &bytecode, lender, owner));
+
+    // Programs have no Huff borrower
+    builder.with_program(Program::default());
+    let err = builder.address_for(owner, lender).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::UnsupportedFlavor(name)) if name == PROGRAM_BORROWER_NAME
+    ));
+}
@@ -0,0 +1,40 @@
+use ethers::prelude::*;
+use std::{str::FromStr, sync::Arc};
+
+use flashloan_rs::prelude::*;
+
+#[test]
+fn test_lender_kinds() {
+    // The ERC-3156 borrower ships with compiled bytecode
+    assert_eq!(LenderKind::default(), LenderKind::Erc3156);
+    assert!(!LenderKind::Erc3156.borrower_bytecode().unwrap().is_empty());
+
+    // Each lender reports its kind
+    let address = Address::random();
+    assert_eq!(Lender::<Provider<Http>>::kind(&AaveV3Lender(address)), LenderKind::AaveV3);
+    assert_eq!(Lender::<Provider<Http>>::kind(&BalancerLender(address)), LenderKind::Balancer);
+    assert_eq!(Lender::<Provider<Http>>::kind(&UniswapV3Lender(address)), LenderKind::UniswapV3);
+    assert_eq!(
+        Lender::<Provider<Http>>::address(&Erc3156Lender::default()),
+        Address::from_str(DSS_FLASH).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_builder_flash_lender() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let mut builder = FlashloanBuilder::new(Arc::clone(&client), 1, None, None, None, None, None);
+    assert_eq!(builder.lender_kind, LenderKind::Erc3156);
+
+    // Selecting a lender sets both the address and protocol
+    let vault = Address::random();
+    builder.with_flash_lender(BalancerLender(vault));
+    assert_eq!(builder.lender, Some(vault));
+    assert_eq!(builder.lender_kind, LenderKind::Balancer);
+
+    // Without a lender, the registry must know a lender of the kind on the chain
+    builder.lender = None;
+    builder.lender_kind = LenderKind::UniswapV3;
+    let err = builder.deploy(None, Some(Address::random())).await.err().unwrap();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingLender)));
+}
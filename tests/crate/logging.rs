@@ -0,0 +1,55 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+};
+use std::sync::Arc;
+use tracing_test::traced_test;
+
+use flashloan_rs::prelude::*;
+
+#[traced_test]
+#[tokio::test]
+async fn test_deploy_events() {
+    let (provider, _mock) = Provider::mocked();
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder =
+        FlashloanBuilder::new(Arc::new(provider), 1, Some(owner), Some(lender), None, None, None);
+
+    // The mock provider has no responses, so the deployment fails once sent
+    let err = builder.deploy(None, None).await.err().unwrap();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::ContractDeployError)
+    ));
+    assert!(logs_contain("deploy{chain_id=1"));
+    assert!(logs_contain(&format!("Resolved lender lender={:?}", lender)));
+    assert!(logs_contain(&format!("Resolved owner owner={:?}", owner)));
+    assert!(logs_contain("Sending borrower deployment"));
+    assert!(!logs_contain("Deployed borrower"));
+}
+
+#[traced_test]
+#[tokio::test]
+async fn test_call_events() {
+    let (provider, mock) = Provider::mocked();
+    let (borrower, token) = (Address::random(), Address::random());
+    let plan = FlashloanBuilder::with_client(Arc::new(provider))
+        .chain_id(1)
+        .borrower(borrower)
+        .token(token)
+        .amount(U256::exp10(18))
+        .build()
+        .unwrap();
+
+    let result = |success| Token::Tuple(vec![Token::Bool(success), Token::Bytes(vec![])]);
+    let results = encode(&[Token::Array(vec![result(true), result(false)])]);
+    mock.push::<Bytes, _>(Bytes::from(results)).unwrap();
+    plan.call().await.unwrap();
+
+    // The span carries the loan, and the event the results
+    assert!(logs_contain(&format!(
+        "flashloan_call{{chain_id=1 borrower={:?} token={:?} amount=1000000000000000000}}",
+        borrower, token
+    )));
+    assert!(logs_contain("Flashloan call succeeded calls=2 failed=1"));
+}
@@ -0,0 +1,119 @@
+use ethers::{
+    abi::{Token, Tokenizable, Tokenize},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+fn legs() -> Vec<FlashloanLeg> {
+    vec![
+        FlashloanLeg::new(LenderKind::Erc3156, Address::random(), Address::random(), U256::from(1)),
+        FlashloanLeg::from_lender::<Provider<Http>, _>(
+            &BalancerLender(Address::random()),
+            Address::random(),
+            U256::exp10(18),
+        ),
+    ]
+}
+
+#[test]
+fn test_leg_tokens() {
+    let legs = legs();
+    assert_eq!(legs[1].kind, LenderKind::Balancer);
+
+    // Legs are encoded as the contract's `Leg` struct
+    let token = legs[1].into_token();
+    assert_eq!(
+        token,
+        Token::Tuple(vec![
+            Token::Uint(2.into()),
+            Token::Address(legs[1].lender),
+            Token::Address(legs[1].token),
+            Token::Uint(legs[1].amount),
+        ])
+    );
+    assert_eq!(FlashloanLeg::from_token(token).unwrap(), legs[1]);
+    assert!(FlashloanLeg::from_token(Token::Bool(true)).is_err());
+
+    // The encoded entrypoint round trips through the abi
+    let function = MULTI_FLASHLOAN_ABI.function("flashBorrowMulti").unwrap();
+    let calls = vec![Call3 {
+        target: Address::random(),
+        allow_failure: true,
+        value: U256::zero(),
+        call_data: Bytes::from(vec![1, 2, 3]),
+    }];
+    let data = function.encode_input(&(legs.clone(), calls.clone()).into_tokens()).unwrap();
+    let decoded = function.decode_input(&data[4..]).unwrap();
+    assert_eq!(Vec::<FlashloanLeg>::from_token(decoded[0].clone()).unwrap(), legs);
+
+    // The typed bindings encode the same calldata
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let borrower = IMultiFlashBorrower::new(Address::random(), client);
+    let typed = legs.iter().copied().map(Leg::from).collect();
+    assert_eq!(borrower.flash_borrow_multi(typed, calls).calldata().unwrap().to_vec(), data);
+}
+
+#[test]
+fn test_multi_borrower_abi() {
+    // The nested borrower extends the shared flashloan abi with every lender callback
+    for name in [
+        "flashBorrow",
+        "flashBorrowWithProfit",
+        "flashBorrowMulti",
+        "flashBorrowMultiWithProfit",
+        "onFlashLoan",
+        "executeOperation",
+        "receiveFlashLoan",
+        "uniswapV3FlashCallback",
+        "withdrawToken",
+        "withdrawEth",
+    ] {
+        assert!(MULTI_FLASHLOAN_ABI.function(name).is_ok(), "missing {}", name);
+    }
+
+    // The typed bindings match the artifact
+    for function in IMULTIFLASHBORROWER_ABI.functions() {
+        assert_eq!(MULTI_FLASHLOAN_ABI.function(&function.name).unwrap(), function);
+    }
+}
+
+#[test]
+fn test_plan_with_legs() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let legs = legs();
+
+    // The token and amount default to the first leg's
+    let plan = FlashloanBuilder::with_client(Arc::clone(&client))
+        .borrower(Address::random())
+        .legs(legs.clone())
+        .build()
+        .unwrap();
+    assert_eq!(plan.legs(), legs.as_slice());
+    assert_eq!(plan.token(), legs[0].token);
+    assert_eq!(plan.amount(), legs[0].amount);
+
+    let mut builder = FlashloanBuilder::new(client, 1, None, None, None, None, None);
+    builder.with_borrower(Address::random());
+    for leg in &legs {
+        builder.add_leg(*leg);
+    }
+    assert_eq!(builder.plan().unwrap().legs(), legs.as_slice());
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_multi_borrower_address() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let mut builder = FlashloanBuilder::new(client, 1, None, None, None, None, None);
+    let (owner, lender) = (Address::random(), Address::random());
+    let single = builder.address_for(owner, lender).unwrap();
+
+    // Legs deploy the nested borrower
+    builder.add_leg(legs()[0]);
+    let bytecode = multi_borrower_bytecode().unwrap();
+    let nested = builder.address_for(owner, lender).unwrap();
+    assert_ne!(nested, single);
+    assert_eq!(nested, create2_borrower_address(builder.create2_factory, &bytecode, lender, owner));
+}
@@ -0,0 +1,157 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+    utils::id,
+};
+use std::{sync::Arc, time::Duration};
+
+use flashloan_rs::prelude::*;
+
+mod common;
+use common::push_transaction;
+
+fn operated_builder() -> (FlashloanBuilder<Provider<MockProvider>>, MockProvider) {
+    let (provider, mock) = Provider::mocked();
+    let provider = provider.interval(Duration::from_millis(10));
+    let builder = FlashloanBuilder::new(
+        Arc::new(provider),
+        1,
+        None,
+        None,
+        None,
+        None,
+        Some(Address::random()),
+    );
+    (builder, mock)
+}
+
+#[test]
+fn test_operated_artifact() {
+    // The operated borrower extends the flashloan abi
+    for function in FLASHLOAN_ABI.functions() {
+        assert!(OPERATED_FLASHLOAN_ABI.function(&function.name).is_ok());
+    }
+    let selectors = [
+        ("addOperator", "addOperator(address)"),
+        ("removeOperator", "removeOperator(address)"),
+        ("isOperator", "isOperator(address)"),
+        ("transferOwnership", "transferOwnership(address)"),
+        ("acceptOwnership", "acceptOwnership()"),
+        ("pendingOwner", "pendingOwner()"),
+    ];
+    for (name, signature) in selectors {
+        assert_eq!(OPERATED_FLASHLOAN_ABI.function(name).unwrap().short_signature(), id(signature));
+    }
+
+    // The typed bindings match the artifact
+    for function in IOPERATEDFLASHBORROWER_ABI.functions() {
+        let artifact = OPERATED_FLASHLOAN_ABI.function(&function.name).unwrap();
+        assert_eq!(artifact.signature(), function.signature());
+        assert_eq!(artifact.state_mutability, function.state_mutability);
+    }
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_operated_borrower_address() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder = FlashloanBuilder::new(client, 1, Some(owner), Some(lender), None, None, None);
+    let single = builder.address_for(owner, lender).unwrap();
+
+    // The operated borrower is deployed instead of the ERC-3156 borrower
+    builder.with_operated_borrower(true);
+    let bytecode = operated_borrower_bytecode().unwrap();
+    let operated = builder.address_for(owner, lender).unwrap();
+    assert_ne!(operated, single);
+    assert_eq!(
+        operated,
+        create2_borrower_address(builder.create2_factory, &bytecode, lender, owner)
+    );
+}
+
+#[test]
+fn test_builder_operated_borrower() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let (owner, lender) = (Address::random(), Address::random());
+    let mut builder = FlashloanBuilder::new(client, 1, Some(owner), Some(lender), None, None, None);
+    assert!(!builder.operated_borrower);
+    assert!(builder.address_for(owner, lender).is_ok());
+
+    // Only the Solidity borrower has operators
+    builder.with_operated_borrower(true);
+    builder.with_borrower_flavor(BorrowerFlavor::Huff);
+    let err = builder.address_for(owner, lender).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::UnsupportedFlavor(name)) if name == OPERATED_BORROWER_NAME
+    ));
+
+    // Only the ERC-3156 borrower has operators
+    builder.with_borrower_flavor(BorrowerFlavor::Solidity);
+    builder.lender_kind = LenderKind::Balancer;
+    let err = builder.address_for(owner, lender).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::UnsupportedOperators(name))
+            if name == LenderKind::Balancer.borrower_name()
+    ));
+
+    builder.lender_kind = LenderKind::Erc3156;
+    builder.with_program(Program::default());
+    let err = builder.address_for(owner, lender).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::UnsupportedOperators(name)) if name == PROGRAM_BORROWER_NAME
+    ));
+}
+
+#[tokio::test]
+async fn test_operators() {
+    let (builder, mock) = operated_builder();
+    let operator = Address::random();
+
+    let tx_hash = H256::random();
+    push_transaction(&mock, tx_hash);
+    let receipt = builder.add_operator(operator).await.unwrap();
+    assert_eq!(receipt.transaction_hash, tx_hash);
+
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Bool(true)]))).unwrap();
+    assert!(builder.is_operator(operator).await.unwrap());
+
+    let tx_hash = H256::random();
+    push_transaction(&mock, tx_hash);
+    let receipt = builder.remove_operator(operator).await.unwrap();
+    assert_eq!(receipt.transaction_hash, tx_hash);
+}
+
+#[tokio::test]
+async fn test_transfer_ownership() {
+    let (mut builder, mock) = operated_builder();
+    let new_owner = Address::random();
+
+    let tx_hash = H256::random();
+    push_transaction(&mock, tx_hash);
+    let receipt = builder.transfer_ownership(new_owner).await.unwrap();
+    assert_eq!(receipt.transaction_hash, tx_hash);
+    assert_eq!(builder.owner, None);
+
+    // The accepted pending owner becomes the builder's owner
+    push_transaction(&mock, H256::random());
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Address(new_owner)]))).unwrap();
+    builder.accept_ownership().await.unwrap();
+    assert_eq!(builder.owner, Some(new_owner));
+}
+
+#[tokio::test]
+async fn test_operators_without_borrower() {
+    let (provider, _mock) = Provider::mocked();
+    let mut builder = FlashloanBuilder::new(Arc::new(provider), 1, None, None, None, None, None);
+    let missing_borrower =
+        |err: anyhow::Error| matches!(err.downcast_ref(), Some(FlashloanError::MissingBorrower));
+    assert!(missing_borrower(builder.add_operator(Address::random()).await.unwrap_err()));
+    assert!(missing_borrower(builder.remove_operator(Address::random()).await.unwrap_err()));
+    assert!(missing_borrower(builder.is_operator(Address::random()).await.unwrap_err()));
+    assert!(missing_borrower(builder.transfer_ownership(Address::random()).await.unwrap_err()));
+    assert!(missing_borrower(builder.accept_ownership().await.unwrap_err()));
+}
@@ -0,0 +1,193 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+    utils::keccak256,
+};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+fn transfer_log(token: Address, from: Address, to: Address, amount: u64) -> Log {
+    Log {
+        address: token,
+        topics: vec![
+            H256::from(keccak256("Transfer(address,address,uint256)")),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: encode(&[Token::Uint(amount.into())]).into(),
+        ..Default::default()
+    }
+}
+
+fn flash_loan_log(
+    lender: Address,
+    receiver: Address,
+    token: Address,
+    amount: u64,
+    fee: u64,
+) -> Log {
+    Log {
+        address: lender,
+        topics: vec![
+            H256::from(keccak256("FlashLoan(address,address,uint256,uint256)")),
+            H256::from(receiver),
+        ],
+        data: encode(&[Token::Address(token), Token::Uint(amount.into()), Token::Uint(fee.into())])
+            .into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_flash_loan_events() {
+    let (lender, borrower, token) = (Address::random(), Address::random(), Address::random());
+    let balancer = Log {
+        address: lender,
+        topics: vec![
+            H256::from(keccak256("FlashLoan(address,address,uint256,uint256)")),
+            H256::from(borrower),
+            H256::from(token),
+        ],
+        data: encode(&[Token::Uint(500.into()), Token::Uint(0.into())]).into(),
+        ..Default::default()
+    };
+    let aave = Log {
+        address: lender,
+        topics: vec![
+            H256::from(keccak256(
+                "FlashLoan(address,address,address,uint256,uint8,uint256,uint16)",
+            )),
+            H256::from(borrower),
+            H256::from(token),
+            H256::zero(),
+        ],
+        data: encode(&[
+            Token::Address(borrower),
+            Token::Uint(1_000.into()),
+            Token::Uint(0.into()),
+            Token::Uint(5.into()),
+        ])
+        .into(),
+        ..Default::default()
+    };
+    let receipt = TransactionReceipt {
+        logs: vec![
+            flash_loan_log(lender, borrower, token, 100, 1),
+            transfer_log(token, lender, borrower, 100),
+            balancer,
+            aave,
+        ],
+        ..Default::default()
+    };
+
+    // Each lender's event layout is decoded, other logs are skipped
+    let events = flash_loan_events(&receipt);
+    assert_eq!(events.len(), 3);
+    assert_eq!(
+        events[0],
+        FlashLoanEvent { lender, receiver: borrower, token, amount: 100.into(), fee: 1.into() }
+    );
+    assert_eq!((events[1].amount, events[1].fee), (500.into(), 0.into()));
+    assert_eq!((events[2].amount, events[2].fee), (1_000.into(), 5.into()));
+    assert!(events.iter().all(|event| event.token == token && event.receiver == borrower));
+}
+
+#[test]
+fn test_outcome_from_receipt() {
+    let (lender, borrower, dex) = (Address::random(), Address::random(), Address::random());
+    let (dai, weth) = (Address::random(), Address::random());
+    let receipt = TransactionReceipt {
+        transaction_hash: H256::random(),
+        status: Some(1.into()),
+        gas_used: Some(100_000.into()),
+        effective_gas_price: Some(10.into()),
+        logs: vec![
+            transfer_log(dai, lender, borrower, 1_000),
+            flash_loan_log(lender, borrower, dai, 1_000, 10),
+            transfer_log(dai, borrower, dex, 1_000),
+            transfer_log(dai, dex, borrower, 1_050),
+            transfer_log(weth, dex, Address::random(), 7),
+            transfer_log(dai, borrower, lender, 1_010),
+        ],
+        ..Default::default()
+    };
+
+    let outcome = FlashloanOutcome::from_receipt(&receipt, borrower).unwrap();
+    assert!(outcome.success);
+    assert_eq!(outcome.transaction_hash, receipt.transaction_hash);
+    assert_eq!(outcome.fees(dai), 10.into());
+    assert_eq!(outcome.fees(weth), U256::zero());
+
+    // Only the borrower's transfers are counted
+    assert_eq!(outcome.transfers_in.len(), 2);
+    assert_eq!(outcome.transfers_out.len(), 2);
+    assert_eq!(outcome.net_change(dai), I256::from(40));
+    assert_eq!(outcome.net_change(weth), I256::zero());
+    assert!(!outcome.net_changes.contains_key(&weth));
+    assert_eq!(outcome.gas_cost, 1_000_000.into());
+    assert_eq!(outcome.profit, None);
+
+    // Quoted in DAI at 2 DAI per ether, the gas cost is 2 * 1e6 wei
+    let outcome = outcome.with_quote(dai, U256::exp10(18) * 2);
+    let profit = outcome.profit.unwrap();
+    assert_eq!(profit.gain, I256::from(40));
+    assert_eq!(profit.gas_cost_in_token, 2_000_000.into());
+    assert_eq!(profit.net_profit, I256::from(40 - 2_000_000));
+}
+
+#[test]
+fn test_outcome_net_change_bounds() {
+    let (borrower, token, other) = (Address::random(), Address::random(), Address::random());
+    let transfer = |from: Address, to: Address, amount: U256| {
+        let mut log = transfer_log(token, from, to, 0);
+        log.data = encode(&[Token::Uint(amount)]).into();
+        log
+    };
+    let outcome = |logs: Vec<Log>| {
+        let receipt = TransactionReceipt { logs, ..Default::default() };
+        FlashloanOutcome::from_receipt(&receipt, borrower)
+    };
+    let half = U256::one() << 255;
+
+    // Changes up to the int256 bounds are exact, including large balances moving out
+    let max = outcome(vec![transfer(other, borrower, half - 1)]).unwrap();
+    assert_eq!(max.net_change(token), I256::MAX);
+    let min = outcome(vec![transfer(borrower, other, half)]).unwrap();
+    assert_eq!(min.net_change(token), I256::MIN);
+    let net = outcome(vec![transfer(other, borrower, U256::MAX), transfer(borrower, other, half)]);
+    assert_eq!(net.unwrap().net_change(token), I256::MAX);
+
+    // Beyond them the outcome can't be decoded, rather than wrapping around
+    let overflow = |err: anyhow::Error| {
+        matches!(err.downcast_ref(), Some(FlashloanError::BalanceDeltaOverflow { .. }))
+    };
+    assert!(overflow(outcome(vec![transfer(other, borrower, half)]).unwrap_err()));
+    assert!(overflow(outcome(vec![transfer(borrower, other, half + 1)]).unwrap_err()));
+    let twice = vec![transfer(other, borrower, U256::MAX), transfer(other, borrower, U256::one())];
+    assert!(overflow(outcome(twice).unwrap_err()));
+}
+
+#[test]
+fn test_builder_outcome() {
+    let (provider, _mock) = Provider::mocked();
+    let (borrower, weth) = (Address::random(), Address::random());
+    let mut builder = FlashloanBuilder::new(Arc::new(provider), 1, None, None, None, None, None);
+    let receipt = TransactionReceipt {
+        status: Some(0.into()),
+        logs: vec![transfer_log(weth, Address::random(), borrower, 5)],
+        ..Default::default()
+    };
+
+    let err = builder.outcome(&receipt).unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingBorrower)));
+
+    // The profit guard token is the quote token
+    builder.with_borrower(borrower);
+    let outcome = builder.outcome(&receipt).unwrap();
+    assert!(!outcome.success);
+    assert_eq!(outcome.profit, None);
+    builder.with_profit_guard(ProfitGuard::new(weth, U256::one()));
+    let profit = builder.outcome(&receipt).unwrap().profit.unwrap();
+    assert_eq!((profit.token, profit.net_profit), (weth, I256::from(5)));
+}
@@ -0,0 +1,98 @@
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+fn client() -> Arc<Provider<Http>> {
+    Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap())
+}
+
+#[test]
+fn test_build_plan_validates_parameters() {
+    let token = Address::random();
+    let borrower = Address::random();
+
+    // Required parameters are checked when the plan is built
+    let err = FlashloanBuilder::with_client(client()).borrower(borrower).build().unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingToken)));
+    let err = FlashloanBuilder::with_client(client())
+        .borrower(borrower)
+        .token(token)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingAmount)));
+    let err = FlashloanBuilder::with_client(client())
+        .token(token)
+        .amount(U256::one())
+        .build()
+        .unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingBorrower)));
+
+    let vault = Address::random();
+    let call = Call3 {
+        target: token,
+        allow_failure: false,
+        value: U256::zero(),
+        call_data: Bytes::default(),
+    };
+    let plan = FlashloanBuilder::with_client(client())
+        .chain_id(1)
+        .flash_lender(BalancerLender(vault))
+        .borrower(borrower)
+        .token(token)
+        .amount(U256::exp10(18))
+        .call(call.clone())
+        .calls(vec![call.clone()])
+        .build()
+        .unwrap();
+    assert_eq!(plan.chain_id(), Some(1));
+    assert_eq!(plan.lender(), Some(vault));
+    assert_eq!(plan.lender_kind(), LenderKind::Balancer);
+    assert_eq!(plan.borrower(), borrower);
+    assert_eq!(plan.token(), token);
+    assert_eq!(plan.amount(), U256::exp10(18));
+    assert_eq!(plan.calls(), &[call.clone(), call][..]);
+}
+
+#[test]
+fn test_plan_from_builder() {
+    let token = Address::random();
+    let borrower = Address::random();
+    let mut builder = FlashloanBuilder::new(client(), 1, None, None, Some(token), None, None);
+    let err = builder.plan().unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingAmount)));
+
+    // The plan is a snapshot, unaffected by later changes to the builder
+    builder.with_amount(U256::one()).with_borrower(borrower);
+    let plan = builder.plan().unwrap();
+    builder.with_amount(U256::from(2));
+    assert_eq!(plan.amount(), U256::one());
+    assert_eq!(plan.borrower(), borrower);
+    assert_eq!(plan.chain_id(), Some(1));
+}
+
+#[tokio::test]
+async fn test_plan_is_shared_across_tasks() {
+    let guard = ProfitGuard::new(Address::random(), U256::from(10));
+    let plan = FlashloanBuilder::with_client(client())
+        .borrower(Address::random())
+        .token(Address::random())
+        .amount(U256::one())
+        .profit_guard(guard)
+        .build()
+        .unwrap();
+
+    // Clones of the plan are checked concurrently without mutable access
+    let handles = (0..4)
+        .map(|i| {
+            let plan = plan.clone();
+            tokio::spawn(async move {
+                plan.check_profit_with_gas_cost(I256::from(10 + i), U256::zero()).await
+            })
+        })
+        .collect::<Vec<_>>();
+    for (i, handle) in handles.into_iter().enumerate() {
+        let check = handle.await.unwrap().unwrap();
+        assert_eq!(check.net_profit, I256::from(10 + i as i64));
+    }
+}
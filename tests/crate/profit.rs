@@ -0,0 +1,63 @@
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+#[test]
+fn test_profit_guard_check() {
+    let token = Address::random();
+    let guard = ProfitGuard::new(token, U256::from(100));
+
+    // The gain must cover the fee, the gas cost and the minimum profit
+    let check = guard.check(I256::from(1_000), U256::from(400), U256::from(500)).unwrap();
+    assert_eq!(check.gas_cost_in_token, U256::from(500));
+    assert_eq!(check.net_profit, I256::from(100));
+
+    let err = guard.check(I256::from(1_000), U256::from(401), U256::from(500)).unwrap_err();
+    match err.downcast_ref::<FlashloanError>() {
+        Some(FlashloanError::Unprofitable { net_profit, min_profit }) => {
+            assert_eq!(*net_profit, I256::from(99));
+            assert_eq!(*min_profit, U256::from(100));
+        }
+        e => panic!("unexpected error: {e:?}"),
+    }
+
+    // A loss is never profitable
+    assert!(guard.check(I256::from(-1), U256::zero(), U256::zero()).is_err());
+}
+
+#[test]
+fn test_profit_guard_eth_price() {
+    // Gas is converted to profit token units at the ether price
+    let guard = ProfitGuard::new(Address::random(), U256::zero())
+        .with_eth_price(U256::exp10(18) * U256::from(1_500));
+    let gas_cost = U256::exp10(15);
+    assert_eq!(guard.gas_cost_in_token(gas_cost), U256::exp10(18) * U256::from(3) / 2);
+
+    let gain = I256::from_raw(U256::exp10(18));
+    assert!(guard.check(gain, U256::zero(), gas_cost).is_err());
+    assert!(guard.check(gain * I256::from(2), U256::zero(), gas_cost).is_ok());
+}
+
+#[tokio::test]
+async fn test_builder_profit_guard() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let token = Address::random();
+    let mut builder =
+        FlashloanBuilder::new(client, 1, None, None, Some(token), Some(U256::one()), None);
+
+    // Checking profit requires a guard
+    let err = builder.check_profit_with_gas_cost(I256::zero(), U256::zero()).await.err().unwrap();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::MissingProfitGuard)
+    ));
+
+    // The fee is not queried when the profit is realized in another token
+    let guard = ProfitGuard::new(Address::random(), U256::from(10));
+    builder.with_profit_guard(guard);
+    assert_eq!(builder.profit_guard, Some(guard));
+    let check = builder.check_profit_with_gas_cost(I256::from(10), U256::zero()).await.unwrap();
+    assert_eq!(check.fee, U256::zero());
+    assert!(builder.check_profit_with_gas_cost(I256::from(9), U256::zero()).await.is_err());
+}
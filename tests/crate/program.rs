@@ -0,0 +1,205 @@
+use ethers::{
+    abi::{encode, Function, HumanReadableParser, Token},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+fn function(signature: &str) -> Function {
+    HumanReadableParser::parse_function(signature).unwrap()
+}
+
+/// Encodes a command's calldata from the state, as the `ProgramFlashBorrower.buildInputs`
+fn build_inputs(command: &[u8; 32], state: &[Bytes]) -> Vec<u8> {
+    let first = (command[4] & FLAG_CALL_WITH_VALUE) as usize;
+    let slots: Vec<u8> =
+        command[5 + first..11].iter().copied().take_while(|idx| *idx != IDX_END).collect();
+    let head_size: usize = slots
+        .iter()
+        .map(|idx| if idx & IDX_DYNAMIC == 0 { state[*idx as usize].len() } else { 32 })
+        .sum();
+    let (mut heads, mut tails) = (command[..4].to_vec(), vec![]);
+    for idx in slots {
+        if idx & IDX_DYNAMIC == 0 {
+            heads.extend_from_slice(&state[idx as usize]);
+        } else {
+            heads.extend(encode(&[Token::Uint((head_size + tails.len()).into())]));
+            tails.extend_from_slice(&state[(idx & !IDX_DYNAMIC) as usize]);
+        }
+    }
+    heads.extend(tails);
+    heads
+}
+
+#[test]
+fn test_program_pipes_return_values() {
+    let balance_of = function("function balanceOf(address) external view returns (uint256)");
+    let transfer = function("function transfer(address,uint256) external returns (bool)");
+    let (token, borrower, recipient) = (Address::random(), Address::random(), Address::random());
+
+    let mut planner = CommandPlanner::new();
+    let balance = planner.call(token, &balance_of, vec![Token::Address(borrower).into()]).unwrap();
+    let sent = planner
+        .call(token, &transfer, vec![Token::Address(recipient).into(), (&balance).into()])
+        .unwrap();
+    assert_eq!((balance.command(), sent.command()), (0, 1));
+    assert_eq!(planner.len(), 2);
+
+    let program = planner.plan().unwrap();
+    // The borrower, the recipient and the piped balance
+    assert_eq!(program.state.len(), 3);
+    assert_eq!(program.state[0].to_vec(), encode(&[Token::Address(borrower)]));
+    assert_eq!(program.state[1].to_vec(), encode(&[Token::Address(recipient)]));
+    assert!(program.state[2].is_empty());
+
+    let first = program.commands[0];
+    assert_eq!(first[..4], balance_of.short_signature());
+    assert_eq!(first[4], 0);
+    assert_eq!(first[5..11], [0, IDX_END, IDX_END, IDX_END, IDX_END, IDX_END]);
+    // The balance is written to the slot read by the transfer
+    assert_eq!(first[11], 2);
+    assert_eq!(Address::from_slice(&first[12..]), token);
+
+    let second = program.commands[1];
+    assert_eq!(second[5..11], [1, 2, IDX_END, IDX_END, IDX_END, IDX_END]);
+    // The unused return value is discarded
+    assert_eq!(second[11], IDX_END);
+}
+
+#[test]
+fn test_program_encodes_inputs() {
+    let swap = function(
+        "function swap(address,bytes,uint256,address[]) external payable returns (uint256)",
+    );
+    let target = Address::random();
+    let args = vec![
+        Token::Address(Address::random()),
+        Token::Bytes(vec![1, 2, 3]),
+        Token::Uint(U256::exp10(18)),
+        Token::Array(vec![Token::Address(Address::random()), Token::Address(Address::random())]),
+    ];
+
+    let mut planner = CommandPlanner::new();
+    planner
+        .call_with_value(
+            target,
+            &swap,
+            Token::Uint(U256::exp10(18)).into(),
+            args.iter().cloned().map(CommandArg::from).collect(),
+        )
+        .unwrap();
+    let program = planner.plan().unwrap();
+    let command = program.commands[0];
+
+    // The value shares its slot with the equal amount argument
+    assert_eq!(command[4], FLAG_CALL_WITH_VALUE);
+    assert_eq!(command[5..11], [0, 1, 2 | IDX_DYNAMIC, 0, 3 | IDX_DYNAMIC, IDX_END]);
+    assert_eq!(program.state.len(), 4);
+
+    // The on-chain encoding matches the abi encoding
+    assert_eq!(build_inputs(&command, &program.state), swap.encode_input(&args).unwrap());
+}
+
+#[test]
+fn test_program_validation() {
+    let balance_of = function("function balanceOf(address) external view returns (uint256)");
+    let transfer = function("function transfer(address,uint256) external returns (bool)");
+    let name = function("function name() external view returns (string)");
+    let target = Address::random();
+
+    let construction_error = |err: anyhow::Error| {
+        matches!(
+            err.downcast_ref::<FlashloanError>(),
+            Some(FlashloanError::CallConstructionError(_))
+        )
+    };
+
+    let mut planner = CommandPlanner::new();
+    // Argument count and types are checked
+    assert!(construction_error(planner.call(target, &balance_of, vec![]).unwrap_err()));
+    assert!(construction_error(
+        planner.call(target, &balance_of, vec![Token::Bool(true).into()]).unwrap_err()
+    ));
+
+    // Return values must fit a slot and match the input type
+    let sent = planner
+        .call(target, &transfer, vec![Token::Address(target).into(), Token::Uint(1.into()).into()])
+        .unwrap();
+    assert!(construction_error(
+        planner
+            .call(target, &transfer, vec![Token::Address(target).into(), sent.into()])
+            .unwrap_err()
+    ));
+    let string = planner.call(target, &name, vec![]).unwrap();
+    assert!(construction_error(
+        planner.call(target, &balance_of, vec![string.into()]).unwrap_err()
+    ));
+
+    // Return values can't be used from another planner
+    let mut other = CommandPlanner::new();
+    let balance = planner.call(target, &name, vec![]).unwrap();
+    assert!(construction_error(other.call(target, &balance_of, vec![balance.into()]).unwrap_err()));
+
+    // Only the valid commands are planned
+    assert_eq!(planner.len(), 3);
+    assert!(other.is_empty());
+}
+
+#[test]
+fn test_plan_with_program() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let balance_of = function("function balanceOf(address) external view returns (uint256)");
+    let mut planner = CommandPlanner::new();
+    planner
+        .call(Address::random(), &balance_of, vec![Token::Address(Address::random()).into()])
+        .unwrap();
+    let program = planner.plan().unwrap();
+
+    let plan = FlashloanBuilder::with_client(Arc::clone(&client))
+        .borrower(Address::random())
+        .token(Address::random())
+        .amount(U256::exp10(18))
+        .program(program.clone())
+        .build()
+        .unwrap();
+    assert_eq!(plan.program(), Some(&program));
+
+    // Programs run in a single loan borrower
+    let leg =
+        FlashloanLeg::new(LenderKind::Erc3156, Address::random(), Address::random(), 1.into());
+    let err = FlashloanBuilder::with_client(Arc::clone(&client))
+        .borrower(Address::random())
+        .leg(leg)
+        .program(program.clone())
+        .build()
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::CallConstructionError(_))
+    ));
+}
+
+#[test]
+#[ignore = "the Solidity artifacts must be rebuilt with scripts/build-artifacts.sh"]
+fn test_program_borrower_address() {
+    for name in ["flashBorrow", "flashBorrowProgram", "flashBorrowProgramWithProfit", "onFlashLoan"]
+    {
+        assert!(PROGRAM_FLASHLOAN_ABI.function(name).is_ok(), "missing {}", name);
+    }
+
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let mut builder = FlashloanBuilder::new(client, 1, None, None, None, None, None);
+    let (owner, lender) = (Address::random(), Address::random());
+    let single = builder.address_for(owner, lender).unwrap();
+
+    // Programs deploy the program borrower
+    builder.with_program(Program::default());
+    let bytecode = program_borrower_bytecode().unwrap();
+    let program = builder.address_for(owner, lender).unwrap();
+    assert_ne!(program, single);
+    assert_eq!(
+        program,
+        create2_borrower_address(builder.create2_factory, &bytecode, lender, owner)
+    );
+}
@@ -0,0 +1,133 @@
+use ethers::prelude::*;
+use std::{str::FromStr, sync::Arc};
+
+use flashloan_rs::prelude::*;
+
+const REGISTRY_TOML: &str = r#"
+[chains.31337]
+name = "anvil"
+tokens = { DAI = "0x6B175474E89094C44Da98b954EedeAC495271d0F" }
+
+[[chains.31337.lenders]]
+name = "mock-lender"
+kind = "erc3156"
+address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+fee = { bps = 9 }
+default = true
+
+[[chains.1.lenders]]
+name = "balancer"
+kind = "balancer"
+address = "0x0000000000000000000000000000000000000001"
+fee = "free"
+"#;
+
+#[test]
+fn test_builtin_registry() {
+    let registry = ChainRegistry::builtin();
+    let dai = Address::from_str("0x6B175474E89094C44Da98b954EedeAC495271d0F").unwrap();
+
+    // Mainnet defaults to the MakerDAO flash lender
+    let lender = registry.default_lender(1, LenderKind::Erc3156).unwrap();
+    assert_eq!(lender.address, Address::from_str(DSS_FLASH).unwrap());
+    assert!(lender.supports(dai));
+    assert!(!lender.supports(Address::random()));
+    assert_eq!(registry.token(1, "DAI"), Some(dai));
+
+    // Other protocols are looked up by kind
+    let lender = registry.default_lender(1, LenderKind::Balancer).unwrap();
+    assert_eq!(lender.kind, LenderKind::Balancer);
+    let err = registry.default_lender(1, LenderKind::UniswapV3).unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingLender)));
+
+    // Chains without an ERC-3156 lender fall back to their default lender
+    for chain_id in [10, 137, 42161] {
+        let lender = registry.default_lender(chain_id, LenderKind::Erc3156).unwrap();
+        assert_eq!(lender.kind, LenderKind::AaveV3);
+    }
+
+    // Unknown chains are unsupported
+    let err = registry.default_lender(31337, LenderKind::Erc3156).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::UnsupportedChain(31337))
+    ));
+    assert!(registry.lenders_for(31337, dai).is_empty());
+}
+
+#[test]
+fn test_load_registry() {
+    let registry = ChainRegistry::from_toml(REGISTRY_TOML).unwrap();
+    let lender = registry.default_lender(31337, LenderKind::Erc3156).unwrap();
+    assert_eq!(lender.name, "mock-lender");
+    assert_eq!(lender.fee, FeeModel::Bps(9));
+    assert_eq!(registry.chain(31337).unwrap().name, "anvil");
+
+    // The same registry round trips through JSON
+    let json = serde_json::to_string(&registry).unwrap();
+    assert_eq!(ChainRegistry::from_json(&json).unwrap(), registry);
+
+    // Files are parsed by extension
+    let path = std::env::temp_dir().join(format!("registry-{}.toml", std::process::id()));
+    std::fs::write(&path, REGISTRY_TOML).unwrap();
+    assert_eq!(ChainRegistry::load(&path).unwrap(), registry);
+    std::fs::remove_file(&path).unwrap();
+
+    // Malformed registries are rejected
+    let err = ChainRegistry::from_toml("[[chains.1.lenders]]\nname = \"x\"").unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::RegistryError(_))));
+    let err = ChainRegistry::load("missing.json").unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::RegistryError(_))));
+}
+
+#[test]
+fn test_merge_registry() {
+    let mut registry = ChainRegistry::builtin();
+    registry.merge(ChainRegistry::from_toml(REGISTRY_TOML).unwrap());
+
+    // New chains are added and lenders are replaced by name
+    assert!(registry.default_lender(31337, LenderKind::Erc3156).is_ok());
+    let balancer = registry.default_lender(1, LenderKind::Balancer).unwrap();
+    assert_eq!(balancer.address, Address::from_low_u64_be(1));
+    assert_eq!(balancer.fee, FeeModel::Free);
+    assert_eq!(registry.chain(1).unwrap().lenders.len(), 3);
+    assert_eq!(registry.chain(1).unwrap().name, "mainnet");
+}
+
+#[test]
+fn test_fee_model() {
+    let amount = U256::from(10_001);
+    assert_eq!(FeeModel::OnChain.fee(amount), None);
+    assert_eq!(FeeModel::Free.fee(amount), Some(U256::zero()));
+    // Fees are rounded up
+    assert_eq!(FeeModel::Bps(9).fee(amount), Some(U256::from(10)));
+    assert_eq!(FeeModel::Bps(9).fee(U256::from(10_000)), Some(U256::from(9)));
+    assert_eq!(FeeModel::Pips(500).fee(U256::from(1_000_000)), Some(U256::from(500)));
+    assert_eq!(FeeModel::Bps(9).fee(U256::zero()), Some(U256::zero()));
+}
+
+#[tokio::test]
+async fn test_builder_registry() {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let mut builder =
+        FlashloanBuilder::new(Arc::clone(&client), 31337, None, None, None, None, None);
+
+    // Deploying on an unknown chain without a lender fails clearly
+    let err = builder.deploy(None, Some(Address::random())).await.err().unwrap();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::UnsupportedChain(31337))
+    ));
+
+    // A plan picks up the registry default lender
+    let mut registry = ChainRegistry::builtin();
+    registry.merge(ChainRegistry::from_toml(REGISTRY_TOML).unwrap());
+    builder.with_registry(registry).with_borrower(Address::random());
+    builder.with_token(Address::random()).with_amount(U256::one());
+    let plan = builder.plan().unwrap();
+    assert_eq!(
+        plan.lender(),
+        Some(Address::from_str("0x5FbDB2315678afecb367f032d93F642f64180aa3").unwrap())
+    );
+    assert_eq!(plan.lender_kind(), LenderKind::Erc3156);
+}
@@ -0,0 +1,148 @@
+use ethers::{
+    abi::{encode, ethabi::AbiError, Param, ParamType, Token},
+    middleware::signer::SignerMiddlewareError,
+    prelude::*,
+};
+use serde_json::json;
+
+use flashloan_rs::prelude::*;
+
+/// Prefix abi encoded arguments with the selector of `signature`
+fn revert_data(signature: &str, args: &[Token]) -> Vec<u8> {
+    let mut data = ethers::utils::id(signature).to_vec();
+    data.extend(encode(args));
+    data
+}
+
+#[test]
+fn test_decode_builtin_reverts() {
+    let decoder = RevertDecoder::default();
+
+    // Error(string)
+    let data = revert_data("Error(string)", &[Token::String("Dai/insufficient-balance".into())]);
+    match decoder.decode(&data) {
+        FlashloanError::Revert(message) => assert_eq!(message, "Dai/insufficient-balance"),
+        e => panic!("unexpected error: {e:?}"),
+    }
+
+    // Panic(uint256)
+    let data = revert_data("Panic(uint256)", &[Token::Uint(U256::from(0x11))]);
+    match decoder.decode(&data) {
+        FlashloanError::Panic { code, reason } => {
+            assert_eq!(code, U256::from(0x11));
+            assert_eq!(reason, "arithmetic overflow or underflow");
+        }
+        e => panic!("unexpected error: {e:?}"),
+    }
+
+    // Empty and unknown revert data is preserved
+    assert!(matches!(decoder.decode(&[]), FlashloanError::UnknownRevert(data) if data.is_empty()));
+    let data = revert_data("Unregistered()", &[]);
+    assert!(
+        matches!(decoder.decode(&data), FlashloanError::UnknownRevert(d) if d.to_vec() == data)
+    );
+}
+
+#[test]
+fn test_decode_borrower_reverts() {
+    let decoder = RevertDecoder::default();
+    assert!(matches!(
+        decoder.decode(&revert_data("Unauthorized()", &[])),
+        FlashloanError::Unauthorized
+    ));
+    assert!(matches!(
+        decoder.decode(&revert_data("UntrustedLender()", &[])),
+        FlashloanError::UntrustedLender
+    ));
+    assert!(matches!(
+        decoder.decode(&revert_data("EmptyBalance()", &[])),
+        FlashloanError::EmptyBalance
+    ));
+
+    let data = revert_data(
+        "InsufficientProfit(uint256,uint256)",
+        &[Token::Uint(U256::from(5)), Token::Uint(U256::from(7))],
+    );
+    assert!(matches!(
+        decoder.decode(&data),
+        FlashloanError::InsufficientProfit { balance, threshold }
+            if balance == U256::from(5) && threshold == U256::from(7)
+    ));
+
+    // A failed call wraps the decoded revert of the call target
+    let inner = revert_data("Error(string)", &[Token::String("STF".into())]);
+    let data = revert_data(
+        "CallFailed(uint256,bytes)",
+        &[Token::Uint(U256::from(2)), Token::Bytes(inner)],
+    );
+    match decoder.decode(&data) {
+        FlashloanError::CallFailed { index, reason } => {
+            assert_eq!(index, 2);
+            assert!(matches!(*reason, FlashloanError::Revert(message) if message == "STF"));
+        }
+        e => panic!("unexpected error: {e:?}"),
+    }
+}
+
+#[test]
+fn test_decode_registered_errors() {
+    let mut decoder = RevertDecoder::default();
+    decoder.register(AbiError {
+        name: "InsufficientLiquidity".to_string(),
+        inputs: vec![Param {
+            name: "available".to_string(),
+            kind: ParamType::Uint(256),
+            internal_type: None,
+        }],
+    });
+
+    let data = revert_data("InsufficientLiquidity(uint256)", &[Token::Uint(U256::from(100))]);
+    match decoder.decode(&data) {
+        FlashloanError::CustomError { name, args } => {
+            assert_eq!(name, "InsufficientLiquidity");
+            assert_eq!(args, vec![Token::Uint(U256::from(100))]);
+        }
+        e => panic!("unexpected error: {e:?}"),
+    }
+}
+
+/// The provider error of a node's JSON-RPC error response over HTTP
+fn rpc_error(error: serde_json::Value) -> ProviderError {
+    let error = HttpClientError::JsonRpcError(serde_json::from_value(error).unwrap());
+    ProviderError::JsonRpcClientError(Box::new(error))
+}
+
+#[test]
+fn test_extract_revert_data() {
+    let reverted =
+        || rpc_error(json!({ "code": 3, "message": "execution reverted", "data": "0x82b42900" }));
+    let data = Some(Bytes::from(vec![0x82, 0xb4, 0x29, 0x00]));
+
+    // The data field is read from the provider, directly or through a signer
+    let error = ContractError::<Provider<Http>>::ProviderError(reverted());
+    assert_eq!(flashloan_rs::revert::revert_data(&error), data);
+    let error = ContractError::<Provider<Http>>::MiddlewareError(reverted());
+    assert_eq!(flashloan_rs::revert::revert_data(&error), data);
+    let error = ContractError::<SignerMiddleware<Provider<Http>, LocalWallet>>::MiddlewareError(
+        SignerMiddlewareError::MiddlewareError(reverted()),
+    );
+    assert_eq!(flashloan_rs::revert::revert_data(&error), data);
+    assert!(matches!(
+        RevertDecoder::default().decode_contract_error(&error),
+        FlashloanError::Unauthorized
+    ));
+
+    // Errors without hex revert data fall back to the error message
+    let error = ContractError::<Provider<Http>>::ProviderError(rpc_error(
+        json!({ "code": -32000, "message": "insufficient funds", "data": null }),
+    ));
+    assert!(flashloan_rs::revert::revert_data(&error).is_none());
+    assert!(matches!(
+        RevertDecoder::default().decode_contract_error(&error),
+        FlashloanError::ContractError(message) if message.contains("insufficient funds")
+    ));
+    let error = ContractError::<Provider<Http>>::ProviderError(ProviderError::CustomError(
+        r#"data: Some(String("0x82b42900"))"#.to_string(),
+    ));
+    assert!(flashloan_rs::revert::revert_data(&error).is_none());
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::prelude::*;
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+/// A lender with fixed liquidity and fee
+#[derive(Debug, Clone, Copy)]
+struct MockLender {
+    address: Address,
+    kind: LenderKind,
+    liquidity: U256,
+    fee: U256,
+    offline: bool,
+}
+
+impl MockLender {
+    fn new(kind: LenderKind, liquidity: u64, fee: u64) -> Self {
+        Self {
+            address: Address::random(),
+            kind,
+            liquidity: U256::from(liquidity),
+            fee: U256::from(fee),
+            offline: false,
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> Lender<M> for MockLender {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn kind(&self) -> LenderKind {
+        self.kind
+    }
+
+    async fn max_flash_loan(&self, _client: Arc<M>, _token: Address) -> Result<U256> {
+        if self.offline {
+            return Err(FlashloanError::ContractError("offline".to_string()).into())
+        }
+        Ok(self.liquidity)
+    }
+
+    async fn flash_fee(&self, _client: Arc<M>, _token: Address, _amount: U256) -> Result<U256> {
+        Ok(self.fee)
+    }
+}
+
+fn client() -> Arc<Provider<Http>> {
+    Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap())
+}
+
+#[tokio::test]
+async fn test_router_ranks_lenders() {
+    let cheap = MockLender::new(LenderKind::Balancer, 1_000, 0);
+    let shallow = MockLender::new(LenderKind::Erc3156, 10, 0);
+    let deep = MockLender::new(LenderKind::AaveV3, 10_000, 5);
+    let shallower = MockLender::new(LenderKind::AaveV3, 5_000, 5);
+    let offline = MockLender { offline: true, ..MockLender::new(LenderKind::Erc3156, 0, 0) };
+    let router = LenderRouter::new(client())
+        .with_lender(shallower)
+        .with_lender(offline)
+        .with_lender(deep)
+        .with_lender(shallow)
+        .with_lender(cheap);
+    assert_eq!(router.len(), 5);
+
+    // Lenders without enough liquidity or that fail are skipped
+    let quotes = router.quote(Address::random(), U256::from(100)).await;
+    let lenders = quotes.iter().map(|quote| quote.lender).collect::<Vec<_>>();
+    assert_eq!(lenders, vec![cheap.address, deep.address, shallower.address]);
+    assert_eq!(
+        quotes[0],
+        LenderQuote {
+            lender: cheap.address,
+            kind: LenderKind::Balancer,
+            liquidity: U256::from(1_000),
+            fee: U256::zero(),
+        }
+    );
+
+    let quote = router.select(Address::random(), U256::from(2_000)).await.unwrap();
+    assert_eq!(quote.lender, deep.address);
+
+    let err = router.select(Address::random(), U256::from(20_000)).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::InsufficientLiquidity { .. })
+    ));
+}
+
+#[test]
+fn test_router_from_registry() {
+    let registry = ChainRegistry::builtin();
+    assert_eq!(LenderRouter::from_registry(client(), &registry, 1).len(), 3);
+    assert_eq!(LenderRouter::from_registry(client(), &registry, 10).len(), 2);
+    assert!(LenderRouter::from_registry(client(), &registry, 31337).is_empty());
+}
+
+#[tokio::test]
+async fn test_builder_auto_select_lender() {
+    let aave = MockLender::new(LenderKind::AaveV3, 1_000, 1);
+    let balancer = MockLender::new(LenderKind::Balancer, 1_000, 0);
+    let router = LenderRouter::new(client()).with_lender(aave).with_lender(balancer);
+
+    let mut builder = FlashloanBuilder::new(client(), 1, None, None, None, None, None);
+    let err = builder.auto_select_lender(&router).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingToken)));
+
+    // A borrower already deployed for the selected lender is reused
+    let borrower = Address::random();
+    builder.borrowers.insert(balancer.address, borrower);
+    builder.with_token(Address::random()).with_amount(U256::from(100));
+    let quote = builder.auto_select_lender(&router).await.unwrap();
+    assert_eq!(quote.lender, balancer.address);
+    assert_eq!(builder.lender, Some(balancer.address));
+    assert_eq!(builder.lender_kind, LenderKind::Balancer);
+    assert_eq!(builder.borrower.as_ref().unwrap().address(), borrower);
+
+    // Otherwise a borrower for the lender's kind is deployed
+    builder.with_amount(U256::from(1_000));
+    builder.borrowers.remove(&balancer.address);
+    builder.with_owner(Address::random());
+    let err = builder.auto_select_lender(&router).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::MissingBytecode(_))
+    ));
+}
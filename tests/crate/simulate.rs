@@ -0,0 +1,273 @@
+use ethers::{
+    abi::{encode, AbiDecode, AbiEncode, Token},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+/// Runtime code returning 32 zero bytes for any call
+const RETURN_ZERO: &str = "0x60206000f3";
+
+#[test]
+fn test_deploy_borrower_in_snapshot() {
+    let owner = Address::random();
+    let lender = Address::random();
+    let mut snapshot = StateSnapshot::new(1);
+    let borrower = snapshot.deploy_borrower(LenderKind::Erc3156, lender, owner).unwrap();
+
+    // The borrower is deployed with the configured owner and lender
+    assert!(!snapshot.accounts[&borrower].code.is_empty());
+    assert_eq!(snapshot.accounts[&owner].nonce, 1);
+    let deployed_owner = snapshot.call(owner, borrower, OwnerCall.encode().into()).unwrap();
+    assert_eq!(Address::decode(deployed_owner).unwrap(), owner);
+    let deployed_lender = snapshot.call(owner, borrower, LenderCall.encode().into()).unwrap();
+    assert_eq!(Address::decode(deployed_lender).unwrap(), lender);
+}
+
+#[test]
+fn test_simulate_flashloan() {
+    let owner = Address::random();
+    let lender = Address::random();
+    let token = Address::random();
+    let mut snapshot = StateSnapshot::new(1);
+    let borrower = snapshot.deploy_borrower(LenderKind::Erc3156, lender, owner).unwrap();
+
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let mut builder = FlashloanBuilder::new(
+        client,
+        1,
+        Some(owner),
+        Some(lender),
+        Some(token),
+        Some(U256::exp10(18)),
+        Some(borrower),
+    );
+
+    // Calling a lender without code reverts
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert!(simulation.gas_used > 0);
+    assert!(simulation.calls.is_empty());
+
+    // Only the owner can borrow
+    builder.with_owner(Address::random());
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert_eq!(simulation.output.to_vec(), ethers::utils::id("Unauthorized()").to_vec());
+
+    // Stub the lender and token so the flashloan succeeds without a callback
+    for stub in [lender, token] {
+        snapshot.insert_account(
+            stub,
+            AccountState { code: RETURN_ZERO.parse().unwrap(), ..Default::default() },
+        );
+    }
+    builder.with_owner(owner);
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success);
+    assert_eq!(simulation.balance_deltas[&token], I256::zero());
+    assert_eq!(simulation.balance_deltas[&Address::zero()], I256::zero());
+}
+
+#[test]
+fn test_simulate_huff_borrower() {
+    let owner = Address::random();
+    let lender = Address::random();
+    let token = Address::random();
+    let mut snapshot = StateSnapshot::new(1);
+    for stub in [lender, token] {
+        snapshot.insert_account(
+            stub,
+            AccountState { code: RETURN_ZERO.parse().unwrap(), ..Default::default() },
+        );
+    }
+    let solidity = snapshot.deploy_borrower(LenderKind::Erc3156, lender, owner).unwrap();
+    let mut init_code = huff_borrower_bytecode().unwrap().to_vec();
+    init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+    let huff = snapshot.deploy(owner, init_code.into()).unwrap();
+
+    // The Huff borrower has the same constructor and getters
+    let deployed_owner = snapshot.call(owner, huff, OwnerCall.encode().into()).unwrap();
+    assert_eq!(Address::decode(deployed_owner).unwrap(), owner);
+    let deployed_lender = snapshot.call(owner, huff, LenderCall.encode().into()).unwrap();
+    assert_eq!(Address::decode(deployed_lender).unwrap(), lender);
+
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let builder = |borrower| {
+        FlashloanBuilder::new(
+            Arc::clone(&client),
+            1,
+            Some(owner),
+            Some(lender),
+            Some(token),
+            Some(U256::exp10(18)),
+            Some(borrower),
+        )
+    };
+
+    // The stubbed flashloan succeeds without a callback, for less gas than the Solidity borrower
+    let simulation = builder(huff).simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success);
+    assert!(simulation.results().unwrap().is_empty());
+    assert!(simulation.gas_used < builder(solidity).simulate(&snapshot, &[]).unwrap().gas_used);
+
+    // Only the owner can borrow
+    let simulation = builder(huff).with_owner(Address::random()).simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    assert_eq!(simulation.output.to_vec(), ethers::utils::id("Unauthorized()").to_vec());
+
+    // The repayment can be computed off-chain
+    let data = FlashBorrowWithRepaymentCall {
+        token,
+        amount: U256::exp10(18),
+        repayment: U256::exp10(18),
+        calls: vec![],
+    }
+    .encode();
+    let results = snapshot.call(owner, huff, data.clone().into()).unwrap();
+    assert!(Vec::<(bool, Bytes)>::decode(results).unwrap().is_empty());
+    assert!(snapshot.call(Address::random(), huff, data.into()).is_err());
+}
+
+#[test]
+fn test_simulate_huff_trusted_lenders() {
+    let owner = Address::random();
+    let lender = Address::random();
+    let token = Address::random();
+    let mut snapshot = StateSnapshot::new(1);
+    for stub in [lender, token] {
+        snapshot.insert_account(
+            stub,
+            AccountState { code: RETURN_ZERO.parse().unwrap(), ..Default::default() },
+        );
+    }
+    let mut init_code = huff_borrower_bytecode().unwrap().to_vec();
+    init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+    let huff = snapshot.deploy(owner, init_code.into()).unwrap();
+
+    // The deployed lender is trusted
+    let trusted = |lender| {
+        let output =
+            snapshot.call(owner, huff, TrustedLendersCall(lender).encode().into()).unwrap();
+        bool::decode(output).unwrap()
+    };
+    assert!(trusted(lender));
+    let other = Address::random();
+    assert!(!trusted(other));
+
+    // Borrowing from a lender only works once it is trusted
+    let borrow = |lender| {
+        let data =
+            FlashBorrowWithLenderCall { token, amount: U256::exp10(18), calls: vec![], lender }
+                .encode();
+        snapshot.call(owner, huff, data.into())
+    };
+    assert!(borrow(lender).is_ok());
+    let err = borrow(other).unwrap_err().to_string();
+    assert!(err.contains(&hex::encode(ethers::utils::id("UntrustedLender()"))));
+
+    // Only the owner can trust lenders
+    let data = TrustLenderCall { lender: other }.encode();
+    assert!(snapshot.call(Address::random(), huff, data.clone().into()).is_err());
+    assert!(snapshot.call(owner, huff, data.into()).is_ok());
+}
+
+#[tokio::test]
+async fn test_simulated_profit_guard() {
+    let owner = Address::random();
+    let lender = Address::random();
+    let token = Address::random();
+    let profit_token = Address::random();
+    let mut snapshot = StateSnapshot::new(1);
+    let mut init_code = huff_borrower_bytecode().unwrap().to_vec();
+    init_code.extend(encode(&[Token::Address(lender), Token::Address(owner)]));
+    let borrower = snapshot.deploy(owner, init_code.into()).unwrap();
+    for stub in [lender, token, profit_token] {
+        snapshot.insert_account(
+            stub,
+            AccountState { code: RETURN_ZERO.parse().unwrap(), ..Default::default() },
+        );
+    }
+
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let mut builder = FlashloanBuilder::new(
+        client,
+        1,
+        Some(owner),
+        Some(lender),
+        Some(token),
+        Some(U256::exp10(18)),
+        Some(borrower),
+    );
+
+    // A profit guard is required to check a simulation
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    let err = builder.check_simulated_profit(&simulation).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::MissingProfitGuard)
+    ));
+
+    // The guarded entrypoint is simulated, and the stubbed profit token never increases
+    builder.with_profit_guard(ProfitGuard::new(profit_token, U256::one()));
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(!simulation.success);
+    let err = builder.check_simulated_profit(&simulation).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::InsufficientProfit { balance, threshold })
+            if balance.is_zero() && *threshold == U256::one()
+    ));
+
+    // The profit token is tracked once a guard is set
+    builder.with_profit_guard(ProfitGuard::new(profit_token, U256::zero()));
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success);
+    assert_eq!(simulation.balance_deltas[&profit_token], I256::zero());
+
+    // A reverted simulation reports the decoded revert
+    builder.with_owner(Address::random());
+    let simulation = builder.simulate(&snapshot, &[]).unwrap();
+    let err = builder.check_simulated_profit(&simulation).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::Unauthorized)));
+}
+
+#[test]
+fn test_simulate_plan() {
+    let owner = Address::random();
+    let lender = Address::random();
+    let token = Address::random();
+    let mut snapshot = StateSnapshot::new(1);
+    let borrower = snapshot.deploy_borrower(LenderKind::Erc3156, lender, owner).unwrap();
+    for stub in [lender, token] {
+        snapshot.insert_account(
+            stub,
+            AccountState { code: RETURN_ZERO.parse().unwrap(), ..Default::default() },
+        );
+    }
+
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    let plan = FlashloanBuilder::with_client(client)
+        .lender(lender)
+        .borrower(borrower)
+        .token(token)
+        .amount(U256::exp10(18))
+        .build()
+        .unwrap();
+
+    // Simulations are sent from the owner
+    let err = plan.simulate(&snapshot, &[]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingOwner)));
+
+    let plan = FlashloanBuilder::with_client(Arc::clone(&plan.client()))
+        .owner(owner)
+        .borrower(borrower)
+        .token(token)
+        .amount(U256::exp10(18))
+        .build()
+        .unwrap();
+    let simulation = plan.simulate(&snapshot, &[]).unwrap();
+    assert!(simulation.success);
+    assert_eq!(simulation.balance_deltas[&token], I256::zero());
+}
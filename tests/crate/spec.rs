@@ -0,0 +1,152 @@
+use ethers::prelude::*;
+use std::{str::FromStr, sync::Arc};
+
+use flashloan_rs::prelude::*;
+
+const SPEC_TOML: &str = r#"
+chain_id = 1
+lender = "0x1eb4cf3a948e7d72a198fe073ccb8c7a948cd853"
+lender_kind = "balancer"
+token = "0x6b175474e89094c44da98b954eedeac495271d0f"
+amount = "0x3635c9adc5dea00000"
+gas_padding_bps = 1000
+
+[profit_guard]
+token = "0x6b175474e89094c44da98b954eedeac495271d0f"
+min_profit = 100
+
+[[calls]]
+target = "0x6b175474e89094c44da98b954eedeac495271d0f"
+function = "approve(address spender, uint256 amount)"
+args = ["0x7a250d5630b4cf539739df2c5dacb4c659f2488d", "0xffffffffffffffff"]
+
+[[calls]]
+target = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+allow_failure = true
+value = "1000"
+data = "0x12345678"
+"#;
+
+fn address(address: &str) -> Address {
+    Address::from_str(address).unwrap()
+}
+
+fn builder() -> FlashloanBuilder<Provider<Http>> {
+    let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+    FlashloanBuilder::new(client, 1, None, None, None, None, None)
+}
+
+fn assert_format_error(err: anyhow::Error) {
+    assert!(matches!(
+        err.downcast_ref::<FlashloanError>(),
+        Some(FlashloanError::PlanFormatError(_))
+    ));
+}
+
+#[test]
+fn test_parse_toml_spec() {
+    let spec = FlashloanSpec::from_toml(SPEC_TOML).unwrap();
+    let dai = address("0x6b175474e89094c44da98b954eedeac495271d0f");
+    let router = address("0x7a250d5630b4cf539739df2c5dacb4c659f2488d");
+
+    assert_eq!(spec.chain_id, Some(1));
+    assert_eq!(spec.lender_kind, LenderKind::Balancer);
+    // Hex and decimal amounts
+    assert_eq!(spec.amount, Some(U256::exp10(21)));
+    assert_eq!(spec.calls[1].value, U256::from(1000));
+    // The guard's eth price defaults to one ether
+    assert_eq!(spec.profit_guard, Some(ProfitGuard::new(dai, U256::from(100))));
+
+    // Function calls are encoded like the typed calls
+    let calls = spec.encode_calls().unwrap();
+    assert_eq!(calls[0], Erc20Token(dai).approve(router, U256::from(u64::MAX)).unwrap());
+    assert_eq!(calls[1].call_data, Bytes::from(vec![0x12, 0x34, 0x56, 0x78]));
+    assert!(calls[1].allow_failure);
+}
+
+#[test]
+fn test_spec_round_trip() {
+    let mut spec = FlashloanSpec::from_toml(SPEC_TOML).unwrap();
+    spec.legs = vec![
+        FlashloanLeg::new(LenderKind::Erc3156, Address::random(), Address::random(), 7.into()),
+        FlashloanLeg::new(LenderKind::AaveV3, Address::random(), Address::random(), U256::MAX),
+    ];
+
+    assert_eq!(FlashloanSpec::from_json(&spec.to_json().unwrap()).unwrap(), spec);
+    assert_eq!(FlashloanSpec::from_toml(&spec.to_toml().unwrap()).unwrap(), spec);
+    // Amounts are saved as decimal strings
+    assert!(spec.to_toml().unwrap().contains("amount = \"1000000000000000000000\""));
+
+    // Through files, by extension
+    let dir = std::env::temp_dir();
+    for name in ["flashloan-spec-test.toml", "flashloan-spec-test.json"] {
+        let path = dir.join(name);
+        spec.save(&path).unwrap();
+        assert_eq!(FlashloanSpec::load(&path).unwrap(), spec);
+        std::fs::remove_file(path).unwrap();
+    }
+    assert_format_error(spec.save(dir.join("flashloan-spec-test.yaml")).unwrap_err());
+}
+
+#[test]
+fn test_invalid_specs() {
+    let target = Address::random();
+
+    // Raw data and a function are exclusive
+    let mut call = CallSpec::abi(target, "transfer(address,uint256)", vec![]);
+    call.data = Some(Bytes::default());
+    assert_format_error(call.to_call3().unwrap_err());
+
+    // Arguments must match the function's inputs
+    let call = CallSpec::abi(target, "transfer(address,uint256)", vec!["1".to_string()]);
+    assert_format_error(call.to_call3().unwrap_err());
+    let args = vec![format!("{:?}", target), "0xnope".to_string()];
+    let call = CallSpec::abi(target, "transfer(address,uint256)", args);
+    assert_format_error(call.to_call3().unwrap_err());
+    let call = CallSpec { target, args: vec!["1".to_string()], ..Default::default() };
+    assert_format_error(call.to_call3().unwrap_err());
+
+    // Unknown fields are rejected rather than ignored
+    assert_format_error(FlashloanSpec::from_json(r#"{ "ammount": "1" }"#).unwrap_err());
+    assert_format_error(FlashloanSpec::from_toml("[[calls]]\ntarget = \"0x00\"").unwrap_err());
+}
+
+#[test]
+fn test_builder_spec() {
+    let spec = FlashloanSpec::from_toml(SPEC_TOML).unwrap();
+    let mut builder = builder();
+    let owner = Address::random();
+    builder.with_owner(owner);
+    builder.with_spec(&spec).unwrap();
+
+    assert_eq!(builder.lender, spec.lender);
+    assert_eq!(builder.lender_kind, LenderKind::Balancer);
+    assert_eq!(builder.amount, Some(U256::exp10(21)));
+    assert_eq!(builder.calls, spec.encode_calls().unwrap());
+    assert_eq!(builder.gas_padding_bps, 1000);
+    // Unset parameters are kept
+    assert_eq!(builder.owner, Some(owner));
+
+    // Calls are saved as raw calldata, and load back into the same builder state
+    let saved = builder.to_spec();
+    assert!(saved.calls.iter().all(|call| call.data.is_some() && call.function.is_none()));
+    let mut reloaded = self::builder();
+    reloaded.with_spec(&saved).unwrap();
+    assert_eq!(reloaded.to_spec(), saved);
+}
+
+#[test]
+fn test_builder_spec_chain_mismatch() {
+    let mut spec = FlashloanSpec::from_toml(SPEC_TOML).unwrap();
+    spec.chain_id = Some(10);
+    let mut builder = builder();
+    assert_format_error(builder.with_spec(&spec).err().unwrap());
+    // The builder is left unchanged
+    assert!(builder.calls.is_empty());
+    assert_eq!(builder.lender_kind, LenderKind::Erc3156);
+
+    // Specs without a chain load on any chain
+    spec.chain_id = None;
+    builder.with_spec(&spec).unwrap();
+    assert_eq!(builder.calls.len(), 2);
+}
@@ -0,0 +1,93 @@
+use ethers::{abi::encode, abi::Token, prelude::*, utils::keccak256};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+fn builder<M: Middleware>(client: Arc<M>, borrower: Option<Address>) -> FlashloanBuilder<M> {
+    FlashloanBuilder::new(client, 1, None, None, None, None, borrower)
+}
+
+fn transfer_log(token: Address, from: Address, to: Address, amount: u64) -> Log {
+    Log {
+        address: token,
+        topics: vec![
+            H256::from(keccak256("Transfer(address,address,uint256)")),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: encode(&[Token::Uint(amount.into())]).into(),
+        ..Default::default()
+    }
+}
+
+fn balance(amount: u64) -> Bytes {
+    encode(&[Token::Uint(amount.into())]).into()
+}
+
+#[test]
+fn test_erc20_transfers() {
+    let (token, borrower, owner) = (Address::random(), Address::random(), Address::random());
+    let mut nft_transfer = transfer_log(Address::random(), borrower, owner, 0);
+    nft_transfer.topics.push(H256::from_low_u64_be(7));
+    nft_transfer.data = Bytes::default();
+    let receipt = TransactionReceipt {
+        logs: vec![
+            transfer_log(token, borrower, owner, 100),
+            nft_transfer,
+            Log { address: token, topics: vec![H256::random()], ..Default::default() },
+            transfer_log(token, owner, borrower, 5),
+        ],
+        ..Default::default()
+    };
+
+    // Only the ERC20 transfers are decoded
+    let transfers = erc20_transfers(&receipt);
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(
+        transfers[0],
+        Erc20Transfer { token, from: borrower, to: owner, amount: 100.into() }
+    );
+    assert_eq!(transfers[1].from, owner);
+}
+
+#[tokio::test]
+async fn test_sweep_empty_balances() {
+    let (provider, mock) = Provider::mocked();
+    let borrower = Address::random();
+    let builder = builder(Arc::new(provider), Some(borrower));
+
+    // Duplicate tokens are queried once, and nothing is withdrawn, which would need more responses
+    mock.push::<Bytes, _>(balance(0)).unwrap();
+    mock.push::<Bytes, _>(balance(0)).unwrap();
+    let (dai, weth) = (Address::random(), Address::random());
+    assert!(builder.sweep_all(&[dai, weth, dai], None).await.unwrap().is_empty());
+
+    mock.push(U256::zero()).unwrap();
+    assert_eq!(builder.sweep_eth(None).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_sweep_recipient() {
+    let (provider, mock) = Provider::mocked();
+    let builder = builder(Arc::new(provider), Some(Address::random()));
+
+    // Without an owner, the recipient is the client's first account
+    mock.push::<Vec<Address>, _>(vec![]).unwrap();
+    mock.push::<Bytes, _>(balance(100)).unwrap();
+    let err = builder.sweep_token(Address::random(), None).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<FlashloanError>(), Some(FlashloanError::MissingOwner)));
+}
+
+#[tokio::test]
+async fn test_sweep_without_borrower() {
+    let (provider, _mock) = Provider::mocked();
+    let mut builder = builder(Arc::new(provider), None);
+    let missing_borrower =
+        |err: anyhow::Error| matches!(err.downcast_ref(), Some(FlashloanError::MissingBorrower));
+    assert!(missing_borrower(builder.sweep_token(Address::random(), None).await.unwrap_err()));
+    assert!(missing_borrower(builder.sweep_eth(None).await.unwrap_err()));
+
+    builder.with_auto_sweep(AutoSweep { eth: true, ..Default::default() });
+    assert_eq!(builder.auto_sweep.as_ref().map(|sweep| sweep.eth), Some(true));
+    assert!(builder.sweeps.is_empty());
+}
@@ -0,0 +1,67 @@
+use ethers::{abi::Token, prelude::*};
+use std::sync::Arc;
+
+use flashloan_rs::prelude::*;
+
+mod common;
+use common::huff::assemble_contract;
+
+#[test]
+fn test_mock_artifacts() {
+    for name in ["flashLoan", "flashFee", "maxFlashLoan"] {
+        assert!(MOCK_LENDER_ABI.function(name).is_ok(), "missing {}", name);
+    }
+    assert!(MOCK_LENDER_ABI.event("FlashLoan").is_ok());
+    for name in ["mint", "burn", "balanceOf", "transfer", "transferFrom", "approve"] {
+        assert!(MOCK_TOKEN_ABI.function(name).is_ok(), "missing {}", name);
+    }
+    assert!(MOCK_TOKEN_ABI.constructor().is_none());
+
+    // The bundled bytecode is assembled from the Huff mocks
+    let mocks = [
+        (mock_lender_bytecode().unwrap(), include_str!("../../contracts/mocks/FlashLender.huff")),
+        (mock_token_bytecode().unwrap(), include_str!("../../contracts/mocks/MockERC20.huff")),
+    ];
+    for (bytecode, source) in mocks {
+        let (creation, _) = assemble_contract(source);
+        assert_eq!(bytecode.to_vec(), creation);
+    }
+}
+
+#[test]
+fn test_harness_builder() {
+    let (provider, _mock) = Provider::mocked();
+    let harness = TestHarness {
+        client: Arc::new(provider),
+        chain_id: 31337,
+        owner: Address::random(),
+        lender: Address::random(),
+        token: Address::random(),
+        borrower: Address::random(),
+    };
+    assert_eq!(mock_flash_fee(U256::exp10(18)), U256::exp10(16));
+
+    // The builder borrows the mock token and mints the fee to the borrower
+    let builder = harness.builder(U256::exp10(18));
+    assert_eq!(builder.chain_id, 31337);
+    assert_eq!(builder.owner, Some(harness.owner));
+    assert_eq!(builder.lender, Some(harness.lender));
+    assert_eq!(builder.token, Some(harness.token));
+    assert_eq!(builder.amount, Some(U256::exp10(18)));
+    assert_eq!(
+        builder.borrower.as_ref().map(|borrower| borrower.address()),
+        Some(harness.borrower)
+    );
+    assert_eq!(builder.calls, vec![harness.mint_call(harness.borrower, U256::exp10(16))]);
+
+    let mint = &builder.calls[0];
+    assert_eq!(mint.target, harness.token);
+    assert!(!mint.allow_failure);
+    let expected = MOCK_TOKEN_ABI
+        .function("mint")
+        .unwrap()
+        .encode_input(&[Token::Address(harness.borrower), Token::Uint(U256::exp10(16))])
+        .unwrap();
+    assert_eq!(mint.call_data.to_vec(), expected);
+    assert_eq!(&expected[..4], &ethers::utils::id("mint(address,uint256)"));
+}
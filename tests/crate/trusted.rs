@@ -0,0 +1,197 @@
+use ethers::{
+    abi::{encode, Token},
+    prelude::*,
+    utils::id,
+};
+use std::{sync::Arc, time::Duration};
+
+use flashloan_rs::prelude::*;
+
+mod common;
+use common::push_transaction;
+
+fn mocked_builder(
+    borrower: Option<Address>,
+) -> (FlashloanBuilder<Provider<MockProvider>>, MockProvider) {
+    let (provider, mock) = Provider::mocked();
+    let provider = provider.interval(Duration::from_millis(10));
+    let builder = FlashloanBuilder::new(
+        Arc::new(provider),
+        1,
+        None,
+        Some(Address::random()),
+        Some(Address::random()),
+        Some(U256::exp10(18)),
+        borrower,
+    );
+    (builder, mock)
+}
+
+#[test]
+fn test_trusted_lender_artifacts() {
+    let selectors = [
+        ("trustLender", "trustLender(address)"),
+        ("untrustLender", "untrustLender(address)"),
+        ("trustedLenders", "trustedLenders(address)"),
+    ];
+    for (name, signature) in selectors {
+        assert_eq!(FLASHLOAN_ABI.function(name).unwrap().short_signature(), id(signature));
+        assert!(OPERATED_FLASHLOAN_ABI.function(name).is_ok());
+    }
+
+    // Each entrypoint has an overload taking the lender last
+    let overloads = [
+        "flashBorrow(address,uint256,(address,bool,uint256,bytes)[],address)",
+        "flashBorrowWithRepayment(address,uint256,uint256,(address,bool,uint256,bytes)[],address)",
+        "flashBorrowWithProfit(address,uint256,(address,bool,uint256,bytes)[],address,uint256,address)",
+    ];
+    for signature in overloads {
+        let name = signature.split('(').next().unwrap();
+        let functions = FLASHLOAN_ABI.functions_by_name(name).unwrap();
+        assert_eq!(functions.len(), 2);
+        assert!(functions.iter().any(|function| function.short_signature() == id(signature)));
+    }
+}
+
+#[test]
+fn test_with_lender_after_deploy() {
+    let borrower = Address::random();
+    let (mut builder, _mock) = mocked_builder(None);
+
+    // Before deployment the lender is the one the borrower is deployed with
+    let lender = Address::random();
+    builder.with_lender(lender);
+    assert_eq!(builder.lender, Some(lender));
+    assert_eq!(builder.borrow_from, None);
+
+    // Once attached, the borrower borrows from the new lender
+    builder.with_borrower(borrower);
+    let plan = builder.plan().unwrap();
+    assert_eq!(plan.borrow_from(), None);
+    let trusted = Address::random();
+    builder.with_lender(trusted);
+    let plan = builder.plan().unwrap();
+    assert_eq!(plan.borrow_from(), Some(trusted));
+    assert_eq!(plan.lender(), Some(trusted));
+
+    // Another borrower borrows from its deployed lender until the lender is set again
+    builder.with_borrower(Address::random());
+    assert_eq!(builder.borrow_from, None);
+    assert_eq!(builder.plan().unwrap().borrow_from(), None);
+    builder.with_lender(trusted);
+    assert_eq!(builder.plan().unwrap().borrow_from(), Some(trusted));
+}
+
+#[tokio::test]
+async fn test_borrow_from_trusted_lender() {
+    let borrower = Address::random();
+    let (mut builder, mock) = mocked_builder(Some(borrower));
+    let trusted = Address::random();
+    builder.with_lender(trusted);
+    let (token, amount) = (builder.token.unwrap(), builder.amount.unwrap());
+
+    // The call passes the lender to the flashBorrow overload
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(vec![])]))).unwrap();
+    assert!(builder.call().await.unwrap().is_empty());
+    let contract = Flashloan::new(borrower, builder.inner());
+    let expected = contract.flash_borrow_with_lender(token, amount, vec![], trusted);
+    mock.assert_request("eth_call", (expected.tx, "latest")).unwrap();
+
+    // So do the profit guarded and precomputed repayment entrypoints
+    let plan = |guard: Option<ProfitGuard>, repayment: Option<U256>| {
+        let mut plan = FlashloanBuilder::with_client(builder.inner())
+            .borrower(borrower)
+            .token(token)
+            .amount(amount)
+            .borrow_from(trusted);
+        if let Some(guard) = guard {
+            plan = plan.profit_guard(guard);
+        }
+        if let Some(repayment) = repayment {
+            plan = plan.repayment(repayment);
+        }
+        plan.build().unwrap()
+    };
+    let guard = ProfitGuard::new(token, U256::one());
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(vec![])]))).unwrap();
+    plan(Some(guard), None).call().await.unwrap();
+    let expected = contract.flash_borrow_with_profit_with_lender(
+        token,
+        amount,
+        vec![],
+        guard.token,
+        guard.min_profit,
+        trusted,
+    );
+    mock.assert_request("eth_call", (expected.tx, "latest")).unwrap();
+
+    let repayment = amount + 1;
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(vec![])]))).unwrap();
+    plan(None, Some(repayment)).call().await.unwrap();
+    let expected =
+        contract.flash_borrow_with_repayment_with_lender(token, amount, repayment, vec![], trusted);
+    mock.assert_request("eth_call", (expected.tx, "latest")).unwrap();
+}
+
+#[test]
+fn test_fixed_lender_borrowers() {
+    let (provider, _mock) = Provider::mocked();
+    let plan = || {
+        FlashloanBuilder::with_client(Arc::new(provider.clone()))
+            .borrower(Address::random())
+            .token(Address::random())
+            .amount(U256::one())
+            .borrow_from(Address::random())
+    };
+    let fixed_lender = |err: anyhow::Error, borrower: &str| {
+        matches!(
+            err.downcast_ref::<FlashloanError>(),
+            Some(FlashloanError::FixedLender(name)) if name == borrower
+        )
+    };
+    assert!(plan().build().is_ok());
+
+    // Only the ERC-3156 borrower can borrow from another lender
+    let leg =
+        FlashloanLeg::new(LenderKind::Erc3156, Address::random(), Address::random(), U256::one());
+    let err = plan().leg(leg).build().unwrap_err();
+    assert!(fixed_lender(err, MULTI_BORROWER_NAME));
+    let err = plan().program(Program::default()).build().unwrap_err();
+    assert!(fixed_lender(err, PROGRAM_BORROWER_NAME));
+    let err = plan().flash_lender(BalancerLender(Address::random())).build().unwrap_err();
+    assert!(fixed_lender(err, LenderKind::Balancer.borrower_name()));
+
+    // The builder checks the lender kind of an attached borrower
+    let (mut builder, _mock) = mocked_builder(Some(Address::random()));
+    builder.lender_kind = LenderKind::AaveV3;
+    builder.with_lender(Address::random());
+    let err = builder.plan().unwrap_err();
+    assert!(fixed_lender(err, LenderKind::AaveV3.borrower_name()));
+}
+
+#[tokio::test]
+async fn test_trust_lender() {
+    let (builder, mock) = mocked_builder(Some(Address::random()));
+    let lender = Address::random();
+
+    let tx_hash = H256::random();
+    push_transaction(&mock, tx_hash);
+    let receipt = builder.trust_lender(lender).await.unwrap();
+    assert_eq!(receipt.transaction_hash, tx_hash);
+
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Bool(true)]))).unwrap();
+    assert!(builder.is_trusted_lender(lender).await.unwrap());
+
+    let tx_hash = H256::random();
+    push_transaction(&mock, tx_hash);
+    let receipt = builder.untrust_lender(lender).await.unwrap();
+    assert_eq!(receipt.transaction_hash, tx_hash);
+
+    // Lenders are managed on a deployed borrower
+    let (builder, _mock) = mocked_builder(None);
+    let missing_borrower =
+        |err: anyhow::Error| matches!(err.downcast_ref(), Some(FlashloanError::MissingBorrower));
+    assert!(missing_borrower(builder.trust_lender(lender).await.unwrap_err()));
+    assert!(missing_borrower(builder.untrust_lender(lender).await.unwrap_err()));
+    assert!(missing_borrower(builder.is_trusted_lender(lender).await.unwrap_err()));
+}
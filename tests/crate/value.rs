@@ -0,0 +1,152 @@
+use ethers::{
+    abi::{encode, StateMutability, Token},
+    prelude::*,
+};
+use std::{slice, sync::Arc, time::Duration};
+
+use flashloan_rs::prelude::*;
+
+fn mocked_builder(
+    borrower: Option<Address>,
+) -> (FlashloanBuilder<Provider<MockProvider>>, MockProvider) {
+    let (provider, mock) = Provider::mocked();
+    let provider = provider.interval(Duration::from_millis(10));
+    let builder = FlashloanBuilder::new(
+        Arc::new(provider),
+        1,
+        None,
+        Some(Address::random()),
+        Some(Address::random()),
+        Some(U256::exp10(18)),
+        borrower,
+    );
+    (builder, mock)
+}
+
+#[test]
+fn test_payable_entrypoints() {
+    let abis =
+        [&*FLASHLOAN_ABI, &*OPERATED_FLASHLOAN_ABI, &*MULTI_FLASHLOAN_ABI, &*PROGRAM_FLASHLOAN_ABI];
+    for abi in abis {
+        let entrypoints: Vec<_> =
+            abi.functions().filter(|function| function.name.starts_with("flashBorrow")).collect();
+        assert!(!entrypoints.is_empty());
+        for function in entrypoints {
+            assert_eq!(function.state_mutability, StateMutability::Payable, "{}", function.name);
+        }
+    }
+}
+
+#[test]
+fn test_weth_wrap_around_calls() {
+    let weth = Weth(Address::random());
+    let call = weth.deposit(U256::from(3)).unwrap();
+    assert_eq!(calls_value(&[]), U256::zero());
+    assert_eq!(calls_value(&[call.clone(), call.clone()]), U256::from(6));
+
+    // The deposit comes first and the withdrawal last
+    let wrap = WethWrap::new(weth.0).with_wrap(U256::from(5)).with_unwrap(U256::from(7));
+    let calls = wrap.around(slice::from_ref(&call)).unwrap();
+    assert_eq!(
+        calls,
+        vec![
+            weth.deposit(U256::from(5)).unwrap(),
+            call.clone(),
+            weth.withdraw(U256::from(7)).unwrap()
+        ]
+    );
+    assert_eq!(calls_value(&calls), U256::from(8));
+
+    // A zero amount skips its call
+    let calls =
+        WethWrap::new(weth.0).with_unwrap(U256::one()).around(slice::from_ref(&call)).unwrap();
+    assert_eq!(calls, vec![call.clone(), weth.withdraw(U256::one()).unwrap()]);
+    assert_eq!(WethWrap::new(weth.0).around(slice::from_ref(&call)).unwrap(), vec![call]);
+
+    let err = WethWrap::new(Address::zero()).with_wrap(U256::one()).around(&[]).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(FlashloanError::CallConstructionError(_))));
+}
+
+#[tokio::test]
+async fn test_attach_call_value() {
+    let borrower = Address::random();
+    let (mut builder, mock) = mocked_builder(Some(borrower));
+    let (token, amount) = (builder.token.unwrap(), builder.amount.unwrap());
+    let weth = Weth(Address::random());
+    builder.add_call(weth.deposit(U256::from(2)).unwrap());
+    builder.with_weth_wrap(WethWrap::new(weth.0).with_wrap(U256::from(3)));
+
+    let plan = builder.plan().unwrap();
+    assert_eq!(plan.call_value(), CallValue::Attach);
+    assert_eq!(plan.calls().len(), 2);
+    assert_eq!(plan.value(), U256::from(5));
+
+    // The summed value of the calls is sent with the flashloan
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(vec![])]))).unwrap();
+    builder.call().await.unwrap();
+    let contract = Flashloan::new(borrower, builder.inner());
+    let expected = contract.flash_borrow(token, amount, plan.calls().to_vec()).value(U256::from(5));
+    mock.assert_request("eth_call", (expected.tx, "latest")).unwrap();
+
+    // Programs don't run the calls, so send no ether
+    let plan = FlashloanBuilder::with_client(builder.inner())
+        .borrower(borrower)
+        .token(token)
+        .amount(amount)
+        .calls(plan.calls().to_vec())
+        .program(Program::default())
+        .build()
+        .unwrap();
+    assert!(plan.value().is_zero());
+}
+
+#[test]
+fn test_weth_wrap_program() {
+    let (mut builder, _mock) = mocked_builder(Some(Address::random()));
+    let weth_wrap = WethWrap::new(Address::random()).with_wrap(U256::one());
+    let construction_error = |err: anyhow::Error| {
+        matches!(err.downcast_ref(), Some(FlashloanError::CallConstructionError(_)))
+    };
+
+    // Programs don't run the calls, so a WETH wrap would be dropped
+    builder.with_weth_wrap(weth_wrap).with_program(Program::default());
+    assert!(construction_error(builder.plan().unwrap_err()));
+    let plan = || {
+        FlashloanBuilder::with_client(builder.inner())
+            .borrower(Address::random())
+            .token(Address::random())
+            .amount(U256::one())
+            .weth_wrap(weth_wrap)
+    };
+    assert!(plan().build().is_ok());
+    assert!(construction_error(plan().program(Program::default()).build().unwrap_err()));
+}
+
+#[tokio::test]
+async fn test_borrower_call_value() {
+    let borrower = Address::random();
+    let (mut builder, mock) = mocked_builder(Some(borrower));
+    let (token, amount) = (builder.token.unwrap(), builder.amount.unwrap());
+    let call = Weth(Address::random()).deposit(U256::from(10)).unwrap();
+    builder.add_call(call.clone()).with_call_value(CallValue::Borrower);
+    assert!(builder.plan().unwrap().value().is_zero());
+
+    // The borrower's balance must cover the value of the calls
+    mock.push(U256::from(9)).unwrap();
+    let err = builder.call().await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(FlashloanError::InsufficientEth { balance, required })
+            if *balance == U256::from(9) && *required == U256::from(10)
+    ));
+    mock.assert_request("eth_getBalance", (borrower, "latest")).unwrap();
+
+    // Then the calls spend the borrower's ether, sending none
+    mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Array(vec![])]))).unwrap();
+    mock.push(U256::from(10)).unwrap();
+    builder.call().await.unwrap();
+    mock.assert_request("eth_getBalance", (borrower, "latest")).unwrap();
+    let contract = Flashloan::new(borrower, builder.inner());
+    let expected = contract.flash_borrow(token, amount, vec![call]);
+    mock.assert_request("eth_call", (expected.tx, "latest")).unwrap();
+}